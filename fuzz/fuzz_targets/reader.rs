@@ -0,0 +1,17 @@
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate xml;
+
+use xml::reader::{EventReader, XmlEvent};
+
+// the reader must never panic, whatever the input; errors are fine
+fuzz_target!(|data: &[u8]| {
+    for event in EventReader::new(data) {
+        match event {
+            Ok(XmlEvent::EndDocument) | Err(_) => break,
+            Ok(_) => {}
+        }
+    }
+});
@@ -53,14 +53,16 @@ fn main() {
     for e in reader {
         match e {
             Ok(e) => match e {
-                XmlEvent::StartDocument { version, encoding, standalone } =>
+                XmlEvent::StartDocument { version, effective_encoding, standalone, .. } =>
                     println!(
                         "XML document version {}, encoded in {}, {}standalone",
-                        version, encoding, if standalone.unwrap_or(false) { "" } else { "not " }
+                        version, effective_encoding, if standalone.unwrap_or(false) { "" } else { "not " }
                     ),
                 XmlEvent::EndDocument => println!("Document finished"),
                 XmlEvent::ProcessingInstruction { .. } => processing_instructions += 1,
                 XmlEvent::Whitespace(_) => {}  // can't happen due to configuration
+                XmlEvent::EntityReference(_) => {}  // can't happen due to configuration
+                XmlEvent::DoctypeDecl { .. } => {}  // can't happen due to configuration
                 XmlEvent::Characters(s) => {
                     character_blocks += 1;
                     characters += s.len();
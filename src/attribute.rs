@@ -1,10 +1,14 @@
 //! Contains XML attributes manipulation types and functions.
 //!
 
+use std::convert::TryFrom;
+use std::error;
 use std::fmt;
 
+use common::{is_name_char, is_name_start_char, is_whitespace_char, is_xml10_char};
 use name::{Name, OwnedName};
 use escape::escape_str_attribute;
+use util::parse_char_reference;
 
 /// A borrowed version of an XML attribute.
 ///
@@ -78,9 +82,214 @@ impl fmt::Display for OwnedAttribute {
     }
 }
 
+/// A name by which an attribute is looked up, as in `reader::XmlEvent::get_attribute()`.
+///
+/// A `(local_name, namespace_uri)` pair denotes a name in a namespace, or a name without
+/// a namespace if the URI is empty. `Name`s and `OwnedName`s denote their local names and
+/// namespace URIs. Prefixes are never considered.
+pub trait AttributeKey {
+    /// Checks whether the given attribute name is denoted by this key.
+    fn matches(&self, name: &OwnedName) -> bool;
+}
+
+impl AttributeKey for (&str, &str) {
+    #[inline]
+    fn matches(&self, name: &OwnedName) -> bool {
+        name.matches_ns(Some(self.1), self.0)
+    }
+}
+
+impl<'a> AttributeKey for Name<'a> {
+    #[inline]
+    fn matches(&self, name: &OwnedName) -> bool {
+        name.matches_ns(self.namespace, self.local_name)
+    }
+}
+
+impl AttributeKey for &OwnedName {
+    #[inline]
+    fn matches(&self, name: &OwnedName) -> bool {
+        AttributeKey::matches(&self.borrow(), name)
+    }
+}
+
+/// An error which occurs when an attribute is parsed from a string.
+///
+/// See the `TryFrom<&str>` implementations of `Attribute` and `OwnedAttribute`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseAttributeError {
+    /// The name of the attribute is not a valid qualified name.
+    InvalidName(String),
+
+    /// The name is not followed by `=` and a value.
+    MissingValue,
+
+    /// The value is not enclosed in matching single or double quotes.
+    UnquotedValue,
+
+    /// There are characters after the closing quote of the value.
+    TrailingCharacters(String),
+
+    /// The value contains a character which must be escaped or is not allowed in XML.
+    InvalidCharacter(char),
+
+    /// The value contains a malformed reference or a reference to an unknown entity.
+    InvalidReference(String),
+
+    /// The value contains a reference, which only an `OwnedAttribute` can hold, because
+    /// the value of a borrowed `Attribute` is a part of the parsed string.
+    BorrowedReference
+}
+
+impl fmt::Display for ParseAttributeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseAttributeError::InvalidName(ref name) => write!(f, "Invalid attribute name: {}", name),
+            ParseAttributeError::MissingValue => f.write_str("Attribute value is missing"),
+            ParseAttributeError::UnquotedValue => f.write_str("Attribute value is not quoted"),
+            ParseAttributeError::TrailingCharacters(ref rest) =>
+                write!(f, "Unexpected characters after attribute value: {}", rest),
+            ParseAttributeError::InvalidCharacter(c) =>
+                write!(f, "Character {:?} must be escaped in attribute values", c),
+            ParseAttributeError::InvalidReference(ref reference) =>
+                write!(f, "Invalid reference in attribute value: {}", reference),
+            ParseAttributeError::BorrowedReference =>
+                f.write_str("A borrowed attribute value cannot contain references"),
+        }
+    }
+}
+
+impl error::Error for ParseAttributeError {
+    fn description(&self) -> &str {
+        "attribute parsing error"
+    }
+}
+
+/// Parses an attribute as it is written in a start tag, like `name="value"` or `p:name='value'`,
+/// and returns its name and its value with references left as they are.
+fn parse_attribute(s: &str) -> Result<(Name<'_>, &str), ParseAttributeError> {
+    let (name, rest) = match s.find('=') {
+        Some(i) => (s[..i].trim_end_matches(is_whitespace_char),
+                    s[i + 1..].trim_start_matches(is_whitespace_char)),
+        None => return Err(ParseAttributeError::MissingValue)
+    };
+
+    let is_ncname = |s: &str| s.starts_with(|c| c != ':' && is_name_start_char(c)) &&
+                              s.chars().all(|c| c != ':' && is_name_char(c));
+    let name = match name.find(':') {
+        Some(i) if is_ncname(&name[..i]) && is_ncname(&name[i + 1..]) =>
+            Name::prefixed(&name[i + 1..], &name[..i]),
+        None if is_ncname(name) => Name::local(name),
+        _ => return Err(ParseAttributeError::InvalidName(name.into()))
+    };
+
+    let quote = match rest.chars().next() {
+        Some(c @ '"') | Some(c @ '\'') => c,
+        _ => return Err(ParseAttributeError::UnquotedValue)
+    };
+    let value = &rest[1..];
+    let (value, rest) = match value.find(quote) {
+        Some(i) => (&value[..i], &value[i + 1..]),
+        None => return Err(ParseAttributeError::UnquotedValue)
+    };
+    if !rest.is_empty() {
+        return Err(ParseAttributeError::TrailingCharacters(rest.into()));
+    }
+
+    if let Some(c) = value.chars().find(|&c| c == '<' || !is_xml10_char(c)) {
+        return Err(ParseAttributeError::InvalidCharacter(c));
+    }
+    let mut references = value.split('&').skip(1);
+    if let Some(reference) = references.find(|r| resolve_reference(r).is_none()) {
+        let end = reference.find(';').map_or(reference.len(), |i| i + 1);
+        return Err(ParseAttributeError::InvalidReference(format!("&{}", &reference[..end])));
+    }
+    Ok((name, value))
+}
+
+/// Resolves the reference at the start of the given string, which follows an `&`,
+/// and returns the replacement character together with the length of the reference.
+/// Character references must refer to characters allowed in XML 1.0 documents.
+fn resolve_reference(s: &str) -> Option<(char, usize)> {
+    let end = s.find(';')?;
+    let c = match &s[..end] {
+        "lt" => '<',
+        "gt" => '>',
+        "amp" => '&',
+        "apos" => '\'',
+        "quot" => '"',
+        reference => parse_char_reference(reference.strip_prefix('#')?).filter(|&c| is_xml10_char(c))?
+    };
+    Some((c, end + 1))
+}
+
+/// Parses an attribute as it is written in a start tag, like `name="value"`.
+///
+/// The name may have a prefix, which is not resolved, and there may be whitespace around
+/// the `=`. The value must be quoted and properly escaped; since the value is borrowed,
+/// it must not contain any references, which `OwnedAttribute` accepts.
+///
+/// ```rust
+/// use std::convert::TryFrom;
+/// use xml::attribute::{Attribute, ParseAttributeError};
+///
+/// let attr = Attribute::try_from("xml:lang=\"en\"").unwrap();
+/// assert_eq!(attr.name.prefix, Some("xml"));
+/// assert_eq!(attr.value, "en");
+///
+/// assert_eq!(Attribute::try_from("a=b"), Err(ParseAttributeError::UnquotedValue));
+/// assert_eq!(Attribute::try_from("a='<'"), Err(ParseAttributeError::InvalidCharacter('<')));
+/// ```
+impl<'a> TryFrom<&'a str> for Attribute<'a> {
+    type Error = ParseAttributeError;
+
+    fn try_from(s: &'a str) -> Result<Attribute<'a>, ParseAttributeError> {
+        let (name, value) = parse_attribute(s)?;
+        if value.contains('&') {
+            return Err(ParseAttributeError::BorrowedReference);
+        }
+        Ok(Attribute::new(name, value))
+    }
+}
+
+/// Parses an attribute as it is written in a start tag, like `name="value"`, replacing
+/// references in the value.
+///
+/// Only character references and references to the predefined entities are allowed.
+/// This is the inverse of the `Display` implementation, except for the namespace, which
+/// is not known:
+///
+/// ```rust
+/// use std::convert::TryFrom;
+/// use xml::attribute::OwnedAttribute;
+///
+/// let attr = OwnedAttribute::try_from("p:name = 'Tom &amp; &#x4A;erry'").unwrap();
+/// assert_eq!(attr.name.prefix_ref(), Some("p"));
+/// assert_eq!(attr.value, "Tom & Jerry");
+/// assert_eq!(attr.to_string(), "p:name=\"Tom &amp; Jerry\"");
+/// ```
+impl<'a> TryFrom<&'a str> for OwnedAttribute {
+    type Error = ParseAttributeError;
+
+    fn try_from(s: &'a str) -> Result<OwnedAttribute, ParseAttributeError> {
+        let (name, value) = parse_attribute(s)?;
+        let mut parts = value.split('&');
+        let mut result = parts.next().unwrap_or("").to_owned();
+        for part in parts {
+            // references are checked by `parse_attribute()`
+            let (c, len) = resolve_reference(part).unwrap();
+            result.push(c);
+            result.push_str(&part[len..]);
+        }
+        Ok(OwnedAttribute::new(name.into(), result))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Attribute};
+    use std::convert::TryFrom;
+
+    use super::{Attribute, OwnedAttribute, ParseAttributeError};
 
     use name::Name;
 
@@ -96,4 +305,39 @@ mod tests {
             "{urn:namespace}n:attribute=\"its value with &gt; &amp; &quot; &apos; &lt; weird symbols\""
         )
     }
+
+    #[test]
+    fn attribute_parsing() {
+        let attr = Attribute::try_from("n:attribute\t=  \"a 'quoted' value\"").unwrap();
+        assert_eq!(attr, Attribute::new(Name::prefixed("attribute", "n"), "a 'quoted' value"));
+        assert_eq!(Attribute::try_from("a=''"), Ok(Attribute::new(Name::local("a"), "")));
+
+        let attr = OwnedAttribute::try_from("a=\"&lt;&#60;&#x3C;&gt;&quot;&apos;&amp;amp;\"").unwrap();
+        assert_eq!(attr.value, "<<<>\"'&amp;");
+        assert_eq!(Attribute::try_from("a='&amp;'"), Err(ParseAttributeError::BorrowedReference));
+
+        let errors = [
+            ("a", ParseAttributeError::MissingValue),
+            ("=\"v\"", ParseAttributeError::InvalidName("".into())),
+            ("1a=\"v\"", ParseAttributeError::InvalidName("1a".into())),
+            ("a:b:c=\"v\"", ParseAttributeError::InvalidName("a:b:c".into())),
+            (":a=\"v\"", ParseAttributeError::InvalidName(":a".into())),
+            ("a b=\"v\"", ParseAttributeError::InvalidName("a b".into())),
+            ("a=v", ParseAttributeError::UnquotedValue),
+            ("a=\"v'", ParseAttributeError::UnquotedValue),
+            ("a=\"v\" b=\"w\"", ParseAttributeError::TrailingCharacters(" b=\"w\"".into())),
+            ("a=\"<\"", ParseAttributeError::InvalidCharacter('<')),
+            ("a=\"\u{1}\"", ParseAttributeError::InvalidCharacter('\u{1}')),
+            ("a=\"&\"", ParseAttributeError::InvalidReference("&".into())),
+            ("a=\"& b\"", ParseAttributeError::InvalidReference("& b".into())),
+            ("a=\"&nbsp;\"", ParseAttributeError::InvalidReference("&nbsp;".into())),
+            ("a=\"&#0;\"", ParseAttributeError::InvalidReference("&#0;".into())),
+            ("a=\"&#xD800;\"", ParseAttributeError::InvalidReference("&#xD800;".into())),
+            ("a=\"&#1;\"", ParseAttributeError::InvalidReference("&#1;".into())),
+            ("a=\"&#xFFFE;\"", ParseAttributeError::InvalidReference("&#xFFFE;".into())),
+        ];
+        for &(s, ref error) in &errors {
+            assert_eq!(OwnedAttribute::try_from(s).as_ref(), Err(error), "{}", s);
+        }
+    }
 }
@@ -3,7 +3,24 @@
 use std::fmt;
 
 /// Represents a position inside some textual document.
-#[derive(Copy, Clone, PartialEq, Eq)]
+///
+/// Positions are ordered by row first and then by column, so they can be sorted and
+/// used as keys in ordered and hashed collections. Both `Display` and `Debug` show the position
+/// as `row:column`, counting from 1:
+///
+/// ```rust
+/// use xml::common::TextPosition;
+///
+/// let mut a = TextPosition::new();
+/// a.advance(5);
+/// let mut b = TextPosition::new();
+/// b.new_line();
+///
+/// assert!(a < b);
+/// assert_eq!(a.to_string(), "1:6");
+/// assert_eq!(format!("{:?}", b), "2:1");
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TextPosition {
     /// Row, counting from 0
     pub row: u64,
@@ -93,6 +110,60 @@ impl fmt::Debug for XmlVersion {
     }
 }
 
+/// Whitespace handling mode, as set by the reserved `xml:space` attribute.
+///
+/// See [2.10 White Space Handling][1] for more information.
+///
+/// [1]: http://www.w3.org/TR/REC-xml/#sec-white-space
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum XmlSpace {
+    /// `xml:space="default"`, or no `xml:space` attribute in scope; the application's
+    /// default whitespace handling applies.
+    Default,
+
+    /// `xml:space="preserve"`; whitespace must be preserved as is.
+    Preserve
+}
+
+impl XmlSpace {
+    /// Parses a value of the `xml:space` attribute.
+    ///
+    /// Returns `None` if the value is neither `default` nor `preserve`.
+    pub fn from_attribute_value(value: &str) -> Option<XmlSpace> {
+        match value {
+            "default" => Some(XmlSpace::Default),
+            "preserve" => Some(XmlSpace::Preserve),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for XmlSpace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            XmlSpace::Default => write!(f, "default"),
+            XmlSpace::Preserve => write!(f, "preserve")
+        }
+    }
+}
+
+/// Determines what the parser or the emitter does with characters which are not allowed
+/// in XML documents.
+///
+/// See `ParserConfig::invalid_character_policy` and `EmitterConfig::invalid_character_policy`
+/// for more information.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum InvalidCharacterPolicy {
+    /// Report an error.
+    Error,
+
+    /// Remove the character.
+    Strip,
+
+    /// Use the given character instead, for example, U+FFFD REPLACEMENT CHARACTER.
+    ReplaceWith(char)
+}
+
 /// Checks whether the given character is a white space character (`S`)
 /// as is defined by XML 1.1 specification, [section 2.3][1].
 ///
@@ -111,6 +182,62 @@ pub fn is_whitespace_str(s: &str) -> bool {
     s.chars().all(is_whitespace_char)
 }
 
+/// Checks whether the given character is allowed in XML 1.0 documents (`Char`)
+/// as is defined by XML 1.0 specification, [section 2.2][1].
+///
+/// [1]: http://www.w3.org/TR/2008/REC-xml-20081126/#charsets
+pub fn is_xml10_char(c: char) -> bool {
+    matches!(c, '\u{09}' | '\u{0A}' | '\u{0D}' |
+                '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..='\u{10FFFF}')
+}
+
+/// Checks whether the given character is allowed in XML 1.1 documents (`Char`)
+/// as is defined by XML 1.1 specification, [section 2.2][1].
+///
+/// Note that restricted characters (`RestrictedChar`) are included; they are allowed
+/// in XML 1.1 documents only in the form of character references.
+///
+/// [1]: http://www.w3.org/TR/2006/REC-xml11-20060816/#charsets
+pub fn is_xml11_char(c: char) -> bool {
+    matches!(c, '\u{01}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..='\u{10FFFF}')
+}
+
+/// Checks whether the given character is allowed in XML 1.1 documents (`Char`) and is
+/// not a restricted character (`RestrictedChar`), that is, whether it may appear in
+/// XML 1.1 documents literally.
+///
+/// [1]: http://www.w3.org/TR/2006/REC-xml11-20060816/#charsets
+pub fn is_xml11_char_not_restricted(c: char) -> bool {
+    is_xml11_char(c) && !matches!(c,
+        '\u{01}'..='\u{08}' | '\u{0B}'..='\u{0C}' | '\u{0E}'..='\u{1F}' |
+        '\u{7F}'..='\u{84}' | '\u{86}'..='\u{9F}')
+}
+
+/// Checks whether the given character may appear literally in a document of the given
+/// XML version.
+#[inline]
+pub fn is_xml_char(c: char, version: XmlVersion) -> bool {
+    // printable ASCII characters are allowed in all versions; checking them first
+    // keeps validation cheap for mostly ASCII documents
+    if ('\u{20}'..'\u{7F}').contains(&c) {
+        return true;
+    }
+    match version {
+        XmlVersion::Version10 => is_xml10_char(c),
+        XmlVersion::Version11 => is_xml11_char_not_restricted(c)
+    }
+}
+
+/// Checks whether the given character may appear in a document of the given XML version
+/// in the form of a character reference.
+#[inline]
+pub fn is_xml_char_reference(c: char, version: XmlVersion) -> bool {
+    match version {
+        XmlVersion::Version10 => is_xml10_char(c),
+        XmlVersion::Version11 => is_xml11_char(c)
+    }
+}
+
 /// Checks whether the given character is a name start character (`NameStartChar`)
 /// as is defined by XML 1.1 specification, [section 2.3][1].
 ///
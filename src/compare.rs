@@ -0,0 +1,226 @@
+//! Contains a semantic comparison of documents, which is useful for testing XML producers.
+//!
+//! Documents are compared by their events, so differences which don't matter to an XML parser,
+//! like the quotes around attribute values, character references or `<a/>` written as
+//! `<a></a>`, never make documents different. Other differences are ignored as configured
+//! with `CompareOptions`:
+//!
+//! ```rust
+//! use xml::compare::{compare, CompareOptions, ComparisonResult};
+//!
+//! let expected = "<a x='1' y='2'><!-- note --><b/></a>";
+//! let actual = "<a y=\"2\" x=\"1\">\n  <b></b>\n</a>";
+//!
+//! let options = CompareOptions::new();
+//! let result = compare(expected.as_bytes(), actual.as_bytes(), &options).unwrap();
+//! let difference = result.difference().unwrap();
+//! assert_eq!(difference.description(), "Attributes of element `a` differ: x=\"1\" y=\"2\" and y=\"2\" x=\"1\"");
+//! assert_eq!(difference.positions().1.to_string(), "1:1");
+//!
+//! let options = CompareOptions::new()
+//!     .ignore_attribute_order(true)
+//!     .ignore_comments(true)
+//!     .normalize_whitespace(true);
+//! let result = compare(expected.as_bytes(), actual.as_bytes(), &options).unwrap();
+//! assert_eq!(result, ComparisonResult::Equal);
+//! ```
+
+use std::fmt;
+use std::io::BufRead;
+
+use attribute::OwnedAttribute;
+use common::{Position, TextPosition};
+use name::OwnedName;
+use reader::{EventReader, ParserConfig, Result, XmlEvent};
+
+/// Options of a document comparison; all of them are disabled by default.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct CompareOptions {
+    /// Whether attributes may be in a different order. Default is false.
+    pub ignore_attribute_order: bool,
+
+    /// Whether comments are ignored. Default is false.
+    pub ignore_comments: bool,
+
+    /// Whether whitespace-only text is ignored. Default is false.
+    ///
+    /// This removes the differences in indentation between elements, but not whitespace
+    /// around other text, which is significant.
+    pub normalize_whitespace: bool,
+
+    /// Whether CDATA sections are compared as ordinary text, so that `<![CDATA[<]]>`
+    /// equals `&lt;`. Default is false.
+    pub cdata_as_text: bool,
+
+    /// Whether element and attribute names are compared by their namespace URIs and local names,
+    /// ignoring their prefixes. Default is false, that is, names must have the same prefixes
+    /// and namespace URIs.
+    ///
+    /// Namespace declarations are never compared by themselves, only the names they apply to.
+    pub compare_namespaces_by_uri: bool
+}
+
+impl CompareOptions {
+    /// Returns options with default values.
+    #[inline]
+    pub fn new() -> CompareOptions {
+        CompareOptions::default()
+    }
+}
+
+gen_setters!(CompareOptions,
+    ignore_attribute_order: val bool,
+    ignore_comments: val bool,
+    normalize_whitespace: val bool,
+    cdata_as_text: val bool,
+    compare_namespaces_by_uri: val bool
+);
+
+/// The result of a comparison of two documents.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ComparisonResult {
+    /// The documents are equivalent.
+    Equal,
+
+    /// The documents are different; the first difference is given.
+    Different(Difference)
+}
+
+impl ComparisonResult {
+    /// Returns `true` if the documents are equivalent.
+    #[inline]
+    pub fn is_equal(&self) -> bool {
+        *self == ComparisonResult::Equal
+    }
+
+    /// Returns the first difference of the documents, if they are different.
+    #[inline]
+    pub fn difference(&self) -> Option<&Difference> {
+        match *self {
+            ComparisonResult::Equal => None,
+            ComparisonResult::Different(ref d) => Some(d)
+        }
+    }
+}
+
+/// The first difference of two documents.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Difference {
+    left: TextPosition,
+    right: TextPosition,
+    description: String
+}
+
+impl Difference {
+    /// Returns the positions of the difference in the first and in the second document.
+    #[inline]
+    pub fn positions(&self) -> (TextPosition, TextPosition) { (self.left, self.right) }
+
+    /// Returns a description of the difference.
+    #[inline]
+    pub fn description(&self) -> &str { &self.description }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (at {} and {})", self.description, self.left, self.right)
+    }
+}
+
+/// Parses both documents and compares them with the given options.
+///
+/// Returns an error if either document is malformed. The XML declarations are not compared.
+pub fn compare<A: BufRead, B: BufRead>(a: A, b: B, options: &CompareOptions) -> Result<ComparisonResult> {
+    let config = ParserConfig::new()
+        .ignore_comments(options.ignore_comments)
+        .cdata_to_characters(options.cdata_as_text)
+        .whitespace_to_characters(!options.normalize_whitespace)
+        .coalesce_characters(true);
+    let mut left = EventReader::new_with_config(a, config.clone());
+    let mut right = EventReader::new_with_config(b, config);
+
+    loop {
+        let l = next_event(&mut left)?;
+        let r = next_event(&mut right)?;
+        if let Some(description) = compare_events(&l, &r, options) {
+            return Ok(ComparisonResult::Different(Difference {
+                left: left.position(),
+                right: right.position(),
+                description
+            }));
+        }
+        if l == XmlEvent::EndDocument {
+            return Ok(ComparisonResult::Equal);
+        }
+    }
+}
+
+/// Returns the next event which is compared.
+fn next_event<R: BufRead>(reader: &mut EventReader<R>) -> Result<XmlEvent> {
+    loop {
+        match reader.next()? {
+            XmlEvent::StartDocument { .. } | XmlEvent::Whitespace(_) => {}
+            e => return Ok(e)
+        }
+    }
+}
+
+/// Compares two events and describes the difference, if there is one.
+fn compare_events(l: &XmlEvent, r: &XmlEvent, options: &CompareOptions) -> Option<String> {
+    match (l, r) {
+        (XmlEvent::StartElement { name: ln, attributes: la, .. },
+         XmlEvent::StartElement { name: rn, attributes: ra, .. }) => {
+            if !names_equal(ln, rn, options) {
+                return Some(format!("Element names differ: `{}` and `{}`", ln, rn));
+            }
+            let equal = if options.ignore_attribute_order {
+                la.len() == ra.len() && la.iter().all(|a| ra.iter().any(|b| attributes_equal(a, b, options)))
+            } else {
+                la.len() == ra.len() && la.iter().zip(ra).all(|(a, b)| attributes_equal(a, b, options))
+            };
+            if equal {
+                None
+            } else {
+                Some(format!("Attributes of element `{}` differ: {} and {}",
+                             ln, attributes_repr(la), attributes_repr(ra)))
+            }
+        }
+        // end elements are paired with the compared start elements
+        (XmlEvent::EndElement { .. }, XmlEvent::EndElement { .. }) |
+        (XmlEvent::EndDocument, XmlEvent::EndDocument) => None,
+        (XmlEvent::Characters(a), XmlEvent::Characters(b)) |
+        (XmlEvent::CData(a), XmlEvent::CData(b)) |
+        (XmlEvent::Comment(a), XmlEvent::Comment(b)) |
+        (XmlEvent::EntityReference(a), XmlEvent::EntityReference(b)) if a == b => None,
+        (XmlEvent::ProcessingInstruction { name: an, data: ad },
+         XmlEvent::ProcessingInstruction { name: bn, data: bd }) if an == bn && ad == bd => None,
+        _ => Some(format!("Expected {}, found {}", event_repr(l), event_repr(r)))
+    }
+}
+
+fn names_equal(a: &OwnedName, b: &OwnedName, options: &CompareOptions) -> bool {
+    a.local_name == b.local_name && a.namespace_ref().unwrap_or("") == b.namespace_ref().unwrap_or("") &&
+        (options.compare_namespaces_by_uri || a.prefix == b.prefix)
+}
+
+fn attributes_equal(a: &OwnedAttribute, b: &OwnedAttribute, options: &CompareOptions) -> bool {
+    names_equal(&a.name, &b.name, options) && a.value == b.value
+}
+
+fn attributes_repr(attributes: &[OwnedAttribute]) -> String {
+    attributes.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+fn event_repr(event: &XmlEvent) -> String {
+    match *event {
+        XmlEvent::StartElement { ref name, .. } => format!("element `{}`", name),
+        XmlEvent::EndElement { ref name } => format!("end of element `{}`", name),
+        XmlEvent::EndDocument => "end of document".into(),
+        XmlEvent::Characters(ref data) => format!("text {:?}", data),
+        XmlEvent::CData(ref data) => format!("CDATA section {:?}", data),
+        XmlEvent::Comment(ref data) => format!("comment {:?}", data),
+        XmlEvent::ProcessingInstruction { ref name, .. } => format!("processing instruction `{}`", name),
+        XmlEvent::EntityReference(ref name) => format!("entity reference `&{};`", name),
+        ref e => format!("{:?}", e)
+    }
+}
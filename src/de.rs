@@ -0,0 +1,693 @@
+//! Contains a `serde` deserializer for XML documents. Available with the `serde` feature.
+//!
+//! `from_str()` and `from_reader()` read a whole document and deserialize its root element
+//! into any type implementing `serde::Deserialize`:
+//!
+//! ```rust
+//! # #[macro_use] extern crate serde_derive;
+//! # extern crate xml;
+//! #[derive(Deserialize, Debug, PartialEq)]
+//! struct Order {
+//!     #[serde(rename = "@id")]
+//!     id: u32,
+//!     customer: String,
+//!     #[serde(rename = "item")]
+//!     items: Vec<Item>
+//! }
+//!
+//! #[derive(Deserialize, Debug, PartialEq)]
+//! struct Item {
+//!     #[serde(rename = "@sku")]
+//!     sku: String,
+//!     #[serde(rename = "$value")]
+//!     quantity: u32
+//! }
+//!
+//! # fn main() {
+//! let order: Order = xml::de::from_str(
+//!     "<order id=\"7\"><customer>Jane</customer><item sku=\"a\">2</item><item sku=\"b\">1</item></order>"
+//! ).unwrap();
+//! assert_eq!(order, Order {
+//!     id: 7,
+//!     customer: "Jane".into(),
+//!     items: vec![Item { sku: "a".into(), quantity: 2 }, Item { sku: "b".into(), quantity: 1 }]
+//! });
+//! # }
+//! ```
+//!
+//! The document is mapped to the data model as follows:
+//!
+//! * an element is deserialized as a struct or a map whose entries are the attributes and
+//!   the child elements of the element. Attributes are named after their local name with an
+//!   `@` prefix, e.g. `@id`, while child elements are named after their local name as is.
+//!   Namespaces and prefixes are not taken into account;
+//! * child elements with the same name are collected into a single entry, which is
+//!   deserialized as a sequence if the field is a `Vec` or another sequence type. An entry of
+//!   a field which is not a sequence must consist of exactly one element; a missing element
+//!   makes an `Option` field `None`;
+//! * the text content of an element is deserialized as a string or a number when the element
+//!   is deserialized as one, including newtype structs around strings, and it is available
+//!   as a special `$value` entry of structs. If a struct declares a `$value` field, the child
+//!   elements which do not correspond to other fields of the struct are available through
+//!   this entry too, e.g. as a sequence of enums;
+//! * enums are dispatched on element names: the variant of an enum entry is selected by the
+//!   name of the first child element of the entry, and the variant of an element inside of
+//!   a `$value` sequence or of the root element is selected by its own name. Unit variants
+//!   can also be selected by text content or attribute values.
+//!
+//! Whitespace-only text, comments and processing instructions are ignored. Errors include the
+//! position of the element which could not be deserialized, if it is known.
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+
+use common::{is_whitespace_str, Position, TextPosition};
+use reader::{self, EventReader, XmlEvent};
+
+/// Name of the entry which contains the text content of an element.
+pub const VALUE_FIELD: &str = "$value";
+
+/// Prefix of the entries which contain the attributes of an element.
+pub const ATTRIBUTE_PREFIX: &str = "@";
+
+/// A deserialization error.
+///
+/// Consists of a textual message and, if the error is related to a specific element,
+/// the position of the element in the document.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Error {
+    pos: Option<TextPosition>,
+    msg: String
+}
+
+/// A specialized `Result` type where the error is hard-wired to `de::Error`.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+impl Error {
+    fn at(pos: TextPosition, msg: String) -> Error {
+        Error { pos: Some(pos), msg }
+    }
+
+    /// Sets the position of this error unless it is known already, so the error points
+    /// at the innermost element which has caused it.
+    fn or_at(mut self, pos: TextPosition) -> Error {
+        self.pos = self.pos.or(Some(pos));
+        self
+    }
+
+    /// Returns the message of this error.
+    #[inline]
+    pub fn msg(&self) -> &str { &self.msg }
+
+    /// Returns the position of the element which has caused this error, if it is known.
+    #[inline]
+    pub fn position(&self) -> Option<TextPosition> { self.pos }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.pos {
+            Some(pos) => write!(f, "{} {}", pos, self.msg),
+            None => f.write_str(&self.msg)
+        }
+    }
+}
+
+impl error::Error for Error {
+    #[inline]
+    fn description(&self) -> &str { &self.msg }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error { pos: None, msg: msg.to_string() }
+    }
+}
+
+impl From<reader::Error> for Error {
+    fn from(e: reader::Error) -> Error {
+        Error::at(e.position(), e.msg().into())
+    }
+}
+
+/// Deserializes an instance of `T` from the given string.
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T> {
+    from_event_reader(EventReader::from_str(s))
+}
+
+/// Deserializes an instance of `T` from the given byte source.
+pub fn from_reader<R: Read, T: DeserializeOwned>(source: R) -> Result<T> {
+    from_event_reader(EventReader::new(source))
+}
+
+/// Deserializes an instance of `T` from the events of the given reader.
+///
+/// This makes it possible to deserialize documents read with a custom `ParserConfig`.
+/// The whole document is consumed.
+pub fn from_event_reader<R: Read, T: DeserializeOwned>(reader: EventReader<R>) -> Result<T> {
+    let root = read_element(reader)?;
+    T::deserialize(ElementDeserializer { element: &root, by_name: true })
+}
+
+/// An element of a document, with the details which are not relevant to deserialization
+/// left out.
+struct Element {
+    name: String,
+    pos: TextPosition,
+    attributes: Vec<(String, String)>,
+    children: Vec<Content>
+}
+
+enum Content {
+    Element(Element),
+    Text(String)
+}
+
+impl Element {
+    fn child_elements(&self) -> impl Iterator<Item=&Element> {
+        self.children.iter().filter_map(|c| match *c {
+            Content::Element(ref e) => Some(e),
+            Content::Text(_) => None
+        })
+    }
+
+    /// Returns the text content of this element, failing if the element has child elements.
+    fn text(&self) -> Result<String> {
+        let mut text = String::new();
+        for c in &self.children {
+            match *c {
+                Content::Element(ref e) => return Err(Error::at(
+                    e.pos, format!("Unexpected element `{}`, expected text content of `{}`", e.name, self.name)
+                )),
+                Content::Text(ref s) => text.push_str(s)
+            }
+        }
+        Ok(text)
+    }
+}
+
+fn read_element<R: Read>(mut reader: EventReader<R>) -> Result<Element> {
+    // elements which are not finished yet; the last one is the innermost
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root = None;
+
+    loop {
+        match reader.next()? {
+            XmlEvent::EndDocument => break,
+            XmlEvent::StartElement { name, attributes, .. } => stack.push(Element {
+                name: name.local_name,
+                pos: reader.position(),
+                attributes: attributes.into_iter().map(|a| (a.name.local_name, a.value)).collect(),
+                children: Vec::new()
+            }),
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().expect("the parser checks that elements are balanced");
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(Content::Element(element)),
+                    None => root = Some(element)
+                }
+            }
+            XmlEvent::Characters(s) | XmlEvent::CData(s) => if let Some(parent) = stack.last_mut() {
+                match parent.children.last_mut() {
+                    Some(&mut Content::Text(ref mut text)) => text.push_str(&s),
+                    _ => parent.children.push(Content::Text(s))
+                }
+            },
+            _ => {}
+        }
+    }
+
+    root.ok_or_else(|| Error::at(reader.position(), "Document has no root element".into()))
+}
+
+/// Deserializes text content or attribute values.
+struct TextDeserializer<'a> {
+    text: Cow<'a, str>,
+    pos: TextPosition
+}
+
+impl<'a> TextDeserializer<'a> {
+    fn parse<T: FromStr>(&self, expected: &str) -> Result<T> {
+        self.text.trim().parse().map_err(|_| Error::at(
+            self.pos, format!("Invalid value `{}`, expected {}", self.text, expected)
+        ))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident, $expected:expr);+ $(;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                visitor.$visit(self.parse($expected)?)
+            }
+        )+
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for TextDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_str(&self.text)
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8, "an integer";
+        deserialize_i16 => visit_i16, "an integer";
+        deserialize_i32 => visit_i32, "an integer";
+        deserialize_i64 => visit_i64, "an integer";
+        deserialize_u8 => visit_u8, "an unsigned integer";
+        deserialize_u16 => visit_u16, "an unsigned integer";
+        deserialize_u32 => visit_u32, "an unsigned integer";
+        deserialize_u64 => visit_u64, "an unsigned integer";
+        deserialize_f32 => visit_f32, "a number";
+        deserialize_f64 => visit_f64, "a number";
+        deserialize_char => visit_char, "a single character";
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.text.trim() {
+            "true" | "1" => visitor.visit_bool(true),
+            "false" | "0" => visitor.visit_bool(false),
+            _ => Err(Error::at(self.pos, format!("Invalid value `{}`, expected a boolean", self.text)))
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value> {
+        let pos = self.pos;
+        let variant: de::value::StrDeserializer<Error> = self.text.trim().into_deserializer();
+        visitor.visit_enum(variant).map_err(|e| e.or_at(pos))
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Deserializes an element.
+struct ElementDeserializer<'a> {
+    element: &'a Element,
+    /// Whether the variant of an enum is selected by the name of the element itself rather than
+    /// by the name of its first child element.
+    by_name: bool
+}
+
+impl<'a> ElementDeserializer<'a> {
+    fn text(&self) -> Result<TextDeserializer<'a>> {
+        Ok(TextDeserializer { text: self.element.text()?.into(), pos: self.element.pos })
+    }
+}
+
+macro_rules! deserialize_text {
+    ($($method:ident)+) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                self.text()?.$method(visitor)
+            }
+        )+
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ElementDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.element.attributes.is_empty() && self.element.child_elements().next().is_none() {
+            self.text()?.deserialize_any(visitor)
+        } else {
+            self.deserialize_map(visitor)
+        }
+    }
+
+    deserialize_text! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32 deserialize_f64
+        deserialize_char deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let elements: Vec<_> = self.element.child_elements().collect();
+        visitor.visit_seq(SeqAccess::new(elements, true)).map_err(|e| e.or_at(self.element.pos))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize,
+                                                 visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(MapAccess::new(self.element, None)).map_err(|e| e.or_at(self.element.pos))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str],
+                                           visitor: V) -> Result<V::Value> {
+        visitor.visit_map(MapAccess::new(self.element, Some(fields))).map_err(|e| e.or_at(self.element.pos))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value> {
+        if self.by_name {
+            return visitor.visit_enum(EnumAccess { element: self.element })
+                .map_err(|e| e.or_at(self.element.pos));
+        }
+        match self.element.child_elements().next() {
+            Some(child) => ElementDeserializer { element: child, by_name: true }
+                .deserialize_enum(name, variants, visitor),
+            None => self.text()?.deserialize_enum(name, variants, visitor)
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+/// Deserializes all child elements with the same name.
+struct GroupDeserializer<'a> {
+    elements: Vec<&'a Element>
+}
+
+impl<'a> GroupDeserializer<'a> {
+    fn single(self) -> Result<ElementDeserializer<'a>> {
+        match self.elements.get(1) {
+            Some(e) => Err(Error::at(e.pos, format!("Unexpected repeated element `{}`", e.name))),
+            None => Ok(ElementDeserializer { element: self.elements[0], by_name: false })
+        }
+    }
+}
+
+macro_rules! deserialize_single {
+    ($($method:ident($($arg:ident: $ty:ty),*))+) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+                self.single()?.$method($($arg,)* visitor)
+            }
+        )+
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for GroupDeserializer<'a> {
+    type Error = Error;
+
+    deserialize_single! {
+        deserialize_any() deserialize_bool() deserialize_i8() deserialize_i16() deserialize_i32()
+        deserialize_i64() deserialize_u8() deserialize_u16() deserialize_u32() deserialize_u64()
+        deserialize_f32() deserialize_f64() deserialize_char() deserialize_str() deserialize_string()
+        deserialize_bytes() deserialize_byte_buf() deserialize_unit() deserialize_map()
+        deserialize_identifier()
+        deserialize_unit_struct(name: &'static str)
+        deserialize_newtype_struct(name: &'static str)
+        deserialize_struct(name: &'static str, fields: &'static [&'static str])
+        deserialize_enum(name: &'static str, variants: &'static [&'static str])
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(SeqAccess::new(self.elements, false))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize,
+                                                 visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+/// Deserializes the `$value` entry of an element: its text and the child elements which
+/// are not deserialized as separate entries.
+struct ContentDeserializer<'a> {
+    element: &'a Element,
+    elements: Vec<&'a Element>
+}
+
+impl<'a> ContentDeserializer<'a> {
+    fn text(&self) -> Result<TextDeserializer<'a>> {
+        let text: String = self.element.children.iter().filter_map(|c| match *c {
+            Content::Text(ref s) => Some(&s[..]),
+            Content::Element(_) => None
+        }).collect();
+        Ok(TextDeserializer { text: text.into(), pos: self.element.pos })
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ContentDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.elements.is_empty() {
+            self.text()?.deserialize_any(visitor)
+        } else {
+            self.deserialize_seq(visitor)
+        }
+    }
+
+    deserialize_text! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32 deserialize_f64
+        deserialize_char deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_identifier deserialize_unit
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.elements.is_empty() && is_whitespace_str(&self.text()?.text) {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(SeqAccess::new(self.elements, true))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize,
+                                                 visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::at(self.element.pos, format!("Content of `{}` cannot be deserialized as a map", self.element.name)))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, _fields: &'static [&'static str],
+                                           visitor: V) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value> {
+        match self.elements.first() {
+            Some(element) => ElementDeserializer { element, by_name: true }
+                .deserialize_enum(name, variants, visitor),
+            None => self.text()?.deserialize_enum(name, variants, visitor)
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+struct SeqAccess<'a> {
+    elements: ::std::vec::IntoIter<&'a Element>,
+    by_name: bool
+}
+
+impl<'a> SeqAccess<'a> {
+    fn new(elements: Vec<&'a Element>, by_name: bool) -> SeqAccess<'a> {
+        SeqAccess { elements: elements.into_iter(), by_name }
+    }
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for SeqAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.elements.next() {
+            Some(element) => seed.deserialize(ElementDeserializer { element, by_name: self.by_name }).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+enum Entry<'a> {
+    Attribute(&'a str),
+    Elements(Vec<&'a Element>),
+    Content(Vec<&'a Element>)
+}
+
+struct MapAccess<'a> {
+    element: &'a Element,
+    entries: ::std::vec::IntoIter<(Cow<'a, str>, Entry<'a>)>,
+    value: Option<Entry<'a>>
+}
+
+impl<'a> MapAccess<'a> {
+    /// Collects the entries of the element; `fields` are the fields of the struct
+    /// the element is deserialized as, if any.
+    fn new(element: &'a Element, fields: Option<&'static [&'static str]>) -> MapAccess<'a> {
+        let mut entries: Vec<(Cow<str>, Entry)> = element.attributes.iter()
+            .map(|(name, value)| (format!("{}{}", ATTRIBUTE_PREFIX, name).into(), Entry::Attribute(value)))
+            .collect();
+
+        // if the struct has a `$value` field, it receives the child elements which do not
+        // correspond to the other fields
+        let has_value_field = fields.is_some_and(|f| f.contains(&VALUE_FIELD));
+        let mut other_elements = Vec::new();
+        for child in element.child_elements() {
+            if has_value_field && !fields.is_some_and(|f| f.contains(&&child.name[..])) {
+                other_elements.push(child);
+                continue;
+            }
+            let group = entries.iter_mut().find(|(name, entry)| match *entry {
+                Entry::Elements(_) => *name == child.name,
+                _ => false
+            });
+            match group {
+                Some((_, Entry::Elements(elements))) => elements.push(child),
+                _ => entries.push((Cow::Borrowed(&child.name), Entry::Elements(vec![child])))
+            }
+        }
+
+        let has_text = element.children.iter().any(|c| match *c {
+            Content::Text(ref s) => !is_whitespace_str(s),
+            Content::Element(_) => false
+        });
+        if has_value_field || has_text {
+            entries.push((VALUE_FIELD.into(), Entry::Content(other_elements)));
+        }
+
+        MapAccess { element, entries: entries.into_iter(), value: None }
+    }
+}
+
+impl<'de, 'a> de::MapAccess<'de> for MapAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.entries.next() {
+            Some((name, entry)) => {
+                self.value = Some(entry);
+                seed.deserialize(TextDeserializer { text: name, pos: self.element.pos }).map(Some)
+            }
+            None => Ok(None)
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        match self.value.take().expect("next_value_seed is called after next_key_seed") {
+            Entry::Attribute(value) => seed.deserialize(TextDeserializer { text: value.into(), pos: self.element.pos }),
+            Entry::Elements(elements) => seed.deserialize(GroupDeserializer { elements }),
+            Entry::Content(elements) => seed.deserialize(ContentDeserializer { element: self.element, elements })
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Selects enum variants by the name of an element.
+struct EnumAccess<'a> {
+    element: &'a Element
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for EnumAccess<'a> {
+    type Error = Error;
+    type Variant = ElementDeserializer<'a>;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let name = TextDeserializer { text: self.element.name[..].into(), pos: self.element.pos };
+        let variant = seed.deserialize(name)?;
+        Ok((variant, ElementDeserializer { element: self.element, by_name: false }))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for ElementDeserializer<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
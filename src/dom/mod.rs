@@ -0,0 +1,394 @@
+//! Contains a lightweight tree representation of XML documents. Available with the `dom` feature.
+//!
+//! The tree is built on top of the pull parser: `read_to_tree()` consumes the events of
+//! an `EventReader` and collects them into an `Element`:
+//!
+//! ```rust
+//! use xml::EventReader;
+//! use xml::dom::{read_to_tree, Node};
+//!
+//! let root = read_to_tree(EventReader::from_str("<a x=\"1\"><b>text</b><?pi data?></a>")).unwrap();
+//! assert_eq!(root.name.local_name, "a");
+//! assert_eq!(root.attribute("x"), Some("1"));
+//! assert_eq!(root.get_child("b").unwrap().text(), "text");
+//! assert_eq!(root.children[1], Node::ProcessingInstruction { name: "pi".into(), data: Some("data".into()) });
+//! ```
+//!
+//! `write_tree()` does the opposite, so a document can be parsed, modified and
+//! serialized again:
+//!
+//! ```rust
+//! use xml::{EmitterConfig, EventReader};
+//! use xml::dom::{read_to_tree, write_tree, Node};
+//!
+//! let mut root = read_to_tree(EventReader::from_str("<a><b>text</b></a>")).unwrap();
+//! root.children.push(Node::Text("more".into()));
+//!
+//! let mut output = Vec::new();
+//! {
+//!     let mut writer = EmitterConfig::new().write_document_declaration(false).create_writer(&mut output);
+//!     write_tree(&mut writer, &root).unwrap();
+//! }
+//! assert_eq!(output, b"<a><b>text</b>more</a>");
+//! ```
+
+use std::borrow::Cow;
+use std::io::{Read, Write};
+use std::mem;
+
+use attribute::OwnedAttribute;
+use common::{is_whitespace_str, Position};
+use name::OwnedName;
+use namespace::Namespace;
+use reader::{self, EventReader};
+use writer::{self, EventWriter};
+
+pub use self::query::{Query, QueryError};
+
+mod query;
+
+/// An element of the document tree.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Element {
+    /// Qualified name of the element.
+    pub name: OwnedName,
+
+    /// Attributes of the element, in the order they appear in the document.
+    pub attributes: Vec<OwnedAttribute>,
+
+    /// Namespace mappings in scope of the element, including the ones declared by its
+    /// ancestors.
+    pub namespace: Namespace,
+
+    /// Child nodes of the element, in document order.
+    pub children: Vec<Node>
+}
+
+/// A child node of an element.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Node {
+    /// A nested element.
+    Element(Element),
+
+    /// Character data, with entities and character references already resolved.
+    /// Whitespace-only character data is represented by this variant too.
+    Text(String),
+
+    /// Contents of a CDATA section.
+    CData(String),
+
+    /// A reference to a general entity, given by its name. References appear in the tree only
+    /// if the reader was configured with `replace_entity_references(false)`.
+    EntityReference(String),
+
+    /// Contents of a comment. Comments appear in the tree only if the reader was configured
+    /// with `ignore_comments(false)`.
+    Comment(String),
+
+    /// A processing instruction.
+    ProcessingInstruction {
+        /// Target of the processing instruction.
+        name: String,
+
+        /// Data of the processing instruction, if any.
+        data: Option<String>
+    }
+}
+
+impl From<Element> for Node {
+    #[inline]
+    fn from(element: Element) -> Node {
+        Node::Element(element)
+    }
+}
+
+impl Element {
+    /// Creates a new element with the given name and without attributes and children.
+    pub fn new<N: Into<OwnedName>>(name: N) -> Element {
+        Element {
+            name: name.into(),
+            attributes: Vec::new(),
+            namespace: Namespace::empty(),
+            children: Vec::new()
+        }
+    }
+
+    /// Reads the whole document from the given reader and returns its root element.
+    ///
+    /// This is equivalent to calling `read_to_tree(reader)`. The limits of the reader
+    /// configuration, like `ParserConfig::max_element_depth` and `ParserConfig::max_document_length`,
+    /// apply to the document, so they also limit the size of the tree.
+    #[inline]
+    pub fn parse<R: Read>(reader: EventReader<R>) -> reader::Result<Element> {
+        read_to_tree(reader)
+    }
+
+    /// Returns the value of the attribute with the given local name and no namespace.
+    pub fn attribute(&self, local_name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|attr| attr.name.namespace.is_none() && attr.name.local_name == local_name)
+            .map(|attr| &*attr.value)
+    }
+
+    /// Sets the value of the attribute with the given local name and no namespace, adding
+    /// the attribute if the element does not have it yet.
+    ///
+    /// Returns the previous value of the attribute, if any.
+    pub fn set_attribute<S: Into<String>>(&mut self, local_name: &str, value: S) -> Option<String> {
+        let value = value.into();
+        match self.attributes.iter_mut()
+            .find(|attr| attr.name.namespace.is_none() && attr.name.local_name == local_name) {
+            Some(attr) => Some(mem::replace(&mut attr.value, value)),
+            None => {
+                self.attributes.push(OwnedAttribute::new(OwnedName::local(local_name), value));
+                None
+            }
+        }
+    }
+
+    /// Removes the attribute with the given local name and no namespace.
+    ///
+    /// Returns the value of the removed attribute, if any.
+    pub fn remove_attribute(&mut self, local_name: &str) -> Option<String> {
+        let i = self.attributes.iter()
+            .position(|attr| attr.name.namespace.is_none() && attr.name.local_name == local_name)?;
+        Some(self.attributes.remove(i).value)
+    }
+
+    /// Appends the given node, e.g. an `Element`, to the children of the element.
+    #[inline]
+    pub fn push_child<N: Into<Node>>(&mut self, child: N) {
+        self.children.push(child.into());
+    }
+
+    /// Returns an iterator over the child elements, skipping other kinds of nodes.
+    pub fn child_elements(&self) -> ChildElements<'_> {
+        ChildElements { children: self.children.iter() }
+    }
+
+    /// Returns the first child element with the given local name.
+    pub fn get_child(&self, local_name: &str) -> Option<&Element> {
+        self.child_elements().find(|e| e.name.local_name == local_name)
+    }
+
+    /// Returns a mutable reference to the first child element with the given local name.
+    pub fn get_child_mut(&mut self, local_name: &str) -> Option<&mut Element> {
+        self.children.iter_mut()
+            .filter_map(|child| match *child {
+                Node::Element(ref mut e) => Some(e),
+                _ => None
+            })
+            .find(|e| e.name.local_name == local_name)
+    }
+
+    /// Returns all child elements with the given local name, in document order.
+    pub fn children_named(&self, local_name: &str) -> Vec<&Element> {
+        self.child_elements().filter(|e| e.name.local_name == local_name).collect()
+    }
+
+    /// Returns the first descendant element matching the given path, in document order.
+    ///
+    /// A path is a sequence of steps separated by `/`, each of which selects child elements
+    /// of the elements selected by the previous steps. A step is either the name of the child
+    /// elements, which is their local name or, to also match the prefix, their qualified name
+    /// like `p:name`, or `*`, which selects all child elements. The path is relative
+    /// to this element, so `"b/c"` selects the `c` children of its `b` children:
+    ///
+    /// ```rust
+    /// use xml::EventReader;
+    /// use xml::dom::read_to_tree;
+    ///
+    /// let root = read_to_tree(EventReader::from_str("<a><b><c>1</c></b><b><d/><c>2</c></b></a>")).unwrap();
+    /// assert_eq!(root.find("b/c").map(|e| e.text()), Some("1".into()));
+    /// assert_eq!(root.find_all("*/c").len(), 2);
+    /// assert!(root.find("b/e").is_none());
+    /// ```
+    ///
+    /// Steps may also have predicates, like `b[@id='1']` or `b[2]`; see `Query` for the full
+    /// syntax. Returns `None` if no element matches the path or if the path is malformed,
+    /// e.g. if it contains empty steps or attribute steps; parse the path with `Query::parse()`
+    /// and use `select_first()` to find out what is wrong with it.
+    pub fn find(&self, path: &str) -> Option<&Element> {
+        self.find_all(path).into_iter().next()
+    }
+
+    /// Returns all descendant elements matching the given path, in document order.
+    ///
+    /// See `find()` for the path syntax. Returns an empty vector if no element matches
+    /// the path or if the path is malformed.
+    pub fn find_all(&self, path: &str) -> Vec<&Element> {
+        match Query::parse(path) {
+            Ok(query) => query.select(self),
+            Err(_) => Vec::new()
+        }
+    }
+
+    /// Returns all descendant elements selected by the given query, in document order.
+    #[inline]
+    pub fn select(&self, query: &Query) -> Vec<&Element> {
+        query.select(self)
+    }
+
+    /// Returns the first descendant element selected by the given query, in document order.
+    #[inline]
+    pub fn select_first(&self, query: &Query) -> Option<&Element> {
+        query.select(self).into_iter().next()
+    }
+
+    /// Returns the value of an attribute selected by the given path.
+    ///
+    /// The path consists of the steps described in `find()`, followed by an attribute step
+    /// `@name`, which selects the attribute with the given local name and no namespace; the
+    /// value is taken from the first element in document order which has this attribute.
+    /// A path consisting only of the attribute step selects an attribute of this element.
+    ///
+    /// ```rust
+    /// use xml::EventReader;
+    /// use xml::dom::read_to_tree;
+    ///
+    /// let root = read_to_tree(EventReader::from_str("<a id=\"0\"><b/><b id=\"1\"/></a>")).unwrap();
+    /// assert_eq!(root.find_attribute("@id"), Some("0"));
+    /// assert_eq!(root.find_attribute("b/@id"), Some("1"));
+    /// assert_eq!(root.find_attribute("b/@missing"), None);
+    /// ```
+    ///
+    /// Returns `None` if no attribute matches the path or if it does not end with
+    /// an attribute step.
+    pub fn find_attribute(&self, path: &str) -> Option<&str> {
+        let (elements, attribute) = match path.rfind('/') {
+            Some(i) => (self.find_all(&path[..i]), &path[i + 1..]),
+            None => (vec![self], path)
+        };
+        if !attribute.starts_with('@') {
+            return None;
+        }
+        elements.into_iter().filter_map(|e| e.attribute(&attribute[1..])).next()
+    }
+
+    /// Returns the concatenated text and CDATA content of the element and all of its
+    /// descendants. Entity references are skipped, since their values are not known.
+    pub fn text(&self) -> String {
+        fn collect(element: &Element, result: &mut String) {
+            for child in &element.children {
+                match *child {
+                    Node::Element(ref e) => collect(e, result),
+                    Node::Text(ref s) | Node::CData(ref s) => result.push_str(s),
+                    Node::EntityReference(_) | Node::Comment(_) | Node::ProcessingInstruction { .. } => {}
+                }
+            }
+        }
+
+        let mut result = String::new();
+        collect(self, &mut result);
+        result
+    }
+
+    /// Writes the element and all of its descendants to the given writer.
+    ///
+    /// This is equivalent to calling `write_tree(w, self)`.
+    pub fn write_to<W: Write>(&self, w: &mut EventWriter<W>) -> writer::Result<()> {
+        let attributes: Vec<_> = self.attributes.iter().map(|attr| attr.borrow()).collect();
+        w.write(writer::XmlEvent::StartElement {
+            name: self.name.borrow(),
+            attributes: Cow::Borrowed(&attributes),
+            namespace: Cow::Borrowed(&self.namespace)
+        })?;
+        for child in &self.children {
+            match *child {
+                Node::Element(ref e) => e.write_to(w)?,
+                // whitespace may be replaced by the writer's own indentation
+                Node::Text(ref s) if is_whitespace_str(s) => w.write(writer::XmlEvent::Whitespace(s))?,
+                Node::Text(ref s) => w.write(writer::XmlEvent::Characters(s))?,
+                Node::CData(ref s) => w.write(writer::XmlEvent::CData(s))?,
+                Node::EntityReference(ref s) => w.write(writer::XmlEvent::EntityReference(s))?,
+                Node::Comment(ref s) => w.write(writer::XmlEvent::Comment(s))?,
+                Node::ProcessingInstruction { ref name, ref data } =>
+                    w.write(writer::XmlEvent::ProcessingInstruction {
+                        name,
+                        data: data.as_ref().map(|s| &s[..])
+                    })?
+            }
+        }
+        w.write(writer::XmlEvent::EndElement { name: Some(self.name.borrow()) })
+    }
+}
+
+/// Checks whether a step of a path selects elements with the given name.
+/// An iterator over the child elements of an element, returned by `Element::child_elements()`.
+pub struct ChildElements<'a> {
+    children: ::std::slice::Iter<'a, Node>
+}
+
+impl<'a> Iterator for ChildElements<'a> {
+    type Item = &'a Element;
+
+    fn next(&mut self) -> Option<&'a Element> {
+        for child in &mut self.children {
+            if let Node::Element(ref e) = *child {
+                return Some(e);
+            }
+        }
+        None
+    }
+}
+
+/// Reads the whole document from the given reader and returns its root element.
+///
+/// Comments, processing instructions, whitespace and the document type declaration outside
+/// of the root element are discarded. Text is stored as it is reported by the reader, so adjacent character data may
+/// be split into several nodes, e.g. when the reader is configured not to coalesce
+/// characters.
+pub fn read_to_tree<R: Read>(mut reader: EventReader<R>) -> reader::Result<Element> {
+    // elements which are not finished yet; the last one is the innermost
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root = None;
+
+    loop {
+        let node = match reader.next()? {
+            reader::XmlEvent::EndDocument => break,
+            reader::XmlEvent::StartElement { name, attributes, namespace } => {
+                stack.push(Element { name, attributes, namespace, children: Vec::new() });
+                continue;
+            }
+            reader::XmlEvent::EndElement { .. } => {
+                let element = match stack.pop() {
+                    Some(element) => element,
+                    None => return Err((&reader.position(), "Unexpected end element").into())
+                };
+                Node::Element(element)
+            }
+            reader::XmlEvent::Characters(s) | reader::XmlEvent::Whitespace(s) => Node::Text(s),
+            reader::XmlEvent::CData(s) => Node::CData(s),
+            reader::XmlEvent::EntityReference(s) => Node::EntityReference(s),
+            reader::XmlEvent::Comment(s) => Node::Comment(s),
+            reader::XmlEvent::ProcessingInstruction { name, data } =>
+                Node::ProcessingInstruction { name, data },
+            reader::XmlEvent::StartDocument { .. } | reader::XmlEvent::DoctypeDecl { .. } => continue
+        };
+
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => if let Node::Element(element) = node {
+                root = Some(element);
+            }
+        }
+    }
+
+    root.ok_or_else(|| (&reader.position(), "Document has no root element").into())
+}
+
+/// Writes the given element and all of its descendants to the writer.
+///
+/// The output follows the configuration of the writer, e.g. text is escaped and indentation
+/// is inserted according to it. Whitespace-only text nodes are written as
+/// `XmlEvent::Whitespace`, so they are replaced with the writer's own indentation if it is
+/// enabled, unless they are inside of an `xml:space="preserve"` element. Attributes are
+/// written in the order they are stored in the element, and namespace mappings which
+/// are not yet in scope of the writer are declared on the elements which introduce them.
+///
+/// Only the element is written; the document declaration is written automatically
+/// if the writer is configured to do so.
+#[inline]
+pub fn write_tree<W: Write>(w: &mut EventWriter<W>, element: &Element) -> writer::Result<()> {
+    element.write_to(w)
+}
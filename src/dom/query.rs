@@ -0,0 +1,301 @@
+//! Contains a tiny query language for selecting elements of a tree.
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+
+use common::{is_name_char, is_name_start_char};
+use name::OwnedName;
+use namespace::Namespace;
+
+use super::Element;
+
+/// A parsed query which selects descendant elements of an element.
+///
+/// A query is a sequence of steps separated by `/`, each of which selects child elements
+/// of the elements selected by the previous steps. A step consists of a name test and
+/// any number of predicates in square brackets, which are applied in order:
+///
+/// * the name test is either the local name of the child elements, which matches them
+///   regardless of their namespace, their qualified name like `p:name`, or `*`, which selects
+///   all child elements;
+/// * `[@name]` keeps the elements which have the given attribute, and `[@name='value']` or
+///   `[@name="value"]` the elements where it has the given value. Unprefixed attribute
+///   names denote attributes without a namespace;
+/// * `[n]` keeps the `n`-th of the elements selected so far among the children of each
+///   element, counting from 1.
+///
+/// ```rust
+/// use xml::EventReader;
+/// use xml::dom::{read_to_tree, Query};
+///
+/// let root = read_to_tree(EventReader::from_str(
+///     "<shelf><book><author lang=\"de\">A</author><author lang=\"en\">B</author></book>\
+///      <book><author lang=\"en\">C</author></book></shelf>"
+/// )).unwrap();
+///
+/// let query = Query::parse("book/author[@lang='en']").unwrap();
+/// let authors: Vec<_> = root.select(&query).iter().map(|e| e.text()).collect();
+/// assert_eq!(authors, ["B", "C"]);
+///
+/// let query = Query::parse("book[2]/*").unwrap();
+/// assert_eq!(root.select_first(&query).map(|e| e.text()), Some("C".into()));
+/// ```
+///
+/// This is deliberately not XPath: there are no axes, functions or operators. Prefixes
+/// are matched literally, unless the query is parsed with `Query::parse_with_namespaces()`,
+/// which resolves them to namespace URIs.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Query {
+    steps: Vec<Step>
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Step {
+    name: NameTest,
+    predicates: Vec<Predicate>
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum NameTest {
+    Any,
+    Name(QueryName)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Predicate {
+    Attribute { name: QueryName, value: Option<String> },
+    Position(usize)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct QueryName {
+    local_name: String,
+    prefix: Option<String>,
+    /// Set if the prefix was resolved with a namespace mapping.
+    namespace: Option<String>
+}
+
+impl QueryName {
+    /// Checks whether the given name matches; unprefixed names match elements by their
+    /// local names only, and attributes only if they have no namespace.
+    fn matches(&self, name: &OwnedName, attribute: bool) -> bool {
+        name.local_name == self.local_name && match (&self.namespace, &self.prefix) {
+            (Some(namespace), _) => name.namespace.as_ref() == Some(namespace),
+            (None, Some(prefix)) => name.prefix.as_ref() == Some(prefix),
+            (None, None) => !attribute || name.namespace.is_none()
+        }
+    }
+}
+
+/// An error in the syntax of a query.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct QueryError {
+    offset: usize,
+    msg: Cow<'static, str>
+}
+
+impl QueryError {
+    fn new<M: Into<Cow<'static, str>>>(offset: usize, msg: M) -> QueryError {
+        QueryError { offset, msg: msg.into() }
+    }
+
+    /// Returns the offset of the offending token in the query, in characters counting from 0.
+    #[inline]
+    pub fn offset(&self) -> usize { self.offset }
+
+    /// Returns the message of this error.
+    #[inline]
+    pub fn msg(&self) -> &str { &self.msg }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.msg, self.offset + 1)
+    }
+}
+
+impl error::Error for QueryError {
+    #[inline]
+    fn description(&self) -> &str { &self.msg }
+}
+
+impl Query {
+    /// Parses the given query.
+    pub fn parse(query: &str) -> Result<Query, QueryError> {
+        Parser::new(query, None).parse()
+    }
+
+    /// Parses the given query, resolving prefixes of names with the given namespace mapping.
+    ///
+    /// Prefixed names then match elements and attributes by their namespace URIs, whatever
+    /// prefixes they have in the document. Prefixes which are not bound in the mapping
+    /// are reported as errors.
+    pub fn parse_with_namespaces(query: &str, namespaces: &Namespace) -> Result<Query, QueryError> {
+        Parser::new(query, Some(namespaces)).parse()
+    }
+
+    /// Returns the descendants of the given element selected by this query, in document order.
+    pub fn select<'a>(&self, element: &'a Element) -> Vec<&'a Element> {
+        let mut current = vec![element];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for parent in current {
+                let mut selected: Vec<_> = parent.child_elements().filter(|e| match step.name {
+                    NameTest::Any => true,
+                    NameTest::Name(ref name) => name.matches(&e.name, false)
+                }).collect();
+                for predicate in &step.predicates {
+                    selected = match *predicate {
+                        Predicate::Attribute { ref name, ref value } => selected.into_iter().filter(|e| {
+                            e.attributes.iter().any(|a| name.matches(&a.name, true) && value.as_ref().is_none_or(|v| a.value == *v))
+                        }).collect(),
+                        Predicate::Position(n) => selected.into_iter().nth(n - 1).into_iter().collect()
+                    };
+                }
+                next.extend(selected);
+            }
+            current = next;
+            if current.is_empty() {
+                break;
+            }
+        }
+        current
+    }
+}
+
+struct Parser<'q> {
+    chars: Vec<char>,
+    pos: usize,
+    namespaces: Option<&'q Namespace>
+}
+
+impl<'q> Parser<'q> {
+    fn new(query: &str, namespaces: Option<&'q Namespace>) -> Parser<'q> {
+        Parser { chars: query.chars().collect(), pos: 0, namespaces }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn unexpected(&self, expected: &str) -> QueryError {
+        match self.peek() {
+            Some(c) => QueryError::new(self.pos, format!("Unexpected `{}`, expected {}", c, expected)),
+            None => QueryError::new(self.pos, format!("Unexpected end of query, expected {}", expected))
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), QueryError> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", c)))
+        }
+    }
+
+    fn parse(mut self) -> Result<Query, QueryError> {
+        let mut steps = vec![self.parse_step()?];
+        while self.peek().is_some() {
+            self.expect('/')?;
+            steps.push(self.parse_step()?);
+        }
+        Ok(Query { steps })
+    }
+
+    fn parse_step(&mut self) -> Result<Step, QueryError> {
+        let name = if self.peek() == Some('*') {
+            self.pos += 1;
+            NameTest::Any
+        } else {
+            NameTest::Name(self.parse_name("an element name or `*`")?)
+        };
+        let mut predicates = Vec::new();
+        while self.peek() == Some('[') {
+            self.pos += 1;
+            predicates.push(self.parse_predicate()?);
+            self.expect(']')?;
+        }
+        Ok(Step { name, predicates })
+    }
+
+    fn parse_predicate(&mut self) -> Result<Predicate, QueryError> {
+        match self.peek() {
+            Some('@') => {
+                self.pos += 1;
+                let name = self.parse_name("an attribute name")?;
+                if self.peek() != Some('=') {
+                    return Ok(Predicate::Attribute { name, value: None });
+                }
+                self.pos += 1;
+                let value = self.parse_literal()?;
+                Ok(Predicate::Attribute { name, value: Some(value) })
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+                let digits: String = self.chars[start..self.pos].iter().collect();
+                match digits.parse() {
+                    Ok(0) => Err(QueryError::new(start, "Positions start at 1")),
+                    Ok(n) => Ok(Predicate::Position(n)),
+                    Err(_) => Err(QueryError::new(start, format!("Position `{}` is too large", digits)))
+                }
+            }
+            _ => Err(self.unexpected("`@` or a position"))
+        }
+    }
+
+    fn parse_literal(&mut self) -> Result<String, QueryError> {
+        let quote = match self.peek() {
+            Some(c @ '\'') | Some(c @ '"') => c,
+            _ => return Err(self.unexpected("a quoted value"))
+        };
+        let start = self.pos;
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                Some(c) if c == quote => break,
+                Some(c) => value.push(c),
+                None => return Err(QueryError::new(start, "Unterminated value"))
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+        Ok(value)
+    }
+
+    fn parse_name(&mut self, expected: &str) -> Result<QueryName, QueryError> {
+        let start = self.pos;
+        let first = self.parse_ncname(expected)?;
+        if self.peek() != Some(':') {
+            return Ok(QueryName { local_name: first, prefix: None, namespace: None });
+        }
+        self.pos += 1;
+        let local_name = self.parse_ncname("a local name")?;
+        let namespace = match self.namespaces {
+            Some(namespaces) => match namespaces.get(&first) {
+                Some(uri) => Some(uri.to_owned()),
+                None => return Err(QueryError::new(start, format!("Unknown namespace prefix `{}`", first)))
+            },
+            None => None
+        };
+        Ok(QueryName { local_name, prefix: Some(first), namespace })
+    }
+
+    /// Parses a name without a colon.
+    fn parse_ncname(&mut self, expected: &str) -> Result<String, QueryError> {
+        let start = self.pos;
+        match self.peek() {
+            Some(c) if c != ':' && is_name_start_char(c) => self.pos += 1,
+            _ => return Err(self.unexpected(expected))
+        }
+        while self.peek().is_some_and(|c| c != ':' && is_name_char(c)) {
+            self.pos += 1;
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+}
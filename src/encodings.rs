@@ -0,0 +1,195 @@
+//! Contains support for non-UTF-8 encodings. Available with the `encodings` feature.
+//!
+//! Encodings are represented by the `Encoding` type from the `encoding_rs` crate. An encoding
+//! can be obtained from one of the constants re-exported from this module or by its label:
+//!
+//! ```rust
+//! use xml::encodings::Encoding;
+//!
+//! let latin1 = Encoding::for_label(b"latin1").unwrap();
+//! assert_eq!(latin1.name(), "windows-1252");
+//! ```
+//!
+//! As the example shows, encodings follow the Encoding Standard, which treats ISO-8859-1 as
+//! an alias of windows-1252; the two differ in the bytes 0x80 to 0x9F. Documents in true
+//! ISO-8859-1 can be read with `reader::Latin1Decoder` instead.
+//!
+//! Documents in these encodings can be read with the decoders of the encodings, which
+//! implement `reader::Decoder`:
+//!
+//! ```rust
+//! use xml::reader::{EventReader, ParserConfig, XmlEvent};
+//! use xml::encodings::Encoding;
+//!
+//! let gb18030 = Encoding::for_label(b"gb18030").unwrap();
+//! let source = &b"<?xml version='1.0' encoding='GB18030'?><a>\xD6\xD0\xCE\xC4</a>"[..];
+//! let mut reader = EventReader::new_with_decoder(source, gb18030.new_decoder(), ParserConfig::new());
+//! reader.next().unwrap();
+//! reader.next().unwrap();
+//! assert_eq!(reader.next().unwrap(), XmlEvent::Characters("\u{4E2D}\u{6587}".into()));
+//! ```
+
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::io;
+
+use encoding_rs::{Decoder, DecoderResult, Encoder, EncoderResult};
+
+use reader;
+
+pub use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252, SHIFT_JIS};
+
+/// Checks whether the given encoding is one of the UTF-16 variants.
+#[inline]
+pub fn is_utf16(encoding: &'static Encoding) -> bool {
+    encoding == UTF_16LE || encoding == UTF_16BE
+}
+
+/// Checks whether the given encoding is able to represent every Unicode character.
+#[inline]
+pub fn is_unicode(encoding: &'static Encoding) -> bool {
+    encoding == UTF_8 || is_utf16(encoding)
+}
+
+/// Returns the first character of the given string which cannot be represented in
+/// the given encoding, if any.
+pub fn find_unencodable(encoding: &'static Encoding, s: &str) -> Option<char> {
+    if is_unicode(encoding) || s.is_ascii() {
+        return None;
+    }
+    let mut encoder = encoding.new_encoder();
+    let mut buf = Vec::with_capacity(s.len() * 2);
+    match encoder.encode_from_utf8_to_vec_without_replacement(s, &mut buf, false).0 {
+        EncoderResult::Unmappable(c) => Some(c),
+        _ => None
+    }
+}
+
+/// Converts UTF-8 output of the emitter into the target encoding.
+///
+/// Characters which cannot be represented in the target encoding are written as
+/// hexadecimal character references, e.g. `&#x263A;`.
+pub struct OutputEncoder {
+    encoding: &'static Encoding,
+    encoder: Encoder
+}
+
+impl OutputEncoder {
+    /// Creates a new encoder for the given target encoding.
+    pub fn new(encoding: &'static Encoding) -> OutputEncoder {
+        OutputEncoder {
+            encoding,
+            encoder: encoding.new_encoder()
+        }
+    }
+
+    /// Encodes the given string, appending the result to `target`.
+    pub fn encode(&mut self, mut input: &str, target: &mut Vec<u8>) {
+        if self.encoding == UTF_16LE {
+            for u in input.encode_utf16() {
+                target.extend_from_slice(&[u as u8, (u >> 8) as u8]);
+            }
+            return;
+        }
+        if self.encoding == UTF_16BE {
+            for u in input.encode_utf16() {
+                target.extend_from_slice(&[(u >> 8) as u8, u as u8]);
+            }
+            return;
+        }
+
+        loop {
+            let needed = self.encoder.max_buffer_length_from_utf8_without_replacement(input.len())
+                .unwrap_or(input.len() * 4);
+            target.reserve(needed);
+
+            let (result, read) = self.encoder.encode_from_utf8_to_vec_without_replacement(input, target, false);
+            input = &input[read..];
+            match result {
+                EncoderResult::InputEmpty => break,
+                EncoderResult::OutputFull => {}
+                EncoderResult::Unmappable(c) => {
+                    let mut reference = String::new();
+                    write!(reference, "&#x{:X};", c as u32).unwrap();
+                    target.extend_from_slice(reference.as_bytes());
+                }
+            }
+        }
+    }
+}
+
+/// Decodes the input with the `encoding_rs` decoder, which sniffs the byte order mark unless
+/// it was created with a method like `Encoding::new_decoder_without_bom_handling()`.
+/// Malformed sequences are errors.
+impl reader::Decoder for Decoder {
+    fn decode<'a>(&mut self, mut bytes: &'a [u8], last: bool) -> io::Result<Cow<'a, str>> {
+        let mut result = String::new();
+        loop {
+            let needed = self.max_utf8_buffer_length_without_replacement(bytes.len())
+                .unwrap_or(bytes.len() * 3 + 16);
+            result.reserve(needed);
+            let (status, read) = self.decode_to_string_without_replacement(bytes, &mut result, last);
+            bytes = &bytes[read..];
+            match status {
+                DecoderResult::InputEmpty => return Ok(result.into()),
+                DecoderResult::OutputFull => {}
+                DecoderResult::Malformed(..) => return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed {} sequence", Decoder::encoding(self).name())
+                ))
+            }
+        }
+    }
+
+    #[inline]
+    fn encoding(&self) -> &str { Decoder::encoding(self).name() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_unencodable, OutputEncoder, UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252, SHIFT_JIS};
+    use reader::Decoder;
+
+    fn encode(encoding: &'static super::Encoding, s: &str) -> Vec<u8> {
+        let mut result = Vec::new();
+        OutputEncoder::new(encoding).encode(s, &mut result);
+        result
+    }
+
+    #[test]
+    fn test_encode_utf16() {
+        assert_eq!(encode(UTF_16LE, "a☺"), vec![b'a', 0, 0x3A, 0x26]);
+        assert_eq!(encode(UTF_16BE, "a☺"), vec![0, b'a', 0x26, 0x3A]);
+    }
+
+    #[test]
+    fn test_encode_unmappable_as_references() {
+        assert_eq!(encode(WINDOWS_1252, "é☺"), b"\xE9&#x263A;".to_vec());
+        assert_eq!(encode(SHIFT_JIS, "あ☺x"), b"\x82\xA0&#x263A;x".to_vec());
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut decoder = SHIFT_JIS.new_decoder();
+        // a character split between chunks
+        assert_eq!(decoder.decode(b"x\x82", false).unwrap(), "x");
+        assert_eq!(decoder.decode(b"\xA0y", false).unwrap(), "あy");
+        assert_eq!(decoder.decode(b"", true).unwrap(), "");
+        assert_eq!(Decoder::encoding(&decoder), "Shift_JIS");
+
+        let mut decoder = UTF_8.new_decoder();
+        assert_eq!(decoder.decode(b"\xEF\xBB\xBFa\xE2\x98", false).unwrap(), "a");
+        assert!(decoder.decode(b"", true).is_err());
+        assert!(UTF_16LE.new_decoder().decode(b"a\x00\x00\xDC", true).is_err());
+    }
+
+    #[test]
+    fn test_find_unencodable() {
+        assert_eq!(find_unencodable(WINDOWS_1252, "abc"), None);
+        assert_eq!(find_unencodable(WINDOWS_1252, "é€"), None);
+        assert_eq!(find_unencodable(WINDOWS_1252, "é☃☺"), Some('☃'));
+        assert_eq!(find_unencodable(SHIFT_JIS, "あé"), Some('é'));
+        assert_eq!(find_unencodable(UTF_8, "☃"), None);
+        assert_eq!(find_unencodable(UTF_16LE, "☃"), None);
+    }
+}
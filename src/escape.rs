@@ -1,7 +1,19 @@
 //! Contains functions for performing XML special characters escaping.
+//!
+//! This module only depends on `alloc` and is available in `no_std` builds as well.
+//!
+//! With the `fast-escape` feature enabled, the built-in escaping functions search for
+//! characters which need escaping several bytes at a time and copy the text between them
+//! in bulk, which is considerably faster for long strings with few escapable characters.
 
+#[cfg(feature = "std")]
 use std::borrow::Cow;
 
+#[cfg(not(feature = "std"))]
+use alloc::borrow::Cow;
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+
 enum Value {
     Char(char),
     Str(&'static str)
@@ -21,6 +33,42 @@ impl Value {
         }
     }
 
+    fn dispatch_for_quoted_attribute(c: char, quote: char) -> Value {
+        match c {
+            '"' | '\'' if c != quote => Value::Char(c),
+            _ => Value::dispatch_for_attribute(c)
+        }
+    }
+
+    fn dispatch_with_extra<F: Fn(char) -> Value>(c: char, extra: &[(char, &'static str)], dispatch: F) -> Value {
+        match extra.iter().find(|&&(e, _)| e == c) {
+            Some(&(_, replacement)) => Value::Str(replacement),
+            None => dispatch(c)
+        }
+    }
+
+    fn dispatch_for_canonical_attribute(c: char) -> Value {
+        match c {
+            '<'  => Value::Str("&lt;"),
+            '"'  => Value::Str("&quot;"),
+            '&'  => Value::Str("&amp;"),
+            '\t' => Value::Str("&#x9;"),
+            '\n' => Value::Str("&#xA;"),
+            '\r' => Value::Str("&#xD;"),
+            _    => Value::Char(c)
+        }
+    }
+
+    fn dispatch_for_canonical_pcdata(c: char) -> Value {
+        match c {
+            '<'  => Value::Str("&lt;"),
+            '>'  => Value::Str("&gt;"),
+            '&'  => Value::Str("&amp;"),
+            '\r' => Value::Str("&#xD;"),
+            _    => Value::Char(c)
+        }
+    }
+
     fn dispatch_for_pcdata(c: char) -> Value {
         match c {
             '<'  => Value::Str("&lt;"),
@@ -70,12 +118,102 @@ impl<'a> Extend<(usize, Value)> for Process<'a> {
     }
 }
 
-fn escape_str(s: &str, dispatch: fn(char) -> Value) -> Cow<str> {
+fn escape_str<F: Fn(char) -> Value>(s: &str, dispatch: F) -> Cow<'_, str> {
     let mut p = Process::Borrowed(s);
     p.extend(s.char_indices().map(|(ind, c)| (ind, dispatch(c))));
     p.into_result()
 }
 
+fn escape_str_into<F: Fn(char) -> Value>(buf: &mut String, s: &str, dispatch: F) {
+    let mut copied = 0;
+    for (i, c) in s.char_indices() {
+        if let Value::Str(replacement) = dispatch(c) {
+            buf.push_str(&s[copied..i]);
+            buf.push_str(replacement);
+            copied = i + c.len_utf8();
+        }
+    }
+    buf.push_str(&s[copied..]);
+}
+
+/// Characters which may be escaped inside attribute values.
+const ATTRIBUTE_BYTES: &[u8] = b"<>\"'&\n\r";
+
+/// Characters which may be escaped inside PCDATA.
+const PCDATA_BYTES: &[u8] = b"<&";
+
+/// Characters which are escaped inside attribute values in canonical XML.
+const CANONICAL_ATTRIBUTE_BYTES: &[u8] = b"<\"&\t\n\r";
+
+/// Characters which are escaped inside PCDATA in canonical XML.
+const CANONICAL_PCDATA_BYTES: &[u8] = b"<>&\r";
+
+/// Escapes a string whose escapable characters are all listed in `needles`.
+///
+/// With the `fast-escape` feature the string is scanned for the next occurrence of any of
+/// `needles` a machine word at a time, and runs of characters between them are copied in bulk;
+/// `dispatch` is only called for the found characters. Otherwise, this is the same as
+/// `escape_str()`.
+#[cfg(feature = "fast-escape")]
+fn escape_str_ascii<'a, F: Fn(char) -> Value>(s: &'a str, needles: &[u8], dispatch: F) -> Cow<'a, str> {
+    // all needles are ASCII, and ASCII bytes never occur inside multi-byte UTF-8 sequences,
+    // so every found position is a character boundary
+    let bytes = s.as_bytes();
+    let mut result = String::new();
+    let mut copied = 0;
+    let mut pos = 0;
+    while let Some(i) = find_any(&bytes[pos..], needles) {
+        let i = pos + i;
+        pos = i + 1;
+        if let Value::Str(replacement) = dispatch(bytes[i] as char) {
+            if copied == 0 {
+                result.reserve(s.len() + replacement.len());
+            }
+            result.push_str(&s[copied..i]);
+            result.push_str(replacement);
+            copied = pos;
+        }
+    }
+    if copied == 0 {
+        Cow::Borrowed(s)
+    } else {
+        result.push_str(&s[copied..]);
+        Cow::Owned(result)
+    }
+}
+
+#[cfg(not(feature = "fast-escape"))]
+#[inline]
+fn escape_str_ascii<'a, F: Fn(char) -> Value>(s: &'a str, needles: &[u8], dispatch: F) -> Cow<'a, str> {
+    escape_str(s, dispatch)
+}
+
+/// Returns the index of the first byte of `haystack` which is equal to any of `needles`.
+///
+/// The haystack is checked eight bytes at a time, using the usual trick of detecting a zero
+/// byte in a word after XOR-ing it with each needle repeated over the word.
+#[cfg(feature = "fast-escape")]
+fn find_any(haystack: &[u8], needles: &[u8]) -> Option<usize> {
+    const LO: u64 = 0x0101_0101_0101_0101;
+    const HI: u64 = 0x8080_8080_8080_8080;
+
+    let mut offset = 0;
+    for chunk in haystack.chunks_exact(8) {
+        let mut word = [0; 8];
+        word.copy_from_slice(chunk);
+        let word = u64::from_le_bytes(word);
+        let found = needles.iter().any(|&n| {
+            let v = word ^ (LO * n as u64);
+            v.wrapping_sub(LO) & !v & HI != 0
+        });
+        if found {
+            break;
+        }
+        offset += 8;
+    }
+    haystack[offset..].iter().position(|b| needles.contains(b)).map(|i| offset + i)
+}
+
 /// Performs escaping of common XML characters inside an attribute value.
 ///
 /// This function replaces several important markup characters with their
@@ -92,7 +230,60 @@ fn escape_str(s: &str, dispatch: fn(char) -> Value) -> Cow<str> {
 /// Does not perform allocations if the given string does not contain escapable characters.
 #[inline]
 pub fn escape_str_attribute(s: &str) -> Cow<str> {
-    escape_str(s, Value::dispatch_for_attribute)
+    escape_str_ascii(s, ATTRIBUTE_BYTES, Value::dispatch_for_attribute)
+}
+
+/// Quote character used to delimit attribute values.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum QuoteStyle {
+    /// Attribute values are delimited by double quotes, `name="value"`.
+    Double,
+
+    /// Attribute values are delimited by single quotes, `name='value'`.
+    Single,
+
+    /// Each attribute value is delimited by whichever quote character does not need to be
+    /// escaped inside it; double quotes are used when the value contains both or neither.
+    Smart
+}
+
+impl QuoteStyle {
+    /// Returns the quote character which should be used to delimit the given value.
+    ///
+    /// ```rust
+    /// use xml::escape::QuoteStyle;
+    ///
+    /// assert_eq!(QuoteStyle::Double.quote_for("it's"), '"');
+    /// assert_eq!(QuoteStyle::Single.quote_for("it's"), '\'');
+    /// assert_eq!(QuoteStyle::Smart.quote_for("say \"hi\""), '\'');
+    /// assert_eq!(QuoteStyle::Smart.quote_for("it's \"hi\""), '"');
+    /// ```
+    pub fn quote_for(self, value: &str) -> char {
+        match self {
+            QuoteStyle::Double => '"',
+            QuoteStyle::Single => '\'',
+            QuoteStyle::Smart => if value.contains('"') && !value.contains('\'') { '\'' } else { '"' }
+        }
+    }
+}
+
+/// Performs escaping of common XML characters inside an attribute value delimited
+/// by the given quote character.
+///
+/// This function works like `escape_str_attribute()`, except that only the given quote
+/// character is replaced with an entity; the other one is left as is:
+///
+/// ```rust
+/// use xml::escape::escape_str_attribute_quoted;
+///
+/// assert_eq!(escape_str_attribute_quoted("'a' \"b\"", '"'), "'a' &quot;b&quot;");
+/// assert_eq!(escape_str_attribute_quoted("'a' \"b\"", '\''), "&apos;a&apos; \"b\"");
+/// ```
+///
+/// Does not perform allocations if the given string does not contain escapable characters.
+#[inline]
+pub fn escape_str_attribute_quoted(s: &str, quote: char) -> Cow<'_, str> {
+    escape_str_ascii(s, ATTRIBUTE_BYTES, |c| Value::dispatch_for_quoted_attribute(c, quote))
 }
 
 /// Performs escaping of common XML characters inside PCDATA.
@@ -103,17 +294,200 @@ pub fn escape_str_attribute(s: &str) -> Cow<str> {
 /// * `<` → `&lt;`
 /// * `&` → `&amp;`
 ///
+/// Additionally, `>` is replaced with `&gt;` in every occurrence of `]]>`, which is not
+/// allowed in character data.
+///
 /// The resulting string is safe to use inside PCDATA sections but NOT inside attribute values.
 ///
 /// Does not perform allocations if the given string does not contain escapable characters.
 #[inline]
 pub fn escape_str_pcdata(s: &str) -> Cow<str> {
-    escape_str(s, Value::dispatch_for_pcdata)
+    escape_cdata_end(escape_str_ascii(s, PCDATA_BYTES, Value::dispatch_for_pcdata))
+}
+
+/// Replaces `>` with `&gt;` in every occurrence of `]]>`.
+fn escape_cdata_end(s: Cow<str>) -> Cow<str> {
+    if s.contains("]]>") {
+        Cow::Owned(s.replace("]]>", "]]&gt;"))
+    } else {
+        s
+    }
+}
+
+/// Performs escaping of an attribute value as required by [Canonical XML][1].
+///
+/// This function replaces the following characters with their references:
+///
+/// * `<` → `&lt;`
+/// * `"` → `&quot;`
+/// * `&` → `&amp;`
+/// * tab → `&#x9;`
+/// * line feed → `&#xA;`
+/// * carriage return → `&#xD;`
+///
+/// The resulting string is only safe to use inside attribute values delimited by double quotes.
+///
+/// Does not perform allocations if the given string does not contain escapable characters.
+///
+/// [1]: https://www.w3.org/TR/xml-c14n/#ProcessingModel
+#[inline]
+pub fn escape_str_canonical_attribute(s: &str) -> Cow<'_, str> {
+    escape_str_ascii(s, CANONICAL_ATTRIBUTE_BYTES, Value::dispatch_for_canonical_attribute)
+}
+
+/// Performs escaping of PCDATA as required by [Canonical XML][1].
+///
+/// This function replaces the following characters with their references:
+///
+/// * `<` → `&lt;`
+/// * `>` → `&gt;`
+/// * `&` → `&amp;`
+/// * carriage return → `&#xD;`
+///
+/// Does not perform allocations if the given string does not contain escapable characters.
+///
+/// [1]: https://www.w3.org/TR/xml-c14n/#ProcessingModel
+#[inline]
+pub fn escape_str_canonical_pcdata(s: &str) -> Cow<'_, str> {
+    escape_str_ascii(s, CANONICAL_PCDATA_BYTES, Value::dispatch_for_canonical_pcdata)
+}
+
+/// Performs escaping of common XML characters inside an attribute value, additionally
+/// replacing the characters from `extra` with the corresponding strings.
+///
+/// This function works like `escape_str_attribute()`, but the characters listed in `extra`
+/// are replaced with the given strings, which take precedence over the built-in entities.
+/// The list is scanned linearly for every character, which is faster than a hash map lookup
+/// for the typical case of no or a handful of custom entities:
+///
+/// ```rust
+/// use xml::escape::escape_str_attribute_with_extra;
+///
+/// let extra = [('\u{A0}', "&#xA0;"), ('\'', "&#39;")];
+/// assert_eq!(escape_str_attribute_with_extra("a\u{A0}'b'", &extra), "a&#xA0;&#39;b&#39;");
+/// ```
+///
+/// Does not perform allocations if the given string does not contain escapable characters.
+pub fn escape_str_attribute_with_extra<'a>(s: &'a str, extra: &[(char, &'static str)]) -> Cow<'a, str> {
+    escape_str(s, |c| Value::dispatch_with_extra(c, extra, Value::dispatch_for_attribute))
+}
+
+/// Performs escaping of common XML characters inside PCDATA, additionally replacing
+/// the characters from `extra` with the corresponding strings.
+///
+/// This function works like `escape_str_pcdata()`, but the characters listed in `extra`
+/// are replaced with the given strings, like in `escape_str_attribute_with_extra()`:
+///
+/// ```rust
+/// use xml::escape::escape_str_pcdata_with_extra;
+///
+/// assert_eq!(escape_str_pcdata_with_extra("a > b", &[('>', "&gt;")]), "a &gt; b");
+/// ```
+///
+/// Does not perform allocations if the given string does not contain escapable characters.
+pub fn escape_str_pcdata_with_extra<'a>(s: &'a str, extra: &[(char, &'static str)]) -> Cow<'a, str> {
+    escape_cdata_end(escape_str(s, |c| Value::dispatch_with_extra(c, extra, Value::dispatch_for_pcdata)))
+}
+
+/// Performs escaping of common XML characters inside an attribute value delimited by the given
+/// quote character, additionally replacing the characters from `extra` with the corresponding
+/// strings.
+///
+/// This function combines `escape_str_attribute_quoted()` and
+/// `escape_str_attribute_with_extra()`; the strings from `extra` take precedence over
+/// the built-in entities, including the one for the quote character:
+///
+/// ```rust
+/// use xml::escape::escape_str_attribute_quoted_with_extra;
+///
+/// let extra = [('\u{A0}', "&#160;")];
+/// assert_eq!(escape_str_attribute_quoted_with_extra("'a\u{A0}b'", '"', &extra), "'a&#160;b'");
+/// ```
+///
+/// Does not perform allocations if the given string does not contain escapable characters.
+pub fn escape_str_attribute_quoted_with_extra<'a>(s: &'a str, quote: char, extra: &[(char, &'static str)]) -> Cow<'a, str> {
+    escape_str(s, |c| Value::dispatch_with_extra(c, extra, |c| Value::dispatch_for_quoted_attribute(c, quote)))
+}
+
+/// Escapes the given attribute value like `escape_str_attribute_with_extra()`, appending
+/// the result to `buf`.
+///
+/// The buffer is not cleared beforehand, so it can be reused for escaping many strings,
+/// amortizing allocations:
+///
+/// ```rust
+/// use xml::escape::escape_str_attribute_into;
+///
+/// let mut buf = String::new();
+/// for value in &["a < b", "plain"] {
+///     buf.clear();
+///     escape_str_attribute_into(&mut buf, value, &[]);
+///     assert!(!buf.contains('<'));
+/// }
+/// assert_eq!(buf, "plain");
+/// ```
+pub fn escape_str_attribute_into(buf: &mut String, s: &str, extra: &[(char, &'static str)]) {
+    escape_str_into(buf, s, |c| Value::dispatch_with_extra(c, extra, Value::dispatch_for_attribute))
+}
+
+/// Escapes the given PCDATA like `escape_str_pcdata_with_extra()`, appending the result
+/// to `buf`.
+///
+/// The buffer is not cleared beforehand, so it can be reused for escaping many strings,
+/// amortizing allocations.
+pub fn escape_str_pcdata_into(buf: &mut String, s: &str, extra: &[(char, &'static str)]) {
+    let start = buf.len();
+    escape_str_into(buf, s, |c| Value::dispatch_with_extra(c, extra, Value::dispatch_for_pcdata));
+    if buf[start..].contains("]]>") {
+        let escaped = buf[start..].replace("]]>", "]]&gt;");
+        buf.truncate(start);
+        buf.push_str(&escaped);
+    }
+}
+
+/// Makes the given string safe to use as the content of a comment.
+///
+/// Comments can't contain `--` and can't end with `-`, and there is no way to escape
+/// characters inside them, so this function inserts a space after every `-` which is
+/// followed by another `-` or which ends the string:
+///
+/// ```rust
+/// use xml::escape::escape_str_comment;
+///
+/// assert_eq!(escape_str_comment("a -- b"), "a - - b");
+/// assert_eq!(escape_str_comment("---"), "- - - ");
+/// assert_eq!(escape_str_comment("a - b"), "a - b");
+/// ```
+///
+/// Does not perform allocations if the given string is already a valid comment.
+pub fn escape_str_comment(s: &str) -> Cow<'_, str> {
+    if !s.contains("--") && !s.ends_with('-') {
+        return Cow::Borrowed(s);
+    }
+
+    let mut result = String::with_capacity(s.len() + 2);
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        result.push(c);
+        if c == '-' && matches!(chars.peek(), None | Some('-')) {
+            result.push(' ');
+        }
+    }
+    Cow::Owned(result)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{escape_str_pcdata, escape_str_attribute};
+    use super::Cow;
+    #[cfg(not(feature = "std"))]
+    use alloc::string::String;
+
+    use super::{escape_str_pcdata, escape_str_attribute, escape_str_comment};
+    use super::{escape_str_pcdata_with_extra, escape_str_attribute_with_extra};
+    use super::escape_str_attribute_quoted_with_extra;
+    use super::{escape_str_pcdata_into, escape_str_attribute_into};
+    use super::{escape_str_canonical_pcdata, escape_str_canonical_attribute};
+    use super::{escape_str, escape_str_ascii, Value, ATTRIBUTE_BYTES, PCDATA_BYTES};
 
     // TODO: add more tests
 
@@ -122,5 +496,80 @@ mod tests {
         assert_eq!(escape_str_attribute("☃<"), "☃&lt;");
         assert_eq!(escape_str_pcdata("☃<"), "☃&lt;");
     }
-}
 
+    #[test]
+    fn test_escape_canonical() {
+        assert_eq!(escape_str_canonical_pcdata("a\r\n<>&\"'\t"), "a&#xD;\n&lt;&gt;&amp;\"'\t");
+        assert_eq!(escape_str_canonical_attribute("a\r\n<>&\"'\t"), "a&#xD;&#xA;&lt;>&amp;&quot;'&#x9;");
+    }
+
+    #[test]
+    fn test_escape_cdata_end_in_pcdata() {
+        assert_eq!(escape_str_pcdata("a]]>b]]]>"), "a]]&gt;b]]]&gt;");
+        assert_eq!(escape_str_pcdata("a] ]>"), "a] ]>");
+        assert_eq!(escape_str_pcdata_with_extra("]]>", &[]), "]]&gt;");
+
+        let mut buf = String::from("]]");
+        escape_str_pcdata_into(&mut buf, "]]>", &[]);
+        assert_eq!(buf, "]]]]&gt;");
+    }
+
+    #[test]
+    fn test_escape_comment() {
+        assert_eq!(escape_str_comment("plain-text"), "plain-text");
+        assert_eq!(escape_str_comment("--"), "- - ");
+        assert_eq!(escape_str_comment("a--->"), "a- - ->");
+        assert_eq!(escape_str_comment("end-"), "end- ");
+    }
+
+    #[test]
+    fn test_escape_with_extra() {
+        let extra = [('é', "&#xE9;"), ('&', "&#38;")];
+        assert_eq!(escape_str_pcdata_with_extra("é & <", &extra), "&#xE9; &#38; &lt;");
+        assert_eq!(escape_str_attribute_with_extra("é\"", &extra), "&#xE9;&quot;");
+        assert_eq!(escape_str_attribute_quoted_with_extra("é'\"", '\'', &extra), "&#xE9;&apos;\"");
+        assert_eq!(escape_str_attribute_quoted_with_extra("'\"", '\'', &[('\'', "&#39;")]), "&#39;\"");
+
+        match escape_str_pcdata_with_extra("plain", &extra) {
+            Cow::Borrowed(s) => assert_eq!(s, "plain"),
+            Cow::Owned(_) => panic!("unexpected allocation")
+        }
+        assert_eq!(escape_str_pcdata_with_extra("a<", &[]), escape_str_pcdata("a<"));
+    }
+
+    #[test]
+    fn test_escape_ascii_matches_scalar() {
+        let inputs = [
+            "", "plain text", "<", "a&b", "☃<☃&☃", "0123456<89abcdef&", "ends with &",
+            "long text without anything to escape, spanning several words", "'\"\n\r<>&'\"\n\r<>&",
+        ];
+        for input in &inputs {
+            assert_eq!(escape_str_ascii(input, ATTRIBUTE_BYTES, Value::dispatch_for_attribute),
+                       escape_str(input, Value::dispatch_for_attribute));
+            assert_eq!(escape_str_ascii(input, PCDATA_BYTES, Value::dispatch_for_pcdata),
+                       escape_str(input, Value::dispatch_for_pcdata));
+            let quoted = |c| Value::dispatch_for_quoted_attribute(c, '\'');
+            assert_eq!(escape_str_ascii(input, ATTRIBUTE_BYTES, quoted), escape_str(input, quoted));
+        }
+
+        match escape_str_ascii("a long string with multibyte ☃ characters", PCDATA_BYTES, Value::dispatch_for_pcdata) {
+            Cow::Borrowed(_) => {}
+            Cow::Owned(_) => panic!("unexpected allocation")
+        }
+    }
+
+    #[test]
+    fn test_escape_into() {
+        let mut buf = String::from("prefix ");
+        escape_str_attribute_into(&mut buf, "☃\"<", &[]);
+        assert_eq!(buf, "prefix ☃&quot;&lt;");
+
+        buf.clear();
+        escape_str_pcdata_into(&mut buf, "é & <x>", &[('é', "&#xE9;")]);
+        assert_eq!(buf, "&#xE9; &amp; &lt;x>");
+
+        buf.clear();
+        escape_str_pcdata_into(&mut buf, "plain", &[]);
+        assert_eq!(buf, "plain");
+    }
+}
@@ -0,0 +1,193 @@
+//! Contains a conversion of documents into JSON values. Available with the `json` feature.
+//!
+//! `to_json()` reads a document from an `EventReader` and maps it to a `serde_json::Value`
+//! which contains a single key for the root element:
+//!
+//! ```rust
+//! use xml::EventReader;
+//! use xml::json::{to_json, JsonOptions};
+//!
+//! let reader = EventReader::from_str("<list kind='colors'><item>red</item><item>green</item><empty/></list>");
+//! let value = to_json(reader, &JsonOptions::new()).unwrap();
+//! assert_eq!(value.to_string(), r#"{"list":{"@kind":"colors","empty":null,"item":["red","green"]}}"#);
+//! ```
+//!
+//! The mapping is the following:
+//!
+//! * An element without attributes and child elements is mapped to its text, or to `null`
+//!   if it has no text at all.
+//! * Any other element is mapped to an object. Its attributes are mapped to keys with
+//!   the attribute prefix, `"@"` by default, and its child elements are mapped to keys
+//!   with their names. Child elements with the same name are collected into an array,
+//!   in document order.
+//! * The text of an element which has attributes or child elements is mapped to the text key,
+//!   `"#text"` by default. In mixed content, where text and child elements alternate, each
+//!   piece of text between child elements is a separate string, and the text key holds
+//!   an array of these strings in document order. The order of text relative to the child
+//!   elements is not preserved.
+//! * Whitespace-only text, as reported by `XmlEvent::Whitespace` events, is ignored. CDATA
+//!   sections are mapped like text. Comments and processing instructions are ignored.
+//! * Names are used as they appear in the document, i.e. with their prefixes, like `"xs:element"`
+//!   or `"@xlink:href"`. Namespace declarations are mapped like attributes, with keys like
+//!   `"@xmlns"` or `"@xmlns:xs"`, on the elements which declare them, so namespace URIs can
+//!   still be looked up.
+//!
+//! Keys of objects are sorted, so the same document is always mapped to the same value.
+//! If an attribute and a child element are mapped to the same key, which is only possible
+//! with an empty attribute prefix, all of their values are collected into an array, attributes
+//! first.
+
+use std::io::Read;
+
+use serde_json::{Map, Value};
+
+use name::OwnedName;
+use namespace::{Namespace, NamespaceStack};
+use reader::{EventReader, Result, XmlEvent};
+
+/// Options of the conversion into JSON.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct JsonOptions {
+    /// The prefix of keys of attributes and namespace declarations. Default is `"@"`.
+    pub attribute_prefix: String,
+
+    /// The key of text in objects of elements with attributes or child elements.
+    /// Default is `"#text"`.
+    pub text_key: String
+}
+
+impl JsonOptions {
+    /// Returns options with default values.
+    #[inline]
+    pub fn new() -> JsonOptions {
+        JsonOptions {
+            attribute_prefix: "@".into(),
+            text_key: "#text".into()
+        }
+    }
+}
+
+impl Default for JsonOptions {
+    #[inline]
+    fn default() -> JsonOptions { JsonOptions::new() }
+}
+
+gen_setters!(JsonOptions,
+    attribute_prefix: into String,
+    text_key: into String
+);
+
+/// An element whose end element has not been read yet.
+struct OpenElement {
+    key: String,
+    namespace: Namespace,
+    // attributes and child elements, in document order
+    entries: Vec<(String, Value)>,
+    texts: Vec<String>,
+    // whether text continues the last piece of text in `texts`
+    in_text: bool
+}
+
+impl OpenElement {
+    fn into_value(self, options: &JsonOptions) -> Value {
+        let OpenElement { mut entries, mut texts, .. } = self;
+        if entries.is_empty() {
+            return match texts.pop() {
+                None => Value::Null,
+                Some(text) => Value::String(text)
+            };
+        }
+        match texts.len() {
+            0 => {}
+            1 => entries.push((options.text_key.clone(), Value::String(texts.pop().unwrap()))),
+            _ => entries.push((options.text_key.clone(), texts.into_iter().map(Value::String).collect()))
+        }
+        Value::Object(collect_entries(entries))
+    }
+}
+
+/// Reads the document and converts it into a JSON value; see the module documentation.
+///
+/// Returns an error if the document is malformed. If the reader allows multiple root elements,
+/// they are all mapped to keys of the returned object, just like child elements.
+pub fn to_json<R: Read>(mut reader: EventReader<R>, options: &JsonOptions) -> Result<Value> {
+    let mut roots = Vec::new();
+    let mut open: Vec<OpenElement> = Vec::new();
+    let document_namespace = NamespaceStack::default().squash();
+    loop {
+        match reader.next()? {
+            XmlEvent::EndDocument => break,
+            XmlEvent::StartElement { name, attributes, namespace } => {
+                let mut entries = Vec::new();
+                {
+                    let parent = open.last().map_or(&document_namespace, |e| &e.namespace);
+                    for (prefix, uri) in &namespace {
+                        if parent.get(prefix) != Some(uri) {
+                            let key = if prefix.is_empty() { "xmlns".to_owned() } else { format!("xmlns:{}", prefix) };
+                            entries.push((format!("{}{}", options.attribute_prefix, key), Value::String(uri.into())));
+                        }
+                    }
+                }
+                for attribute in attributes {
+                    entries.push((format!("{}{}", options.attribute_prefix, key(&attribute.name)),
+                                  Value::String(attribute.value)));
+                }
+                if let Some(parent) = open.last_mut() {
+                    parent.in_text = false;
+                }
+                open.push(OpenElement {
+                    key: key(&name),
+                    namespace,
+                    entries,
+                    texts: Vec::new(),
+                    in_text: false
+                });
+            }
+            XmlEvent::EndElement { .. } => {
+                let element = open.pop().unwrap();
+                let key = element.key.clone();
+                let value = element.into_value(options);
+                match open.last_mut() {
+                    Some(parent) => parent.entries.push((key, value)),
+                    None => roots.push((key, value))
+                }
+            }
+            XmlEvent::Characters(data) | XmlEvent::CData(data) => if let Some(element) = open.last_mut() {
+                if element.in_text {
+                    element.texts.last_mut().unwrap().push_str(&data);
+                } else {
+                    element.texts.push(data);
+                    element.in_text = true;
+                }
+            },
+            _ => {}
+        }
+    }
+    Ok(Value::Object(collect_entries(roots)))
+}
+
+fn key(name: &OwnedName) -> String {
+    name.borrow().to_repr()
+}
+
+/// Builds an object from the entries, collecting the values of repeated keys into arrays.
+fn collect_entries(entries: Vec<(String, Value)>) -> Map<String, Value> {
+    let mut map = Map::new();
+    let mut repeated = Vec::new();
+    for (key, value) in entries {
+        if !map.contains_key(&key) {
+            map.insert(key, value);
+        } else {
+            let existing = map.get_mut(&key).unwrap();
+            if !repeated.contains(&key) {
+                let first = existing.take();
+                *existing = Value::Array(vec![first]);
+                repeated.push(key);
+            }
+            if let Value::Array(ref mut values) = *existing {
+                values.push(value);
+            }
+        }
+    }
+    map
+}
@@ -3,27 +3,86 @@
 #![allow(unused_variables)]
 #![forbid(non_camel_case_types)]
 #![forbid(unsafe_code)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 //! This crate currently provides an almost XML 1.0/1.1-compliant pull parser.
+//!
+//! The parser and the emitter require the standard library and are available with the `std`
+//! feature, which is enabled by default. With default features disabled the crate is `no_std`
+//! and only provides the `escape` module, which requires nothing but `alloc`:
+//!
+//! ```toml
+//! [dependencies]
+//! xml-rs = { version = "0.8", default-features = false }
+//! ```
+//!
+//! The optional `dom` feature adds the `dom` module, which reads whole documents into
+//! a lightweight element tree, and the optional `serde` feature adds the `de` and `ser` modules,
+//! which convert documents from and into types implementing `serde::Deserialize` and
+//! `serde::Serialize`. The optional `json` feature adds the `json` module, which converts
+//! documents into `serde_json::Value`s.
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
 
 #[cfg(doctest)]
 #[macro_use]
 extern crate doc_comment;
 
+#[cfg(feature = "encodings")]
+extern crate encoding_rs;
+
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+
+#[cfg(feature = "json")]
+extern crate serde_json;
+
 #[cfg(doctest)]
 doctest!("../Readme.md");
 
+#[cfg(feature = "std")]
 pub use reader::EventReader;
+#[cfg(feature = "std")]
 pub use reader::ParserConfig;
+#[cfg(feature = "std")]
 pub use writer::EventWriter;
+#[cfg(feature = "std")]
 pub use writer::EmitterConfig;
+#[cfg(feature = "std")]
+pub use compare::compare;
+#[cfg(feature = "json")]
+pub use json::to_json;
 
+#[cfg(feature = "std")]
 pub mod macros;
+#[cfg(feature = "std")]
 pub mod name;
+#[cfg(feature = "std")]
 pub mod attribute;
+#[cfg(feature = "std")]
 pub mod common;
 pub mod escape;
+#[cfg(feature = "std")]
 pub mod namespace;
+#[cfg(feature = "std")]
 pub mod reader;
+#[cfg(feature = "std")]
 pub mod writer;
+#[cfg(feature = "std")]
+pub mod transform;
+#[cfg(feature = "std")]
+pub mod compare;
+#[cfg(feature = "encodings")]
+pub mod encodings;
+#[cfg(feature = "dom")]
+pub mod dom;
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "serde")]
+pub mod ser;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "std")]
 mod util;
@@ -3,18 +3,24 @@
 //! Contains several macros used in this crate.
 
 macro_rules! gen_setter {
-    ($target:ty, $field:ident : into $t:ty) => {
-        impl $target {
+    ($target:ty, $field:ident : $k:tt $t:ty) => {
+        gen_setter! { $target,
             /// Sets the field to the provided value and returns updated config object.
+            $field : $k $t
+        }
+    };
+    ($target:ty, $(#[$attr:meta])+ $field:ident : into $t:ty) => {
+        impl $target {
+            $(#[$attr])+
             pub fn $field<T: Into<$t>>(mut self, value: T) -> $target {
                 self.$field = value.into();
                 self
             }
         }
     };
-    ($target:ty, $field:ident : val $t:ty) => {
+    ($target:ty, $(#[$attr:meta])+ $field:ident : val $t:ty) => {
         impl $target {
-            /// Sets the field to the provided value and returns updated config object.
+            $(#[$attr])+
             pub fn $field(mut self, value: $t) -> $target {
                 self.$field = value;
                 self
@@ -24,7 +30,26 @@ macro_rules! gen_setter {
 }
 
 macro_rules! gen_setters {
-    ($target:ty, $($field:ident : $k:tt $tpe:ty),+) => ($(
-        gen_setter! { $target, $field : $k $tpe }
+    ($target:ty, $($(#[$attr:meta])* $field:ident : $k:tt $tpe:ty),+) => ($(
+        gen_setter! { $target, $(#[$attr])* $field : $k $tpe }
+    )+)
+}
+
+macro_rules! gen_getter {
+    ($target:ty, $getter:ident : $field:ident val $t:ty) => {
+        impl $target {
+            #[doc = concat!("Returns the value of the `", stringify!($field), "` option; see `",
+                            stringify!($target), "::", stringify!($field), "()`.")]
+            #[inline]
+            pub fn $getter(&self) -> $t {
+                self.$field
+            }
+        }
+    }
+}
+
+macro_rules! gen_getters {
+    ($target:ty, $($getter:ident : $field:ident $k:tt $tpe:ty),+) => ($(
+        gen_getter! { $target, $getter : $field $k $tpe }
     )+)
 }
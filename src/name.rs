@@ -2,9 +2,10 @@
 //!
 
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
-use namespace::NS_NO_PREFIX;
+use namespace::{NS_EMPTY_URI, NS_NO_PREFIX};
 
 /// Represents a qualified XML name.
 ///
@@ -44,7 +45,23 @@ use namespace::NS_NO_PREFIX;
 /// ```
 ///
 /// This is added to support easy specification of XML elements when writing XML documents.
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+///
+/// # Equality
+///
+/// Names are compared as namespace-aware qualified names: names with a namespace URI are equal
+/// if their local names and namespace URIs are equal, whatever their prefixes are, and they
+/// are hashed accordingly. An empty namespace URI is the same as no namespace, as in
+/// `matches_ns()`. Names without a namespace, like those constructed with `Name::prefixed()`
+/// for writing, are equal if their local names and prefixes are equal.
+///
+/// ```rust
+/// # use xml::name::Name;
+/// assert_eq!(Name::qualified("item", "urn:x", Some("a")), Name::qualified("item", "urn:x", Some("b")));
+/// assert_eq!(Name::qualified("item", "urn:x", Some("a")), Name::qualified("item", "urn:x", None));
+/// assert_ne!(Name::prefixed("item", "a"), Name::prefixed("item", "b"));
+/// assert_ne!(Name::qualified("item", "urn:x", Some("a")), Name::prefixed("item", "a"));
+/// ```
+#[derive(Copy, Clone, Debug)]
 pub struct Name<'a> {
     /// A local name, e.g. `string` in `xsi:string`.
     pub local_name: &'a str,
@@ -151,6 +168,58 @@ impl<'a> Name<'a> {
     pub fn prefix_repr(&self) -> &str {
         self.prefix.unwrap_or(NS_NO_PREFIX)
     }
+
+    /// Checks whether the local part of this name is equal to the given string,
+    /// ignoring the prefix and the namespace.
+    #[inline]
+    pub fn matches(&self, local_name: &str) -> bool {
+        self.local_name == local_name
+    }
+
+    /// Checks whether this name has the given namespace URI and local part, ignoring
+    /// the prefix.
+    ///
+    /// Names with different prefixes bound to the same namespace match, as do names without
+    /// a namespace and with an empty namespace URI when `None` is given:
+    ///
+    /// ```rust
+    /// use xml::name::Name;
+    ///
+    /// let name = Name::qualified("item", "urn:x", Some("a"));
+    /// assert!(name.matches_ns(Some("urn:x"), "item"));
+    /// assert!(Name::qualified("item", "urn:x", Some("b")).matches_ns(Some("urn:x"), "item"));
+    /// assert!(!name.matches_ns(None, "item"));
+    /// assert!(Name::local("item").matches_ns(None, "item"));
+    /// ```
+    pub fn matches_ns(&self, namespace: Option<&str>, local_name: &str) -> bool {
+        self.local_name == local_name &&
+            self.namespace.unwrap_or(NS_EMPTY_URI) == namespace.unwrap_or(NS_EMPTY_URI)
+    }
+
+    /// Returns the namespace URI of this name, or `NS_EMPTY_URI` if there is none.
+    #[inline]
+    fn namespace_repr(&self) -> &str {
+        self.namespace.unwrap_or(NS_EMPTY_URI)
+    }
+}
+
+impl<'a, 'b> PartialEq<Name<'b>> for Name<'a> {
+    fn eq(&self, other: &Name<'b>) -> bool {
+        self.local_name == other.local_name && self.namespace_repr() == other.namespace_repr() &&
+            (self.namespace_repr() != NS_EMPTY_URI || self.prefix == other.prefix)
+    }
+}
+
+impl<'a> Eq for Name<'a> {}
+
+impl<'a> Hash for Name<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.local_name.hash(state);
+        self.namespace_repr().hash(state);
+        if self.namespace_repr() == NS_EMPTY_URI {
+            self.prefix.hash(state);
+        }
+    }
 }
 
 /// A wrapper around `Name` whose `Display` implementation prints the wrapped name as it is
@@ -168,8 +237,8 @@ impl<'a, 'b:'a> fmt::Display for ReprDisplay<'a, 'b> {
 
 /// An owned variant of `Name`.
 ///
-/// Everything about `Name` applies to this structure as well.
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+/// Everything about `Name` applies to this structure as well, including equality.
+#[derive(Clone, Debug)]
 pub struct OwnedName {
     /// A local name, e.g. `string` in `xsi:string`.
     pub local_name: String,
@@ -234,6 +303,36 @@ impl OwnedName {
     pub fn namespace_ref(&self) -> Option<&str> {
         self.namespace.as_ref().map(|s| &**s)
     }
+
+    /// Checks whether the local part of this name is equal to the given string; see
+    /// `Name::matches()`.
+    #[inline]
+    pub fn matches(&self, local_name: &str) -> bool {
+        self.borrow().matches(local_name)
+    }
+
+    /// Checks whether this name has the given namespace URI and local part; see
+    /// `Name::matches_ns()`.
+    #[inline]
+    pub fn matches_ns(&self, namespace: Option<&str>, local_name: &str) -> bool {
+        self.borrow().matches_ns(namespace, local_name)
+    }
+}
+
+impl PartialEq for OwnedName {
+    #[inline]
+    fn eq(&self, other: &OwnedName) -> bool {
+        self.borrow() == other.borrow()
+    }
+}
+
+impl Eq for OwnedName {}
+
+impl Hash for OwnedName {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.borrow().hash(state)
+    }
 }
 
 impl<'a> From<Name<'a>> for OwnedName {
@@ -276,7 +375,10 @@ impl FromStr for OwnedName {
 
 #[cfg(test)]
 mod tests {
-    use super::OwnedName;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use super::{Name, OwnedName};
 
     #[test]
     fn test_owned_name_from_str() {
@@ -298,4 +400,72 @@ mod tests {
         assert_eq!("a:".parse(), Err::<OwnedName, ()>(()));
         assert_eq!("a:b:c".parse(), Err::<OwnedName, ()>(()));
     }
+
+    #[test]
+    fn test_name_matches() {
+        assert!(Name::local("a").matches("a"));
+        assert!(Name::prefixed("a", "p").matches("a"));
+        assert!(Name::qualified("a", "urn:x", Some("p")).matches("a"));
+        assert!(!Name::prefixed("a", "p").matches("p:a"));
+        assert!(!Name::local("a").matches("b"));
+
+        let name: OwnedName = "p:a".parse().unwrap();
+        assert!(name.matches("a"));
+    }
+
+    #[test]
+    fn test_name_matches_ns() {
+        let a = Name::qualified("item", "urn:x", Some("a"));
+        let b = Name::qualified("item", "urn:x", Some("b"));
+        let default = Name::qualified("item", "urn:x", None);
+        for name in &[a, b, default] {
+            assert!(name.matches_ns(Some("urn:x"), "item"));
+            assert!(name.to_owned().matches_ns(Some("urn:x"), "item"));
+            assert!(!name.matches_ns(Some("urn:y"), "item"));
+            assert!(!name.matches_ns(None, "item"));
+            assert!(!name.matches_ns(Some("urn:x"), "other"));
+        }
+
+        // the prefix alone does not determine the namespace
+        assert!(!Name::prefixed("item", "a").matches_ns(Some("urn:x"), "item"));
+
+        assert!(Name::local("item").matches_ns(None, "item"));
+        assert!(Name::local("item").matches_ns(Some(""), "item"));
+        assert!(Name::qualified("item", "", None).matches_ns(None, "item"));
+        assert!(!Name::local("item").matches_ns(Some("urn:x"), "item"));
+    }
+
+    #[test]
+    fn test_equality_ignores_prefixes_of_namespaced_names() {
+        fn hash<T: Hash>(value: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let a = OwnedName::qualified("item", "urn:x", Some("a"));
+        let b = OwnedName::qualified("item", "urn:x", Some("b"));
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_eq!(a.borrow(), b.borrow());
+        assert_eq!(hash(&a.borrow()), hash(&b.borrow()));
+        assert_eq!(hash(&a), hash(&a.borrow()));
+
+        assert_ne!(a, OwnedName::qualified("item", "urn:y", Some("a")));
+        assert_ne!(a, OwnedName::qualified("other", "urn:x", Some("a")));
+        assert_ne!(a, OwnedName::local("item"));
+
+        // without namespaces, prefixes are all there is to distinguish names
+        assert_ne!(Name::prefixed("item", "a"), Name::prefixed("item", "b"));
+        assert_ne!(Name::prefixed("item", "a"), Name::local("item"));
+        assert_eq!(Name::prefixed("item", "a"), Name::from("a:item"));
+
+        // an empty namespace URI is no namespace
+        let empty = OwnedName::qualified("item", "", None::<&str>);
+        assert_eq!(empty, OwnedName::local("item"));
+        assert_eq!(hash(&empty), hash(&OwnedName::local("item")));
+        assert_eq!(empty.borrow(), Name::local("item"));
+        assert_eq!(hash(&empty.borrow()), hash(&Name::local("item")));
+        assert_ne!(empty, OwnedName::local("other"));
+    }
 }
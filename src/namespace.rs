@@ -255,6 +255,7 @@ impl NamespaceStack {
     /// Returns a boolean flag indicating whether the insertion has completed successfully.
     /// Note that both key and value are matched and the mapping is inserted if either
     /// namespace prefix is not already mapped, or if it is mapped, but to a different URI.
+    /// Only the innermost mapping of the prefix is considered, since it is the one in effect.
     ///
     /// # Parameters
     /// * `prefix` --- namespace prefix;
@@ -262,12 +263,12 @@ impl NamespaceStack {
     ///
     /// # Return value
     /// `true` if `prefix` has been inserted successfully; `false` if the `prefix`
-    /// was already present in the namespace stack.
+    /// was already mapped to `uri` in the namespace stack.
     pub fn put_checked<P, U>(&mut self, prefix: P, uri: U) -> bool
         where P: Into<String> + AsRef<str>,
               U: Into<String> + AsRef<str>
     {
-        if self.0.iter().any(|ns| ns.get(&prefix) == Some(uri.as_ref())) {
+        if self.get(&prefix) == Some(uri.as_ref()) {
             false
         } else {
             self.put(prefix, uri);
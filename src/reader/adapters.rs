@@ -0,0 +1,222 @@
+//! Contains adapters for post-processing streams of XML events.
+
+use std::io::Read;
+
+use common::{Position, TextPosition};
+
+use super::{Events, Result, XmlEvent};
+
+/// An iterator over XML events which knows the position of the last event it returned.
+///
+/// This trait provides adapters for post-processing events, like dropping comments or
+/// renaming elements. The adapters work on owned events, so they don't need to clone them,
+/// and they can be combined with each other:
+///
+/// ```rust
+/// use xml::common::Position;
+/// use xml::reader::{EventIterator, EventReader, XmlEvent};
+///
+/// let reader = EventReader::from_str("<a><!--x--><b>text</b></a>");
+/// let mut events = reader
+///     .filter_events(|e| !matches!(*e, XmlEvent::Characters(_)))
+///     .map_events(|e| match e {
+///         XmlEvent::StartElement { mut name, attributes, namespace } => {
+///             name.local_name = name.local_name.to_uppercase();
+///             XmlEvent::StartElement { name, attributes, namespace }
+///         }
+///         e => e
+///     });
+///
+/// events.next(); // StartDocument
+/// match events.next().unwrap().unwrap() {
+///     XmlEvent::StartElement { ref name, .. } => assert_eq!(name.local_name, "A"),
+///     e => panic!("Unexpected event: {:?}", e)
+/// }
+/// match events.next().unwrap().unwrap() {
+///     XmlEvent::StartElement { ref name, .. } => assert_eq!(name.local_name, "B"),
+///     e => panic!("Unexpected event: {:?}", e)
+/// }
+/// assert_eq!(events.position().to_string(), "1:12");
+/// ```
+///
+/// Errors are never filtered or mapped; they are passed through as they are.
+pub trait EventIterator: Iterator<Item=Result<XmlEvent>> + Position + Sized {
+    /// Returns an iterator which yields only the events for which the predicate returns `true`.
+    #[inline]
+    fn filter_events<P>(self, predicate: P) -> FilterEvents<Self, P>
+        where P: FnMut(&XmlEvent) -> bool
+    {
+        FilterEvents { inner: self, predicate }
+    }
+
+    /// Returns an iterator which replaces each event with the result of the given function.
+    #[inline]
+    fn map_events<F>(self, f: F) -> MapEvents<Self, F>
+        where F: FnMut(XmlEvent) -> XmlEvent
+    {
+        MapEvents { inner: self, f }
+    }
+
+    /// Returns an iterator which yields only the `StartElement` and `EndElement` events,
+    /// for consumers which are only interested in the structure of the document.
+    ///
+    /// If `attributes` is `false`, the attributes of the start elements are dropped as well:
+    ///
+    /// ```rust
+    /// use xml::reader::{EventIterator, EventReader, XmlEvent};
+    ///
+    /// let events: Vec<_> = EventReader::from_str("<a x=\"1\"><!--c-->text<b/></a>")
+    ///     .elements_only(false)
+    ///     .map(|e| match e.unwrap() {
+    ///         XmlEvent::StartElement { name, attributes, .. } => format!("<{} {}>", name, attributes.len()),
+    ///         XmlEvent::EndElement { name } => format!("</{}>", name),
+    ///         e => panic!("Unexpected event: {:?}", e)
+    ///     })
+    ///     .collect();
+    /// assert_eq!(events, ["<a 0>", "<b 0>", "</b>", "</a>"]);
+    /// ```
+    #[inline]
+    fn elements_only(self, attributes: bool) -> ElementsOnly<Self> {
+        ElementsOnly { inner: self, attributes }
+    }
+
+    /// Returns an iterator which yields only the `Characters` events.
+    ///
+    /// Note that character data may be split into several events, e.g. around comments
+    /// or CDATA sections, unless these are converted to characters or ignored, which
+    /// is the default for comments.
+    #[inline]
+    fn text_only(self) -> FilterEvents<Self, fn(&XmlEvent) -> bool> {
+        fn is_text(e: &XmlEvent) -> bool {
+            matches!(*e, XmlEvent::Characters(_))
+        }
+        self.filter_events(is_text)
+    }
+}
+
+impl<R: Read> EventIterator for Events<R> {}
+
+/// An iterator which skips events not matching a predicate.
+///
+/// This iterator is created by `EventIterator::filter_events()`.
+pub struct FilterEvents<I, P> {
+    inner: I,
+    predicate: P
+}
+
+impl<I, P> FilterEvents<I, P> {
+    /// Unwraps this adapter, returning the underlying iterator.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I, P> Iterator for FilterEvents<I, P>
+    where I: EventIterator, P: FnMut(&XmlEvent) -> bool
+{
+    type Item = Result<XmlEvent>;
+
+    fn next(&mut self) -> Option<Result<XmlEvent>> {
+        loop {
+            match self.inner.next() {
+                Some(Ok(e)) => if (self.predicate)(&e) { return Some(Ok(e)) },
+                other => return other
+            }
+        }
+    }
+}
+
+impl<I: Position, P> Position for FilterEvents<I, P> {
+    /// Returns the position of the last event produced by the underlying iterator.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.inner.position()
+    }
+}
+
+impl<I, P> EventIterator for FilterEvents<I, P>
+    where I: EventIterator, P: FnMut(&XmlEvent) -> bool {}
+
+/// An iterator which transforms events with a function.
+///
+/// This iterator is created by `EventIterator::map_events()`.
+pub struct MapEvents<I, F> {
+    inner: I,
+    f: F
+}
+
+impl<I, F> MapEvents<I, F> {
+    /// Unwraps this adapter, returning the underlying iterator.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I, F> Iterator for MapEvents<I, F>
+    where I: EventIterator, F: FnMut(XmlEvent) -> XmlEvent
+{
+    type Item = Result<XmlEvent>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Result<XmlEvent>> {
+        let f = &mut self.f;
+        self.inner.next().map(|r| r.map(f))
+    }
+}
+
+impl<I: Position, F> Position for MapEvents<I, F> {
+    /// Returns the position of the last event produced by the underlying iterator.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.inner.position()
+    }
+}
+
+impl<I, F> EventIterator for MapEvents<I, F>
+    where I: EventIterator, F: FnMut(XmlEvent) -> XmlEvent {}
+
+/// An iterator which yields only the start and end elements of a document.
+///
+/// This iterator is created by `EventIterator::elements_only()`.
+pub struct ElementsOnly<I> {
+    inner: I,
+    attributes: bool
+}
+
+impl<I> ElementsOnly<I> {
+    /// Unwraps this adapter, returning the underlying iterator.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: EventIterator> Iterator for ElementsOnly<I> {
+    type Item = Result<XmlEvent>;
+
+    fn next(&mut self) -> Option<Result<XmlEvent>> {
+        loop {
+            match self.inner.next() {
+                Some(Ok(XmlEvent::StartElement { name, attributes, namespace })) => {
+                    let attributes = if self.attributes { attributes } else { Vec::new() };
+                    return Some(Ok(XmlEvent::StartElement { name, attributes, namespace }));
+                }
+                Some(Ok(e @ XmlEvent::EndElement { .. })) => return Some(Ok(e)),
+                Some(Ok(_)) => {}
+                other => return other
+            }
+        }
+    }
+}
+
+impl<I: Position> Position for ElementsOnly<I> {
+    /// Returns the position of the last event produced by the underlying iterator.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.inner.position()
+    }
+}
+
+impl<I: EventIterator> EventIterator for ElementsOnly<I> {}
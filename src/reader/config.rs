@@ -1,93 +1,85 @@
 //! Contains parser configuration structure.
-use std::io::Read;
+use std::io::{BufRead, Read};
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
 
-use reader::EventReader;
+use common::InvalidCharacterPolicy;
+use reader::{EntityResolver, EventReader, Result};
+
+/// Determines what the parser does with element and attribute names whose prefixes
+/// are not bound to any namespace.
+///
+/// See `ParserConfig::unbound_prefix_policy` for more information.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum UnboundPrefixPolicy {
+    /// Report an error.
+    Error,
+
+    /// Keep the prefix in the name and leave its namespace unresolved, i.e. `None`.
+    Keep
+}
 
 /// Parser configuration structure.
 ///
 /// This structure contains various configuration options which affect
 /// behavior of the parser.
+///
+/// Every option is set with a chainable method of the same name, where it is documented,
+/// so the configuration can be built in a single expression and turned directly into a reader:
+///
+/// ```rust
+/// use xml::reader::{ParserConfig, XmlEvent};
+///
+/// let mut reader = ParserConfig::new()
+///     .trim_whitespace(true)
+///     .whitespace_to_characters(true)
+///     .cdata_to_characters(true)
+///     .ignore_comments(false)
+///     .coalesce_characters(false)
+///     .ignore_end_of_stream(false)
+///     .replace_unknown_entity_references(true)
+///     .ignore_root_level_whitespace(true)
+///     .allow_multiple_root_elements(false)
+///     .add_entity("nbsp", "\u{a0}")
+///     .create_reader("<a> &nbsp; </a>".as_bytes());
+///
+/// assert!(match reader.next().unwrap() { XmlEvent::StartDocument { .. } => true, _ => false });
+/// assert!(match reader.next().unwrap() { XmlEvent::StartElement { .. } => true, _ => false });
+/// assert_eq!(reader.next().unwrap(), XmlEvent::Characters("\u{a0}".into()));
+/// ```
+///
+/// The current values of the options are returned by getters with the `get_` prefix, like
+/// `get_trim_whitespace()`. The fields of the structure are private, so that new options
+/// can be added without breaking changes.
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct ParserConfig {
-    /// Whether or not should whitespace in textual events be removed. Default is false.
-    ///
-    /// When true, all standalone whitespace will be removed (this means no
-    /// `Whitespace` events will be emitted), and leading and trailing whitespace
-    /// from `Character` events will be deleted. If after trimming `Characters`
-    /// event will be empty, it will also be omitted from output stream. This is
-    /// possible, however, only if `whitespace_to_characters` or
-    /// `cdata_to_characters` options are set.
-    ///
-    /// This option does not affect CDATA events, unless `cdata_to_characters`
-    /// option is also set. In that case CDATA content will also be trimmed.
-    pub trim_whitespace: bool,
-
-    /// Whether or not should whitespace be converted to characters.
-    /// Default is false.
-    ///
-    /// If true, instead of `Whitespace` events `Characters` events with the
-    /// same content will be emitted. If `trim_whitespace` is also true, these
-    /// events will be trimmed to nothing and, consequently, not emitted.
-    pub whitespace_to_characters: bool,
-
-    /// Whether or not should CDATA be converted to characters.
-    /// Default is false.
-    ///
-    /// If true, instead of `CData` events `Characters` events with the same
-    /// content will be emitted. If `trim_whitespace` is also true, these events
-    /// will be trimmed. If corresponding CDATA contained nothing but whitespace,
-    /// this event will be omitted from the stream.
-    pub cdata_to_characters: bool,
-
-    /// Whether or not should comments be omitted. Default is true.
-    ///
-    /// If true, `Comment` events will not be emitted at all.
-    pub ignore_comments: bool,
-
-    /// Whether or not should sequential `Characters` events be merged.
-    /// Default is true.
-    ///
-    /// If true, multiple sequential `Characters` events will be merged into
-    /// a single event, that is, their data will be concatenated.
-    ///
-    /// Multiple sequential `Characters` events are only possible if either
-    /// `cdata_to_characters` or `ignore_comments` are set. Otherwise character
-    /// events will always be separated by other events.
-    pub coalesce_characters: bool,
-
-    /// A map of extra entities recognized by the parser. Default is an empty map.
-    ///
-    /// By default the XML parser recognizes the entities defined in the XML spec. Sometimes,
-    /// however, it is convenient to make the parser recognize additional entities which
-    /// are also not available through the DTD definitions (especially given that at the moment
-    /// DTD parsing is not supported).
-    pub extra_entities: HashMap<String, String>,
-
-    /// Whether or not the parser should ignore the end of stream. Default is false.
-    ///
-    /// By default the parser will either error out when it encounters a premature end of
-    /// stream or complete normally if the end of stream was expected. If you want to continue
-    /// reading from a stream whose input is supplied progressively, you can set this option to true.
-    /// In this case the parser will allow you to invoke the next() method even if a supposed end
-    /// of stream has happened.
-    ///
-    /// Note that support for this functionality is incomplete; for example, the parser will fail if
-    /// the premature end of stream happens inside PCDATA. Therefore, use this option at your own risk.
-    pub ignore_end_of_stream: bool,
-
-    /// Whether or not non-unicode entity references get replaced with the replacement character
-    ///
-    /// When true, any decimal or hexadecimal character reference that cannot be converted from a
-    /// u32 to a char using [std::char::from_u32](https://doc.rust-lang.org/std/char/fn.from_u32.html)
-    /// will be converted into the unicode REPLACEMENT CHARACTER (U+FFFD).
-    pub replace_unknown_entity_references: bool,
-
-    /// Whether or not whitespace at the root level of the document is ignored. Default is true.
-    ///
-    /// By default any whitespace that is not enclosed within at least one level of elements will be
-    /// ignored. Setting this value to false will cause root level whitespace events to be emitted.
-    pub ignore_root_level_whitespace: bool,
+    trim_whitespace: bool,
+    whitespace_to_characters: bool,
+    cdata_to_characters: bool,
+    ignore_comments: bool,
+    coalesce_characters: bool,
+    extra_entities: HashMap<String, String>,
+    ignore_end_of_stream: bool,
+    replace_unknown_entity_references: bool,
+    replace_entity_references: bool,
+    report_doctype: bool,
+    ignore_root_level_whitespace: bool,
+    ignore_root_level_comments: bool,
+    allow_multiple_root_elements: bool,
+    multiple_documents: bool,
+    max_data_length: Option<usize>,
+    max_element_depth: Option<usize>,
+    max_document_length: Option<u64>,
+    invalid_character_policy: InvalidCharacterPolicy,
+    unbound_prefix_policy: UnboundPrefixPolicy,
+    max_entity_expansion_depth: u8,
+    max_entity_expansion_length: usize,
+    skip_external_entities: bool,
+    entity_resolver: Option<SharedEntityResolver>,
+    validate_dtd: bool,
+    validity_errors_as_warnings: bool,
+    buffer_size: usize,
 }
 
 impl ParserConfig {
@@ -113,7 +105,24 @@ impl ParserConfig {
             extra_entities: HashMap::new(),
             ignore_end_of_stream: false,
             replace_unknown_entity_references: false,
+            replace_entity_references: true,
+            report_doctype: false,
             ignore_root_level_whitespace: true,
+            ignore_root_level_comments: false,
+            allow_multiple_root_elements: false,
+            multiple_documents: false,
+            max_data_length: None,
+            max_element_depth: None,
+            max_document_length: None,
+            invalid_character_policy: InvalidCharacterPolicy::Error,
+            unbound_prefix_policy: UnboundPrefixPolicy::Error,
+            max_entity_expansion_depth: 8,
+            max_entity_expansion_length: 1024 * 1024,
+            skip_external_entities: false,
+            entity_resolver: None,
+            validate_dtd: false,
+            validity_errors_as_warnings: false,
+            buffer_size: 8 * 1024,
         }
     }
 
@@ -160,6 +169,110 @@ impl ParserConfig {
         self.extra_entities.insert(entity.into(), value.into());
         self
     }
+
+    /// Returns the map of extra entities recognized by the parser, which are added with
+    /// `add_entity()`. Default is an empty map.
+    ///
+    /// By default the XML parser recognizes the entities defined in the XML spec. Sometimes,
+    /// however, it is convenient to make the parser recognize additional entities which
+    /// are also not available through the DTD definitions (especially given that at the moment
+    /// only entities declared in the internal DTD subset are supported).
+    #[inline]
+    pub fn get_extra_entities(&self) -> &HashMap<String, String> {
+        &self.extra_entities
+    }
+
+    /// Sets the resolver which fetches the content of external entities and returns an updated
+    /// config object. Default is no resolver.
+    ///
+    /// External entities are declared in the internal subset with an external identifier, e.g.
+    /// `<!ENTITY chapter SYSTEM "chapter.ent">`. The parser never reads them by itself; when
+    /// such an entity is referenced in the document content, the resolver is called with its
+    /// identifiers and returns a reader of the entity content, which is then parsed in place of
+    /// the reference. A text declaration at the beginning of the content is skipped. The same
+    /// restrictions and limits as for internal entities apply; in particular, elements in
+    /// the content must be balanced, and references to external entities are not allowed in
+    /// attribute values. External parameter entities are never read.
+    ///
+    /// The resolver is also called with the identifiers of the external DTD subset, e.g.
+    /// `<!DOCTYPE doc SYSTEM "doc.dtd">`, whose declarations are then read after those
+    /// of the internal subset, which take precedence. The external subset may contain the same
+    /// declarations as the internal subset; conditional sections are not supported.
+    ///
+    /// When the resolver returns `None`, the entity is handled as if there were no resolver:
+    /// the external subset is not read at all, and references to external entities are errors,
+    /// or expand to nothing if the `skip_external_entities` option is enabled. This allows
+    /// resolving only the entities from a local catalog:
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use std::io::{BufRead, Cursor};
+    /// use xml::reader::{EntityResolver, ParserConfig, Result, XmlEvent};
+    ///
+    /// struct Catalog(HashMap<&'static str, &'static str>);
+    ///
+    /// impl EntityResolver for Catalog {
+    ///     fn resolve(&mut self, _: Option<&str>, system_id: &str) -> Result<Option<Box<dyn BufRead>>> {
+    ///         Ok(self.0.get(system_id).map(|&text| Box::new(Cursor::new(text)) as Box<dyn BufRead>))
+    ///     }
+    /// }
+    ///
+    /// let mut catalog = HashMap::new();
+    /// catalog.insert("e.txt", "external text");
+    /// let doc = r#"<!DOCTYPE a [<!ENTITY e SYSTEM "e.txt"><!ENTITY f SYSTEM "f.txt">]><a>&e;&f;</a>"#;
+    /// let config = ParserConfig::new().entity_resolver(Catalog(catalog));
+    ///
+    /// let mut reader = config.clone().create_reader(doc.as_bytes());
+    /// reader.next().unwrap();
+    /// reader.next().unwrap();
+    /// assert_eq!(reader.next().unwrap_err().msg(), "External entities are disabled, as no resolver is set: &f;");
+    ///
+    /// let mut reader = config.skip_external_entities(true).create_reader(doc.as_bytes());
+    /// reader.next().unwrap();
+    /// reader.next().unwrap();
+    /// assert_eq!(reader.next().unwrap(), XmlEvent::Characters("external text".into()));
+    /// ```
+    ///
+    /// The resolver is shared by all copies of this configuration, and so by the readers
+    /// created with them.
+    pub fn entity_resolver<R: EntityResolver + Send + 'static>(mut self, resolver: R) -> ParserConfig {
+        self.entity_resolver = Some(SharedEntityResolver(Arc::new(Mutex::new(resolver))));
+        self
+    }
+
+    /// Fetches the content of the external entity with the given identifiers with the resolver
+    /// set by `entity_resolver()`.
+    ///
+    /// Returns `None` if there is no resolver or the resolver doesn't resolve the entity.
+    pub fn resolve_entity(&self, public_id: Option<&str>, system_id: &str) -> Result<Option<Box<dyn BufRead>>> {
+        match self.entity_resolver {
+            Some(ref resolver) => {
+                let mut resolver = resolver.0.lock().unwrap_or_else(|e| e.into_inner());
+                resolver.resolve(public_id, system_id)
+            }
+            None => Ok(None)
+        }
+    }
+}
+
+/// An entity resolver shared by copies of a configuration. Copies are equal if they share
+/// the same resolver.
+#[derive(Clone)]
+struct SharedEntityResolver(Arc<Mutex<dyn EntityResolver + Send>>);
+
+impl PartialEq for SharedEntityResolver {
+    #[inline]
+    fn eq(&self, other: &SharedEntityResolver) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedEntityResolver {}
+
+impl fmt::Debug for SharedEntityResolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("EntityResolver")
+    }
 }
 
 impl Default for ParserConfig {
@@ -170,12 +283,278 @@ impl Default for ParserConfig {
 }
 
 gen_setters! { ParserConfig,
+    /// Whether or not should whitespace in textual events be removed. Default is false.
+    ///
+    /// When true, all standalone whitespace will be removed (this means no
+    /// `Whitespace` events will be emitted), and leading and trailing whitespace
+    /// from `Character` events will be deleted. If after trimming `Characters`
+    /// event will be empty, it will also be omitted from output stream. This is
+    /// possible, however, only if `whitespace_to_characters` or
+    /// `cdata_to_characters` options are set.
+    ///
+    /// This option does not affect CDATA events, unless `cdata_to_characters`
+    /// option is also set. In that case CDATA content will also be trimmed.
+    ///
+    /// Whitespace inside elements with `xml:space="preserve"` in scope is never trimmed.
     trim_whitespace: val bool,
+
+    /// Whether or not should whitespace be converted to characters.
+    /// Default is false.
+    ///
+    /// If true, instead of `Whitespace` events `Characters` events with the
+    /// same content will be emitted. If `trim_whitespace` is also true, these
+    /// events will be trimmed to nothing and, consequently, not emitted.
     whitespace_to_characters: val bool,
+
+    /// Whether or not should CDATA be converted to characters.
+    /// Default is false.
+    ///
+    /// If true, instead of `CData` events `Characters` events with the same
+    /// content will be emitted. If `trim_whitespace` is also true, these events
+    /// will be trimmed. If corresponding CDATA contained nothing but whitespace,
+    /// this event will be omitted from the stream.
     cdata_to_characters: val bool,
+
+    /// Whether or not should comments be omitted. Default is true.
+    ///
+    /// If true, `Comment` events will not be emitted at all.
     ignore_comments: val bool,
+
+    /// Whether or not should sequential `Characters` events be merged.
+    /// Default is true.
+    ///
+    /// If true, multiple sequential `Characters` events will be merged into
+    /// a single event, that is, their data will be concatenated.
+    ///
+    /// Multiple sequential `Characters` events are only possible if either
+    /// `cdata_to_characters` or `ignore_comments` are set. Otherwise character
+    /// events will always be separated by other events.
     coalesce_characters: val bool,
+
+    /// Whether or not the parser should ignore the end of stream. Default is false.
+    ///
+    /// By default the parser will either error out when it encounters a premature end of
+    /// stream or complete normally if the end of stream was expected. If you want to continue
+    /// reading from a stream whose input is supplied progressively, you can set this option to true.
+    /// In this case the parser will allow you to invoke the next() method even if a supposed end
+    /// of stream has happened.
+    ///
+    /// Note that support for this functionality is incomplete; for example, the parser will fail if
+    /// the premature end of stream happens inside PCDATA. Therefore, use this option at your own risk.
     ignore_end_of_stream: val bool,
+
+    /// Whether or not non-unicode entity references get replaced with the replacement character
+    ///
+    /// When true, any decimal or hexadecimal character reference that cannot be converted from a
+    /// u32 to a char using [std::char::from_u32](https://doc.rust-lang.org/std/char/fn.from_u32.html)
+    /// will be converted into the unicode REPLACEMENT CHARACTER (U+FFFD).
     replace_unknown_entity_references: val bool,
-    ignore_root_level_whitespace: val bool
+
+    /// Whether or not references to general entities in character data are replaced with
+    /// the values of the entities. Default is true.
+    ///
+    /// When this option is disabled, references to entities other than the five predefined
+    /// ones (`&lt;`, `&gt;`, `&amp;`, `&apos;` and `&quot;`) are reported as separate
+    /// `XmlEvent::EntityReference` events instead, splitting the surrounding character data,
+    /// so that they can be written back verbatim. The entities are not looked up, so
+    /// references to undeclared entities are not errors in this mode. Character references
+    /// and the predefined entities are always replaced, as are all references inside
+    /// attribute values.
+    replace_entity_references: val bool,
+
+    /// Whether or not the document type declaration is reported as an `XmlEvent::DoctypeDecl`
+    /// event. Default is false.
+    ///
+    /// The declarations of the internal subset are processed regardless of this option. When
+    /// it is enabled, the event contains the name of the root element, the identifiers of the
+    /// external subset and the internal subset exactly as it is written, so that the declaration
+    /// can be written back verbatim, e.g. with `XmlEvent::as_writer_event()`.
+    report_doctype: val bool,
+
+    /// Whether or not whitespace at the root level of the document is ignored. Default is true.
+    ///
+    /// By default any whitespace that is not enclosed within at least one level of elements will be
+    /// ignored. Setting this value to false will cause root level whitespace events to be emitted.
+    ///
+    /// Whitespace in the prolog and the epilog, that is, before and after the root element,
+    /// is controlled only by this option; `trim_whitespace` applies to the content of elements.
+    /// Character data other than whitespace is an error outside of the root element regardless
+    /// of this option.
+    ignore_root_level_whitespace: val bool,
+
+    /// Whether or not comments at the root level of the document are ignored. Default is false.
+    ///
+    /// Comments in the prolog and the epilog are often uninteresting, e.g. license headers.
+    /// When this option is true, `Comment` events are not emitted for them, even if
+    /// `ignore_comments` is false, so only the comments inside the root element are reported.
+    /// When it is false, comments at the root level are treated like all other comments.
+    ignore_root_level_comments: val bool,
+
+    /// Whether or not the document may contain more than one root element. Default is false.
+    ///
+    /// A well-formed XML document has exactly one root element, so by default the parser
+    /// reports an error if another element starts after the root element is closed. Setting
+    /// this option to true allows parsing document fragments and concatenated records like
+    /// `<a/><b/>`; the events for all top-level elements are emitted as usual, and `EndDocument`
+    /// is emitted at the end of the stream.
+    allow_multiple_root_elements: val bool,
+
+    /// Whether or not the stream may contain several documents one after another.
+    /// Default is false.
+    ///
+    /// Some protocols send multiple complete documents back-to-back over one connection. When
+    /// this option is true, the end of the root element is also the end of the document, so
+    /// `EndDocument` is emitted right after its `EndElement`, without waiting for the end
+    /// of the stream. After that the parser starts reading the next document, which may have
+    /// its own XML declaration, document type declaration and root element; its events start
+    /// with `StartDocument` as usual. Anything after the root element, like comments or
+    /// whitespace, belongs to the prolog of the next document.
+    ///
+    /// Positions and byte offsets are not reset between documents, so they are always relative
+    /// to the beginning of the stream. At the end of the stream after the last document,
+    /// `EventReader::next()` returns `EndDocument` once more, and the iterator returned by
+    /// `EventReader::into_iter()` ends. This option takes precedence over
+    /// `allow_multiple_root_elements`.
+    multiple_documents: val bool,
+
+    /// Maximum length in bytes of a single piece of textual data. Default is `None`, that is,
+    /// no limit.
+    ///
+    /// This limit applies to the contents of every `Characters`, `Whitespace`, `CData` and
+    /// `Comment` event and to processing instruction data, as well as to attribute values.
+    /// It is checked while the data is accumulated, so the parser reports an error before
+    /// its buffers grow beyond the limit. This is useful to protect against malicious input.
+    max_data_length: into Option<usize>,
+
+    /// Maximum nesting depth of elements. Default is `None`, that is, no limit.
+    ///
+    /// The root element has depth 1. When an element would be nested deeper than allowed,
+    /// the parser reports an error. This is useful to protect against malicious input, in
+    /// particular when the document is turned into a tree, e.g. with `dom::read_to_tree()`.
+    max_element_depth: into Option<usize>,
+
+    /// Maximum length in bytes of the whole document. Default is `None`, that is, no limit.
+    ///
+    /// When the parser reads more bytes than allowed from the stream, it reports an error.
+    /// This is useful to protect against malicious input.
+    max_document_length: into Option<u64>,
+
+    /// What to do with characters which are not allowed in XML documents. Default is
+    /// `InvalidCharacterPolicy::Error`.
+    ///
+    /// This policy applies to characters of character data, including CDATA sections, of
+    /// attribute values, comments and processing instructions which are not allowed in documents
+    /// of the declared XML version, for example, control characters like U+000B or noncharacters
+    /// like U+FFFE. XML 1.0 documents may contain the characters matching the `Char` production
+    /// of the XML 1.0 specification, while XML 1.1 documents additionally must not contain
+    /// restricted characters (`RestrictedChar`) literally. Some producers emit such characters,
+    /// and this option allows reading their documents anyway: invalid characters can be
+    /// replaced, typically with `InvalidCharacterPolicy::ReplaceWith('\u{FFFD}')`, or dropped. In both cases the parser notes
+    /// every such character as a warning, which can be retrieved with
+    /// `EventReader::take_warnings()`.
+    invalid_character_policy: val InvalidCharacterPolicy,
+
+    /// What to do with element and attribute names with prefixes which are not bound
+    /// to a namespace. Default is `UnboundPrefixPolicy::Error`.
+    ///
+    /// Documents which are not namespace-well-formed, like HTML-ish documents using
+    /// `xlink:href` attributes without declaring the `xlink` prefix, can be read with
+    /// `UnboundPrefixPolicy::Keep`: such names keep their prefixes, and their namespaces are
+    /// `None`. Note that unprefixed attribute names never have a namespace, regardless of this
+    /// option, because the default namespace does not apply to attributes. Every unbound prefix
+    /// is noted as a warning, which can be retrieved with `EventReader::take_warnings()`.
+    unbound_prefix_policy: val UnboundPrefixPolicy,
+
+    /// Maximum nesting depth of entity references. Default is 8.
+    ///
+    /// Entities declared in the internal DTD subset may refer to other entities; this limit
+    /// applies to such nested references, both of general entities in the document content
+    /// and of parameter entities in the internal subset. Exceeding it, for example with
+    /// a recursive entity, is reported as an error.
+    ///
+    /// The replacement text of an entity referenced in content may contain markup, which is
+    /// then parsed as if it were written in place of the reference; the elements of the text
+    /// must be balanced, and the text must not refer to the entity itself.
+    max_entity_expansion_depth: val u8,
+
+    /// Maximum length in bytes of entity expansions. Default is 1 MiB.
+    ///
+    /// This limit applies to the fully expanded text of every general entity reference, to
+    /// the total length of the replacement texts with markup which are parsed for a reference
+    /// in content, and to the total length of all parameter entity expansions in the internal
+    /// subset. It protects
+    /// against maliciously crafted entities which expand to huge amounts of text.
+    max_entity_expansion_length: val usize,
+
+    /// Whether or not references to external entities expand to nothing. Default is false.
+    ///
+    /// External entities are never fetched by default, because reading arbitrary files or URLs
+    /// referenced by a document is a security risk. Their content can only be provided with
+    /// a resolver set by `entity_resolver()`. If there is no resolver, references to external
+    /// entities are errors, unless this option is enabled, in which case they are silently
+    /// replaced with nothing.
+    skip_external_entities: val bool,
+
+    /// Whether or not the document is validated against the element type and attribute-list
+    /// declarations of its internal DTD subset. Default is false.
+    ///
+    /// Validation checks that the root element matches the document type declaration, that
+    /// every element is declared and its content matches the declared content model, that
+    /// attributes are declared, required attributes are present, and values of enumerated
+    /// and `#FIXED` attributes are allowed. Default values of declared attributes which are
+    /// missing in an element are added to its `XmlEvent::StartElement` event, and values of
+    /// attributes of tokenized types, like `NMTOKENS`, are normalized. ID uniqueness and IDREF
+    /// targets are not checked. A document without a document type declaration is invalid.
+    ///
+    /// Validity errors are reported as errors, unless `validity_errors_as_warnings` is enabled.
+    validate_dtd: val bool,
+
+    /// Whether or not validity errors are noted as warnings instead of being reported as errors.
+    /// Default is false.
+    ///
+    /// This option only applies when `validate_dtd` is enabled. Warnings can be retrieved
+    /// with `EventReader::take_warnings()`, and reading continues after them.
+    validity_errors_as_warnings: val bool,
+
+    /// Size of the internal buffer in bytes. Default is 8 KiB.
+    ///
+    /// This option is only used by readers created with `EventReader::new_buffered()`,
+    /// which read their source through an internal buffer of this size. It is the largest
+    /// chunk requested from the source with a single `read()` call.
+    ///
+    /// Larger chunks mean fewer calls to the source, which improves throughput. Sources like
+    /// sockets return as much data as is available, so the reader does not wait for a whole
+    /// chunk before emitting events; however, sources which block until the requested amount
+    /// of data arrives, or which process data in units of the requested size, delay events
+    /// until a whole chunk is filled. Smaller chunks reduce this delay for latency-sensitive
+    /// streams. A size of zero disables buffering, so every byte is read with a separate
+    /// call to the source.
+    buffer_size: val usize
+}
+
+gen_getters! { ParserConfig,
+    get_trim_whitespace: trim_whitespace val bool,
+    get_whitespace_to_characters: whitespace_to_characters val bool,
+    get_cdata_to_characters: cdata_to_characters val bool,
+    get_ignore_comments: ignore_comments val bool,
+    get_coalesce_characters: coalesce_characters val bool,
+    get_ignore_end_of_stream: ignore_end_of_stream val bool,
+    get_replace_unknown_entity_references: replace_unknown_entity_references val bool,
+    get_replace_entity_references: replace_entity_references val bool,
+    get_report_doctype: report_doctype val bool,
+    get_ignore_root_level_whitespace: ignore_root_level_whitespace val bool,
+    get_ignore_root_level_comments: ignore_root_level_comments val bool,
+    get_allow_multiple_root_elements: allow_multiple_root_elements val bool,
+    get_multiple_documents: multiple_documents val bool,
+    get_max_data_length: max_data_length val Option<usize>,
+    get_max_element_depth: max_element_depth val Option<usize>,
+    get_max_document_length: max_document_length val Option<u64>,
+    get_invalid_character_policy: invalid_character_policy val InvalidCharacterPolicy,
+    get_unbound_prefix_policy: unbound_prefix_policy val UnboundPrefixPolicy,
+    get_max_entity_expansion_depth: max_entity_expansion_depth val u8,
+    get_max_entity_expansion_length: max_entity_expansion_length val usize,
+    get_skip_external_entities: skip_external_entities val bool,
+    get_validate_dtd: validate_dtd val bool,
+    get_validity_errors_as_warnings: validity_errors_as_warnings val bool,
+    get_buffer_size: buffer_size val usize
 }
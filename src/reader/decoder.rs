@@ -0,0 +1,293 @@
+//! Contains the interface for decoding documents which are not encoded in UTF-8.
+
+use std::borrow::Cow;
+use std::io::{self, Read};
+use std::str;
+
+#[cfg(feature = "encodings")]
+use encodings;
+
+/// Converts the bytes of a document in some encoding into text.
+///
+/// The parser itself reads UTF-8, and a reader created with `EventReader::new_with_decoder()`
+/// passes the bytes read from its source through a decoder. The bytes are passed in chunks
+/// of arbitrary size, down to single bytes, so a chunk may end in the middle of a character;
+/// decoders must keep such incomplete sequences and decode them together with the next chunk.
+///
+/// With the `encodings` feature, this trait is implemented for the decoders of the `encoding_rs`
+/// crate, which support all encodings of the Encoding Standard, like GB18030, EUC-KR or
+/// Shift_JIS. Other encodings can be supported with implementations of this trait:
+///
+/// ```rust
+/// use std::borrow::Cow;
+/// use std::io;
+///
+/// use xml::reader::{Decoder, EventReader, ParserConfig, XmlEvent};
+///
+/// /// Decodes US-ASCII, rejecting the bytes above 0x7F.
+/// struct Ascii;
+///
+/// impl Decoder for Ascii {
+///     fn decode<'a>(&mut self, bytes: &'a [u8], _last: bool) -> io::Result<Cow<'a, str>> {
+///         match bytes.iter().find(|b| !b.is_ascii()) {
+///             Some(b) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("byte 0x{:X} is not ASCII", b))),
+///             None => Ok(bytes.iter().map(|&b| b as char).collect::<String>().into())
+///         }
+///     }
+///
+///     fn encoding(&self) -> &str { "US-ASCII" }
+/// }
+///
+/// let source = &b"<?xml version='1.0' encoding='US-ASCII'?><a>cafe</a>"[..];
+/// let mut reader = EventReader::new_with_decoder(source, Ascii, ParserConfig::new());
+/// match reader.next().unwrap() {
+///     XmlEvent::StartDocument { effective_encoding, .. } => assert_eq!(effective_encoding, "US-ASCII"),
+///     e => panic!("unexpected event: {:?}", e)
+/// }
+/// reader.next().unwrap();
+/// assert_eq!(reader.next().unwrap(), XmlEvent::Characters("cafe".into()));
+///
+/// let source = &b"<a>caf\xE9</a>"[..];
+/// let reader = EventReader::new_with_decoder(source, Ascii, ParserConfig::new());
+/// assert!(reader.into_iter().any(|e| e.is_err()));
+/// ```
+pub trait Decoder {
+    /// Decodes the next chunk of bytes.
+    ///
+    /// `last` is `true` when the source has ended; the chunk is empty then, and an incomplete
+    /// sequence kept from the previous chunks is an error. Errors are reported as I/O errors,
+    /// usually of kind `io::ErrorKind::InvalidData`.
+    fn decode<'a>(&mut self, bytes: &'a [u8], last: bool) -> io::Result<Cow<'a, str>>;
+
+    /// Returns the name of the encoding, which is reported as the effective encoding
+    /// of the document in `XmlEvent::StartDocument`.
+    fn encoding(&self) -> &str;
+}
+
+impl<D: Decoder + ?Sized> Decoder for &mut D {
+    #[inline]
+    fn decode<'a>(&mut self, bytes: &'a [u8], last: bool) -> io::Result<Cow<'a, str>> {
+        (**self).decode(bytes, last)
+    }
+
+    #[inline]
+    fn encoding(&self) -> &str { (**self).encoding() }
+}
+
+impl<D: Decoder + ?Sized> Decoder for Box<D> {
+    #[inline]
+    fn decode<'a>(&mut self, bytes: &'a [u8], last: bool) -> io::Result<Cow<'a, str>> {
+        (**self).decode(bytes, last)
+    }
+
+    #[inline]
+    fn encoding(&self) -> &str { (**self).encoding() }
+}
+
+/// A decoder which validates UTF-8.
+///
+/// This is the encoding which the parser reads without any decoder, so this decoder is only
+/// useful where a decoder is required, e.g. when the encoding is chosen at runtime.
+#[derive(Clone, Debug, Default)]
+pub struct Utf8Decoder {
+    // an incomplete sequence at the end of the last chunk
+    pending: Vec<u8>
+}
+
+impl Utf8Decoder {
+    /// Creates a new decoder.
+    #[inline]
+    pub fn new() -> Utf8Decoder { Utf8Decoder::default() }
+}
+
+impl Decoder for Utf8Decoder {
+    fn decode<'a>(&mut self, bytes: &'a [u8], last: bool) -> io::Result<Cow<'a, str>> {
+        if self.pending.is_empty() {
+            if let Ok(s) = str::from_utf8(bytes) {
+                return Ok(s.into());
+            }
+        }
+        let mut input = ::std::mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+        if let Err(e) = str::from_utf8(&input) {
+            if e.error_len().is_some() || last {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+            }
+            self.pending = input.split_off(e.valid_up_to());
+        }
+        Ok(String::from_utf8(input).unwrap().into())
+    }
+
+    #[inline]
+    fn encoding(&self) -> &str { "UTF-8" }
+}
+
+/// A decoder of ISO 8859-1 (Latin-1), where every byte is the character with the same code.
+///
+/// Unlike the decoder of the `iso-8859-1` label of the Encoding Standard, which is an alias
+/// of windows-1252, this decoder maps the bytes 0x80 to 0x9F to the C1 control characters.
+///
+/// ```rust
+/// use xml::reader::{EventReader, Latin1Decoder, ParserConfig, XmlEvent};
+///
+/// let source = &b"<a>caf\xE9\x80</a>"[..];
+/// let mut reader = EventReader::new_with_decoder(source, Latin1Decoder, ParserConfig::new());
+/// reader.next().unwrap();
+/// reader.next().unwrap();
+/// assert_eq!(reader.next().unwrap(), XmlEvent::Characters("caf\u{E9}\u{80}".into()));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Latin1Decoder;
+
+impl Decoder for Latin1Decoder {
+    fn decode<'a>(&mut self, bytes: &'a [u8], _last: bool) -> io::Result<Cow<'a, str>> {
+        if bytes.is_ascii() {
+            Ok(str::from_utf8(bytes).unwrap().into())
+        } else {
+            Ok(bytes.iter().map(|&b| b as char).collect::<String>().into())
+        }
+    }
+
+    #[inline]
+    fn encoding(&self) -> &str { "ISO-8859-1" }
+}
+
+/// Returns a decoder for the encoding declared in a document, if the reader decodes it
+/// instead of reading the document as UTF-8.
+///
+/// These are ISO-8859-1 and, with the `encodings` feature, the other encodings of the Encoding
+/// Standard which are compatible with ASCII, so that the declaration itself can be read before
+/// the decoder is known. Labels of UTF-8, e.g. `utf8`, and of unsupported encodings give `None`.
+pub fn for_declared_encoding(label: &str) -> Option<Box<dyn Decoder + Send>> {
+    let label = label.trim();
+    if ["ISO-8859-1", "ISO8859-1", "ISO_8859-1", "latin1", "l1"].iter().any(|l| l.eq_ignore_ascii_case(label)) {
+        return Some(Box::new(Latin1Decoder));
+    }
+    #[cfg(feature = "encodings")]
+    {
+        if let Some(encoding) = encodings::Encoding::for_label(label.as_bytes()) {
+            if encoding != encodings::UTF_8 && encoding.is_ascii_compatible() {
+                return Some(Box::new(encoding.new_decoder_without_bom_handling()));
+            }
+        }
+    }
+    None
+}
+
+/// A source which decodes the bytes of another source with a `Decoder`, yielding UTF-8.
+///
+/// Unlike readers created with `EventReader::new_with_decoder()`, which decode the stream
+/// one byte at a time, this source reads the underlying source in chunks, so, like with
+/// `EventReader::new_buffered()`, the underlying source may be read ahead of the parser.
+pub struct DecodingReader<R: Read, D: Decoder> {
+    source: R,
+    decoder: D,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    output_pos: usize,
+    finished: bool
+}
+
+impl<R: Read, D: Decoder> DecodingReader<R, D> {
+    /// Creates a new source which reads chunks of at most `chunk_size` bytes from the given
+    /// source.
+    pub fn new(source: R, decoder: D, chunk_size: usize) -> DecodingReader<R, D> {
+        DecodingReader {
+            source,
+            decoder,
+            input: vec![0; chunk_size.max(1)],
+            output: Vec::new(),
+            output_pos: 0,
+            finished: false
+        }
+    }
+
+    /// Returns a reference to the decoder.
+    #[inline]
+    pub fn decoder(&self) -> &D { &self.decoder }
+
+    /// Unwraps this source, returning the underlying source and the decoder.
+    ///
+    /// Bytes which were read from the underlying source but were not consumed by the parser
+    /// are lost.
+    #[inline]
+    pub fn into_inner(self) -> (R, D) { (self.source, self.decoder) }
+}
+
+impl<R: Read, D: Decoder> Read for DecodingReader<R, D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output_pos == self.output.len() && !self.finished {
+            let n = loop {
+                match self.source.read(&mut self.input) {
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    result => break result?
+                }
+            };
+            self.finished = n == 0;
+            let decoded = self.decoder.decode(&self.input[..n], self.finished)?;
+            self.output.clear();
+            self.output.extend_from_slice(decoded.as_bytes());
+            self.output_pos = 0;
+        }
+        let n = buf.len().min(self.output.len() - self.output_pos);
+        buf[..n].copy_from_slice(&self.output[self.output_pos..self.output_pos + n]);
+        self.output_pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::{Decoder, DecodingReader, Latin1Decoder, Utf8Decoder};
+
+    #[test]
+    fn utf8_decoding() {
+        let mut decoder = Utf8Decoder::new();
+        assert_eq!(decoder.decode(b"ab", false).unwrap(), "ab");
+        // a character split between chunks
+        assert_eq!(decoder.decode(b"c\xE2\x98", false).unwrap(), "c");
+        assert_eq!(decoder.decode(b"\xBA", false).unwrap(), "\u{263A}");
+        assert_eq!(decoder.decode(b"", true).unwrap(), "");
+
+        assert!(decoder.decode(b"a\xFFb", false).is_err());
+        let mut decoder = Utf8Decoder::new();
+        assert_eq!(decoder.decode(b"a\xE2", false).unwrap(), "a");
+        assert!(decoder.decode(b"", true).is_err());
+    }
+
+    #[test]
+    fn latin1_decoding() {
+        let mut decoder = Latin1Decoder;
+        assert_eq!(decoder.decode(b"ab", false).unwrap(), "ab");
+        assert_eq!(decoder.decode(b"\xE9\x80\xFF", false).unwrap(), "\u{E9}\u{80}\u{FF}");
+        assert_eq!(decoder.decode(b"", true).unwrap(), "");
+    }
+
+    #[test]
+    fn declared_encodings() {
+        use super::for_declared_encoding;
+
+        assert_eq!(for_declared_encoding("ISO-8859-1").unwrap().encoding(), "ISO-8859-1");
+        assert_eq!(for_declared_encoding("latin1").unwrap().encoding(), "ISO-8859-1");
+        assert!(for_declared_encoding("UTF-8").is_none());
+        assert!(for_declared_encoding("utf8").is_none());
+        assert!(for_declared_encoding("UTF-16").is_none());
+        assert!(for_declared_encoding("unknown").is_none());
+        #[cfg(feature = "encodings")]
+        assert_eq!(for_declared_encoding("Shift_JIS").unwrap().encoding(), "Shift_JIS");
+        #[cfg(not(feature = "encodings"))]
+        assert!(for_declared_encoding("Shift_JIS").is_none());
+    }
+
+    #[test]
+    fn decoding_reader() {
+        for &chunk_size in &[1, 2, 3, 100] {
+            let mut reader = DecodingReader::new(&b"x\xE2\x98\xBAy\xF0\x9F\x98\x80"[..], Utf8Decoder::new(), chunk_size);
+            let mut result = String::new();
+            reader.read_to_string(&mut result).unwrap();
+            assert_eq!(result, "x\u{263A}y\u{1F600}");
+        }
+    }
+}
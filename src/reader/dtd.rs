@@ -0,0 +1,1354 @@
+//! Contains a simple parser for document type declarations.
+//!
+//! This module is for internal use, except for the types describing external entities and
+//! declarations. Only the internal subset is processed; entity, element type and attribute-list
+//! declarations are interpreted, while notation declarations are checked for well-formedness
+//! and skipped.
+
+use std::borrow::Cow;
+use std::char;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Read};
+
+use common::{is_name_start_char, is_name_char, is_whitespace_char, XmlVersion};
+use reader::config::ParserConfig;
+use reader::error::ErrorKind;
+use util::parse_xml_char_reference;
+
+/// A declared entity.
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Entity {
+    /// An internal entity with its replacement text.
+    ///
+    /// Character references in the entity value are already replaced, while general
+    /// entity references are left intact.
+    Internal(String),
+
+    /// An external entity.
+    External(ExternalEntity)
+}
+
+/// The external identifier of an entity, as declared with `SYSTEM` or `PUBLIC` keywords.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ExternalId {
+    /// The public identifier, if the entity is declared with the `PUBLIC` keyword.
+    pub public_id: Option<String>,
+
+    /// The system identifier, usually a URI of the entity content.
+    pub system_id: String
+}
+
+/// Fetches the content of external entities and of the external DTD subset.
+///
+/// See `ParserConfig::entity_resolver()` for more information. The trait is implemented for
+/// functions taking an `ExternalId`, which resolve every entity:
+///
+/// ```rust
+/// use std::io::{self, BufRead, Cursor};
+/// use xml::reader::{EntityResolver, ExternalId, Result};
+///
+/// let mut resolver = |id: &ExternalId| -> io::Result<Box<dyn BufRead>> {
+///     Ok(Box::new(Cursor::new(format!("content of {}", id.system_id))))
+/// };
+/// let mut content = String::new();
+/// resolver.resolve(None, "a.ent").unwrap().unwrap().read_line(&mut content).unwrap();
+/// assert_eq!(content, "content of a.ent");
+/// ```
+pub trait EntityResolver {
+    /// Returns a reader of the content of the entity with the given public and system
+    /// identifiers, or `None` to handle the entity as if there were no resolver.
+    fn resolve(&mut self, public_id: Option<&str>, system_id: &str) -> ::reader::Result<Option<Box<dyn BufRead>>>;
+}
+
+impl<F> EntityResolver for F where F: FnMut(&ExternalId) -> io::Result<Box<dyn BufRead>> {
+    fn resolve(&mut self, public_id: Option<&str>, system_id: &str) -> ::reader::Result<Option<Box<dyn BufRead>>> {
+        let id = ExternalId { public_id: public_id.map(|id| id.into()), system_id: system_id.into() };
+        Ok(Some(self(&id)?))
+    }
+}
+
+/// An external entity. The `notation` is present for unparsed entities.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ExternalEntity {
+    id: ExternalId,
+    notation: Option<String>
+}
+
+/// The content model of an element type, as declared with `<!ELEMENT>`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ContentModel {
+    /// `EMPTY`: the element has no content.
+    Empty,
+
+    /// `ANY`: the element may contain any declared elements and text.
+    Any,
+
+    /// Mixed content, e.g. `(#PCDATA | b | i)*`: text and any of the given elements,
+    /// in any order. The list is empty for `(#PCDATA)`.
+    Mixed(Vec<String>),
+
+    /// Element content, e.g. `(head, body)`: child elements as described by the particle,
+    /// and no text other than whitespace.
+    Children(ContentParticle)
+}
+
+/// A particle of an element content model: an element name or a group of particles,
+/// together with how many times it may occur.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ContentParticle {
+    /// The element name or group.
+    pub kind: ParticleKind,
+
+    /// How many times the particle may occur.
+    pub repetition: Repetition
+}
+
+/// The contents of a `ContentParticle`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParticleKind {
+    /// An element with the given name.
+    Element(String),
+
+    /// A sequence of particles, e.g. `(a, b)`, which must occur in the given order.
+    Sequence(Vec<ContentParticle>),
+
+    /// A choice of particles, e.g. `(a | b)`, one of which must occur.
+    Choice(Vec<ContentParticle>)
+}
+
+/// How many times a `ContentParticle` may occur.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Repetition {
+    /// Exactly once.
+    Once,
+
+    /// `?`: once or not at all.
+    Optional,
+
+    /// `*`: any number of times.
+    ZeroOrMore,
+
+    /// `+`: at least once.
+    OneOrMore
+}
+
+impl fmt::Display for ContentModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ContentModel::Empty => f.write_str("EMPTY"),
+            ContentModel::Any => f.write_str("ANY"),
+            ContentModel::Mixed(ref names) if names.is_empty() => f.write_str("(#PCDATA)"),
+            ContentModel::Mixed(ref names) => write!(f, "(#PCDATA|{})*", names.join("|")),
+            ContentModel::Children(ref particle) => particle.fmt(f)
+        }
+    }
+}
+
+impl fmt::Display for ContentParticle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ParticleKind::Element(ref name) => f.write_str(name)?,
+            ParticleKind::Sequence(ref particles) | ParticleKind::Choice(ref particles) => {
+                let separator = if let ParticleKind::Sequence(_) = self.kind { "," } else { "|" };
+                f.write_str("(")?;
+                for (i, p) in particles.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(separator)?;
+                    }
+                    p.fmt(f)?;
+                }
+                f.write_str(")")?;
+            }
+        }
+        f.write_str(match self.repetition {
+            Repetition::Once => "",
+            Repetition::Optional => "?",
+            Repetition::ZeroOrMore => "*",
+            Repetition::OneOrMore => "+"
+        })
+    }
+}
+
+/// The declaration of an attribute in an `<!ATTLIST>` declaration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AttributeDeclaration {
+    /// The qualified name of the attribute, as it is declared, e.g. `xml:lang`.
+    pub name: String,
+
+    /// The type of the attribute.
+    pub attribute_type: AttributeType,
+
+    /// Whether the attribute is required, and its default value.
+    pub default: AttributeDefault
+}
+
+/// The type of a declared attribute.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AttributeType {
+    /// `CDATA`: any text.
+    CData,
+    /// `ID`.
+    Id,
+    /// `IDREF`.
+    IdRef,
+    /// `IDREFS`.
+    IdRefs,
+    /// `ENTITY`.
+    Entity,
+    /// `ENTITIES`.
+    Entities,
+    /// `NMTOKEN`.
+    NmToken,
+    /// `NMTOKENS`.
+    NmTokens,
+    /// `NOTATION (a | b)`: one of the given notation names.
+    Notation(Vec<String>),
+    /// `(a | b)`: one of the given tokens.
+    Enumeration(Vec<String>)
+}
+
+/// Whether a declared attribute is required, and its default value.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AttributeDefault {
+    /// `#REQUIRED`: the attribute must be specified.
+    Required,
+
+    /// `#IMPLIED`: the attribute is optional and has no default value.
+    Implied,
+
+    /// `#FIXED "value"`: the attribute must have the given value, which is also its default.
+    Fixed(String),
+
+    /// `"value"`: the attribute is optional and has the given default value.
+    Value(String)
+}
+
+/// A finite automaton which matches sequences of child elements against an element
+/// content model.
+///
+/// States are indices into `transitions`; a transition without a name is an epsilon
+/// transition. The start state is 0.
+#[derive(Clone, Debug)]
+pub struct ContentAutomaton {
+    transitions: Vec<Vec<(Option<String>, usize)>>,
+    accepting: usize
+}
+
+impl ContentAutomaton {
+    fn new(particle: &ContentParticle) -> ContentAutomaton {
+        let mut automaton = ContentAutomaton { transitions: vec![Vec::new()], accepting: 0 };
+        automaton.accepting = automaton.add_particle(particle, 0);
+        automaton
+    }
+
+    fn add_state(&mut self) -> usize {
+        self.transitions.push(Vec::new());
+        self.transitions.len() - 1
+    }
+
+    /// Adds the states matching the particle, starting from the given state, and returns
+    /// the state at the end of the particle.
+    fn add_particle(&mut self, particle: &ContentParticle, start: usize) -> usize {
+        let inner_start = self.add_state();
+        self.transitions[start].push((None, inner_start));
+        let inner_end = match particle.kind {
+            ParticleKind::Element(ref name) => {
+                let end = self.add_state();
+                self.transitions[inner_start].push((Some(name.clone()), end));
+                end
+            }
+            ParticleKind::Sequence(ref particles) =>
+                particles.iter().fold(inner_start, |state, p| self.add_particle(p, state)),
+            ParticleKind::Choice(ref particles) => {
+                let end = self.add_state();
+                for p in particles {
+                    let state = self.add_particle(p, inner_start);
+                    self.transitions[state].push((None, end));
+                }
+                end
+            }
+        };
+        let end = self.add_state();
+        self.transitions[inner_end].push((None, end));
+        match particle.repetition {
+            Repetition::Once => {}
+            Repetition::Optional => self.transitions[start].push((None, end)),
+            Repetition::ZeroOrMore => {
+                self.transitions[start].push((None, end));
+                self.transitions[inner_end].push((None, inner_start));
+            }
+            Repetition::OneOrMore => self.transitions[inner_end].push((None, inner_start))
+        }
+        end
+    }
+
+    /// Adds the states reachable with epsilon transitions to the set.
+    fn close(&self, mut states: Vec<usize>) -> Vec<usize> {
+        let mut i = 0;
+        while i < states.len() {
+            for &(ref name, target) in &self.transitions[states[i]] {
+                if name.is_none() && !states.contains(&target) {
+                    states.push(target);
+                }
+            }
+            i += 1;
+        }
+        states
+    }
+
+    /// Returns the states before any child element.
+    pub fn start(&self) -> Vec<usize> {
+        self.close(vec![0])
+    }
+
+    /// Returns the states after a child element with the given name; the result is empty
+    /// if the element is not allowed.
+    pub fn step(&self, states: &[usize], name: &str) -> Vec<usize> {
+        let mut next = Vec::new();
+        for &state in states {
+            for &(ref n, target) in &self.transitions[state] {
+                if n.as_ref().is_some_and(|n| n == name) && !next.contains(&target) {
+                    next.push(target);
+                }
+            }
+        }
+        self.close(next)
+    }
+
+    /// Checks whether the content may end in one of the states.
+    #[inline]
+    pub fn accepts(&self, states: &[usize]) -> bool {
+        states.contains(&self.accepting)
+    }
+}
+
+/// Entities, element types and attribute lists declared in a document type declaration.
+///
+/// Internal entities are kept separately from external ones, so their replacement texts
+/// can be exposed as plain maps.
+#[derive(Clone, Default)]
+pub struct Dtd {
+    root_name: Option<String>,
+    external_subset: Option<ExternalId>,
+    internal_subset: Option<String>,
+    general_entities: HashMap<String, String>,
+    parameter_entities: HashMap<String, String>,
+    external_general_entities: HashMap<String, ExternalEntity>,
+    external_parameter_entities: HashMap<String, ExternalEntity>,
+    elements: HashMap<String, ContentModel>,
+    automata: HashMap<String, ContentAutomaton>,
+    attribute_lists: HashMap<String, Vec<AttributeDeclaration>>,
+    // the version of the document, which determines the characters allowed in references
+    xml_version: Option<XmlVersion>
+}
+
+/// A cursor over a piece of the document type declaration.
+struct Cursor<'a> {
+    input: &'a str
+}
+
+impl<'a> Cursor<'a> {
+    #[inline]
+    fn new(input: &'a str) -> Cursor<'a> { Cursor { input } }
+
+    #[inline]
+    fn is_empty(&self) -> bool { self.input.is_empty() }
+
+    #[inline]
+    fn peek(&self) -> Option<char> { self.input.chars().next() }
+
+    #[inline]
+    fn starts_with(&self, s: &str) -> bool { self.input.starts_with(s) }
+
+    #[inline]
+    fn advance(&mut self, n: usize) { self.input = &self.input[n..]; }
+
+    /// Skips whitespace, returning `true` if there was any.
+    fn skip_whitespace(&mut self) -> bool {
+        let rest = self.input.trim_start_matches(is_whitespace_char);
+        let skipped = rest.len() != self.input.len();
+        self.input = rest;
+        skipped
+    }
+
+    fn require_whitespace(&mut self, context: &str) -> Result<(), String> {
+        if self.skip_whitespace() {
+            Ok(())
+        } else {
+            Err(format!("Expected whitespace {}", context))
+        }
+    }
+
+    fn expect(&mut self, s: &str, context: &str) -> Result<(), String> {
+        if self.starts_with(s) {
+            self.advance(s.len());
+            Ok(())
+        } else {
+            Err(format!("Expected '{}' {}", s, context))
+        }
+    }
+
+    fn read_name(&mut self) -> Option<&'a str> {
+        match self.peek() {
+            Some(c) if is_name_start_char(c) => {}
+            _ => return None
+        }
+        let len = self.input.find(|c| !is_name_char(c)).unwrap_or(self.input.len());
+        let name = &self.input[..len];
+        self.advance(len);
+        Some(name)
+    }
+
+    fn read_nmtoken(&mut self) -> Option<&'a str> {
+        let len = self.input.find(|c| !is_name_char(c)).unwrap_or(self.input.len());
+        if len == 0 {
+            return None;
+        }
+        let token = &self.input[..len];
+        self.advance(len);
+        Some(token)
+    }
+
+    fn read_quoted(&mut self) -> Result<&'a str, String> {
+        let quote = match self.peek() {
+            Some(c) if c == '"' || c == '\'' => c,
+            _ => return Err("Expected a quoted literal".into())
+        };
+        match self.input[1..].find(quote) {
+            Some(len) => {
+                let literal = &self.input[1..len + 1];
+                self.advance(len + 2);
+                Ok(literal)
+            }
+            None => Err("Unterminated quoted literal".into())
+        }
+    }
+
+    /// Skips everything up to and including the given string.
+    fn skip_past(&mut self, s: &str, context: &str) -> Result<(), String> {
+        match self.input.find(s) {
+            Some(pos) => {
+                self.advance(pos + s.len());
+                Ok(())
+            }
+            None => Err(format!("Unexpected end of {}", context))
+        }
+    }
+
+    /// Reads an external identifier: `SYSTEM "system"` or `PUBLIC "public" "system"`.
+    fn read_external_id(&mut self) -> Result<(Option<String>, String), String> {
+        if self.starts_with("SYSTEM") {
+            self.advance("SYSTEM".len());
+            self.require_whitespace("after SYSTEM")?;
+            Ok((None, self.read_quoted()?.into()))
+        } else if self.starts_with("PUBLIC") {
+            self.advance("PUBLIC".len());
+            self.require_whitespace("after PUBLIC")?;
+            let public_id = self.read_quoted()?.into();
+            self.require_whitespace("after public identifier")?;
+            Ok((Some(public_id), self.read_quoted()?.into()))
+        } else {
+            Err("Expected an entity value or an external identifier".into())
+        }
+    }
+}
+
+static PE_IN_MARKUP_ERROR: &str =
+    "Parameter entity references are not allowed within markup declarations in the internal subset";
+
+// signals that the replacement text of an entity referenced in content contains markup
+static MARKUP_IN_CONTENT: &str = "Entity replacement text contains markup";
+
+/// The expansion of a reference to a general entity.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Replacement {
+    /// The fully expanded text of the entity.
+    Text(String),
+
+    /// The replacement text of the entity, which contains markup, with its references not
+    /// expanded yet.
+    Markup(String)
+}
+
+impl Dtd {
+    /// Returns the replacement texts of the declared internal general entities.
+    #[inline]
+    pub fn general_entities(&self) -> &HashMap<String, String> {
+        &self.general_entities
+    }
+
+    /// Returns the replacement texts of the declared internal parameter entities.
+    #[inline]
+    pub fn parameter_entities(&self) -> &HashMap<String, String> {
+        &self.parameter_entities
+    }
+
+    /// Returns the name of the root element given in the document type declaration, if
+    /// the document has one.
+    #[inline]
+    pub fn root_name(&self) -> Option<&str> {
+        self.root_name.as_ref().map(|n| &n[..])
+    }
+
+    /// Returns the identifier of the external subset given in the document type declaration.
+    #[inline]
+    pub fn external_subset(&self) -> Option<&ExternalId> {
+        self.external_subset.as_ref()
+    }
+
+    /// Returns the internal subset of the document type declaration, as it is written in
+    /// the document, without the enclosing brackets.
+    #[inline]
+    pub fn internal_subset(&self) -> Option<&str> {
+        self.internal_subset.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the content models of the declared element types.
+    #[inline]
+    pub fn element_declarations(&self) -> &HashMap<String, ContentModel> {
+        &self.elements
+    }
+
+    /// Returns the automaton of the element content model of the given element type, if it
+    /// has one.
+    #[inline]
+    pub fn content_automaton(&self, name: &str) -> Option<&ContentAutomaton> {
+        self.automata.get(name)
+    }
+
+    /// Returns the declared attributes of element types.
+    #[inline]
+    pub fn attribute_declarations(&self) -> &HashMap<String, Vec<AttributeDeclaration>> {
+        &self.attribute_lists
+    }
+
+    /// Parses the contents of a `<!DOCTYPE` tag, remembering the declarations.
+    ///
+    /// On error, returns the message together with the byte offset in `doctype` at which
+    /// the erroneous declaration or parameter entity reference starts.
+    ///
+    /// If the declaration refers to an external subset which is resolved with the resolver
+    /// of the configuration, the external subset is parsed after the internal subset, so that declarations of
+    /// the internal subset take precedence. Errors in the external subset are reported at
+    /// the end of the declaration.
+    pub fn parse(&mut self, doctype: &str, config: &ParserConfig, version: XmlVersion) -> Result<(), (usize, String)> {
+        self.xml_version = Some(version);
+        let mut cursor = Cursor::new(doctype);
+        self.parse_doctype(&mut cursor, config)
+            .map_err(|e| (doctype.len() - cursor.input.len(), e))
+    }
+
+    fn parse_doctype(&mut self, cursor: &mut Cursor, config: &ParserConfig) -> Result<(), String> {
+        cursor.require_whitespace("after <!DOCTYPE")?;
+        match cursor.read_name() {
+            Some(name) => self.root_name = Some(name.into()),
+            None => return Err("Expected the root element name in the document type declaration".into())
+        }
+        cursor.skip_whitespace();
+        if cursor.starts_with("SYSTEM") || cursor.starts_with("PUBLIC") {
+            let (public_id, system_id) = cursor.read_external_id()?;
+            self.external_subset = Some(ExternalId { public_id, system_id });
+            cursor.skip_whitespace();
+        }
+        if cursor.starts_with("[") {
+            cursor.advance(1);
+            let subset = cursor.input;
+            let mut expanded = 0;
+            self.parse_subset(cursor, config, 0, &mut expanded)?;
+            // the cursor is right after the closing bracket
+            self.internal_subset = Some(subset[..subset.len() - cursor.input.len() - 1].into());
+            cursor.skip_whitespace();
+        }
+        if !cursor.is_empty() {
+            return Err(format!("Unexpected content in the document type declaration: {}", cursor.input));
+        }
+        match self.external_subset.clone() {
+            Some(id) => self.parse_external_subset(&id, config),
+            None => Ok(())
+        }
+    }
+
+    /// Fetches and parses the external subset with the given identifier.
+    ///
+    /// Only declarations which are allowed in the internal subset are supported; in particular,
+    /// conditional sections and parameter entity references within markup declarations are not.
+    fn parse_external_subset(&mut self, id: &ExternalId, config: &ParserConfig) -> Result<(), String> {
+        let what = format!("the external DTD subset {:?}", id.system_id);
+        let content = match fetch_external_text(id, &what, config)? {
+            Some(content) => content,
+            // without a resolver, the external subset is not read at all
+            None => return Ok(())
+        };
+        let mut expanded = 0;
+        // like the replacement text of a parameter entity, the subset ends with its content
+        self.parse_subset(&mut Cursor::new(&content), config, 1, &mut expanded).map_err(|e| {
+            let e = if e == PE_IN_MARKUP_ERROR {
+                "Parameter entity references within markup declarations are not supported"
+            } else {
+                &e[..]
+            };
+            format!("{} in {}", e, what)
+        })
+    }
+
+    /// Parses declarations of the internal subset, either up to the closing `]` when `depth`
+    /// is zero, or up to the end of the replacement text of a parameter entity.
+    ///
+    /// When `depth` is zero and an error occurs, the cursor is left at the beginning of the
+    /// erroneous declaration or reference.
+    fn parse_subset(&mut self, cursor: &mut Cursor, config: &ParserConfig,
+                    depth: u8, expanded: &mut usize) -> Result<(), String> {
+        loop {
+            cursor.skip_whitespace();
+            if cursor.is_empty() {
+                return if depth == 0 { Err("Unexpected end of the internal subset".into()) } else { Ok(()) };
+            }
+
+            if depth == 0 && cursor.starts_with("]") {
+                cursor.advance(1);
+                return Ok(());
+            }
+
+            let start = cursor.input;
+            if let Err(e) = self.parse_subset_item(cursor, config, depth, expanded) {
+                if depth == 0 {
+                    cursor.input = start;
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    /// Parses a single declaration, comment, processing instruction or parameter entity
+    /// reference of the internal subset.
+    fn parse_subset_item(&mut self, cursor: &mut Cursor, config: &ParserConfig,
+                         depth: u8, expanded: &mut usize) -> Result<(), String> {
+        if cursor.starts_with("%") {
+            cursor.advance(1);
+            let name = cursor.read_name().ok_or("Invalid parameter entity reference")?;
+            cursor.expect(";", "at the end of parameter entity reference")?;
+            self.expand_parameter_entity(name, config, depth, expanded)
+        } else if cursor.starts_with("<!--") {
+            cursor.skip_past("-->", "comment")
+        } else if cursor.starts_with("<?") {
+            cursor.skip_past("?>", "processing instruction")
+        } else if cursor.starts_with("<!ENTITY") {
+            cursor.advance("<!ENTITY".len());
+            self.parse_entity_declaration(cursor, config)
+        } else if cursor.starts_with("<!ELEMENT") {
+            cursor.advance("<!ELEMENT".len());
+            self.parse_element_declaration(cursor)
+        } else if cursor.starts_with("<!ATTLIST") {
+            cursor.advance("<!ATTLIST".len());
+            self.parse_attribute_list_declaration(cursor, config)
+        } else if cursor.starts_with("<!NOTATION") {
+            skip_markup_declaration(cursor)
+        } else if cursor.starts_with("<![") {
+            Err("Conditional sections are not supported".into())
+        } else {
+            let c = cursor.peek().unwrap();
+            Err(format!("Unexpected character in the internal subset: {}", c))
+        }
+    }
+
+    fn expand_parameter_entity(&mut self, name: &str, config: &ParserConfig,
+                               depth: u8, expanded: &mut usize) -> Result<(), String> {
+        let value = match self.parameter_entities.get(name) {
+            Some(value) => value.clone(),
+            // external parameter entities are not read
+            None if self.external_parameter_entities.contains_key(name) => return Ok(()),
+            None => return Err(format!("Undeclared parameter entity: %{};", name))
+        };
+        if depth >= config.get_max_entity_expansion_depth() {
+            return Err(format!("Entity expansion depth limit exceeded: more than {} levels",
+                               config.get_max_entity_expansion_depth()));
+        }
+        *expanded += value.len();
+        if *expanded > config.get_max_entity_expansion_length() {
+            return Err(format!("Entity expansion length limit exceeded: more than {} bytes",
+                               config.get_max_entity_expansion_length()));
+        }
+        self.parse_subset(&mut Cursor::new(&value), config, depth + 1, expanded)
+    }
+
+    /// Parses an entity declaration after `<!ENTITY`.
+    fn parse_entity_declaration(&mut self, cursor: &mut Cursor, config: &ParserConfig) -> Result<(), String> {
+        cursor.require_whitespace("after <!ENTITY")?;
+        let parameter = cursor.starts_with("%");
+        if parameter {
+            cursor.advance(1);
+            if !cursor.skip_whitespace() {
+                return Err(PE_IN_MARKUP_ERROR.into());
+            }
+        }
+        let name = cursor.read_name().ok_or("Invalid entity name in entity declaration")?;
+        cursor.require_whitespace("after entity name")?;
+
+        let entity = match cursor.peek() {
+            Some('"') | Some('\'') => {
+                let value = expand_entity_value(cursor.read_quoted()?, self.version())?;
+                if value.len() > config.get_max_entity_expansion_length() {
+                    return Err(format!("Entity expansion length limit exceeded: more than {} bytes",
+                                       config.get_max_entity_expansion_length()));
+                }
+                Entity::Internal(value)
+            }
+            _ => {
+                let (public_id, system_id) = cursor.read_external_id()?;
+                let had_whitespace = cursor.skip_whitespace();
+                let notation = if !parameter && had_whitespace && cursor.starts_with("NDATA") {
+                    cursor.advance("NDATA".len());
+                    cursor.require_whitespace("after NDATA")?;
+                    Some(cursor.read_name().ok_or("Invalid notation name in entity declaration")?.into())
+                } else {
+                    None
+                };
+                Entity::External(ExternalEntity { id: ExternalId { public_id, system_id }, notation })
+            }
+        };
+        cursor.skip_whitespace();
+        cursor.expect(">", "at the end of entity declaration")?;
+
+        let (internal, external) = if parameter {
+            (&mut self.parameter_entities, &mut self.external_parameter_entities)
+        } else {
+            (&mut self.general_entities, &mut self.external_general_entities)
+        };
+        // the first declaration is binding
+        if !internal.contains_key(name) && !external.contains_key(name) {
+            match entity {
+                Entity::Internal(value) => { internal.insert(name.into(), value); }
+                Entity::External(entity) => { external.insert(name.into(), entity); }
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses an element type declaration after `<!ELEMENT`.
+    fn parse_element_declaration(&mut self, cursor: &mut Cursor) -> Result<(), String> {
+        // checks for parameter entity references and the end of the declaration
+        skip_markup_declaration(&mut Cursor::new(cursor.input))?;
+        cursor.require_whitespace("after <!ELEMENT")?;
+        let name = cursor.read_name().ok_or("Invalid element name in element type declaration")?;
+        cursor.require_whitespace("after element name")?;
+
+        let model = if cursor.starts_with("EMPTY") {
+            cursor.advance("EMPTY".len());
+            ContentModel::Empty
+        } else if cursor.starts_with("ANY") {
+            cursor.advance("ANY".len());
+            ContentModel::Any
+        } else if cursor.starts_with("(") {
+            cursor.advance(1);
+            cursor.skip_whitespace();
+            if cursor.starts_with("#PCDATA") {
+                cursor.advance("#PCDATA".len());
+                read_mixed_content(cursor)?
+            } else {
+                ContentModel::Children(read_group(cursor)?)
+            }
+        } else {
+            return Err("Expected a content specification in element type declaration".into());
+        };
+        cursor.skip_whitespace();
+        cursor.expect(">", "at the end of element type declaration")?;
+
+        // declaring an element type more than once makes a document invalid; the first
+        // declaration is used
+        if !self.elements.contains_key(name) {
+            if let ContentModel::Children(ref particle) = model {
+                self.automata.insert(name.into(), ContentAutomaton::new(particle));
+            }
+            self.elements.insert(name.into(), model);
+        }
+        Ok(())
+    }
+
+    /// Parses an attribute-list declaration after `<!ATTLIST`.
+    fn parse_attribute_list_declaration(&mut self, cursor: &mut Cursor, config: &ParserConfig) -> Result<(), String> {
+        skip_markup_declaration(&mut Cursor::new(cursor.input))?;
+        cursor.require_whitespace("after <!ATTLIST")?;
+        let element = cursor.read_name().ok_or("Invalid element name in attribute-list declaration")?;
+
+        let mut declarations = Vec::new();
+        loop {
+            let had_whitespace = cursor.skip_whitespace();
+            if cursor.starts_with(">") {
+                cursor.advance(1);
+                break;
+            }
+            if !had_whitespace {
+                return Err("Expected whitespace before attribute definition".into());
+            }
+            let name = cursor.read_name().ok_or("Invalid attribute name in attribute-list declaration")?;
+            cursor.require_whitespace("after attribute name")?;
+            let attribute_type = read_attribute_type(cursor)?;
+            cursor.require_whitespace("after attribute type")?;
+            let default = if cursor.starts_with("#REQUIRED") {
+                cursor.advance("#REQUIRED".len());
+                AttributeDefault::Required
+            } else if cursor.starts_with("#IMPLIED") {
+                cursor.advance("#IMPLIED".len());
+                AttributeDefault::Implied
+            } else {
+                let fixed = cursor.starts_with("#FIXED");
+                if fixed {
+                    cursor.advance("#FIXED".len());
+                    cursor.require_whitespace("after #FIXED")?;
+                }
+                let mut value = self.read_default_value(cursor.read_quoted()?, config)?;
+                if attribute_type != AttributeType::CData {
+                    value = normalize_tokens(&value);
+                }
+                if fixed { AttributeDefault::Fixed(value) } else { AttributeDefault::Value(value) }
+            };
+            declarations.push(AttributeDeclaration { name: name.into(), attribute_type, default });
+        }
+
+        // attribute lists of an element type are merged, and the first declaration
+        // of an attribute is binding
+        let list = self.attribute_lists.entry(element.into()).or_default();
+        for declaration in declarations {
+            if !list.iter().any(|d| d.name == declaration.name) {
+                list.push(declaration);
+            }
+        }
+        Ok(())
+    }
+
+    /// Normalizes the literal of an attribute default value, replacing references and
+    /// whitespace characters.
+    /// Returns the version of the document, XML 1.0 until it is known.
+    #[inline]
+    fn version(&self) -> XmlVersion {
+        self.xml_version.unwrap_or(XmlVersion::Version10)
+    }
+
+    fn read_default_value(&self, literal: &str, config: &ParserConfig) -> Result<String, String> {
+        let mut value = String::with_capacity(literal.len());
+        let mut rest = literal;
+        while let Some(start) = rest.find(&['&', '<', '\t', '\n', '\r'][..]) {
+            value.push_str(&rest[..start]);
+            if !rest[start..].starts_with('&') {
+                if rest[start..].starts_with('<') {
+                    return Err("Unexpected '<' in attribute default value".into());
+                }
+                value.push(' ');
+                rest = &rest[start + 1..];
+                continue;
+            }
+            let end = match rest[start..].find(';') {
+                Some(len) => start + len,
+                None => return Err("Unterminated reference in attribute default value".into())
+            };
+            let reference = &rest[start + 1..end];
+            rest = &rest[end + 1..];
+            match reference {
+                "lt"   => value.push('<'),
+                "gt"   => value.push('>'),
+                "amp"  => value.push('&'),
+                "apos" => value.push('\''),
+                "quot" => value.push('"'),
+                _ if reference.starts_with('#') => value.push(parse_xml_char_reference(&reference[1..], self.version())?),
+                _ => {
+                    // entities must be declared before they are referenced in default values
+                    let expanded = match config.get_extra_entities().get(reference) {
+                        Some(v) => v.clone(),
+                        // markup is an error in attribute values, so the replacement is always text
+                        None => match self.expand_general_entity(reference, config, true)? {
+                            Some(Replacement::Text(v)) | Some(Replacement::Markup(v)) => v,
+                            None => return Err(format!("Undeclared entity in attribute default value: &{};", reference))
+                        }
+                    };
+                    value.extend(expanded.chars().map(|c| if is_whitespace_char(c) { ' ' } else { c }));
+                }
+            }
+        }
+        value.push_str(rest);
+        Ok(value)
+    }
+
+    /// Expands a reference to a declared general entity.
+    ///
+    /// External entities are fetched with the resolver of the configuration; references to
+    /// them are not allowed in attribute values. Returns `Ok(None)` if there is no such entity.
+    ///
+    /// In content, if the replacement text of the entity, or of an entity it refers to,
+    /// contains markup, the replacement text of the referenced entity is returned as
+    /// `Replacement::Markup` as it is, to be parsed like the rest of the document.
+    pub fn expand_general_entity(&self, name: &str, config: &ParserConfig,
+                                 in_attribute: bool) -> Result<Option<Replacement>, String> {
+        if !self.general_entities.contains_key(name) && !self.external_general_entities.contains_key(name) {
+            return Ok(None);
+        }
+        let value = self.replacement_text(name, config, in_attribute)?;
+        let mut result = String::new();
+        match self.expand_value_into(name, &value, config, in_attribute, 0, &mut result) {
+            Ok(()) => Ok(Some(Replacement::Text(result))),
+            Err(ref e) if e == MARKUP_IN_CONTENT => Ok(Some(Replacement::Markup(value.into_owned()))),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Returns the replacement text of a declared general entity, fetching the content of
+    /// external entities.
+    fn replacement_text(&self, name: &str, config: &ParserConfig, in_attribute: bool) -> Result<Cow<'_, str>, String> {
+        match self.general_entities.get(name) {
+            Some(value) => Ok(Cow::Borrowed(value)),
+            None => match self.external_general_entities.get(name) {
+                Some(&ExternalEntity { notation: Some(_), .. }) =>
+                    Err(format!("Unparsed entity cannot be referenced: &{};", name)),
+                Some(_) if in_attribute =>
+                    Err(format!("External entity references are not allowed in attribute values: &{};", name)),
+                Some(entity) => match fetch_external_text(&entity.id, &format!("external entity &{};", name), config)? {
+                    Some(content) => Ok(Cow::Owned(content)),
+                    None if config.get_skip_external_entities() => Ok(Cow::Borrowed("")),
+                    None => Err(format!("External entities are disabled, as no resolver is set: &{};", name))
+                },
+                None => Err(format!("Unexpected entity: {}", name))
+            }
+        }
+    }
+
+    fn expand_general_entity_into(&self, name: &str, config: &ParserConfig, in_attribute: bool,
+                                  depth: u8, result: &mut String) -> Result<(), String> {
+        let value = self.replacement_text(name, config, in_attribute)?;
+        self.expand_value_into(name, &value, config, in_attribute, depth, result)
+    }
+
+    /// Expands the references in the replacement text of the given entity.
+    fn expand_value_into(&self, name: &str, value: &str, config: &ParserConfig, in_attribute: bool,
+                         depth: u8, result: &mut String) -> Result<(), String> {
+        if depth >= config.get_max_entity_expansion_depth() {
+            return Err(format!("Entity expansion depth limit exceeded: more than {} levels",
+                               config.get_max_entity_expansion_depth()));
+        }
+        if value.contains('<') {
+            return Err(if in_attribute {
+                format!("Entity replacement text containing markup is not allowed in attribute values: &{};", name)
+            } else {
+                MARKUP_IN_CONTENT.into()
+            });
+        }
+
+        let mut rest = value;
+        while let Some(start) = rest.find('&') {
+            result.push_str(&rest[..start]);
+            // internal entity values are checked on declaration, but external ones are not
+            let end = match rest[start..].find(';') {
+                Some(len) => start + len,
+                None => return Err(format!("Unterminated reference in the replacement text of &{};", name))
+            };
+            let reference = &rest[start + 1..end];
+            rest = &rest[end + 1..];
+            match reference {
+                "lt"   => result.push('<'),
+                "gt"   => result.push('>'),
+                "amp"  => result.push('&'),
+                "apos" => result.push('\''),
+                "quot" => result.push('"'),
+                _ if reference.starts_with('#') => result.push(parse_xml_char_reference(&reference[1..], self.version())?),
+                _ => match config.get_extra_entities().get(reference) {
+                    Some(v) => result.push_str(v),
+                    None => self.expand_general_entity_into(reference, config, in_attribute,
+                                                            depth + 1, result)?
+                }
+            }
+            if result.len() > config.get_max_entity_expansion_length() {
+                break;
+            }
+        }
+        result.push_str(rest);
+
+        if result.len() > config.get_max_entity_expansion_length() {
+            Err(format!("Entity expansion length limit exceeded: more than {} bytes",
+                        config.get_max_entity_expansion_length()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Reads the content of an external parsed entity or the external subset with the resolver
+/// of the configuration, skipping its text declaration. Returns `None` if the entity is not
+/// resolved. `what` describes the entity in error messages.
+fn fetch_external_text(id: &ExternalId, what: &str, config: &ParserConfig) -> Result<Option<String>, String> {
+    let source = match config.resolve_entity(id.public_id.as_ref().map(|id| &id[..]), &id.system_id) {
+        Ok(Some(source)) => source,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            // the messages of I/O errors are only available through `Display`
+            let reason = match *e.kind() {
+                ErrorKind::Io(ref e) => e.to_string(),
+                _ => e.msg().into()
+            };
+            return Err(format!("Failed to read {}: {}", what, reason));
+        }
+    };
+    let mut content = String::new();
+    let limit = config.get_max_entity_expansion_length() as u64 + 1;
+    source.take(limit).read_to_string(&mut content).map_err(|e| format!("Failed to read {}: {}", what, e))?;
+    if content.len() as u64 >= limit {
+        return Err(format!("Entity expansion length limit exceeded: more than {} bytes",
+                           config.get_max_entity_expansion_length()));
+    }
+
+    if content.starts_with("<?xml") && content[5..].starts_with(is_whitespace_char) {
+        match content.find("?>") {
+            Some(end) => { content.drain(..end + 2); }
+            None => return Err(format!("Unterminated text declaration in {}", what))
+        }
+    }
+    Ok(Some(content))
+}
+
+/// Skips an element, attribute list or notation declaration.
+fn skip_markup_declaration(cursor: &mut Cursor) -> Result<(), String> {
+    loop {
+        match cursor.peek() {
+            Some('>') => {
+                cursor.advance(1);
+                return Ok(());
+            }
+            Some('"') | Some('\'') => { cursor.read_quoted()?; }
+            Some('%') if cursor.input[1..].starts_with(is_name_start_char) =>
+                return Err(PE_IN_MARKUP_ERROR.into()),
+            Some(c) => cursor.advance(c.len_utf8()),
+            None => return Err("Unexpected end of markup declaration".into())
+        }
+    }
+}
+
+/// Reads the rest of a mixed content declaration after `(#PCDATA`.
+fn read_mixed_content(cursor: &mut Cursor) -> Result<ContentModel, String> {
+    let mut names = Vec::new();
+    loop {
+        cursor.skip_whitespace();
+        if cursor.starts_with(")") {
+            cursor.advance(1);
+            break;
+        }
+        cursor.expect("|", "in mixed content declaration")?;
+        cursor.skip_whitespace();
+        names.push(cursor.read_name().ok_or("Invalid element name in mixed content declaration")?.into());
+    }
+    if cursor.starts_with("*") {
+        cursor.advance(1);
+    } else if !names.is_empty() {
+        return Err("Expected '*' at the end of mixed content declaration".into());
+    }
+    Ok(ContentModel::Mixed(names))
+}
+
+/// Reads an element name or a group of a content model, with its repetition.
+fn read_particle(cursor: &mut Cursor) -> Result<ContentParticle, String> {
+    if cursor.starts_with("(") {
+        cursor.advance(1);
+        cursor.skip_whitespace();
+        return read_group(cursor);
+    }
+    let name = cursor.read_name().ok_or("Invalid element name in content model")?;
+    Ok(ContentParticle { kind: ParticleKind::Element(name.into()), repetition: read_repetition(cursor) })
+}
+
+/// Reads the rest of a sequence or a choice after the opening `(`, with its repetition.
+fn read_group(cursor: &mut Cursor) -> Result<ContentParticle, String> {
+    let mut particles = vec![read_particle(cursor)?];
+    let mut separator = None;
+    loop {
+        cursor.skip_whitespace();
+        match cursor.peek() {
+            Some(')') => {
+                cursor.advance(1);
+                break;
+            }
+            Some(c) if c == ',' || c == '|' => {
+                if separator.is_some_and(|s| s != c) {
+                    return Err("Sequences and choices cannot be mixed in a content model group".into());
+                }
+                separator = Some(c);
+                cursor.advance(1);
+                cursor.skip_whitespace();
+                particles.push(read_particle(cursor)?);
+            }
+            _ => return Err("Expected ',', '|' or ')' in content model".into())
+        }
+    }
+    let kind = if separator == Some('|') { ParticleKind::Choice(particles) } else { ParticleKind::Sequence(particles) };
+    Ok(ContentParticle { kind, repetition: read_repetition(cursor) })
+}
+
+fn read_repetition(cursor: &mut Cursor) -> Repetition {
+    let repetition = match cursor.peek() {
+        Some('?') => Repetition::Optional,
+        Some('*') => Repetition::ZeroOrMore,
+        Some('+') => Repetition::OneOrMore,
+        _ => return Repetition::Once
+    };
+    cursor.advance(1);
+    repetition
+}
+
+fn read_attribute_type(cursor: &mut Cursor) -> Result<AttributeType, String> {
+    if cursor.starts_with("(") {
+        cursor.advance(1);
+        return Ok(AttributeType::Enumeration(read_token_list(cursor, "enumerated attribute type")?));
+    }
+    let attribute_type = match cursor.read_name() {
+        Some("CDATA") => AttributeType::CData,
+        Some("ID") => AttributeType::Id,
+        Some("IDREF") => AttributeType::IdRef,
+        Some("IDREFS") => AttributeType::IdRefs,
+        Some("ENTITY") => AttributeType::Entity,
+        Some("ENTITIES") => AttributeType::Entities,
+        Some("NMTOKEN") => AttributeType::NmToken,
+        Some("NMTOKENS") => AttributeType::NmTokens,
+        Some("NOTATION") => {
+            cursor.require_whitespace("after NOTATION")?;
+            cursor.expect("(", "after NOTATION")?;
+            AttributeType::Notation(read_token_list(cursor, "notation attribute type")?)
+        }
+        Some(name) => return Err(format!("Invalid attribute type: {}", name)),
+        None => return Err("Expected an attribute type".into())
+    };
+    Ok(attribute_type)
+}
+
+/// Reads the rest of a list like `(a | b)` after the opening `(`.
+fn read_token_list(cursor: &mut Cursor, context: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    loop {
+        cursor.skip_whitespace();
+        tokens.push(cursor.read_nmtoken().ok_or_else(|| format!("Invalid token in {}", context))?.into());
+        cursor.skip_whitespace();
+        if cursor.starts_with(")") {
+            cursor.advance(1);
+            return Ok(tokens);
+        }
+        cursor.expect("|", &format!("in {}", context))?;
+    }
+}
+
+/// Normalizes the value of an attribute whose type is not `CDATA`: leading and trailing spaces
+/// are removed, and sequences of spaces are replaced with a single space.
+pub fn normalize_tokens(value: &str) -> String {
+    value.split(' ').filter(|t| !t.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+/// Expands character references in an entity value, leaving general entity references intact.
+fn expand_entity_value(literal: &str, version: XmlVersion) -> Result<String, String> {
+    let mut value = String::with_capacity(literal.len());
+    let mut rest = literal;
+    while let Some(start) = rest.find(&['&', '%'][..]) {
+        value.push_str(&rest[..start]);
+        if rest[start..].starts_with('%') {
+            return Err(PE_IN_MARKUP_ERROR.into());
+        }
+        let end = match rest[start..].find(';') {
+            Some(len) => start + len,
+            None => return Err("Unterminated reference in entity value".into())
+        };
+        let reference = &rest[start + 1..end];
+        if let Some(num) = reference.strip_prefix('#') {
+            value.push(parse_xml_char_reference(num, version)?);
+        } else if !reference.is_empty() && reference.starts_with(is_name_start_char) &&
+                  reference.chars().all(is_name_char) {
+            value.push_str(&rest[start..end + 1]);
+        } else {
+            return Err(format!("Invalid entity reference in entity value: &{};", reference));
+        }
+        rest = &rest[end + 1..];
+    }
+    value.push_str(rest);
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use common::XmlVersion;
+    use reader::config::ParserConfig;
+
+    use super::{
+        AttributeDeclaration, AttributeDefault, AttributeType, ContentModel, Dtd, ExternalEntity, ExternalId,
+        Replacement,
+    };
+
+    fn parse(doctype: &str) -> Result<Dtd, String> {
+        let mut dtd = Dtd::default();
+        dtd.parse(doctype, &ParserConfig::new(), XmlVersion::Version10).map(|_| dtd).map_err(|(_, e)| e)
+    }
+
+    #[test]
+    fn test_entity_declarations() {
+        let dtd = parse(r#" root SYSTEM "root.dtd" [
+            <!-- a comment with 'quotes' and <!ENTITY> -->
+            <!ELEMENT root (#PCDATA)>
+            <!ATTLIST root a CDATA "%b;">
+            <!ENTITY a "x&#x41;&b;y">
+            <!ENTITY a "ignored">
+            <!ENTITY ext PUBLIC "-//ext" 'ext.xml'>
+            <!ENTITY img SYSTEM "img.png" NDATA png>
+            <?pi data?>
+        ] "#).unwrap();
+
+        assert_eq!(dtd.general_entities.get("a"), Some(&"xA&b;y".into()));
+        assert_eq!(dtd.external_general_entities.get("ext"), Some(&ExternalEntity {
+            id: ExternalId { public_id: Some("-//ext".into()), system_id: "ext.xml".into() },
+            notation: None
+        }));
+        assert_eq!(dtd.external_general_entities.get("img"), Some(&ExternalEntity {
+            id: ExternalId { public_id: None, system_id: "img.png".into() },
+            notation: Some("png".into())
+        }));
+        assert_eq!(dtd.general_entities.len(), 1);
+
+        assert_eq!(parse(r#" root [ <!ENTITY e "&#+65;"> ]"#).err().unwrap(),
+                   "Invalid character reference: &#+65;");
+        assert_eq!(parse(r#" root [ <!ATTLIST root a CDATA "&#x+41;"> ]"#).err().unwrap(),
+                   "Invalid character reference: &#x+41;");
+    }
+
+    #[test]
+    fn test_parameter_entities() {
+        let dtd = parse(r#" root [
+            <!ENTITY % decl "<!ENTITY b 'bee'>">
+            <!ENTITY % nested "&#37;decl; <!ENTITY c 'sea'>">
+            %nested;
+        ]"#).unwrap();
+        assert_eq!(dtd.general_entities.get("b"), Some(&"bee".into()));
+        assert_eq!(dtd.general_entities.get("c"), Some(&"sea".into()));
+        assert!(dtd.parameter_entities.contains_key("decl"));
+        assert!(!dtd.general_entities.contains_key("decl"));
+    }
+
+    #[test]
+    fn test_parameter_entities_in_markup_declarations() {
+        let messages = [
+            parse(r#" root [ <!ENTITY % a "x"> <!ENTITY b "%a;"> ]"#),
+            parse(r#" root [ <!ENTITY % a "b"> <!ENTITY %a; "x"> ]"#),
+            parse(r#" root [ <!ENTITY % a "b"> <!ELEMENT %a; ANY> ]"#),
+        ];
+        for m in messages.iter() {
+            assert_eq!(m.as_ref().err().unwrap(),
+                       "Parameter entity references are not allowed within markup declarations in the internal subset");
+        }
+
+        assert_eq!(parse(" root [ %a; ]").err().unwrap(), "Undeclared parameter entity: %a;");
+        assert_eq!(parse(r#" root [ <!ENTITY % a "]> <a>"> %a; ]"#).err().unwrap(),
+                   "Unexpected character in the internal subset: ]");
+    }
+
+    #[test]
+    fn test_expansion_limits() {
+        let config = ParserConfig::new().max_entity_expansion_length(20);
+
+        let mut dtd = Dtd::default();
+        dtd.parse(r#" root [
+            <!ENTITY a "0123456789">
+            <!ENTITY b "&a;&a;">
+            <!ENTITY c "&b;&b;">
+            <!ENTITY self "x&self;">
+            <!ENTITY m "<b>&a;</b>">
+            <!ENTITY n "x&m;">
+        ]"#, &config, XmlVersion::Version10).unwrap();
+
+        let expand = |name| dtd.expand_general_entity(name, &config, false);
+        assert_eq!(expand("b"), Ok(Some(Replacement::Text("01234567890123456789".into()))));
+        assert_eq!(expand("c"), Err("Entity expansion length limit exceeded: more than 20 bytes".into()));
+        assert_eq!(expand("self"), Err("Entity expansion depth limit exceeded: more than 8 levels".into()));
+        assert_eq!(expand("unknown"), Ok(None));
+
+        // the replacement texts of entities with markup are not expanded in content
+        assert_eq!(expand("n"), Ok(Some(Replacement::Markup("x&m;".into()))));
+        assert_eq!(dtd.expand_general_entity("n", &config, true),
+                   Err("Entity replacement text containing markup is not allowed in attribute values: &m;".into()));
+
+        let mut dtd = Dtd::default();
+        let result = dtd.parse(r#" root [ <!ENTITY % a "&#37;a;"> %a; ]"#, &ParserConfig::new(), XmlVersion::Version10);
+        assert_eq!(result, Err((32, "Entity expansion depth limit exceeded: more than 8 levels".into())));
+    }
+
+    #[test]
+    fn test_doctype_parts() {
+        let dtd = parse(r#" doc PUBLIC "-//doc" 'doc.dtd' [ <!ENTITY e "]"> ] "#).unwrap();
+        assert_eq!(dtd.root_name(), Some("doc"));
+        assert_eq!(dtd.external_subset(), Some(&ExternalId { public_id: Some("-//doc".into()), system_id: "doc.dtd".into() }));
+        assert_eq!(dtd.internal_subset(), Some(r#" <!ENTITY e "]"> "#));
+
+        let dtd = parse(" doc").unwrap();
+        assert_eq!((dtd.external_subset(), dtd.internal_subset()), (None, None));
+    }
+
+    #[test]
+    fn test_element_declarations() {
+        let dtd = parse(r#" doc [
+            <!ELEMENT doc (head, (p | list)*, foot?)>
+            <!ELEMENT head EMPTY>
+            <!ELEMENT head ANY>
+            <!ELEMENT p (#PCDATA | b)*>
+            <!ELEMENT b ( #PCDATA ) >
+            <!ELEMENT list (item+)>
+            <!ELEMENT foot ANY>
+        ]"#).unwrap();
+        assert_eq!(dtd.root_name(), Some("doc"));
+        let elements = dtd.element_declarations();
+        assert_eq!(elements.len(), 6);
+        assert_eq!(elements["doc"].to_string(), "(head,(p|list)*,foot?)");
+        assert_eq!(elements["head"], ContentModel::Empty);
+        assert_eq!(elements["p"], ContentModel::Mixed(vec!["b".into()]));
+        assert_eq!(elements["b"], ContentModel::Mixed(vec![]));
+        assert_eq!(elements["foot"], ContentModel::Any);
+
+        let automaton = dtd.content_automaton("doc").unwrap();
+        let matches = |names: &[&str]| {
+            let states = names.iter().fold(automaton.start(), |states, name| automaton.step(&states, name));
+            automaton.accepts(&states)
+        };
+        assert!(matches(&["head"]));
+        assert!(matches(&["head", "p", "list", "p", "foot"]));
+        assert!(!matches(&[]));
+        assert!(!matches(&["head", "foot", "p"]));
+        assert!(!matches(&["head", "head"]));
+        assert!(dtd.content_automaton("p").is_none());
+
+        assert_eq!(parse(" a [ <!ELEMENT a (b, c | d)> ]").err().unwrap(),
+                   "Sequences and choices cannot be mixed in a content model group");
+        assert_eq!(parse(" a [ <!ELEMENT a (#PCDATA | b)> ]").err().unwrap(),
+                   "Expected '*' at the end of mixed content declaration");
+        assert!(parse(" a [ <!ELEMENT a (b c)> ]").is_err());
+        assert!(parse(" a [ <!ELEMENT a EMPTY ANY> ]").is_err());
+    }
+
+    #[test]
+    fn test_attribute_list_declarations() {
+        let dtd = parse(r#" a [
+            <!ENTITY e "I&#38;#38;J">
+            <!ATTLIST a
+                id ID #REQUIRED
+                kind (x | y) "x"
+                version CDATA #FIXED "1.0"
+                label CDATA "&e; &lt;&#x9;2&#10;">
+            <!ATTLIST a kind CDATA #IMPLIED tokens NMTOKENS "  t1   t2 " note NOTATION (png|gif) #IMPLIED>
+        ]"#).unwrap();
+        let declarations = &dtd.attribute_declarations()["a"];
+        let attribute = |name: &str, attribute_type, default| AttributeDeclaration {
+            name: name.into(), attribute_type, default
+        };
+        assert_eq!(declarations[..], [
+            attribute("id", AttributeType::Id, AttributeDefault::Required),
+            attribute("kind", AttributeType::Enumeration(vec!["x".into(), "y".into()]), AttributeDefault::Value("x".into())),
+            attribute("version", AttributeType::CData, AttributeDefault::Fixed("1.0".into())),
+            attribute("label", AttributeType::CData, AttributeDefault::Value("I&J <\t2\n".into())),
+            attribute("tokens", AttributeType::NmTokens, AttributeDefault::Value("t1 t2".into())),
+            attribute("note", AttributeType::Notation(vec!["png".into(), "gif".into()]), AttributeDefault::Implied),
+        ]);
+
+        assert_eq!(parse(r#" a [ <!ATTLIST a b CDATA "&u;"> ]"#).err().unwrap(),
+                   "Undeclared entity in attribute default value: &u;");
+        assert_eq!(parse(r#" a [ <!ATTLIST a b CDATA "<"> ]"#).err().unwrap(),
+                   "Unexpected '<' in attribute default value");
+        assert_eq!(parse(" a [ <!ATTLIST a b STRING #IMPLIED> ]").err().unwrap(), "Invalid attribute type: STRING");
+        assert!(parse(" a [ <!ATTLIST a b CDATA> ]").is_err());
+    }
+}
@@ -2,9 +2,10 @@
 
 use std::fmt;
 use std::borrow::Cow;
+use std::slice;
 
-use name::OwnedName;
-use attribute::OwnedAttribute;
+use name::{Name, OwnedName};
+use attribute::{AttributeKey, OwnedAttribute};
 use common::XmlVersion;
 use namespace::Namespace;
 
@@ -21,15 +22,24 @@ pub enum XmlEvent {
     StartDocument {
         /// XML version.
         ///
-        /// If XML declaration is not present, defaults to `Version10`.
+        /// If XML declaration is not present, defaults to `Version10`. Versions `1.x` other
+        /// than `1.1` are processed as `Version10`, as is required by the specification.
         version: XmlVersion,
 
-        /// XML document encoding.
+        /// XML document encoding, exactly as it is declared.
         ///
         /// If XML declaration is not present or does not contain `encoding` attribute,
-        /// defaults to `"UTF-8"`. This field is currently used for no other purpose than
-        /// informational.
-        encoding: String,
+        /// this is `None`. The rest of the document is decoded in this encoding if it is
+        /// ISO-8859-1 or, with the `encodings` feature, another encoding of the Encoding Standard
+        /// which is compatible with ASCII; otherwise the document is read as UTF-8.
+        encoding: Option<String>,
+
+        /// The encoding which is actually used to decode the document.
+        ///
+        /// This is `"UTF-8"`, unless the declared encoding is one which the reader decodes, like
+        /// `"ISO-8859-1"`, or the reader was created with `EventReader::new_with_decoder()`,
+        /// in which case it is the name of the encoding of the decoder.
+        effective_encoding: String,
 
         /// XML standalone declaration.
         ///
@@ -111,23 +121,213 @@ pub enum XmlEvent {
     /// It is possible to configure a parser to emit `Characters` event instead of `Whitespace`.
     /// See `pull::ParserConfiguration` structure for more information. When combined with whitespace
     /// trimming, it will eliminate standalone whitespace from the event stream completely.
-    Whitespace(String)
+    ///
+    /// Runs of text consisting only of whitespace are reported as this event regardless of
+    /// `xml:space`, so consumers which ignore insignificant whitespace can simply skip it.
+    /// Inside elements with `xml:space="preserve"` in scope, however, whitespace is significant:
+    /// it is never trimmed or removed by the parser, and consumers should check
+    /// `EventReader::xml_space()` before dropping these events. The writer takes this
+    /// into account when this event is written back.
+    Whitespace(String),
+
+    /// Denotes a reference to a general entity in character data, e.g. `&custom;`.
+    ///
+    /// Contains the name of the entity. This event is only emitted when the
+    /// `replace_entity_references` option is disabled; references to the predefined entities
+    /// like `&amp;`, as well as character references, are still replaced with the characters
+    /// they denote.
+    EntityReference(String),
+
+    /// Denotes a document type declaration, e.g. `<!DOCTYPE html SYSTEM "about:legacy-compat">`.
+    ///
+    /// This event is only emitted when the `report_doctype` option is enabled. It is emitted
+    /// after the declarations of the internal subset have been processed, so that the entities
+    /// they declare are already available.
+    DoctypeDecl {
+        /// The name of the root element.
+        name: String,
+
+        /// The public identifier of the external subset.
+        public_id: Option<String>,
+
+        /// The system identifier of the external subset.
+        system_id: Option<String>,
+
+        /// The internal subset, exactly as it is written, without the enclosing brackets.
+        internal_subset: Option<String>
+    }
 }
 
-impl fmt::Debug for XmlEvent {
+/// The kind of an XML event, without any of its data.
+///
+/// Values of this enum correspond to the variants of `XmlEvent`. They are returned by
+/// `EventReader::next_kind()`, which is useful when only the structure of a document matters.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum XmlEventKind {
+    /// Corresponds to `XmlEvent::StartDocument`.
+    StartDocument,
+    /// Corresponds to `XmlEvent::EndDocument`.
+    EndDocument,
+    /// Corresponds to `XmlEvent::ProcessingInstruction`.
+    ProcessingInstruction,
+    /// Corresponds to `XmlEvent::StartElement`.
+    StartElement,
+    /// Corresponds to `XmlEvent::EndElement`.
+    EndElement,
+    /// Corresponds to `XmlEvent::CData`.
+    CData,
+    /// Corresponds to `XmlEvent::Comment`.
+    Comment,
+    /// Corresponds to `XmlEvent::Characters`.
+    Characters,
+    /// Corresponds to `XmlEvent::Whitespace`.
+    Whitespace,
+    /// Corresponds to `XmlEvent::EntityReference`.
+    EntityReference,
+    /// Corresponds to `XmlEvent::DoctypeDecl`.
+    DoctypeDecl
+}
+
+/// A borrowed variant of `XmlEvent`.
+///
+/// Its variants correspond to the variants of `XmlEvent`, but refer to names, attributes
+/// and text stored elsewhere, e.g. in an owned event; the reader itself only produces owned
+/// events. `XmlEvent::borrow()` and `into_owned()` convert between the two without losing
+/// any data, and borrowing does not allocate, so a few events can be kept as owned values,
+/// while others are only borrowed. Both types are formatted the same way by `Debug`:
+///
+/// ```rust
+/// use xml::reader::{EventReader, XmlEvent, XmlEventRef};
+///
+/// let mut reader = EventReader::from_str("<a x='1'>text</a>");
+/// let event = reader.next().and_then(|_| reader.next()).unwrap();
+/// match event.borrow() {
+///     XmlEventRef::StartElement { name, ref attributes, .. } => {
+///         assert_eq!(name.local_name, "a");
+///         assert_eq!(attributes[0].value, "1");
+///     }
+///     e => panic!("unexpected event: {:?}", e)
+/// }
+/// assert_eq!(event.borrow().into_owned(), event);
+/// assert_eq!(format!("{:?}", event.borrow()), format!("{:?}", event));
+/// ```
+#[derive(Clone, PartialEq)]
+pub enum XmlEventRef<'a> {
+    /// Corresponds to `XmlEvent::StartDocument`.
+    StartDocument {
+        /// XML version.
+        version: XmlVersion,
+        /// XML document encoding, exactly as it is declared.
+        encoding: Option<&'a str>,
+        /// The encoding which is actually used to decode the document.
+        effective_encoding: &'a str,
+        /// XML standalone declaration.
+        standalone: Option<bool>
+    },
+    /// Corresponds to `XmlEvent::EndDocument`.
+    EndDocument,
+    /// Corresponds to `XmlEvent::ProcessingInstruction`.
+    ProcessingInstruction {
+        /// Processing instruction target.
+        name: &'a str,
+        /// Processing instruction content.
+        data: Option<&'a str>
+    },
+    /// Corresponds to `XmlEvent::StartElement`.
+    StartElement {
+        /// Qualified name of the element.
+        name: Name<'a>,
+        /// A list of attributes associated with the element.
+        attributes: &'a [OwnedAttribute],
+        /// Contents of the namespace mapping at this point of the document.
+        namespace: &'a Namespace
+    },
+    /// Corresponds to `XmlEvent::EndElement`.
+    EndElement {
+        /// Qualified name of the element.
+        name: Name<'a>
+    },
+    /// Corresponds to `XmlEvent::CData`.
+    CData(&'a str),
+    /// Corresponds to `XmlEvent::Comment`.
+    Comment(&'a str),
+    /// Corresponds to `XmlEvent::Characters`.
+    Characters(&'a str),
+    /// Corresponds to `XmlEvent::Whitespace`.
+    Whitespace(&'a str),
+    /// Corresponds to `XmlEvent::EntityReference`.
+    EntityReference(&'a str),
+    /// Corresponds to `XmlEvent::DoctypeDecl`.
+    DoctypeDecl {
+        /// The name of the root element.
+        name: &'a str,
+        /// The public identifier of the external subset.
+        public_id: Option<&'a str>,
+        /// The system identifier of the external subset.
+        system_id: Option<&'a str>,
+        /// The internal subset, without the enclosing brackets.
+        internal_subset: Option<&'a str>
+    }
+}
+
+impl<'a> XmlEventRef<'a> {
+    /// Returns an owned event with the same data.
+    pub fn into_owned(self) -> XmlEvent {
+        match self {
+            XmlEventRef::StartDocument { version, encoding, effective_encoding, standalone } =>
+                XmlEvent::StartDocument {
+                    version,
+                    encoding: encoding.map(|e| e.into()),
+                    effective_encoding: effective_encoding.into(),
+                    standalone
+                },
+            XmlEventRef::EndDocument => XmlEvent::EndDocument,
+            XmlEventRef::ProcessingInstruction { name, data } =>
+                XmlEvent::ProcessingInstruction { name: name.into(), data: data.map(|d| d.into()) },
+            XmlEventRef::StartElement { name, attributes, namespace } =>
+                XmlEvent::StartElement {
+                    name: name.to_owned(),
+                    attributes: attributes.to_vec(),
+                    namespace: namespace.clone()
+                },
+            XmlEventRef::EndElement { name } => XmlEvent::EndElement { name: name.to_owned() },
+            XmlEventRef::CData(data) => XmlEvent::CData(data.into()),
+            XmlEventRef::Comment(data) => XmlEvent::Comment(data.into()),
+            XmlEventRef::Characters(data) => XmlEvent::Characters(data.into()),
+            XmlEventRef::Whitespace(data) => XmlEvent::Whitespace(data.into()),
+            XmlEventRef::EntityReference(name) => XmlEvent::EntityReference(name.into()),
+            XmlEventRef::DoctypeDecl { name, public_id, system_id, internal_subset } =>
+                XmlEvent::DoctypeDecl {
+                    name: name.into(),
+                    public_id: public_id.map(|s| s.into()),
+                    system_id: system_id.map(|s| s.into()),
+                    internal_subset: internal_subset.map(|s| s.into())
+                }
+        }
+    }
+}
+
+impl<'a> From<XmlEventRef<'a>> for XmlEvent {
+    #[inline]
+    fn from(event: XmlEventRef<'a>) -> XmlEvent {
+        event.into_owned()
+    }
+}
+
+impl<'a> fmt::Debug for XmlEventRef<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            XmlEvent::StartDocument { ref version, ref encoding, ref standalone } =>
-                write!(f, "StartDocument({}, {}, {:?})", version, *encoding, *standalone),
-            XmlEvent::EndDocument =>
+            XmlEventRef::StartDocument { version, encoding, effective_encoding, standalone } =>
+                write!(f, "StartDocument({}, {:?}, {}, {:?})", version, encoding, effective_encoding, standalone),
+            XmlEventRef::EndDocument =>
                 write!(f, "EndDocument"),
-            XmlEvent::ProcessingInstruction { ref name, ref data } =>
-                write!(f, "ProcessingInstruction({}{})", *name, match *data {
-                    Some(ref data) => format!(", {}", data),
+            XmlEventRef::ProcessingInstruction { name, data } =>
+                write!(f, "ProcessingInstruction({}{})", name, match data {
+                    Some(data) => format!(", {}", data),
                     None       => String::new()
                 }),
-            XmlEvent::StartElement { ref name, ref attributes, namespace: Namespace(ref namespace) } =>
-                write!(f, "StartElement({}, {:?}{})", name, namespace, if attributes.is_empty() {
+            XmlEventRef::StartElement { name, attributes, namespace } =>
+                write!(f, "StartElement({}, {:?}{})", name, namespace.0, if attributes.is_empty() {
                     String::new()
                 } else {
                     let attributes: Vec<String> = attributes.iter().map(
@@ -135,21 +335,165 @@ impl fmt::Debug for XmlEvent {
                     ).collect();
                     format!(", [{}]", attributes.join(", "))
                 }),
-            XmlEvent::EndElement { ref name } =>
+            XmlEventRef::EndElement { name } =>
                 write!(f, "EndElement({})", name),
-            XmlEvent::Comment(ref data) =>
+            XmlEventRef::Comment(data) =>
                 write!(f, "Comment({})", data),
-            XmlEvent::CData(ref data) =>
+            XmlEventRef::CData(data) =>
                 write!(f, "CData({})", data),
-            XmlEvent::Characters(ref data) =>
+            XmlEventRef::Characters(data) =>
                 write!(f, "Characters({})", data),
-            XmlEvent::Whitespace(ref data) =>
-                write!(f, "Whitespace({})", data)
+            XmlEventRef::Whitespace(data) =>
+                write!(f, "Whitespace({})", data),
+            XmlEventRef::EntityReference(name) =>
+                write!(f, "EntityReference({})", name),
+            XmlEventRef::DoctypeDecl { name, public_id, system_id, internal_subset } =>
+                write!(f, "DoctypeDecl({}, {:?}, {:?}, {:?})", name, public_id, system_id, internal_subset)
         }
     }
 }
 
+impl fmt::Debug for XmlEvent {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.borrow(), f)
+    }
+}
+
 impl XmlEvent {
+    /// Returns the kind of this event.
+    pub fn kind(&self) -> XmlEventKind {
+        match *self {
+            XmlEvent::StartDocument { .. } => XmlEventKind::StartDocument,
+            XmlEvent::EndDocument => XmlEventKind::EndDocument,
+            XmlEvent::ProcessingInstruction { .. } => XmlEventKind::ProcessingInstruction,
+            XmlEvent::StartElement { .. } => XmlEventKind::StartElement,
+            XmlEvent::EndElement { .. } => XmlEventKind::EndElement,
+            XmlEvent::CData(_) => XmlEventKind::CData,
+            XmlEvent::Comment(_) => XmlEventKind::Comment,
+            XmlEvent::Characters(_) => XmlEventKind::Characters,
+            XmlEvent::Whitespace(_) => XmlEventKind::Whitespace,
+            XmlEvent::EntityReference(_) => XmlEventKind::EntityReference,
+            XmlEvent::DoctypeDecl { .. } => XmlEventKind::DoctypeDecl
+        }
+    }
+
+    /// Returns the attributes of a `StartElement` event, in the order they appear in
+    /// the document.
+    ///
+    /// The iterator is empty for other events.
+    #[inline]
+    pub fn attributes(&self) -> slice::Iter<'_, OwnedAttribute> {
+        match *self {
+            XmlEvent::StartElement { ref attributes, .. } => attributes.iter(),
+            _ => [].iter()
+        }
+    }
+
+    /// Returns the value of an attribute of a `StartElement` event with the given name.
+    ///
+    /// The name may be prefixed, e.g. `xlink:href`; the prefix is then resolved with the
+    /// namespace mapping of the event, so the attribute is found regardless of the prefix it is
+    /// written with in the document. A name without a prefix denotes an attribute without
+    /// a namespace. Returns `None` if there is no such attribute, if the prefix is not bound,
+    /// or if this is not a `StartElement` event.
+    ///
+    /// ```rust
+    /// use xml::EventReader;
+    /// use xml::reader::XmlEvent;
+    ///
+    /// let mut reader = EventReader::from_str("<a xmlns:l=\"http://www.w3.org/1999/xlink\" l:href=\"#x\" id=\"1\"/>");
+    /// let event = reader.next().and_then(|_| reader.next()).unwrap();
+    /// assert_eq!(event.attribute("id"), Some("1"));
+    /// assert_eq!(event.attribute("href"), None);
+    /// assert_eq!(event.attribute("l:href"), Some("#x"));
+    /// assert_eq!(event.attribute_ns("http://www.w3.org/1999/xlink", "href"), Some("#x"));
+    /// ```
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        let name = Name::from(name);
+        match name.prefix {
+            Some(prefix) => match *self {
+                XmlEvent::StartElement { ref namespace, .. } =>
+                    self.attribute_ns(namespace.get(prefix)?, name.local_name),
+                _ => None
+            },
+            None => self.attributes()
+                .find(|a| a.name.namespace.is_none() && a.name.local_name == name.local_name)
+                .map(|a| &a.value[..])
+        }
+    }
+
+    /// Returns the value of an attribute of a `StartElement` event with the given namespace
+    /// URI and local name.
+    ///
+    /// Returns `None` if there is no such attribute or if this is not a `StartElement` event.
+    pub fn attribute_ns(&self, namespace: &str, local_name: &str) -> Option<&str> {
+        self.attributes()
+            .find(|a| a.name.namespace.as_ref().is_some_and(|ns| ns == namespace) && a.name.local_name == local_name)
+            .map(|a| &a.value[..])
+    }
+
+    /// Returns the value of an attribute of a `StartElement` event with the given name,
+    /// whatever its prefix is; see `attribute::AttributeKey` for the kinds of names accepted.
+    ///
+    /// Returns `None` if there is no such attribute or if this is not a `StartElement` event.
+    ///
+    /// ```rust
+    /// use xml::EventReader;
+    /// use xml::name::Name;
+    /// use xml::reader::XmlEvent;
+    ///
+    /// const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+    ///
+    /// let mut reader = EventReader::from_str("<a xmlns:l=\"http://www.w3.org/1999/xlink\" l:href=\"#x\" id=\"1\"/>");
+    /// let event = reader.next().and_then(|_| reader.next()).unwrap();
+    /// assert_eq!(event.get_attribute(("href", XLINK_NS)), Some("#x"));
+    /// assert_eq!(event.get_attribute(Name::qualified("href", XLINK_NS, Some("xlink"))), Some("#x"));
+    /// assert_eq!(event.get_attribute(("id", "")), Some("1"));
+    /// assert_eq!(event.get_attribute(Name::local("id")), Some("1"));
+    /// assert_eq!(event.get_attribute(("id", XLINK_NS)), None);
+    /// ```
+    pub fn get_attribute<K: AttributeKey>(&self, key: K) -> Option<&str> {
+        self.attributes()
+            .find(|a| key.matches(&a.name))
+            .map(|a| &a.value[..])
+    }
+
+    /// Returns a borrowed variant of this event, without allocating; see `XmlEventRef`.
+    pub fn borrow(&self) -> XmlEventRef<'_> {
+        match *self {
+            XmlEvent::StartDocument { version, ref encoding, ref effective_encoding, standalone } =>
+                XmlEventRef::StartDocument {
+                    version,
+                    encoding: encoding.as_ref().map(|e| &e[..]),
+                    effective_encoding,
+                    standalone
+                },
+            XmlEvent::EndDocument => XmlEventRef::EndDocument,
+            XmlEvent::ProcessingInstruction { ref name, ref data } =>
+                XmlEventRef::ProcessingInstruction { name, data: data.as_ref().map(|d| &d[..]) },
+            XmlEvent::StartElement { ref name, ref attributes, ref namespace } =>
+                XmlEventRef::StartElement {
+                    name: name.borrow(),
+                    attributes,
+                    namespace
+                },
+            XmlEvent::EndElement { ref name } => XmlEventRef::EndElement { name: name.borrow() },
+            XmlEvent::CData(ref data) => XmlEventRef::CData(data),
+            XmlEvent::Comment(ref data) => XmlEventRef::Comment(data),
+            XmlEvent::Characters(ref data) => XmlEventRef::Characters(data),
+            XmlEvent::Whitespace(ref data) => XmlEventRef::Whitespace(data),
+            XmlEvent::EntityReference(ref name) => XmlEventRef::EntityReference(name),
+            XmlEvent::DoctypeDecl { ref name, ref public_id, ref system_id, ref internal_subset } =>
+                XmlEventRef::DoctypeDecl {
+                    name,
+                    public_id: public_id.as_ref().map(|s| &s[..]),
+                    system_id: system_id.as_ref().map(|s| &s[..]),
+                    internal_subset: internal_subset.as_ref().map(|s| &s[..])
+                }
+        }
+    }
+
     /// Obtains a writer event from this reader event.
     ///
     /// This method is useful for streaming processing of XML documents where the output
@@ -187,13 +531,21 @@ impl XmlEvent {
     /// );
     /// ```
     ///
+    /// The writer event borrows names, attributes and text from this event, so nothing but
+    /// the list of attributes is copied. Every event except `EndDocument` has a writer
+    /// counterpart, so writing all events of a document which are not `None` reproduces
+    /// an equivalent document. Note, however, that the document type declaration is only
+    /// reproduced if the reader reports it, which requires the `report_doctype` option;
+    /// otherwise, the declaration is dropped, and references to its entities are written
+    /// as their values.
+    ///
     /// Note that this API may change or get additions in future to improve its ergonomics.
     pub fn as_writer_event<'a>(&'a self) -> Option<::writer::events::XmlEvent<'a>> {
         match *self {
-            XmlEvent::StartDocument { version, ref encoding, standalone } =>
+            XmlEvent::StartDocument { version, ref encoding, standalone, .. } =>
                 Some(::writer::events::XmlEvent::StartDocument {
                     version: version,
-                    encoding: Some(encoding),
+                    encoding: encoding.as_ref().map(|e| &e[..]),
                     standalone: standalone
                 }),
             XmlEvent::ProcessingInstruction { ref name, ref data } =>
@@ -212,7 +564,15 @@ impl XmlEvent {
             XmlEvent::Comment(ref data) => Some(::writer::events::XmlEvent::Comment(data)),
             XmlEvent::CData(ref data) => Some(::writer::events::XmlEvent::CData(data)),
             XmlEvent::Characters(ref data) => Some(::writer::events::XmlEvent::Characters(data)),
-            XmlEvent::Whitespace(ref data) => Some(::writer::events::XmlEvent::Characters(data)),
+            XmlEvent::Whitespace(ref data) => Some(::writer::events::XmlEvent::Whitespace(data)),
+            XmlEvent::EntityReference(ref name) => Some(::writer::events::XmlEvent::EntityReference(name)),
+            XmlEvent::DoctypeDecl { ref name, ref public_id, ref system_id, ref internal_subset } =>
+                Some(::writer::events::XmlEvent::DoctypeDecl {
+                    name,
+                    public_id: public_id.as_ref().map(|s| &s[..]),
+                    system_id: system_id.as_ref().map(|s| &s[..]),
+                    internal_subset: internal_subset.as_ref().map(|s| &s[..])
+                }),
             _ => None
         }
     }
@@ -0,0 +1,183 @@
+//! Contains a callback-based interface to the parser.
+
+use std::io::Read;
+use std::ops::ControlFlow;
+
+use attribute::OwnedAttribute;
+use common::{Position, TextPosition, XmlVersion};
+use name::OwnedName;
+use namespace::{Namespace, NamespaceStack};
+
+use super::{Error, EventReader, ParserConfig, Result, XmlEvent};
+
+/// The state of the parser at the event passed to a `Handler` method.
+#[derive(Clone, Copy, Debug)]
+pub struct HandlerContext<'a> {
+    position: TextPosition,
+    namespace: &'a Namespace
+}
+
+impl<'a> HandlerContext<'a> {
+    /// Returns the position of the event in the document.
+    #[inline]
+    pub fn position(&self) -> TextPosition { self.position }
+
+    /// Returns the namespace mappings in scope at the event.
+    ///
+    /// For start and end elements, these are the mappings of the element itself, including
+    /// the ones it declares.
+    #[inline]
+    pub fn namespace(&self) -> &'a Namespace { self.namespace }
+}
+
+/// Receives the events of a document parsed with `parse_with_handler()`.
+///
+/// All methods do nothing by default, so handlers only need to implement the ones for
+/// the events they are interested in. Returning `ControlFlow::Break` from a method stops
+/// the parsing.
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use std::ops::ControlFlow;
+///
+/// use xml::attribute::OwnedAttribute;
+/// use xml::name::OwnedName;
+/// use xml::reader::{self, Handler, HandlerContext, ParserConfig};
+///
+/// #[derive(Default)]
+/// struct ElementCounter(HashMap<String, usize>);
+///
+/// impl Handler for ElementCounter {
+///     fn start_element(&mut self, name: &OwnedName, _: &[OwnedAttribute],
+///                      _: &HandlerContext) -> ControlFlow<()> {
+///         *self.0.entry(name.local_name.clone()).or_insert(0) += 1;
+///         ControlFlow::Continue(())
+///     }
+/// }
+///
+/// let mut counter = ElementCounter::default();
+/// reader::parse_with_handler("<a><b/><b/><c/></a>".as_bytes(), &ParserConfig::new(), &mut counter).unwrap();
+/// assert_eq!(counter.0["b"], 2);
+/// ```
+pub trait Handler {
+    /// Called for the `XmlEvent::StartDocument` event, with the encoding as it is declared.
+    fn start_document(&mut self, _version: XmlVersion, _encoding: Option<&str>, _standalone: Option<bool>,
+                      _context: &HandlerContext) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for the `XmlEvent::DoctypeDecl` event, which is only reported with the
+    /// `report_doctype` option.
+    fn doctype_decl(&mut self, _name: &str, _public_id: Option<&str>, _system_id: Option<&str>,
+                    _internal_subset: Option<&str>, _context: &HandlerContext) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for the `XmlEvent::EndDocument` event.
+    fn end_document(&mut self, _context: &HandlerContext) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for the `XmlEvent::StartElement` event.
+    fn start_element(&mut self, _name: &OwnedName, _attributes: &[OwnedAttribute],
+                     _context: &HandlerContext) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for the `XmlEvent::EndElement` event.
+    fn end_element(&mut self, _name: &OwnedName, _context: &HandlerContext) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for the `XmlEvent::Characters`, `XmlEvent::CData` and `XmlEvent::Whitespace`
+    /// events; use the parser configuration to control which of them are reported.
+    fn characters(&mut self, _data: &str, _context: &HandlerContext) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for the `XmlEvent::EntityReference` event, which is only reported when
+    /// the `replace_entity_references` option is disabled.
+    fn entity_reference(&mut self, _name: &str, _context: &HandlerContext) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for the `XmlEvent::Comment` event.
+    fn comment(&mut self, _data: &str, _context: &HandlerContext) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for the `XmlEvent::ProcessingInstruction` event.
+    fn processing_instruction(&mut self, _name: &str, _data: Option<&str>,
+                              _context: &HandlerContext) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for every warning of the parser, after the event during which it was noted;
+    /// see `EventReader::take_warnings()`.
+    fn warning(&mut self, _warning: &Error) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called with the error which stops the parsing, before it is returned
+    /// by `parse_with_handler()`.
+    fn error(&mut self, _error: &Error) {}
+}
+
+/// Parses the document read from the source with the provided configuration and passes its
+/// events to the handler.
+///
+/// Returns successfully once the document has ended or a handler method has returned
+/// `ControlFlow::Break`.
+pub fn parse_with_handler<R: Read, H: Handler + ?Sized>(source: R, config: &ParserConfig,
+                                                        handler: &mut H) -> Result<()> {
+    let mut reader = EventReader::new_with_config(source, config.clone());
+    // the mappings of the open elements, with the document level at the bottom
+    let mut namespaces = vec![NamespaceStack::default().squash()];
+    loop {
+        let event = match reader.next() {
+            Ok(event) => event,
+            Err(e) => {
+                handler.error(&e);
+                return Err(e);
+            }
+        };
+        let position = reader.position();
+        let flow = {
+            if let XmlEvent::StartElement { ref namespace, .. } = event {
+                namespaces.push(namespace.clone());
+            }
+            let context = HandlerContext { position, namespace: namespaces.last().unwrap() };
+            match event {
+                XmlEvent::StartDocument { version, ref encoding, standalone, .. } =>
+                    handler.start_document(version, encoding.as_ref().map(|e| &e[..]), standalone, &context),
+                XmlEvent::DoctypeDecl { ref name, ref public_id, ref system_id, ref internal_subset } =>
+                    handler.doctype_decl(name, public_id.as_ref().map(|s| &s[..]), system_id.as_ref().map(|s| &s[..]),
+                                         internal_subset.as_ref().map(|s| &s[..]), &context),
+                XmlEvent::EndDocument => handler.end_document(&context),
+                XmlEvent::StartElement { ref name, ref attributes, .. } =>
+                    handler.start_element(name, attributes, &context),
+                XmlEvent::EndElement { ref name } => handler.end_element(name, &context),
+                XmlEvent::Characters(ref data) | XmlEvent::CData(ref data) | XmlEvent::Whitespace(ref data) =>
+                    handler.characters(data, &context),
+                XmlEvent::EntityReference(ref name) => handler.entity_reference(name, &context),
+                XmlEvent::Comment(ref data) => handler.comment(data, &context),
+                XmlEvent::ProcessingInstruction { ref name, ref data } =>
+                    handler.processing_instruction(name, data.as_ref().map(|d| &d[..]), &context)
+            }
+        };
+        if let XmlEvent::EndElement { .. } = event {
+            namespaces.pop();
+        }
+        if flow.is_break() {
+            return Ok(());
+        }
+        for warning in reader.take_warnings() {
+            if handler.warning(&warning).is_break() {
+                return Ok(());
+            }
+        }
+        if let XmlEvent::EndDocument = event {
+            return Ok(());
+        }
+    }
+}
@@ -1,15 +1,17 @@
 //! Contains simple lexer for XML documents.
 //!
-//! This module is for internal use. Use `xml::pull` module to do parsing.
+//! This module is for internal use. Use `reader::Tokenizer` for a public stream of tokens.
 
 use std::fmt;
+use std::mem;
 use std::collections::VecDeque;
-use std::io::Read;
+use std::io::{self, Read};
 use std::result;
 use std::borrow::Cow;
 
 use common::{Position, TextPosition, is_whitespace_char, is_name_char};
 use reader::Error;
+use reader::decoder::Decoder;
 use util;
 
 /// `Token` represents a single lexeme of an XML document. These lexemes
@@ -154,6 +156,12 @@ enum State {
     DoctypeStarted(DoctypeStartedSubstate),
     /// Triggered after DoctypeStarted to handle sub elements
     DoctypeFinishing(u8),
+    /// Triggered on a quote inside the <!DOCTYPE tag, up to the matching quote
+    DoctypeQuoted(u8, char),
+    /// Triggered on '<!--' inside the <!DOCTYPE tag, up to the '-->'
+    DoctypeComment(u8),
+    /// Triggered on '<?' inside the <!DOCTYPE tag, up to the '?>'
+    DoctypeInstruction(u8),
     /// Triggered on '<![' up to '<![CDATA'
     CDataStarted(CDataStartedSubstate),
     /// Triggered on '?'
@@ -220,11 +228,35 @@ pub struct Lexer {
     pos: TextPosition,
     head_pos: TextPosition,
     char_queue: VecDeque<char>,
+    char_reader: util::CharReader,
+    // the decoder of the rest of the stream, if it is not in UTF-8, and the characters it
+    // decoded which were not read yet, with the numbers of bytes they were decoded from
+    decoder: Option<Box<dyn Decoder + Send>>,
+    decoded: VecDeque<(char, u64)>,
+    decoder_finished: bool,
+    // the number of bytes of the stream which the last read character was decoded from
+    char_len: u64,
+    // the names and the unread replacement texts of the entities being lexed inline, the
+    // innermost last; a finished entity is removed when the next character is read
+    entities: Vec<(String, VecDeque<char>)>,
+    // the total length of the replacement texts pushed since the outermost entity
+    entities_length: usize,
+    // whether the last read character comes from the replacement text of an entity
+    entity_char: bool,
     st: State,
     skip_errors: bool,
     inside_comment: bool,
     inside_token: bool,
-    eof_handled: bool
+    eof_handled: bool,
+    // whether the last character read from the stream was a carriage return
+    after_cr: bool,
+    offset: u64,
+    head_offset: u64,
+    bytes_read: u64,
+    max_bytes: Option<u64>,
+    doctype: String,
+    // whether the internal subset of the current <!DOCTYPE tag has started
+    doctype_subset: bool
 }
 
 impl Position for Lexer {
@@ -240,14 +272,41 @@ impl Lexer {
             pos: TextPosition::new(),
             head_pos: TextPosition::new(),
             char_queue: VecDeque::with_capacity(4),  // TODO: check size
+            char_reader: util::CharReader::new(),
+            decoder: None,
+            decoded: VecDeque::new(),
+            decoder_finished: false,
+            char_len: 0,
+            entities: Vec::new(),
+            entities_length: 0,
+            entity_char: false,
             st: State::Normal,
             skip_errors: false,
             inside_comment: false,
             inside_token: false,
-            eof_handled: false
+            eof_handled: false,
+            after_cr: false,
+            offset: 0,
+            head_offset: 0,
+            bytes_read: 0,
+            max_bytes: None,
+            doctype: String::new(),
+            doctype_subset: false
         }
     }
 
+    /// Sets the maximum number of bytes this lexer is allowed to read from the stream.
+    #[inline]
+    pub fn limit_bytes(&mut self, max_bytes: Option<u64>) { self.max_bytes = max_bytes; }
+
+    /// Returns the byte offset of the last token produced by the lexer.
+    #[inline]
+    pub fn token_offset(&self) -> u64 { self.offset }
+
+    /// Returns the number of bytes read from the stream so far.
+    #[inline]
+    pub fn bytes_read(&self) -> u64 { self.bytes_read }
+
     /// Enables error handling so `next_token` will return `Some(Err(..))`
     /// upon invalid lexeme.
     #[inline]
@@ -267,6 +326,53 @@ impl Lexer {
     #[inline]
     pub fn outside_comment(&mut self) { self.inside_comment = false; }
 
+    /// Returns the contents of the last `<!DOCTYPE` tag, that is, everything between
+    /// `<!DOCTYPE` and the closing `>`.
+    #[inline]
+    pub fn take_doctype(&mut self) -> String { mem::take(&mut self.doctype) }
+
+    /// Makes the lexer decode the rest of the stream with the given decoder, or read it
+    /// as UTF-8 if there is none.
+    ///
+    /// The stream is read one byte at a time, so that no bytes are decoded ahead of the lexer.
+    pub fn set_decoder(&mut self, decoder: Option<Box<dyn Decoder + Send>>) {
+        self.decoder = decoder;
+        self.decoded.clear();
+        self.decoder_finished = false;
+    }
+
+    /// Makes the lexer read the replacement text of the given entity before the rest of
+    /// the input, as if the text were written in place of the reference to the entity.
+    ///
+    /// The characters of the text do not advance the position of the lexer and are not
+    /// counted as bytes of the stream.
+    pub fn push_entity(&mut self, name: &str, text: &str) {
+        if self.entities.is_empty() {
+            self.entities_length = 0;
+        }
+        self.entities_length += text.len();
+        let mut chars: VecDeque<char> = text.chars().collect();
+        // characters pushed back before the reference follow the replacement text
+        chars.extend(self.char_queue.drain(..));
+        self.entities.push((name.into(), chars));
+    }
+
+    /// Returns whether the replacement text of the given entity is being read.
+    pub fn is_inside_entity(&self, name: &str) -> bool {
+        self.entities.iter().any(|(n, _)| n == name)
+    }
+
+    /// Returns the number of entities whose replacement texts are being read.
+    #[inline]
+    pub fn entity_depth(&self) -> usize { self.entities.len() }
+
+    /// Returns the total length of the replacement texts pushed since the outermost entity
+    /// whose text is being read.
+    #[inline]
+    pub fn entities_length(&self) -> usize {
+        if self.entities.is_empty() { 0 } else { self.entities_length }
+    }
+
     /// Reset the eof handled flag of the lexer.
     #[inline]
     pub fn reset_eof_handled(&mut self) { self.eof_handled = false; }
@@ -288,6 +394,7 @@ impl Lexer {
 
         if !self.inside_token {
             self.pos = self.head_pos;
+            self.offset = self.head_offset;
             self.inside_token = true;
         }
 
@@ -303,10 +410,31 @@ impl Lexer {
         }
 
         loop {
-            // TODO: this should handle multiple encodings
-            let c = match try!(util::next_char_from(b)) {
-                Some(c) => c,   // got next char
-                None => break,  // nothing to read left
+            let c = match self.read_entity_char() {
+                Some(c) => c,
+                None => match try!(self.read_char(b)) {
+                    Some(c) => c,   // got next char
+                    None => break,  // nothing to read left
+                }
+            };
+
+            self.bytes_read += self.char_len;
+            if let Some(max_bytes) = self.max_bytes {
+                if self.bytes_read > max_bytes {
+                    return Err((&self.head_pos, format!("Document length limit exceeded: more than {} bytes", max_bytes)).into());
+                }
+            }
+
+            // line breaks are normalized as described in section 2.11 of the specification:
+            // `\r\n` and a lone `\r` are both passed on as a single `\n`
+            let after_cr = mem::replace(&mut self.after_cr, c == '\r');
+            let c = match c {
+                '\n' if after_cr => {
+                    self.head_offset += self.char_len;
+                    continue;
+                }
+                '\r' => '\n',
+                c => c
             };
 
             match try!(self.read_next_token(c)) {
@@ -323,11 +451,13 @@ impl Lexer {
         // Handle end of stream
         self.eof_handled = true;
         self.pos = self.head_pos;
+        self.offset = self.head_offset;
         match self.st {
             State::TagStarted | State::CommentOrCDataOrDoctypeStarted |
             State::CommentStarted | State::CDataStarted(_)| State::DoctypeStarted(_) |
             State::CommentClosing(ClosingSubstate::Second) |
-            State::DoctypeFinishing(_) =>
+            State::DoctypeFinishing(_) | State::DoctypeQuoted(..) | State::DoctypeComment(_) |
+            State::DoctypeInstruction(_) =>
                 Err(self.error("Unexpected end of stream")),
             State::ProcessingInstructionClosing =>
                 Ok(Some(Token::Character('?'))),
@@ -344,6 +474,60 @@ impl Lexer {
         }
     }
 
+    /// Reads the next character of the innermost entity which has one left, removing
+    /// the finished entities.
+    fn read_entity_char(&mut self) -> Option<char> {
+        while let Some(&mut (_, ref mut chars)) = self.entities.last_mut() {
+            if let Some(c) = chars.pop_front() {
+                self.entity_char = true;
+                self.char_len = 0;
+                return Some(c);
+            }
+            self.entities.pop();
+        }
+        None
+    }
+
+    /// Reads the next character from the stream, with the decoder if there is one, and notes
+    /// the number of bytes it was decoded from.
+    fn read_char<B: Read>(&mut self, b: &mut B) -> result::Result<Option<char>, util::CharReadError> {
+        self.entity_char = false;
+        let decoder = match self.decoder {
+            Some(ref mut decoder) => decoder,
+            None => {
+                let c = self.char_reader.next_char_from(b)?;
+                self.char_len = c.map_or(0, |c| c.len_utf8() as u64);
+                return Ok(c);
+            }
+        };
+
+        let mut consumed = 0;
+        loop {
+            if let Some((c, len)) = self.decoded.pop_front() {
+                self.char_len = len;
+                return Ok(Some(c));
+            }
+            if self.decoder_finished {
+                return Ok(None);
+            }
+
+            let mut byte = [0];
+            let n = loop {
+                match b.read(&mut byte) {
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    result => break result?
+                }
+            };
+            consumed += n as u64;
+            self.decoder_finished = n == 0;
+            let text = decoder.decode(&byte[..n], self.decoder_finished)?;
+            // the bytes of a sequence are attributed to the first character decoded from it
+            for c in text.chars() {
+                self.decoded.push_back((c, mem::replace(&mut consumed, 0)));
+            }
+        }
+    }
+
     #[inline]
     fn error<M: Into<Cow<'static, str>>>(&self, msg: M) -> Error {
         (self, msg).into()
@@ -352,7 +536,8 @@ impl Lexer {
     #[inline]
     fn read_next_token(&mut self, c: char) -> Result {
         let res = self.dispatch_char(c);
-        if self.char_queue.is_empty() {
+        if self.char_queue.is_empty() && !self.entity_char {
+            self.head_offset += self.char_len;
             if c == '\n' {
                 self.head_pos.new_line();
             } else {
@@ -371,6 +556,9 @@ impl Lexer {
             State::CDataStarted(s)                => self.cdata_started(c, s),
             State::DoctypeStarted(s)              => self.doctype_started(c, s),
             State::DoctypeFinishing(d)            => self.doctype_finishing(c, d),
+            State::DoctypeQuoted(d, q)            => self.doctype_quoted(c, d, q),
+            State::DoctypeComment(d)              => self.doctype_comment(c, d),
+            State::DoctypeInstruction(d)          => self.doctype_processing_instruction(c, d),
             State::ProcessingInstructionClosing   => self.processing_instruction_closing(c),
             State::EmptyTagClosing                => self.empty_element_closing(c),
             State::CommentClosing(s)              => self.comment_closing(c, s),
@@ -476,20 +664,68 @@ impl Lexer {
             DOC    ; 'T' ; DOCT   ; "<!DOC",
             DOCT   ; 'Y' ; DOCTY  ; "<!DOCT",
             DOCTY  ; 'P' ; DOCTYP ; "<!DOCTY";
-            DOCTYP ; 'E' ; "<!DOCTYP" ; self.move_to_with(State::DoctypeFinishing(1), Token::DoctypeStart)
+            DOCTYP ; 'E' ; "<!DOCTYP" ; {
+                self.doctype.clear();
+                self.doctype_subset = false;
+                self.move_to_with(State::DoctypeFinishing(1), Token::DoctypeStart)
+            }
         )
     }
 
     /// State used while awaiting the closing bracket for the <!DOCTYPE tag
+    ///
+    /// Quotes are only tracked in the parameters of declarations, that is, in the external
+    /// identifier of the <!DOCTYPE tag itself and in the markup declarations of its internal
+    /// subset; comments and processing instructions may contain unpaired quotes.
     fn doctype_finishing(&mut self, c: char, d: u8) -> Result {
+        if c == '>' && d == 1 {
+            return self.move_to_with(State::Normal, Token::TagEnd);
+        }
+        self.doctype.push(c);
         match c {
             '<' => self.move_to(State::DoctypeFinishing(d + 1)),
-            '>' if d == 1 => self.move_to_with(State::Normal, Token::TagEnd),
             '>' => self.move_to(State::DoctypeFinishing(d - 1)),
+            '[' if d == 1 => {
+                self.doctype_subset = true;
+                Ok(None)
+            }
+            '"' | '\'' if d > 1 || !self.doctype_subset => self.move_to(State::DoctypeQuoted(d, c)),
+            '-' if self.doctype.ends_with("<!--") => self.move_to(State::DoctypeComment(d)),
+            '?' if self.doctype.ends_with("<?") => self.move_to(State::DoctypeInstruction(d)),
             _ => Ok(None),
         }
     }
 
+    /// Encountered a quote inside the <!DOCTYPE tag
+    fn doctype_quoted(&mut self, c: char, d: u8, q: char) -> Result {
+        self.doctype.push(c);
+        if c == q {
+            self.move_to(State::DoctypeFinishing(d))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Encountered '<!--' inside the <!DOCTYPE tag
+    fn doctype_comment(&mut self, c: char, d: u8) -> Result {
+        self.doctype.push(c);
+        if c == '>' && self.doctype.ends_with("-->") {
+            self.move_to(State::DoctypeFinishing(d - 1))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Encountered '<?' inside the <!DOCTYPE tag
+    fn doctype_processing_instruction(&mut self, c: char, d: u8) -> Result {
+        self.doctype.push(c);
+        if c == '>' && self.doctype.ends_with("?>") {
+            self.move_to(State::DoctypeFinishing(d - 1))
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Encountered '?'
     fn processing_instruction_closing(&mut self, c: char) -> Result {
         match c {
@@ -703,7 +939,7 @@ mod tests {
             Token::TagEnd
             Token::Whitespace(' ')
         );
-        assert_none!(for lex and buf)
+        assert_none!(for lex and buf);
     }
 
     #[test]
@@ -719,7 +955,45 @@ mod tests {
             Token::TagEnd
             Token::Whitespace(' ')
         );
-        assert_none!(for lex and buf)
+        assert_none!(for lex and buf);
+    }
+
+    #[test]
+    fn entity_text_test() {
+        let (mut lex, mut buf) = make_lex_and_buf("&e;x");
+        assert_oks!(for lex and buf ;
+            Token::ReferenceStart
+            Token::Character('e')
+            Token::ReferenceEnd
+        );
+        lex.push_entity("e", "<b/>");
+        assert!(lex.is_inside_entity("e"));
+        assert_oks!(for lex and buf ;
+            Token::OpeningTagStart
+            Token::Character('b')
+            Token::EmptyTagEnd
+        );
+        // the entity is finished once the next character is read
+        assert_eq!(lex.entity_depth(), 1);
+        assert_oks!(for lex and buf ; Token::Character('x'));
+        assert_eq!(lex.entity_depth(), 0);
+        assert_eq!(lex.position().to_string(), "1:4");
+        assert_eq!(lex.bytes_read(), 4);
+        assert_none!(for lex and buf);
+    }
+
+    #[test]
+    fn doctype_with_unpaired_quotes_in_subset_test() {
+        let (mut lex, mut buf) = make_lex_and_buf(
+            r#"<!DOCTYPE ab [<?pi it's?><!-- "quoted --><!ENTITY x "it's <b>]></b>">]> "#
+        );
+        assert_oks!(for lex and buf ;
+            Token::DoctypeStart
+            Token::TagEnd
+            Token::Whitespace(' ')
+        );
+        assert_none!(for lex and buf);
+        assert_eq!(lex.take_doctype(), r#" ab [<?pi it's?><!-- "quoted --><!ENTITY x "it's <b>]></b>">]"#);
     }
 
     #[test]
@@ -3,20 +3,40 @@
 //! The most important type in this module is `EventReader`, which provides an iterator
 //! view for events in XML document.
 
-use std::io::{Read};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Cursor, Read};
 use std::result;
 
-use common::{Position, TextPosition};
+use attribute::OwnedAttribute;
+use common::{Position, TextPosition, XmlSpace};
+use name::{Name, OwnedName};
 
-pub use self::config::ParserConfig;
-pub use self::events::XmlEvent;
+pub use self::config::{ParserConfig, UnboundPrefixPolicy};
+pub use common::InvalidCharacterPolicy;
+pub use self::events::{XmlEvent, XmlEventKind, XmlEventRef};
+pub use self::dtd::{
+    EntityResolver, ExternalId, ContentModel, ContentParticle, ParticleKind, Repetition, ContentAutomaton,
+    AttributeDeclaration, AttributeType, AttributeDefault,
+};
+pub use self::adapters::{EventIterator, FilterEvents, MapEvents, ElementsOnly};
+pub use self::handler::{Handler, HandlerContext, parse_with_handler};
+pub use self::tokenizer::{RawToken, Tokenizer};
+pub use self::decoder::{Decoder, DecodingReader, Latin1Decoder, Utf8Decoder};
 
 use self::parser::PullParser;
+pub use self::parser::CurrentPath;
 
 mod lexer;
+mod dtd;
 mod parser;
 mod config;
 mod events;
+mod adapters;
+mod handler;
+mod tokenizer;
+mod decoder;
+mod validator;
 
 mod error;
 pub use self::error::{Error, ErrorKind};
@@ -27,7 +47,34 @@ pub type Result<T> = result::Result<T, Error>;
 /// A wrapper around an `std::io::Read` instance which provides pull-based XML parsing.
 pub struct EventReader<R: Read> {
     source: R,
-    parser: PullParser
+    parser: PullParser,
+    // the event returned by `peek()` and the position of the event before it
+    peeked: Option<(Result<XmlEvent>, TextPosition)>
+}
+
+/// Shows the state of the parser, like the current position, the open elements and the most
+/// recently read token, which is useful for debugging. Neither the source nor the contents of
+/// the internal buffers are shown.
+///
+/// ```rust
+/// use xml::reader::EventReader;
+///
+/// let mut reader = EventReader::from_str("<a><b>secret</b></a>");
+/// for _ in 0..4 {
+///     reader.next().unwrap();
+/// }
+/// let state = format!("{:?}", reader);
+/// assert!(state.contains("position: 1:7"));
+/// assert!(state.contains("open_elements: [\"a\", \"b\"]"));
+/// assert!(!state.contains("secret"));
+/// ```
+impl<R: Read> fmt::Debug for EventReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EventReader")
+            .field("parser", &self.parser)
+            .field("peeked", &self.peeked.as_ref().map(|(e, _)| e.as_ref().map(|e| e.kind())))
+            .finish()
+    }
 }
 
 impl<R: Read> EventReader<R> {
@@ -40,7 +87,22 @@ impl<R: Read> EventReader<R> {
     /// Creates a new reader with the provded configuration, consuming the given stream.
     #[inline]
     pub fn new_with_config(source: R, config: ParserConfig) -> EventReader<R> {
-        EventReader { source: source, parser: PullParser::new(config) }
+        EventReader { source: source, parser: PullParser::new(config), peeked: None }
+    }
+
+    /// Creates a new reader with the provided configuration which decodes the given stream
+    /// with the decoder.
+    ///
+    /// The encoding declared in the document is not checked against the decoder, nor does it
+    /// select another decoder, and the name of the decoder's encoding is reported as the
+    /// effective encoding in `XmlEvent::StartDocument`. The stream is passed to the decoder one
+    /// byte at a time, so byte offsets, as well as the document length limit, refer to the bytes
+    /// of the stream, like for UTF-8 documents. Wrap the stream into a `DecodingReader` instead
+    /// to decode it in larger chunks, for which byte offsets refer to the decoded UTF-8 text.
+    pub fn new_with_decoder<D: Decoder + Send + 'static>(source: R, decoder: D, config: ParserConfig) -> EventReader<R> {
+        let mut reader = EventReader::new_with_config(source, config);
+        reader.parser.set_decoder(Box::new(decoder));
+        reader
     }
 
     /// Pulls and returns next XML event from the stream.
@@ -49,12 +111,436 @@ impl<R: Read> EventReader<R> {
     /// further calls to this method will return this event again.
     #[inline]
     pub fn next(&mut self) -> Result<XmlEvent> {
-        self.parser.next(&mut self.source)
+        match self.peeked.take() {
+            Some((event, _)) => event,
+            None => self.parser.next(&mut self.source)
+        }
+    }
+
+    /// Reads the next XML event without consuming it, so that the following call to `next()`
+    /// returns the same event.
+    ///
+    /// Only one event is read ahead; calling this method again without calling `next()`
+    /// returns the same event.
+    ///
+    /// ```rust
+    /// use xml::reader::{EventReader, XmlEvent};
+    ///
+    /// let mut reader = EventReader::from_str("<a><b/>text</a>");
+    /// reader.next().unwrap(); // StartDocument
+    /// reader.next().unwrap(); // <a>
+    /// reader.next().unwrap(); // <b/>
+    /// // check whether the element is empty before deciding how to handle it
+    /// assert!(match *reader.peek() { Ok(XmlEvent::EndElement { .. }) => true, _ => false });
+    /// assert!(match reader.next().unwrap() { XmlEvent::EndElement { .. } => true, _ => false });
+    /// ```
+    ///
+    /// The position reported by the reader remains the position of the last event returned
+    /// by `next()` until the peeked event is returned. Other state of the parser, like
+    /// `depth()`, `current_path()` or `byte_offset()`, already reflects the peeked event.
+    pub fn peek(&mut self) -> &Result<XmlEvent> {
+        if self.peeked.is_none() {
+            let position = self.parser.position();
+            let event = self.parser.next(&mut self.source);
+            self.peeked = Some((event, position));
+        }
+        &self.peeked.as_ref().unwrap().0
+    }
+
+    /// Pulls the next XML event from the stream and returns only its kind.
+    ///
+    /// The document is parsed exactly as with `next()`, so all well-formedness errors are
+    /// still detected, but the data of the event is not built: text, comments and processing
+    /// instruction data are not copied out of the parser buffers, and no namespace mappings
+    /// or copies of element names and attributes are made. Names and attribute values are
+    /// still read, as they are needed to check the document, e.g. to match end tags and
+    /// to resolve namespace prefixes. When the document is validated against its DTD,
+    /// the data is built for the validation as usual.
+    ///
+    /// This is convenient when only the structure of the document is of interest, e.g. to
+    /// check its well-formedness or to measure the speed of parsing. A peeked event is
+    /// returned as with `next()`.
+    pub fn next_kind(&mut self) -> Result<XmlEventKind> {
+        match self.peeked.take() {
+            Some((event, _)) => event.map(|e| e.kind()),
+            None => self.parser.next_kind(&mut self.source)
+        }
+    }
+
+    /// Reads the rest of the document, discarding all events, and checks that it is
+    /// well-formed.
+    ///
+    /// ```rust
+    /// use xml::reader::EventReader;
+    ///
+    /// assert!(EventReader::from_str("<a><b/>text</a>").validate().is_ok());
+    /// assert!(EventReader::from_str("<a><b></a>").validate().is_err());
+    /// ```
+    ///
+    /// Returns the first error found in the document, if there is any.
+    pub fn validate(&mut self) -> Result<()> {
+        while self.next_kind()? != XmlEventKind::EndDocument {}
+        Ok(())
     }
 
     pub fn source(&self) -> &R { &self.source }
     pub fn source_mut(&mut self) -> &mut R { &mut self.source }
 
+    /// Returns an iterator over names of the currently open elements, from the root element
+    /// to the innermost one.
+    ///
+    /// The path includes the element whose `StartElement` event was returned most recently,
+    /// and an element is removed from it once its `EndElement` event is returned:
+    ///
+    /// ```rust
+    /// use xml::reader::{EventReader, XmlEvent};
+    ///
+    /// let mut reader = EventReader::from_str("<root><item><title>x</title></item></root>");
+    /// loop {
+    ///     match reader.next().unwrap() {
+    ///         XmlEvent::Characters(_) => break,
+    ///         _ => {}
+    ///     }
+    /// }
+    ///
+    /// let path: Vec<_> = reader.current_path().map(|n| n.local_name).collect();
+    /// assert_eq!(path.join("/"), "root/item/title");
+    /// assert_eq!(reader.depth(), 3);
+    /// ```
+    #[inline]
+    pub fn current_path(&self) -> CurrentPath<'_> {
+        self.parser.current_path()
+    }
+
+    /// Returns the number of currently open elements.
+    ///
+    /// This is the same as the length of `current_path()`.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.parser.depth()
+    }
+
+    /// Returns the whitespace handling mode in effect at the current element.
+    ///
+    /// The mode is set by the `xml:space` attribute and is inherited by descendant elements.
+    /// When it is `XmlSpace::Preserve`, the `trim_whitespace` option of the parser
+    /// configuration is not applied. Outside of the root element this is always
+    /// `XmlSpace::Default`.
+    #[inline]
+    pub fn xml_space(&self) -> XmlSpace {
+        self.parser.xml_space()
+    }
+
+    /// Returns the language of the current element, as declared by the nearest
+    /// `xml:lang` attribute, if there is any.
+    #[inline]
+    pub fn xml_lang(&self) -> Option<&str> {
+        self.parser.xml_lang()
+    }
+
+    /// Reads events until a start element with the given name and returns its attributes,
+    /// or `None` if the document ends first.
+    ///
+    /// If the name has a namespace URI, elements are matched by the URI and the local name,
+    /// whatever prefix they have; otherwise they are matched by the local name and, if it is
+    /// given, the prefix. Nothing is read after the start element, so reading can be continued
+    /// from there, e.g. to read the element content:
+    ///
+    /// ```rust
+    /// use xml::name::Name;
+    /// use xml::reader::{EventReader, XmlEvent};
+    ///
+    /// let mut reader = EventReader::from_str(
+    ///     "<feed xmlns='http://www.w3.org/2005/Atom'><link href='/'/><title>News</title></feed>"
+    /// );
+    /// let attributes = reader.read_to_element("link").unwrap().unwrap();
+    /// assert_eq!(attributes[0].value, "/");
+    ///
+    /// let title = Name::qualified("title", "http://www.w3.org/2005/Atom", None);
+    /// assert!(reader.read_to_element(title).unwrap().is_some());
+    /// assert_eq!(reader.next().unwrap(), XmlEvent::Characters("News".into()));
+    /// ```
+    pub fn read_to_element<'n, N: Into<Name<'n>>>(&mut self, name: N) -> Result<Option<Vec<OwnedAttribute>>> {
+        Ok(self.skip_to_element(name, None)?.map(|event| match event {
+            XmlEvent::StartElement { attributes, .. } => attributes,
+            _ => unreachable!()
+        }))
+    }
+
+    /// Reads events until a start element with the given name and returns the event, or `None`
+    /// if the document ends first.
+    ///
+    /// Names are matched like in `read_to_element()`. If a depth is given, only elements at that
+    /// depth are matched, where the root element has depth 1, so the search doesn't descend
+    /// into nested elements with the same name. Errors found while skipping events are returned,
+    /// and, like after any other error, reading can't be continued after them.
+    ///
+    /// ```rust
+    /// use xml::reader::{EventReader, XmlEvent};
+    ///
+    /// let mut reader = EventReader::from_str(
+    ///     "<app><module><config id='inner'/></module><config id='top'/></app>"
+    /// );
+    /// match reader.skip_to_element("config", Some(2)).unwrap() {
+    ///     Some(XmlEvent::StartElement { attributes, .. }) => assert_eq!(attributes[0].value, "top"),
+    ///     e => panic!("unexpected event: {:?}", e)
+    /// }
+    /// assert_eq!(reader.skip_to_element("config", None).unwrap(), None);
+    /// ```
+    pub fn skip_to_element<'n, N: Into<Name<'n>>>(&mut self, name: N, depth: Option<usize>) -> Result<Option<XmlEvent>> {
+        let name = name.into();
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement { name: found, attributes, namespace }
+                        if element_matches(&found, name) && depth.is_none_or(|d| d == self.depth()) =>
+                    return Ok(Some(XmlEvent::StartElement { name: found, attributes, namespace })),
+                XmlEvent::EndDocument => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads events until the first element with the given path and returns its text content,
+    /// or `None` if there is no such element.
+    ///
+    /// The path consists of the names of the element and its ancestors, starting with the root
+    /// element and separated by `/`, e.g. `rss/channel/title`. Each name is either a local name
+    /// or a name with a prefix, which matches only elements written with that prefix. The text
+    /// content is all character data of the element and its descendants, including CDATA sections.
+    ///
+    /// Reading stops after the end element of the found element, or after the start of
+    /// the root element if its name doesn't match, so the rest of a large document is not parsed.
+    ///
+    /// ```rust
+    /// use xml::reader::EventReader;
+    ///
+    /// let mut reader = EventReader::from_str(
+    ///     "<rss><channel><item><title>Item</title></item><title>An <b>important</b> feed</title></channel></rss>"
+    /// );
+    /// assert_eq!(reader.read_first_text("rss/channel/title").unwrap().as_ref().map(|s| &s[..]),
+    ///            Some("An important feed"));
+    /// ```
+    pub fn read_first_text(&mut self, path: &str) -> Result<Option<String>> {
+        let path: Vec<_> = path.split('/').filter(|s| !s.is_empty()).map(Name::from).collect();
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement { .. } => {
+                    if self.depth() == 1 {
+                        let root = self.current_path().next().unwrap();
+                        if !path.first().is_some_and(|&n| step_matches(root, n)) {
+                            return Ok(None);
+                        }
+                    }
+                    if self.depth() == path.len() && self.current_path().zip(&path).all(|(e, &n)| step_matches(e, n)) {
+                        return self.read_text_content().map(Some);
+                    }
+                }
+                XmlEvent::EndDocument => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads the rest of the current element and returns its character data.
+    fn read_text_content(&mut self) -> Result<String> {
+        let depth = self.depth();
+        let mut text = String::new();
+        while self.depth() >= depth {
+            match self.next()? {
+                XmlEvent::Characters(ref data) | XmlEvent::CData(ref data) | XmlEvent::Whitespace(ref data) =>
+                    text.push_str(data),
+                _ => {}
+            }
+        }
+        Ok(text)
+    }
+
+    /// Sets the function which fetches the content of external entities.
+    ///
+    /// The function is called with the identifiers of every external entity referenced in the
+    /// document content and of the external DTD subset, and its errors are reported as parsing
+    /// errors. This is a shorthand for setting the function as the resolver with
+    /// `ParserConfig::entity_resolver()`; see there for how external entities are read.
+    ///
+    /// ```rust
+    /// use std::io::{self, BufRead, Cursor};
+    /// use xml::reader::{EventReader, ExternalId, XmlEvent};
+    ///
+    /// let doc = r#"<!DOCTYPE a [<!ENTITY e SYSTEM "e.txt">]><a>&e;</a>"#;
+    ///
+    /// let mut reader = EventReader::from_str(doc);
+    /// reader.set_external_entity_resolver(|id: &ExternalId| {
+    ///     if id.system_id == "e.txt" {
+    ///         Ok(Box::new(Cursor::new("external text")) as Box<dyn BufRead>)
+    ///     } else {
+    ///         Err(io::Error::new(io::ErrorKind::NotFound, "no such entity"))
+    ///     }
+    /// });
+    /// reader.next().unwrap();
+    /// reader.next().unwrap();
+    /// assert_eq!(reader.next().unwrap(), XmlEvent::Characters("external text".into()));
+    ///
+    /// let mut reader = EventReader::from_str(doc);
+    /// reader.next().unwrap();
+    /// reader.next().unwrap();
+    /// assert!(reader.next().is_err());
+    /// ```
+    pub fn set_external_entity_resolver<F>(&mut self, resolver: F)
+        where F: FnMut(&ExternalId) -> io::Result<Box<dyn BufRead>> + Send + 'static
+    {
+        self.parser.set_entity_resolver(resolver);
+    }
+
+    /// Returns the internal general entities declared in the document type declaration,
+    /// mapped to their replacement texts.
+    ///
+    /// The map is filled when the `<!DOCTYPE` declaration is parsed, so it is complete once
+    /// the `StartElement` event of the root element is returned; it is empty if the document
+    /// has no internal subset. In replacement texts, character references are already
+    /// replaced, while references to other entities are left as they are. External entities
+    /// are not included, as well as entities provided with the `extra_entities` configuration
+    /// option:
+    ///
+    /// ```rust
+    /// use xml::reader::{EventReader, XmlEvent};
+    ///
+    /// let mut reader = EventReader::from_str(
+    ///     "<!DOCTYPE a [<!ENTITY who 'world'><!ENTITY % p 'x'>]><a>&who;</a>"
+    /// );
+    /// loop {
+    ///     match reader.next().unwrap() {
+    ///         XmlEvent::StartElement { .. } => break,
+    ///         _ => {}
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(reader.entities().get("who").map(|s| &s[..]), Some("world"));
+    /// assert_eq!(reader.entities().len(), 1);
+    /// assert_eq!(reader.parameter_entities().get("p").map(|s| &s[..]), Some("x"));
+    /// ```
+    #[inline]
+    pub fn entities(&self) -> &HashMap<String, String> {
+        self.parser.entities()
+    }
+
+    /// Returns the internal parameter entities declared in the document type declaration,
+    /// mapped to their replacement texts.
+    ///
+    /// See `entities()` for more information.
+    #[inline]
+    pub fn parameter_entities(&self) -> &HashMap<String, String> {
+        self.parser.parameter_entities()
+    }
+
+    /// Returns the element types declared in the document type declaration, mapped to their
+    /// content models.
+    ///
+    /// Like `entities()`, declarations are available once the document type declaration has
+    /// been read, and until the next document of a stream of multiple documents starts. They are
+    /// used for validation when `ParserConfig::validate_dtd` is enabled.
+    ///
+    /// ```rust
+    /// use xml::reader::{ContentModel, EventReader, XmlEvent};
+    ///
+    /// let mut reader = EventReader::from_str("<!DOCTYPE a [<!ELEMENT a (b+)><!ELEMENT b EMPTY>]><a><b/></a>");
+    /// loop {
+    ///     if let XmlEvent::StartElement { .. } = reader.next().unwrap() { break; }
+    /// }
+    /// assert_eq!(reader.element_declarations()["a"].to_string(), "(b+)");
+    /// assert_eq!(reader.element_declarations()["b"], ContentModel::Empty);
+    /// ```
+    #[inline]
+    pub fn element_declarations(&self) -> &HashMap<String, ContentModel> {
+        self.parser.element_declarations()
+    }
+
+    /// Returns the attributes declared in the document type declaration, by element type.
+    ///
+    /// The attribute lists of an element type are merged, in declaration order. See
+    /// `element_declarations()` for more information.
+    #[inline]
+    pub fn attribute_declarations(&self) -> &HashMap<String, Vec<AttributeDeclaration>> {
+        self.parser.attribute_declarations()
+    }
+
+    /// Returns the number of bytes consumed from the underlying stream so far.
+    ///
+    /// Unlike `position()`, which is a logical row and column in the text, this is an
+    /// absolute offset into the input, so it can be used to slice the original bytes of the
+    /// document. The parser reads the stream one character at a time, so no bytes are consumed
+    /// in advance except for the few characters of lookahead needed to recognize the end of
+    /// the last event, e.g. the `</` which terminates character data:
+    ///
+    /// ```rust
+    /// use xml::reader::{EventReader, XmlEvent};
+    ///
+    /// let source = "<a>zoë</a>".as_bytes();
+    /// let mut reader = EventReader::new(source);
+    /// let mut offsets = Vec::new();
+    /// loop {
+    ///     match reader.next().unwrap() {
+    ///         XmlEvent::EndDocument => break,
+    ///         _ => offsets.push(reader.byte_offset())
+    ///     }
+    /// }
+    /// // StartDocument, StartElement, Characters and EndElement
+    /// assert_eq!(offsets, vec![2, 3, 9, 11]);
+    /// assert_eq!(reader.byte_offset(), source.len() as u64);
+    /// ```
+    #[inline]
+    pub fn byte_offset(&self) -> u64 {
+        self.parser.byte_offset()
+    }
+
+    /// Returns the warnings noted by the parser since the last call of this method.
+    ///
+    /// Warnings are issued for recoverable problems with the document, for example, for every
+    /// invalid character which was replaced or skipped because of the `invalid_character_policy`
+    /// configuration option. Each warning carries the position of the problem and a message.
+    /// Warnings accumulate until they are taken with this method.
+    #[inline]
+    pub fn take_warnings(&mut self) -> Vec<Error> {
+        self.parser.take_warnings()
+    }
+
+    /// Turns this reader into an iterator which yields only the events for which
+    /// the predicate returns `true`.
+    ///
+    /// See `EventIterator` for more information.
+    #[inline]
+    pub fn filter_events<P>(self, predicate: P) -> FilterEvents<Events<R>, P>
+        where P: FnMut(&XmlEvent) -> bool
+    {
+        self.into_iter().filter_events(predicate)
+    }
+
+    /// Turns this reader into an iterator which replaces each event with the result
+    /// of the given function.
+    ///
+    /// See `EventIterator` for more information.
+    #[inline]
+    pub fn map_events<F>(self, f: F) -> MapEvents<Events<R>, F>
+        where F: FnMut(XmlEvent) -> XmlEvent
+    {
+        self.into_iter().map_events(f)
+    }
+
+    /// Turns this reader into an iterator which yields only the start and end elements.
+    ///
+    /// See `EventIterator::elements_only()` for more information.
+    #[inline]
+    pub fn elements_only(self, attributes: bool) -> ElementsOnly<Events<R>> {
+        self.into_iter().elements_only(attributes)
+    }
+
+    /// Turns this reader into an iterator which yields only the `Characters` events.
+    ///
+    /// See `EventIterator::text_only()` for more information.
+    #[inline]
+    pub fn text_only(self) -> FilterEvents<Events<R>, fn(&XmlEvent) -> bool> {
+        self.into_iter().text_only()
+    }
+
     /// Unwraps this `EventReader`, returning the underlying reader.
     ///
     /// Note that this operation is destructive; unwrapping the reader and wrapping it
@@ -65,11 +551,165 @@ impl<R: Read> EventReader<R> {
     }
 }
 
+impl<R: Read> EventReader<BufReader<R>> {
+    /// Creates a new reader which reads the given stream through an internal buffer.
+    ///
+    /// The source is only required to implement `Read`, so this constructor is convenient
+    /// for unbuffered sources like sockets or files.
+    #[inline]
+    pub fn new_buffered(source: R) -> EventReader<BufReader<R>> {
+        EventReader::new_buffered_with_config(source, ParserConfig::new())
+    }
+
+    /// Creates a new reader with the provided configuration which reads the given stream
+    /// through an internal buffer.
+    ///
+    /// The capacity of the buffer is determined by the `buffer_size` configuration option.
+    #[inline]
+    pub fn new_buffered_with_config(source: R, config: ParserConfig) -> EventReader<BufReader<R>> {
+        let source = BufReader::with_capacity(config.get_buffer_size(), source);
+        EventReader::new_with_config(source, config)
+    }
+
+    /// Unwraps this `EventReader`, returning the underlying stream and the bytes which were
+    /// already read from it into the internal buffer but were not consumed by the parser.
+    ///
+    /// This allows continuing reading the stream after the end of the document, e.g. when
+    /// XML documents are a part of some protocol:
+    ///
+    /// ```rust
+    /// use xml::reader::{EventReader, XmlEvent};
+    ///
+    /// let mut reader = EventReader::new_buffered(&b"<a>text</a>\x00\x01\x02"[..]);
+    /// loop {
+    ///     match reader.next().unwrap() {
+    ///         XmlEvent::EndElement { .. } if reader.depth() == 0 => break,
+    ///         _ => {}
+    ///     }
+    /// }
+    ///
+    /// let (rest, buffered) = reader.into_inner_with_buffer();
+    /// assert_eq!([&buffered[..], rest].concat(), b"\x00\x01\x02");
+    /// ```
+    pub fn into_inner_with_buffer(self) -> (R, Vec<u8>) {
+        let buffered = self.source.buffer().to_vec();
+        (self.source.into_inner(), buffered)
+    }
+}
+
+impl<'r> EventReader<Cursor<&'r [u8]>> {
+    /// Reads the rest of the innermost open element and returns its original bytes, from the
+    /// beginning of its start tag to the end of its end tag.
+    ///
+    /// This method is meant to be called right after the `StartElement` event of the element.
+    /// The content of the element is still parsed and checked for well-formedness, but the
+    /// events are discarded; the next call to `next()` returns the event following the
+    /// corresponding `EndElement`. The bytes are returned exactly as they are in the input,
+    /// without any entity expansion or normalization, which is useful e.g. for computing
+    /// signatures over parts of the document.
+    ///
+    /// This method is only available for in-memory documents read through a `Cursor`, because
+    /// it needs access to the whole input:
+    ///
+    /// ```rust
+    /// use std::io::Cursor;
+    /// use xml::reader::{EventReader, XmlEvent};
+    ///
+    /// let data = b"<doc><sig alg='x'>a &amp; b</sig><rest/></doc>";
+    /// let mut reader = EventReader::new(Cursor::new(&data[..]));
+    /// loop {
+    ///     match reader.next().unwrap() {
+    ///         XmlEvent::StartElement { ref name, .. } if name.local_name == "sig" => break,
+    ///         _ => {}
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(reader.read_element_raw().unwrap(), &b"<sig alg='x'>a &amp; b</sig>"[..]);
+    /// assert!(match reader.next().unwrap() { XmlEvent::StartElement { .. } => true, _ => false });
+    /// ```
+    ///
+    /// Returns an error if there is no open element, if the next event was already read with
+    /// `peek()` or if the content of the element is malformed.
+    pub fn read_element_raw(&mut self) -> Result<&'r [u8]> {
+        if self.peeked.is_some() {
+            return Err((&*self, "The next event is already read").into());
+        }
+        let depth = self.depth();
+        let start = match self.parser.element_start_offset() {
+            Some(start) => start,
+            None => return Err((&*self, "There is no open element to read").into())
+        };
+        while self.depth() >= depth {
+            self.next()?;
+        }
+
+        // the cursor could have been advanced before the reader was created
+        let base = self.source.position() - self.parser.byte_offset();
+        let data: &'r [u8] = self.source.get_ref();
+        Ok(&data[(base + start) as usize..(base + self.parser.byte_offset()) as usize])
+    }
+}
+
+/// Parses the given string with the provided configuration and returns all its events.
+///
+/// The returned vector starts with `StartDocument` and ends with `EndDocument`. Trailing
+/// content after the root element other than whitespace, comments and processing instructions
+/// is an error, as well as any other well-formedness error:
+///
+/// ```rust
+/// use xml::reader::{self, ParserConfig, XmlEvent};
+///
+/// let config = ParserConfig::new();
+/// let events = reader::parse_str("<a>text</a>", &config).unwrap();
+/// assert_eq!(events.len(), 5);
+/// assert_eq!(events[2], XmlEvent::Characters("text".into()));
+/// assert_eq!(events[4], XmlEvent::EndDocument);
+///
+/// assert!(reader::parse_str("<a>text</a>trailing", &config).is_err());
+/// ```
+pub fn parse_str(source: &str, config: &ParserConfig) -> Result<Vec<XmlEvent>> {
+    parse_bytes(source.as_bytes(), config)
+}
+
+/// Parses the given byte slice with the provided configuration and returns all its events.
+///
+/// See `parse_str()` for more information.
+pub fn parse_bytes(source: &[u8], config: &ParserConfig) -> Result<Vec<XmlEvent>> {
+    let mut reader = EventReader::new_with_config(source, config.clone());
+    let mut events = Vec::new();
+    loop {
+        match reader.next()? {
+            XmlEvent::EndDocument => {
+                events.push(XmlEvent::EndDocument);
+                return Ok(events);
+            }
+            e => events.push(e)
+        }
+    }
+}
+
+/// Checks whether the name of an element matches the name given to `read_to_element()`.
+fn element_matches(found: &OwnedName, name: Name) -> bool {
+    match name.namespace {
+        Some(namespace) => found.borrow().matches_ns(Some(namespace), name.local_name),
+        None => step_matches(found.borrow(), name)
+    }
+}
+
+/// Checks whether the name of an element matches a name without a namespace, which matches
+/// any prefix if it has none.
+fn step_matches(found: Name, name: Name) -> bool {
+    found.local_name == name.local_name && name.prefix.is_none_or(|p| found.prefix == Some(p))
+}
+
 impl<B: Read> Position for EventReader<B> {
     /// Returns the position of the last event produced by the reader.
     #[inline]
     fn position(&self) -> TextPosition {
-        self.parser.position()
+        match self.peeked {
+            Some((_, position)) => position,
+            None => self.parser.position()
+        }
     }
 }
 
@@ -78,17 +718,36 @@ impl<R: Read> IntoIterator for EventReader<R> {
     type IntoIter = Events<R>;
 
     fn into_iter(self) -> Events<R> {
-        Events { reader: self, finished: false }
+        Events { reader: self, finished: false, after_end_document: false }
     }
 }
 
 /// An iterator over XML events created from some type implementing `Read`.
 ///
 /// When the next event is `xml::event::Error` or `xml::event::EndDocument`, then
-/// it will be returned by the iterator once, and then it will stop producing events.
+/// it will be returned by the iterator once, and then it will stop producing events,
+/// so the usual `for` loop reads the document exactly once:
+///
+/// ```rust
+/// use xml::reader::{EventReader, XmlEvent};
+///
+/// let mut names = Vec::new();
+/// for e in EventReader::from_str("<a><b/></a>") {
+///     if let XmlEvent::StartElement { name, .. } = e.unwrap() {
+///         names.push(name.local_name);
+///     }
+/// }
+/// assert_eq!(names, vec!["a", "b"]);
+/// ```
+///
+/// The only exception is the `ignore_end_of_stream` configuration option, with which
+/// the iterator never stops, so more input can be read when it becomes available. For this
+/// reason the iterator doesn't implement `FusedIterator`.
 pub struct Events<R: Read> {
     reader: EventReader<R>,
-    finished: bool
+    finished: bool,
+    // whether the last returned event is the end of one of multiple documents
+    after_end_document: bool
 }
 
 impl<R: Read> Events<R> {
@@ -101,6 +760,28 @@ impl<R: Read> Events<R> {
     pub fn source(&self) -> &R { &self.reader.source }
     pub fn source_mut(&mut self) -> &mut R { &mut self.reader.source }
 
+    /// Returns the next event without consuming it, like `EventReader::peek()`.
+    ///
+    /// Returns `None` if the iterator is finished.
+    pub fn peek(&mut self) -> Option<&Result<XmlEvent>> {
+        if self.finished && !self.reader.parser.is_ignoring_end_of_stream() {
+            return None;
+        }
+        let after_end_document = self.after_end_document;
+        match *self.reader.peek() {
+            // the end of the stream after multiple documents
+            Ok(XmlEvent::EndDocument) if after_end_document => None,
+            ref ev => Some(ev)
+        }
+    }
+}
+
+impl<R: Read> Position for Events<R> {
+    /// Returns the position of the last event produced by the iterator.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.reader.position()
+    }
 }
 
 impl<R: Read> Iterator for Events<R> {
@@ -112,8 +793,17 @@ impl<R: Read> Iterator for Events<R> {
         else {
             let ev = self.reader.next();
             match ev {
+                // the end of the stream after multiple documents
+                Ok(XmlEvent::EndDocument) if self.after_end_document => {
+                    self.finished = true;
+                    return None;
+                }
+                Ok(XmlEvent::EndDocument) if self.reader.parser.is_reading_multiple_documents() =>
+                    self.after_end_document = true,
                 Ok(XmlEvent::EndDocument) | Err(_) => self.finished = true,
-                _ => {}
+                // whitespace after the last document is followed by the end of the stream
+                Ok(XmlEvent::Whitespace(_)) if self.after_end_document => {}
+                _ => self.after_end_document = false
             }
             Some(ev)
         }
@@ -122,8 +812,60 @@ impl<R: Read> Iterator for Events<R> {
 
 impl<'r> EventReader<&'r [u8]> {
     /// A convenience method to create an `XmlReader` from a string slice.
+    ///
+    /// ```rust
+    /// use xml::reader::{EventReader, XmlEvent};
+    ///
+    /// let mut reader = EventReader::from_str("<hello>world</hello>");
+    ///
+    /// assert!(match reader.next().unwrap() { XmlEvent::StartDocument { .. } => true, _ => false });
+    /// assert!(match reader.next().unwrap() { XmlEvent::StartElement { .. } => true, _ => false });
+    /// assert_eq!(reader.next().unwrap(), XmlEvent::Characters("world".into()));
+    /// ```
     #[inline]
     pub fn from_str(source: &'r str) -> EventReader<&'r [u8]> {
         EventReader::new(source.as_bytes())
     }
+
+    /// A convenience method to create an `XmlReader` from a byte slice.
+    ///
+    /// ```rust
+    /// use xml::reader::{EventReader, XmlEvent};
+    ///
+    /// let data: Vec<u8> = b"<hello>world</hello>".to_vec();
+    /// let mut reader = EventReader::from_bytes(&data);
+    ///
+    /// assert!(match reader.next().unwrap() { XmlEvent::StartDocument { .. } => true, _ => false });
+    /// assert!(match reader.next().unwrap() { XmlEvent::StartElement { .. } => true, _ => false });
+    /// assert_eq!(reader.next().unwrap(), XmlEvent::Characters("world".into()));
+    /// ```
+    #[inline]
+    pub fn from_bytes(source: &'r [u8]) -> EventReader<&'r [u8]> {
+        EventReader::new(source)
+    }
+
+    /// A convenience method to create an `XmlReader` with the provided configuration
+    /// from a string slice.
+    ///
+    /// ```rust
+    /// use xml::reader::{EventReader, ParserConfig, XmlEvent};
+    ///
+    /// let config = ParserConfig::new().trim_whitespace(true);
+    /// let mut reader = EventReader::from_str_with_config("<hello> world </hello>", config);
+    ///
+    /// assert!(match reader.next().unwrap() { XmlEvent::StartDocument { .. } => true, _ => false });
+    /// assert!(match reader.next().unwrap() { XmlEvent::StartElement { .. } => true, _ => false });
+    /// assert_eq!(reader.next().unwrap(), XmlEvent::Characters("world".into()));
+    /// ```
+    #[inline]
+    pub fn from_str_with_config(source: &'r str, config: ParserConfig) -> EventReader<&'r [u8]> {
+        EventReader::new_with_config(source.as_bytes(), config)
+    }
+
+    /// A convenience method to create an `XmlReader` with the provided configuration
+    /// from a byte slice.
+    #[inline]
+    pub fn from_bytes_with_config(source: &'r [u8], config: ParserConfig) -> EventReader<&'r [u8]> {
+        EventReader::new_with_config(source, config)
+    }
 }
@@ -8,10 +8,10 @@ impl PullParser {
         match t {
             Token::CDataEnd => {
                 self.lexer.enable_errors();
-                let event = if self.config.cdata_to_characters {
+                let event = if self.config.get_cdata_to_characters() {
                     None
                 } else {
-                    let data = self.take_buf();
+                    let data = self.take_data();
                     Some(Ok(XmlEvent::CData(data)))
                 };
                 self.into_state(State::OutsideTag, event)
@@ -24,8 +24,7 @@ impl PullParser {
 
             _ => {
                 self.inside_whitespace = false;
-                t.push_to_string(&mut self.buf);
-                None
+                self.append_content_token(t)
             }
         }
     }
@@ -9,23 +9,20 @@ impl PullParser {
             // Double dash is illegal inside a comment
             Token::Chunk(ref s) if &s[..] == "--" => Some(self_error!(self; "Unexpected token inside a comment: --")),
 
-            Token::CommentEnd if self.config.ignore_comments => {
+            Token::CommentEnd if self.ignore_comments() => {
                 self.lexer.outside_comment();
                 self.into_state_continue(State::OutsideTag)
             }
 
             Token::CommentEnd => {
                 self.lexer.outside_comment();
-                let data = self.take_buf();
+                let data = self.take_data();
                 self.into_state_emit(State::OutsideTag, Ok(XmlEvent::Comment(data)))
             }
 
-            _ if self.config.ignore_comments => None,  // Do not modify buffer if ignoring the comment
+            _ if self.ignore_comments() => None,  // Do not modify buffer if ignoring the comment
 
-            _ => {
-                t.push_to_string(&mut self.buf);
-                None
-            }
+            _ => self.append_content_token(t)
         }
     }
 
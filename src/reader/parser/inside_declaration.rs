@@ -6,7 +6,7 @@ use reader::lexer::Token;
 
 use super::{
     Result, PullParser, State, DeclarationSubstate, QualifiedNameTarget,
-    DEFAULT_VERSION, DEFAULT_ENCODING
+    DEFAULT_VERSION
 };
 
 impl PullParser {
@@ -21,12 +21,17 @@ impl PullParser {
         fn emit_start_document(this: &mut PullParser) -> Option<Result> {
             this.parsed_declaration = true;
             let version = this.data.take_version();
+            this.xml_version = version.unwrap_or(DEFAULT_VERSION);
             let encoding = this.data.take_encoding();
+            if let Some(ref encoding) = encoding {
+                this.select_decoder(encoding);
+            }
             let standalone = this.data.take_standalone();
             this.into_state_emit(State::OutsideTag, Ok(XmlEvent::StartDocument {
                 version: version.unwrap_or(DEFAULT_VERSION),
-                encoding: encoding.unwrap_or(DEFAULT_ENCODING.into()),
-                standalone: standalone
+                encoding,
+                effective_encoding: this.effective_encoding.clone(),
+                standalone
             }))
         }
 
@@ -58,9 +63,11 @@ impl PullParser {
             },
 
             DeclarationSubstate::InsideVersionValue => self.read_attribute_value(t, |this, value| {
+                // VersionNum ::= '1.' [0-9]+
                 this.data.version = match &value[..] {
-                    "1.0" => Some(XmlVersion::Version10),
                     "1.1" => Some(XmlVersion::Version11),
+                    _ if value.starts_with("1.") && value.len() > 2 &&
+                         value[2..].bytes().all(|b| b.is_ascii_digit()) => Some(XmlVersion::Version10),
                     _     => None
                 };
                 if this.data.version.is_some() {
@@ -95,8 +102,15 @@ impl PullParser {
             },
 
             DeclarationSubstate::InsideEncodingValue => self.read_attribute_value(t, |this, value| {
-                this.data.encoding = Some(value);
-                this.into_state_continue(State::InsideDeclaration(DeclarationSubstate::BeforeStandaloneDecl))
+                // EncName ::= [A-Za-z] ([A-Za-z0-9._] | '-')*
+                let valid = value.starts_with(|c: char| c.is_ascii_alphabetic()) &&
+                    value.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-');
+                if valid {
+                    this.data.encoding = Some(value);
+                    this.into_state_continue(State::InsideDeclaration(DeclarationSubstate::BeforeStandaloneDecl))
+                } else {
+                    Some(self_error!(this; "Invalid encoding name: {}", value))
+                }
             }),
 
             DeclarationSubstate::BeforeStandaloneDecl => match t {
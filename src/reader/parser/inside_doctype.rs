@@ -1,3 +1,5 @@
+use common::Position;
+use reader::events::XmlEvent;
 use reader::lexer::Token;
 
 use super::{Result, PullParser, State};
@@ -7,7 +9,25 @@ impl PullParser {
         match t {
             Token::TagEnd => {
                 self.lexer.enable_errors();
-                self.into_state_continue(State::OutsideTag)
+                let doctype = self.lexer.take_doctype();
+                if let Err((offset, e)) = self.dtd.parse(&doctype, &self.config, self.xml_version) {
+                    // the contents of the declaration start right after `<!DOCTYPE`
+                    let mut pos = self.lexer.position();
+                    for c in doctype[..offset].chars() {
+                        if c == '\n' { pos.new_line(); } else { pos.advance(1); }
+                    }
+                    return Some(Err((&pos, e).into()));
+                }
+                if !self.config.get_report_doctype() {
+                    return self.into_state_continue(State::OutsideTag);
+                }
+                let event = XmlEvent::DoctypeDecl {
+                    name: self.dtd.root_name().unwrap_or_default().into(),
+                    public_id: self.dtd.external_subset().and_then(|id| id.public_id.clone()),
+                    system_id: self.dtd.external_subset().map(|id| id.system_id.clone()),
+                    internal_subset: self.dtd.internal_subset().map(|s| s.into())
+                };
+                self.into_state_emit(State::OutsideTag, Ok(event))
             }
 
             _ => None
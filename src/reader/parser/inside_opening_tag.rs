@@ -14,7 +14,7 @@ impl PullParser {
                 match name.prefix_ref() {
                     Some(prefix) if prefix == namespace::NS_XML_PREFIX ||
                                     prefix == namespace::NS_XMLNS_PREFIX =>
-                        Some(self_error!(this; "'{:?}' cannot be an element name prefix", name.prefix)),
+                        Some(self_error!(this; "'{}' cannot be an element name prefix", prefix)),
                     _ => {
                         this.data.element_name = Some(name.clone());
                         match token {
@@ -72,6 +72,10 @@ impl PullParser {
                                 Some(self_error!(this; "Cannot redefine prefix '{}'", namespace::NS_XMLNS_PREFIX))
                             } else if ln == namespace::NS_XML_PREFIX && &value[..] != namespace::NS_XML_URI {
                                 Some(self_error!(this; "Prefix '{}' cannot be rebound to another value", namespace::NS_XML_PREFIX))
+                            } else if ln != namespace::NS_XML_PREFIX && &value[..] == namespace::NS_XML_URI {
+                                Some(self_error!(this; "Namespace '{}' cannot be bound to a prefix other than '{}'", value, namespace::NS_XML_PREFIX))
+                            } else if &value[..] == namespace::NS_XMLNS_URI {
+                                Some(self_error!(this; "Namespace '{}' cannot be bound to a prefix", value))
                             } else if value.is_empty() {
                                 Some(self_error!(this; "Cannot undefine prefix '{}'", ln))
                             } else {
@@ -83,7 +87,7 @@ impl PullParser {
                         // declaring default namespace
                         None if &name.local_name[..] == namespace::NS_XMLNS_PREFIX =>
                             match &value[..] {
-                                namespace::NS_XMLNS_PREFIX | namespace::NS_XML_PREFIX =>
+                                namespace::NS_XMLNS_URI | namespace::NS_XML_URI =>
                                     Some(self_error!(this; "Namespace '{}' cannot be default", value)),
                                 _ => {
                                     this.nst.put(namespace::NS_NO_PREFIX, value.clone());
@@ -74,7 +74,7 @@ impl PullParser {
                 Token::ProcessingInstructionEnd => {
                     self.lexer.enable_errors();
                     let name = self.data.take_name();
-                    let data = self.take_buf();
+                    let data = self.take_data();
                     self.into_state_emit(
                         State::OutsideTag,
                         Ok(XmlEvent::ProcessingInstruction {
@@ -85,10 +85,7 @@ impl PullParser {
                 },
 
                 // Any other token should be treated as plain characters
-                _ => {
-                    t.push_to_string(&mut self.buf);
-                    None
-                }
+                _ => self.append_content_token(t)
             },
         }
     }
@@ -1,7 +1,10 @@
 use std::char;
 
-use common::{is_name_start_char, is_name_char, is_whitespace_str};
+use common::{is_name_start_char, is_name_char, is_whitespace_str, is_xml_char_reference};
+use util::parse_char_reference_code;
 
+use reader::dtd::Replacement;
+use reader::events::XmlEvent;
 use reader::lexer::Token;
 
 use super::{Result, PullParser, State};
@@ -18,7 +21,10 @@ impl PullParser {
             Token::ReferenceEnd => {
                 // TODO: check for unicode correctness
                 let name = self.data.take_ref_data();
-                let name_len = name.len();  // compute once
+                if !self.config.get_replace_entity_references() && prev_st == State::OutsideTag &&
+                   self.depth() > 0 && is_named_entity(&name) {
+                    return self.emit_entity_reference(name);
+                }
                 let c = match &name[..] {
                     "lt"   => Ok('<'.to_string()),
                     "gt"   => Ok('>'.to_string()),
@@ -26,48 +32,18 @@ impl PullParser {
                     "apos" => Ok('\''.to_string()),
                     "quot" => Ok('"'.to_string()),
                     ""     => Err(self_error!(self; "Encountered empty entity")),
-                    _ if name_len > 2 && name.starts_with("#x") => {
-                        let num_str = &name[2..name_len];
-                        if num_str == "0" {
-                            Err(self_error!(self; "Null character entity is not allowed"))
-                        } else {
-                            if self.config.replace_unknown_entity_references {
-                                match u32::from_str_radix(num_str, 16).ok().map(|i| char::from_u32(i).unwrap_or('\u{fffd}')) {
-                                    Some(c) => Ok(c.to_string()),
-                                    None    => Err(self_error!(self; "Invalid hexadecimal character number in an entity: {}", name))
-                                }
-                            } else {
-                                match u32::from_str_radix(num_str, 16).ok().and_then(char::from_u32) {
-                                    Some(c) => Ok(c.to_string()),
-                                    None    => Err(self_error!(self; "Invalid hexadecimal character number in an entity: {}", name))
-                                }
-                            }
-                        }
-                    }
-                    _ if name_len > 1 && name.starts_with('#') => {
-                        let num_str = &name[1..name_len];
-                        if num_str == "0" {
-                            Err(self_error!(self; "Null character entity is not allowed"))
-                        } else {
-                            if self.config.replace_unknown_entity_references {
-                                match u32::from_str_radix(num_str, 10).ok().map(|i| char::from_u32(i).unwrap_or('\u{fffd}')) {
-                                    Some(c) => Ok(c.to_string()),
-                                    None    => Err(self_error!(self; "Invalid decimal character number in an entity: {}", name))
-                                }
-                            }
-                            else {
-                                match u32::from_str_radix(num_str, 10).ok().and_then(char::from_u32) {
-                                    Some(c) => Ok(c.to_string()),
-                                    None    => Err(self_error!(self; "Invalid decimal character number in an entity: {}", name))
-                                }
-                            }
-                        }
-                    },
+                    _ if name.starts_with('#') => self.char_reference(&name),
                     _ => {
-                        if let Some(v) = self.config.extra_entities.get(&name) {
+                        if let Some(v) = self.config.get_extra_entities().get(&name) {
                             Ok(v.clone())
                         } else {
-                            Err(self_error!(self; "Unexpected entity: {}", name))
+                            let in_attribute = prev_st != State::OutsideTag;
+                            match self.dtd.expand_general_entity(&name, &self.config, in_attribute) {
+                                Ok(Some(Replacement::Text(v))) => Ok(v),
+                                Ok(Some(Replacement::Markup(text))) => return self.push_entity(name, &text),
+                                Ok(None) => Err(self_error!(self; "Unexpected entity: {}", name)),
+                                Err(e) => Err(self_error!(self; e))
+                            }
                         }
                     }
                 };
@@ -86,4 +62,63 @@ impl PullParser {
             _ => Some(self_error!(self; "Unexpected token inside an entity: {}", t))
         }
     }
+
+    /// Returns the character of a character reference, given with the leading `#` but without
+    /// the trailing `;`.
+    ///
+    /// References to code points which are not characters are replaced with U+FFFD if
+    /// `replace_unknown_entity_references` is enabled, but references to characters which are
+    /// not allowed in XML documents are always an error.
+    fn char_reference(&mut self, name: &str) -> ::std::result::Result<String, Result> {
+        let code = parse_char_reference_code(&name[1..]);
+        match code.and_then(char::from_u32) {
+            Some(c) if is_xml_char_reference(c, self.xml_version) => Ok(c.to_string()),
+            Some(c) => Err(self_error!(self; "Character U+{:04X} is not allowed in XML documents: &{};", c as u32, name)),
+            None if code.is_some() && self.config.get_replace_unknown_entity_references() => Ok('\u{fffd}'.to_string()),
+            None => {
+                let kind = if name.starts_with("#x") { "hexadecimal" } else { "decimal" };
+                Err(self_error!(self; "Invalid {} character number in an entity: {}", kind, name))
+            }
+        }
+    }
+
+    /// Makes the lexer read the replacement text of an entity referenced in content, which
+    /// contains markup, in place of the reference.
+    fn push_entity(&mut self, name: String, text: &str) -> Option<Result> {
+        if self.lexer.is_inside_entity(&name) {
+            return Some(self_error!(self; "Recursive entity reference: &{};", name));
+        }
+        if self.lexer.entity_depth() >= self.config.get_max_entity_expansion_depth() as usize {
+            return Some(self_error!(self; "Entity expansion depth limit exceeded: more than {} levels",
+                                    self.config.get_max_entity_expansion_depth()));
+        }
+        if self.lexer.entities_length() + text.len() > self.config.get_max_entity_expansion_length() {
+            return Some(self_error!(self; "Entity expansion length limit exceeded: more than {} bytes",
+                                    self.config.get_max_entity_expansion_length()));
+        }
+        self.lexer.push_entity(&name, text);
+        self.entity_depths.push(self.depth());
+        self.into_state_continue(State::OutsideTag)
+    }
+
+    /// Emits a reference which is not replaced, preceded by the character data buffered
+    /// before it, if any.
+    fn emit_entity_reference(&mut self, name: String) -> Option<Result> {
+        self.st = State::OutsideTag;
+        self.pos.push(self.reference_pos);
+        let reference = Ok(XmlEvent::EntityReference(name));
+        match self.take_text_event() {
+            Some(text) => {
+                self.next_event = Some(reference);
+                Some(text)
+            }
+            None => Some(reference)
+        }
+    }
+}
+
+/// Checks whether the given reference is a reference to a general entity other than
+/// the predefined ones, as opposed to a character reference.
+fn is_named_entity(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('#') && !matches!(name, "lt" | "gt" | "amp" | "apos" | "quot")
 }
@@ -1,21 +1,28 @@
 //! Contains an implementation of pull-based XML parser.
 
+use std::fmt;
 use std::mem;
+use std::slice;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::io::prelude::*;
 
 use common::{
     self,
-    XmlVersion, Position, TextPosition,
+    XmlVersion, XmlSpace, Position, TextPosition, InvalidCharacterPolicy,
     is_name_start_char, is_name_char,
 };
-use name::OwnedName;
+use name::{Name, OwnedName};
 use attribute::OwnedAttribute;
-use namespace::NamespaceStack;
+use namespace::{self, Namespace, NamespaceStack};
 
-use reader::events::XmlEvent;
-use reader::config::ParserConfig;
+use reader::{Error, ErrorKind};
+use reader::events::{XmlEvent, XmlEventKind};
+use reader::config::{ParserConfig, UnboundPrefixPolicy};
+use reader::decoder::{self, Decoder};
 use reader::lexer::{Lexer, Token};
+use reader::dtd::{AttributeDeclaration, ContentModel, Dtd, EntityResolver};
+use reader::validator::Validator;
 
 macro_rules! gen_takes(
     ($($field:ident -> $method:ident, $t:ty, $def:expr);+) => (
@@ -63,7 +70,44 @@ static DEFAULT_VERSION: XmlVersion      = XmlVersion::Version10;
 static DEFAULT_ENCODING: &'static str   = "UTF-8";
 static DEFAULT_STANDALONE: Option<bool> = None;
 
-type ElementStack = Vec<OwnedName>;
+/// An element which is currently open, together with the scoped `xml:*` attribute values.
+struct ElementScope {
+    name: OwnedName,
+    space: XmlSpace,  // xml:space in effect, possibly inherited from an ancestor
+    lang: Option<String>,  // xml:lang declared on this very element
+    start_offset: u64  // byte offset of the start tag
+}
+
+type ElementStack = Vec<ElementScope>;
+
+/// An iterator over names of the currently open elements, from the root element
+/// to the innermost one.
+pub struct CurrentPath<'a> {
+    elements: slice::Iter<'a, ElementScope>
+}
+
+impl<'a> Iterator for CurrentPath<'a> {
+    type Item = Name<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Name<'a>> {
+        self.elements.next().map(|e| e.name.borrow())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.elements.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for CurrentPath<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Name<'a>> {
+        self.elements.next_back().map(|e| e.name.borrow())
+    }
+}
+
+impl<'a> ExactSizeIterator for CurrentPath<'a> {}
 pub type Result = super::Result<XmlEvent>;
 
 /// Pull-based XML parser.
@@ -79,20 +123,36 @@ pub struct PullParser {
     next_event: Option<Result>,
     est: ElementStack,
     pos: Vec<TextPosition>,
+    reference_pos: TextPosition,
+    last_token: Option<Token>,
+    dtd: Dtd,
+    // the numbers of open elements at the references to the entities which the lexer reads inline
+    entity_depths: Vec<usize>,
+    validator: Validator,
+    warnings: Vec<Error>,
+    xml_version: XmlVersion,
+    effective_encoding: String,
+    // whether the decoder was chosen by the user, so that the declared encoding is ignored
+    decoder_fixed: bool,
 
     encountered_element: bool,
     parsed_declaration: bool,
+    finished_document: bool,
     inside_whitespace: bool,
     read_prefix_separator: bool,
-    pop_namespace: bool
+    pop_namespace: bool,
+    // whether the data of the event being read is not needed, see `next_kind()`
+    discard_data: bool
 }
 
 impl PullParser {
     /// Returns a new parser using the given config.
     pub fn new(config: ParserConfig) -> PullParser {
+        let mut lexer = Lexer::new();
+        lexer.limit_bytes(config.get_max_document_length());
         PullParser {
             config: config,
-            lexer: Lexer::new(),
+            lexer,
             st: State::OutsideTag,
             buf: String::new(),
             nst: NamespaceStack::default(),
@@ -104,6 +164,7 @@ impl PullParser {
                 standalone: None,
                 ref_data: String::new(),
                 element_name: None,
+                element_offset: 0,
                 quote: None,
                 attr_name: None,
                 attributes: Vec::new()
@@ -112,17 +173,116 @@ impl PullParser {
             next_event: None,
             est: Vec::new(),
             pos: vec![TextPosition::new()],
+            reference_pos: TextPosition::new(),
+            last_token: None,
+            dtd: Dtd::default(),
+            entity_depths: Vec::new(),
+            validator: Validator::default(),
+            warnings: Vec::new(),
+            xml_version: DEFAULT_VERSION,
+            effective_encoding: DEFAULT_ENCODING.into(),
+            decoder_fixed: false,
 
             encountered_element: false,
             parsed_declaration: false,
+            finished_document: false,
             inside_whitespace: true,
             read_prefix_separator: false,
-            pop_namespace: false
+            pop_namespace: false,
+            discard_data: false
+        }
+    }
+
+    /// Makes the lexer decode the stream with the given decoder, whose encoding is reported
+    /// as the effective encoding of documents. The encodings declared in the documents are
+    /// ignored then.
+    pub fn set_decoder(&mut self, decoder: Box<dyn Decoder + Send>) {
+        self.effective_encoding = decoder.encoding().into();
+        self.decoder_fixed = true;
+        self.lexer.set_decoder(Some(decoder));
+    }
+
+    /// Makes the lexer decode the rest of the document in its declared encoding, if the
+    /// encoding is one of those which are decoded by the parser.
+    fn select_decoder(&mut self, encoding: &str) {
+        if self.decoder_fixed {
+            return;
+        }
+        if let Some(decoder) = decoder::for_declared_encoding(encoding) {
+            self.effective_encoding = decoder.encoding().into();
+            self.lexer.set_decoder(Some(decoder));
         }
     }
 
     /// Checks if this parser ignores the end of stream errors.
-    pub fn is_ignoring_end_of_stream(&self) -> bool { self.config.ignore_end_of_stream }
+    pub fn is_ignoring_end_of_stream(&self) -> bool { self.config.get_ignore_end_of_stream() }
+
+    /// Checks if this parser reads multiple documents from the stream.
+    pub fn is_reading_multiple_documents(&self) -> bool { self.config.get_multiple_documents() }
+
+    /// Returns an iterator over names of the currently open elements.
+    #[inline]
+    pub fn current_path(&self) -> CurrentPath<'_> {
+        CurrentPath { elements: self.est.iter() }
+    }
+
+    /// Returns the number of bytes consumed from the stream so far.
+    #[inline]
+    pub fn byte_offset(&self) -> u64 {
+        self.lexer.bytes_read()
+    }
+
+    /// Returns the byte offset of the start tag of the innermost open element, if any.
+    #[inline]
+    pub fn element_start_offset(&self) -> Option<u64> {
+        self.est.last().map(|e| e.start_offset)
+    }
+
+    /// Returns the warnings noted so far, clearing the list.
+    #[inline]
+    pub fn take_warnings(&mut self) -> Vec<Error> {
+        mem::take(&mut self.warnings)
+    }
+
+    /// Returns the `xml:space` mode in effect for the innermost open element.
+    pub fn xml_space(&self) -> XmlSpace {
+        self.est.last().map(|e| e.space).unwrap_or(XmlSpace::Default)
+    }
+
+    /// Returns the `xml:lang` value in effect for the innermost open element, if any.
+    pub fn xml_lang(&self) -> Option<&str> {
+        self.est.iter().rev().filter_map(|e| e.lang.as_ref()).next().map(|s| &s[..])
+    }
+
+    /// Sets the resolver which fetches the content of external entities.
+    #[inline]
+    pub fn set_entity_resolver<R: EntityResolver + Send + 'static>(&mut self, resolver: R) {
+        self.config = mem::take(&mut self.config).entity_resolver(resolver);
+    }
+
+    /// Returns the internal general entities declared in the document type declaration.
+    #[inline]
+    pub fn entities(&self) -> &HashMap<String, String> {
+        self.dtd.general_entities()
+    }
+
+    /// Returns the internal parameter entities declared in the document type declaration.
+    #[inline]
+    pub fn parameter_entities(&self) -> &HashMap<String, String> {
+        self.dtd.parameter_entities()
+    }
+
+    /// Returns the content models of the element types declared in the document type declaration.
+    #[inline]
+    pub fn element_declarations(&self) -> &HashMap<String, ContentModel> {
+        self.dtd.element_declarations()
+    }
+
+    /// Returns the attributes declared in the document type declaration, by element type.
+    #[inline]
+    pub fn attribute_declarations(&self) -> &HashMap<String, Vec<AttributeDeclaration>> {
+        self.dtd.attribute_declarations()
+    }
 }
 
 impl Position for PullParser {
@@ -133,7 +293,40 @@ impl Position for PullParser {
     }
 }
 
-#[derive(Clone, PartialEq)]
+/// Shows the state of the parser which is useful for debugging, but not the contents of its
+/// buffers, which may be arbitrarily large.
+impl fmt::Debug for PullParser {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let open_elements: Vec<_> = self.est.iter().map(|e| e.name.to_string()).collect();
+        f.debug_struct("PullParser")
+            .field("position", &self.position())
+            .field("lexer_position", &self.lexer.position())
+            .field("depth", &self.depth())
+            .field("open_elements", &open_elements)
+            .field("state", &self.st)
+            .field("last_token", &self.last_token.map(TokenKind))
+            .field("buffered_text_length", &self.buf.len())
+            .field("pending_event", &self.next_event.as_ref().map(|e| e.as_ref().map(|e| e.kind())))
+            .field("finished", &self.final_result.is_some())
+            .finish()
+    }
+}
+
+/// Shows the kind of a token without the character it contains, if any.
+struct TokenKind(Token);
+
+impl fmt::Debug for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Token::Chunk(_) => f.write_str("Chunk"),
+            Token::Character(_) => f.write_str("Character"),
+            Token::Whitespace(_) => f.write_str("Whitespace"),
+            t => fmt::Debug::fmt(&t, f)
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum State {
     OutsideTag,
     InsideOpeningTag(OpeningTagSubstate),
@@ -146,7 +339,7 @@ pub enum State {
     InsideReference(Box<State>)
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum OpeningTagSubstate {
     InsideName,
 
@@ -158,19 +351,19 @@ pub enum OpeningTagSubstate {
     InsideAttributeValue,
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum ClosingTagSubstate {
     CTInsideName,
     CTAfterName
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum ProcessingInstructionSubstate {
     PIInsideName,
     PIInsideData
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum DeclarationSubstate {
     BeforeVersion,
     InsideVersion,
@@ -231,6 +424,7 @@ struct MarkupData {
     standalone: Option<bool>,  // used for XML declaration standalone parameter
 
     element_name: Option<OwnedName>,  // used for element name
+    element_offset: u64,  // used for byte offset of the element start tag
 
     quote: Option<QuoteToken>,  // used to hold opening quote for attribute value
     attr_name: Option<OwnedName>,  // used to hold attribute name
@@ -243,11 +437,59 @@ impl PullParser {
     /// This method should be always called with the same buffer. If you call it
     /// providing different buffers each time, the result will be undefined.
     pub fn next<R: Read>(&mut self, r: &mut R) -> Result {
+        let mut result = self.read_next(r);
+        if self.config.get_validate_dtd() && self.final_result.is_none() {
+            if let Ok(ref mut event) = result {
+                for msg in self.validator.validate(&self.dtd, event) {
+                    let error: Error = (&*self, msg).into();
+                    if self.config.get_validity_errors_as_warnings() {
+                        self.warnings.push(error);
+                    } else {
+                        return self.set_final_result(Err(error));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns the kind of the next event read from the given buffer.
+    ///
+    /// The document is checked exactly as with `next()`, but the data of the event is not
+    /// built, unless it is needed for validation against the DTD: no text, comment or processing
+    /// instruction data is copied out of the buffer, and no namespace mappings or copies
+    /// of element names and attributes are made for the event.
+    pub fn next_kind<R: Read>(&mut self, r: &mut R) -> super::Result<XmlEventKind> {
+        self.discard_data = !self.config.get_validate_dtd();
+        let result = self.next(r);
+        self.discard_data = false;
+        result.map(|e| e.kind())
+    }
+
+    fn read_next<R: Read>(&mut self, r: &mut R) -> Result {
         if let Some(ref ev) = self.final_result {
             return ev.clone();
         }
 
         if let Some(ev) = self.next_event.take() {
+            match ev {
+                // the end of an empty element closes its scope, and shares its position; the name
+                // of the element is kept in the scope until then
+                Ok(XmlEvent::EndElement { .. }) => {
+                    let name = self.est.pop().unwrap().name;
+                    self.finish_document_if_closed();
+                    return Ok(XmlEvent::EndElement { name });
+                }
+                Ok(XmlEvent::EndDocument) if self.config.get_multiple_documents() => {
+                    self.next_pos();
+                    self.reset_for_next_document();
+                }
+                Ok(XmlEvent::EndDocument) => {
+                    self.next_pos();
+                    return self.set_final_result(ev);
+                }
+                _ => self.next_pos()
+            }
             return ev;
         }
 
@@ -274,6 +516,9 @@ impl PullParser {
                                 Some(Ok(xml_event)) =>
                                     return {
                                         self.next_pos();
+                                        if let XmlEvent::EndElement { .. } = xml_event {
+                                            self.finish_document_if_closed();
+                                        }
                                         Ok(xml_event)
                                     },
                                 Some(Err(xml_error)) =>
@@ -283,8 +528,11 @@ impl PullParser {
                                     },
                             }
                     },
-                Err(lexer_error) =>
-                    return self.set_final_result(Err(lexer_error)),
+                // a character cut off by the end of the stream is completed if the stream continues
+                Err(lexer_error) => return match *lexer_error.kind() {
+                    ErrorKind::UnexpectedEof if self.config.get_ignore_end_of_stream() => Err(lexer_error),
+                    _ => self.set_final_result(Err(lexer_error))
+                },
             }
         }
 
@@ -293,6 +541,18 @@ impl PullParser {
         self.next_pos();
         let ev = if self.depth() == 0 {
             if self.encountered_element && self.st == State::OutsideTag {  // all is ok
+                // whitespace at the end of the epilog is reported before the end of the document,
+                // which is reported by the next call, as the lexer stays at the end of the stream
+                if let Some(ev) = self.take_text_event() {
+                    return ev;
+                }
+                Ok(XmlEvent::EndDocument)
+            } else if self.finished_document && self.st == State::OutsideTag {
+                // the end of the stream after the last of multiple documents; the end
+                // of that document has already been reported, and is reported again
+                if let Some(ev) = self.take_text_event() {
+                    return ev;
+                }
                 Ok(XmlEvent::EndDocument)
             } else if !self.encountered_element {
                 self_error!(self; "Unexpected end of stream: no root element found")
@@ -300,7 +560,7 @@ impl PullParser {
                 self_error!(self; "Unexpected end of stream")  // TODO: add expected hint?
             }
         } else {
-            if self.config.ignore_end_of_stream {
+            if self.config.get_ignore_end_of_stream() {
                 self.final_result = None;
                 self.lexer.reset_eof_handled();
                 return self_error!(self; "Unexpected end of stream: still inside the root element");
@@ -311,6 +571,34 @@ impl PullParser {
         self.set_final_result(ev)
     }
 
+    /// Schedules the end of the document after the end of the root element, if multiple
+    /// documents are read from the stream.
+    fn finish_document_if_closed(&mut self) {
+        if self.config.get_multiple_documents() && self.depth() == 0 && self.next_event.is_none() {
+            self.next_event = Some(Ok(XmlEvent::EndDocument));
+        }
+    }
+
+    /// Resets the state of the parser which belongs to a single document, so that the next
+    /// document can be read from the same stream. The position is not reset.
+    fn reset_for_next_document(&mut self) {
+        // documents without a declaration are in UTF-8
+        if !self.decoder_fixed {
+            self.effective_encoding = DEFAULT_ENCODING.into();
+            self.lexer.set_decoder(None);
+        }
+        self.st = State::OutsideTag;
+        self.nst = NamespaceStack::default();
+        self.dtd = Dtd::default();
+        self.validator = Validator::default();
+        self.xml_version = DEFAULT_VERSION;
+        self.encountered_element = false;
+        self.parsed_declaration = false;
+        self.inside_whitespace = true;
+        self.pop_namespace = false;
+        self.finished_document = true;
+    }
+
     // This function is to be called when a terminal event is reached.
     // The function sets up the `self.final_result` into `Some(result)` and return `result`.
     fn set_final_result(&mut self, result: Result) -> Result {
@@ -338,6 +626,23 @@ impl PullParser {
     }
 
     fn dispatch_token(&mut self, t: Token) -> Option<Result> {
+        self.last_token = Some(t);
+        // the replacement text of an entity must be balanced, like the content of an element
+        while self.entity_depths.len() > self.lexer.entity_depth() {
+            let depth = self.entity_depths.pop().unwrap();
+            if self.depth() != depth || self.st != State::OutsideTag {
+                return Some(self_error!(self; "Unbalanced markup in the replacement text of an entity"));
+            }
+        }
+        let result = self.dispatch_token_in_state(t);
+        match self.config.get_max_data_length() {
+            Some(max_length) if result.is_none() && self.buf.len() > max_length =>
+                Some(self_error!(self; "Data length limit exceeded: more than {} bytes", max_length)),
+            _ => result
+        }
+    }
+
+    fn dispatch_token_in_state(&mut self, t: Token) -> Option<Result> {
         match self.st.clone() {
             State::OutsideTag                     => self.outside_tag(t),
             State::InsideProcessingInstruction(s) => self.inside_processing_instruction(t, s),
@@ -351,11 +656,26 @@ impl PullParser {
         }
     }
 
+    /// Returns the number of currently open elements.
     #[inline]
-    fn depth(&self) -> usize {
+    pub fn depth(&self) -> usize {
         self.est.len()
     }
 
+    /// Checks whether whitespace should be trimmed at this point of the document, that is,
+    /// `trim_whitespace` option is set and it is not overridden by `xml:space="preserve"`.
+    /// Whitespace outside of the root element is only controlled by `ignore_root_level_whitespace`.
+    #[inline]
+    fn trim_whitespace(&self) -> bool {
+        self.config.get_trim_whitespace() && self.depth() > 0 && self.xml_space() != XmlSpace::Preserve
+    }
+
+    /// Checks whether comments should be skipped at this point of the document.
+    #[inline]
+    fn ignore_comments(&self) -> bool {
+        self.config.get_ignore_comments() || self.depth() == 0 && self.config.get_ignore_root_level_comments()
+    }
+
     #[inline]
     fn buf_has_data(&self) -> bool {
         self.buf.len() > 0
@@ -366,6 +686,57 @@ impl PullParser {
         mem::replace(&mut self.buf, String::new())
     }
 
+    /// Takes the buffer as the data of an event, or clears it if the data is discarded.
+    #[inline]
+    fn take_data(&mut self) -> String {
+        if self.discard_data {
+            self.buf.clear();
+            String::new()
+        } else {
+            self.take_buf()
+        }
+    }
+
+    /// Appends the contents of a token of character data, of an attribute value, of a comment or
+    /// of a processing instruction to the buffer, applying the invalid character policy to
+    /// characters which are not allowed in documents of this XML version.
+    fn append_content_token(&mut self, t: Token) -> Option<Result> {
+        match t {
+            Token::Character(c) if !common::is_xml_char(c, self.xml_version) => {
+                let msg = format!("Character U+{:04X} is not allowed in XML documents", c as u32);
+                match self.config.get_invalid_character_policy() {
+                    InvalidCharacterPolicy::Error => return Some(self_error!(self; msg)),
+                    InvalidCharacterPolicy::ReplaceWith(r) => self.buf.push(r),
+                    InvalidCharacterPolicy::Strip => {}
+                }
+                self.warnings.push((&self.lexer, msg).into());
+            }
+            _ => t.push_to_string(&mut self.buf)
+        }
+        None
+    }
+
+    /// Takes the buffered character data and converts it into a `Characters` or a `Whitespace`
+    /// event according to the configuration, resetting the whitespace tracking.
+    ///
+    /// Returns `None` if the buffer is empty or if its contents are trimmed away.
+    fn take_text_event(&mut self) -> Option<Result> {
+        let event = if self.buf_has_data() {
+            let buf = self.take_data();
+            if self.inside_whitespace && self.trim_whitespace() {
+                None
+            } else if self.inside_whitespace && !self.config.get_whitespace_to_characters() {
+                Some(Ok(XmlEvent::Whitespace(buf)))
+            } else if self.trim_whitespace() {
+                Some(Ok(XmlEvent::Characters(buf.trim_matches(common::is_whitespace_char).into())))
+            } else {
+                Some(Ok(XmlEvent::Characters(buf)))
+            }
+        } else { None };
+        self.inside_whitespace = true;  // Reset inside_whitespace flag
+        event
+    }
+
     #[inline]
     fn append_char_continue(&mut self, c: char) -> Option<Result> {
         self.buf.push(c);
@@ -470,65 +841,117 @@ impl PullParser {
                 Some(self_error!(self; "Unexpected token inside attribute value: <")),
 
             // Every character except " and ' and < is okay
-            _  => {
-                t.push_to_string(&mut self.buf);
-                None
-            }
+            _  => self.append_content_token(t)
         }
     }
 
     fn emit_start_element(&mut self, emit_end_element: bool) -> Option<Result> {
+        if let Some(max_depth) = self.config.get_max_element_depth() {
+            if self.depth() >= max_depth {
+                return Some(self_error!(self; "Element depth limit exceeded: more than {} levels", max_depth));
+            }
+        }
+
         let mut name = self.data.take_element_name().unwrap();
         let mut attributes = self.data.take_attributes();
 
         // check whether the name prefix is bound and fix its namespace
-        match self.nst.get(name.borrow().prefix_repr()) {
-            Some("") => name.namespace = None,  // default namespace
-            Some(ns) => name.namespace = Some(ns.into()),
-            None => return Some(self_error!(self; "Element {} prefix is unbound", name))
-        }
+        name.namespace = match self.resolve_namespace("Element", &name) {
+            Ok(ns) => ns,
+            Err(e) => return Some(Err(e))
+        };
 
-        // check and fix accumulated attributes prefixes
+        // check and fix accumulated attributes prefixes; unprefixed attributes are never
+        // in the default namespace
         for attr in attributes.iter_mut() {
-            if let Some(ref pfx) = attr.name.prefix {
-                let new_ns = match self.nst.get(pfx) {
-                    Some("") => None,  // default namespace
-                    Some(ns) => Some(ns.into()),
-                    None => return Some(self_error!(self; "Attribute {} prefix is unbound", attr.name))
+            if attr.name.prefix.is_some() {
+                attr.name.namespace = match self.resolve_namespace("Attribute", &attr.name) {
+                    Ok(ns) => ns,
+                    Err(e) => return Some(Err(e))
                 };
-                attr.name.namespace = new_ns;
+            }
+        }
+
+        // xml:space and xml:lang are inherited by descendants unless overridden
+        let mut space = self.xml_space();
+        let mut lang = None;
+        for attr in attributes.iter() {
+            if attr.name.namespace_ref() != Some(namespace::NS_XML_URI) {
+                continue;
+            }
+            match &attr.name.local_name[..] {
+                "space" => match XmlSpace::from_attribute_value(&attr.value) {
+                    Some(s) => space = s,
+                    None => return Some(self_error!(self; "Invalid xml:space value: {}", attr.value))
+                },
+                "lang" => lang = Some(attr.value.clone()),
+                _ => {}
             }
         }
 
         if emit_end_element {
             self.pop_namespace = true;
+            // the name is taken from the scope of the element when the event is returned
             self.next_event = Some(Ok(XmlEvent::EndElement {
-                name: name.clone()
+                name: OwnedName::local("")
             }));
+        }
+        let start_offset = self.data.element_offset;
+        let event = if self.discard_data {
+            self.est.push(ElementScope { name, space, lang, start_offset });
+            // the buffer of attributes is reused for the next start tag
+            attributes.clear();
+            self.data.attributes = attributes;
+            XmlEvent::StartElement {
+                name: OwnedName::local(""),
+                attributes: Vec::new(),
+                namespace: Namespace::empty()
+            }
         } else {
-            self.est.push(name.clone());
+            self.est.push(ElementScope { name: name.clone(), space, lang, start_offset });
+            XmlEvent::StartElement {
+                name: name,
+                attributes: attributes,
+                namespace: self.nst.squash()
+            }
+        };
+        self.into_state_emit(State::OutsideTag, Ok(event))
+    }
+
+    /// Returns the namespace URI bound to the prefix of an element or attribute name, applying
+    /// the unbound prefix policy if the prefix is not bound.
+    fn resolve_namespace(&mut self, kind: &str, name: &OwnedName) -> ::std::result::Result<Option<String>, Error> {
+        match self.nst.get(name.borrow().prefix_repr()) {
+            Some("") => return Ok(None),  // default namespace
+            Some(ns) => return Ok(Some(ns.into())),
+            None => {}
+        }
+        let msg = format!("{} {} prefix is unbound", kind, name);
+        match self.config.get_unbound_prefix_policy() {
+            UnboundPrefixPolicy::Error => Err((&self.lexer, msg).into()),
+            UnboundPrefixPolicy::Keep => {
+                self.warnings.push((&self.lexer, msg).into());
+                Ok(None)
+            }
         }
-        let namespace = self.nst.squash();
-        self.into_state_emit(State::OutsideTag, Ok(XmlEvent::StartElement {
-            name: name,
-            attributes: attributes,
-            namespace: namespace
-        }))
     }
 
     fn emit_end_element(&mut self) -> Option<Result> {
         let mut name = self.data.take_element_name().unwrap();
 
         // check whether the name prefix is bound and fix its namespace
-        match self.nst.get(name.borrow().prefix_repr()) {
-            Some("") => name.namespace = None,  // default namespace
-            Some(ns) => name.namespace = Some(ns.into()),
-            None => return Some(self_error!(self; "Element {} prefix is unbound", name))
-        }
+        name.namespace = match self.resolve_namespace("Element", &name) {
+            Ok(ns) => ns,
+            Err(e) => return Some(Err(e))
+        };
 
-        let op_name = self.est.pop().unwrap();
+        let op_name = self.est.pop().unwrap().name;
+        if self.entity_depths.last().is_some_and(|&depth| self.depth() < depth) {
+            return Some(self_error!(self; "Unbalanced markup in the replacement text of an entity"));
+        }
 
-        if name == op_name {
+        // the end tag must repeat the prefix of the start tag, not just its namespace
+        if name == op_name && name.prefix == op_name.prefix {
             self.pop_namespace = true;
             self.into_state_emit(State::OutsideTag, Ok(XmlEvent::EndElement { name: name }))
         } else {
@@ -542,7 +965,7 @@ impl PullParser {
 mod tests {
     use std::io::BufReader;
 
-    use common::{Position, TextPosition};
+    use common::{Position, TextPosition, XmlSpace};
     use name::OwnedName;
     use attribute::OwnedAttribute;
     use reader::parser::PullParser;
@@ -577,6 +1000,15 @@ mod tests {
         })
     );
 
+    #[test]
+    fn end_tag_with_another_prefix_of_the_same_namespace() {
+        let (mut r, mut p) = test_data!(r#"<a:x xmlns:a="urn:x" xmlns:b="urn:x"></b:x>"#);
+
+        expect_event!(r, p, Ok(XmlEvent::StartDocument { .. }));
+        expect_event!(r, p, Ok(XmlEvent::StartElement { .. }));
+        expect_event!(r, p, Err(ref e) => e.msg() == "Unexpected closing tag: {urn:x}b:x, expected {urn:x}a:x");
+    }
+
     #[test]
     fn issue_3_semicolon_in_attribute_value() {
         let (mut r, mut p) = test_data!(r#"
@@ -619,4 +1051,51 @@ mod tests {
             e.position() == TextPosition { row: 1, column: 24 }
         );
     }
+
+    #[test]
+    fn xml_space_and_lang_are_inherited() {
+        let (mut r, mut p) = test_data!(r#"<a xml:lang="en"><b xml:space="preserve" xml:lang="ru"><c/></b><d/></a>"#);
+
+        expect_event!(r, p, Ok(XmlEvent::StartDocument { .. }));
+        expect_event!(r, p, Ok(XmlEvent::StartElement { .. }));
+        assert_eq!(p.xml_space(), XmlSpace::Default);
+        assert_eq!(p.xml_lang(), Some("en"));
+        expect_event!(r, p, Ok(XmlEvent::StartElement { .. }));
+        assert_eq!(p.xml_space(), XmlSpace::Preserve);
+        assert_eq!(p.xml_lang(), Some("ru"));
+        expect_event!(r, p, Ok(XmlEvent::StartElement { ref name, .. }) => *name == OwnedName::local("c"));
+        assert_eq!(p.xml_space(), XmlSpace::Preserve);
+        assert_eq!(p.xml_lang(), Some("ru"));
+        expect_event!(r, p, Ok(XmlEvent::EndElement { .. }));
+        expect_event!(r, p, Ok(XmlEvent::EndElement { .. }));
+        expect_event!(r, p, Ok(XmlEvent::StartElement { ref name, .. }) => *name == OwnedName::local("d"));
+        assert_eq!(p.xml_space(), XmlSpace::Default);
+        assert_eq!(p.xml_lang(), Some("en"));
+        expect_event!(r, p, Ok(XmlEvent::EndElement { .. }));
+        expect_event!(r, p, Ok(XmlEvent::EndElement { .. }));
+        assert_eq!(p.xml_lang(), None);
+        expect_event!(r, p, Ok(XmlEvent::EndDocument));
+    }
+
+    #[test]
+    fn xml_space_preserve_overrides_trimming() {
+        let mut r = BufReader::new(&b"<a> <b xml:space='preserve'> x </b> <c> y </c></a>"[..]);
+        let mut p = PullParser::new(ParserConfig::new().trim_whitespace(true));
+
+        expect_event!(r, p, Ok(XmlEvent::StartDocument { .. }));
+        expect_event!(r, p, Ok(XmlEvent::StartElement { .. }));
+        expect_event!(r, p, Ok(XmlEvent::StartElement { .. }));
+        expect_event!(r, p, Ok(XmlEvent::Characters(ref s)) => s == " x ");
+        expect_event!(r, p, Ok(XmlEvent::EndElement { .. }));
+        expect_event!(r, p, Ok(XmlEvent::StartElement { .. }));
+        expect_event!(r, p, Ok(XmlEvent::Characters(ref s)) => s == "y");
+    }
+
+    #[test]
+    fn invalid_xml_space_value() {
+        let (mut r, mut p) = test_data!(r#"<a xml:space="keep"/>"#);
+
+        expect_event!(r, p, Ok(XmlEvent::StartDocument { .. }));
+        expect_event!(r, p, Err(ref e) => e.msg() == "Invalid xml:space value: keep");
+    }
 }
@@ -1,22 +1,24 @@
-use common::is_whitespace_char;
+use common::Position;
 
 use reader::events::XmlEvent;
 use reader::lexer::Token;
 
 use super::{
     Result, PullParser, State, ClosingTagSubstate, OpeningTagSubstate,
-    ProcessingInstructionSubstate, DEFAULT_VERSION, DEFAULT_ENCODING, DEFAULT_STANDALONE
+    ProcessingInstructionSubstate, DEFAULT_VERSION, DEFAULT_STANDALONE
 };
 
 impl PullParser {
     pub fn outside_tag(&mut self, t: Token) -> Option<Result> {
         match t {
-            Token::ReferenceStart =>
-                self.into_state_continue(State::InsideReference(Box::new(State::OutsideTag))),
+            Token::ReferenceStart => {
+                self.reference_pos = self.lexer.position();
+                self.into_state_continue(State::InsideReference(Box::new(State::OutsideTag)))
+            }
 
-            Token::Whitespace(_) if self.depth() == 0 && self.config.ignore_root_level_whitespace => None,  // skip whitespace outside of the root element
+            Token::Whitespace(_) if self.depth() == 0 && self.config.get_ignore_root_level_whitespace() => None,  // skip whitespace outside of the root element
 
-            Token::Whitespace(_) if self.config.trim_whitespace && !self.buf_has_data() => None,
+            Token::Whitespace(_) if self.trim_whitespace() && !self.buf_has_data() => None,
 
             Token::Whitespace(c) => {
                 if !self.buf_has_data() {
@@ -33,8 +35,7 @@ impl PullParser {
                     self.push_pos();
                 }
                 self.inside_whitespace = false;
-                t.push_to_string(&mut self.buf);
-                None
+                self.append_content_token(t)
             }
 
             Token::ReferenceEnd => { // Semi-colon in a text outside an entity
@@ -43,13 +44,13 @@ impl PullParser {
                 None
             }
 
-            Token::CommentStart if self.config.coalesce_characters && self.config.ignore_comments => {
+            Token::CommentStart if self.config.get_coalesce_characters() && self.ignore_comments() => {
                 // We need to switch the lexer into a comment mode inside comments
                 self.lexer.inside_comment();
                 self.into_state_continue(State::InsideComment)
             }
 
-            Token::CDataStart if self.config.coalesce_characters && self.config.cdata_to_characters => {
+            Token::CDataStart if self.config.get_coalesce_characters() && self.config.get_cdata_to_characters() => {
                 if !self.buf_has_data() {
                     self.push_pos();
                 }
@@ -61,32 +62,25 @@ impl PullParser {
             _ => {
                 // Encountered some markup event, flush the buffer as characters
                 // or a whitespace
-                let mut next_event = if self.buf_has_data() {
-                    let buf = self.take_buf();
-                    if self.inside_whitespace && self.config.trim_whitespace {
-                        None
-                    } else if self.inside_whitespace && !self.config.whitespace_to_characters {
-                        Some(Ok(XmlEvent::Whitespace(buf)))
-                    } else if self.config.trim_whitespace {
-                        Some(Ok(XmlEvent::Characters(buf.trim_matches(is_whitespace_char).into())))
-                    } else {
-                        Some(Ok(XmlEvent::Characters(buf)))
-                    }
-                } else { None };
-                self.inside_whitespace = true;  // Reset inside_whitespace flag
+                let mut next_event = self.take_text_event();
                 self.push_pos();
                 match t {
                     Token::ProcessingInstructionStart =>
                         self.into_state(State::InsideProcessingInstruction(ProcessingInstructionSubstate::PIInsideName), next_event),
 
                     Token::DoctypeStart if !self.encountered_element => {
-                        // We don't have a doctype event so skip this position
-                        // FIXME: update when we have a doctype event
-                        self.next_pos();
+                        // the position is kept for the doctype event, if it is reported
+                        if !self.config.get_report_doctype() {
+                            self.next_pos();
+                        }
                         self.lexer.disable_errors();
                         self.into_state(State::InsideDoctype, next_event)
                     }
 
+                    Token::OpeningTagStart if self.encountered_element && self.depth() == 0 &&
+                                              !self.config.get_allow_multiple_root_elements() =>
+                        Some(self_error!(self; "Unexpected element after the root element")),
+
                     Token::OpeningTagStart => {
                         // If declaration was not parsed and we have encountered an element,
                         // emit this declaration as the next event.
@@ -94,7 +88,8 @@ impl PullParser {
                             self.parsed_declaration = true;
                             let sd_event = XmlEvent::StartDocument {
                                 version: DEFAULT_VERSION,
-                                encoding: DEFAULT_ENCODING.into(),
+                                encoding: None,
+                                effective_encoding: self.effective_encoding.clone(),
                                 standalone: DEFAULT_STANDALONE
                             };
                             // next_event is always none here because we're outside of
@@ -103,6 +98,7 @@ impl PullParser {
                             self.push_pos();
                         }
                         self.encountered_element = true;
+                        self.data.element_offset = self.lexer.token_offset();
                         self.nst.push_empty();
                         self.into_state(State::InsideOpeningTag(OpeningTagSubstate::InsideName), next_event)
                     }
@@ -0,0 +1,430 @@
+//! Contains a tokenizer which splits a document into its syntactic parts.
+
+use std::io::Read;
+
+use common::{is_name_char, is_name_start_char, Position, TextPosition};
+
+use super::lexer::{Lexer, Token};
+use super::{Error, Result};
+
+/// A syntactic part of an XML document, as returned by `Tokenizer`.
+///
+/// Names, values and text are returned exactly as they are written, except that line breaks
+/// are normalized to `\n`; in particular, references are not replaced.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RawToken {
+    /// `<`, which starts a start tag.
+    StartTagOpen,
+
+    /// `</`, which starts an end tag.
+    EndTagOpen,
+
+    /// `>`, which ends a start or end tag.
+    TagClose,
+
+    /// `/>`, which ends an empty element tag.
+    EmptyTagClose,
+
+    /// The name of an element in a start or end tag, including the prefix.
+    Name(String),
+
+    /// The name of an attribute, including the prefix.
+    AttributeName(String),
+
+    /// `=` between an attribute name and its value.
+    Equals,
+
+    /// The value of an attribute, without the quotes.
+    QuotedValue(String),
+
+    /// Character data between markup, including whitespace.
+    Text(String),
+
+    /// The content of a comment, between `<!--` and `-->`.
+    Comment(String),
+
+    /// A processing instruction, including the XML declaration, with its target and its data,
+    /// which starts after the whitespace following the target.
+    ProcessingInstruction { name: String, data: Option<String> },
+
+    /// The content of a CDATA section, between `<![CDATA[` and `]]>`.
+    CData(String),
+
+    /// The content of a document type declaration, between `<!DOCTYPE` and the closing `>`.
+    Doctype(String)
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum State {
+    Content,
+    TagName { end: bool },
+    /// Inside a tag, after the name or an attribute value and possibly whitespace.
+    InsideTag { end: bool, separated: bool },
+    AttributeEquals,
+    AttributeValue
+}
+
+/// A tokenizer of XML documents, which is the layer below `EventReader`.
+///
+/// The tokenizer only checks the syntax of individual tokens. A start tag is always returned
+/// as `StartTagOpen`, `Name`, any number of `AttributeName`, `Equals` and `QuotedValue`
+/// sequences, and `TagClose` or `EmptyTagClose`; an end tag as `EndTagOpen`, `Name`
+/// and `TagClose`. Whitespace inside tags is not returned. Every other token is complete:
+/// there are never two `Text` tokens in a row, and comments, processing instructions, CDATA
+/// sections and document type declarations are returned as single tokens. Nothing else
+/// is checked; tags don't have to match, and namespaces, references and declarations
+/// are not processed.
+///
+/// ```rust
+/// use xml::common::Position;
+/// use xml::reader::{RawToken, Tokenizer};
+///
+/// let mut tokenizer = Tokenizer::new("<a href='/'>x &amp; y</a>".as_bytes());
+/// assert_eq!(tokenizer.next_token().unwrap(), Some(RawToken::StartTagOpen));
+/// assert_eq!(tokenizer.next_token().unwrap(), Some(RawToken::Name("a".into())));
+/// assert_eq!(tokenizer.next_token().unwrap(), Some(RawToken::AttributeName("href".into())));
+/// assert_eq!(tokenizer.position().to_string(), "1:4");
+///
+/// let tokens: Vec<_> = tokenizer.map(|t| t.unwrap()).collect();
+/// assert_eq!(tokens, [
+///     RawToken::Equals, RawToken::QuotedValue("/".into()), RawToken::TagClose,
+///     RawToken::Text("x &amp; y".into()),
+///     RawToken::EndTagOpen, RawToken::Name("a".into()), RawToken::TagClose
+/// ]);
+/// ```
+pub struct Tokenizer<R: Read> {
+    source: R,
+    lexer: Lexer,
+    state: State,
+    // a token which was read but belongs to the next raw token
+    peeked: Option<(Token, TextPosition)>,
+    pos: TextPosition,
+    finished: bool
+}
+
+impl<R: Read> Tokenizer<R> {
+    /// Creates a tokenizer of the document read from the given source.
+    pub fn new(source: R) -> Tokenizer<R> {
+        Tokenizer {
+            source,
+            lexer: Lexer::new(),
+            state: State::Content,
+            peeked: None,
+            pos: TextPosition::new(),
+            finished: false
+        }
+    }
+
+    /// Returns the next token, or `None` at the end of the document.
+    ///
+    /// After an error, tokenizing can not be continued and `None` is returned.
+    pub fn next_token(&mut self) -> Result<Option<RawToken>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let result = self.read_token();
+        if let Ok(None) | Err(_) = result {
+            self.finished = true;
+        }
+        result
+    }
+
+    /// Unwraps this tokenizer, returning the underlying source.
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    fn lex(&mut self) -> Result<Option<(Token, TextPosition)>> {
+        if let Some(t) = self.peeked.take() {
+            return Ok(Some(t));
+        }
+        Ok(self.lexer.next_token(&mut self.source)?.map(|t| (t, self.lexer.position())))
+    }
+
+    /// Returns the next token, failing at the end of the document.
+    fn lex_in(&mut self, what: &str) -> Result<Token> {
+        match self.lex()? {
+            Some((t, _)) => Ok(t),
+            None => Err(self.error(format!("Unexpected end of stream inside {}", what)))
+        }
+    }
+
+    fn error(&self, msg: String) -> Error {
+        (&self.lexer.position(), msg).into()
+    }
+
+    fn read_token(&mut self) -> Result<Option<RawToken>> {
+        loop {
+            let (t, pos) = match self.lex()? {
+                Some(t) => t,
+                None if self.state == State::Content => return Ok(None),
+                None => return Err(self.error("Unexpected end of stream inside a tag".into()))
+            };
+            self.pos = pos;
+            return match self.state {
+                State::Content => self.read_content(t).map(Some),
+                State::TagName { end } => {
+                    let name = self.read_name(t)?;
+                    self.state = State::InsideTag { end, separated: false };
+                    Ok(Some(RawToken::Name(name)))
+                }
+                State::InsideTag { end, separated } => match t {
+                    Token::Whitespace(_) => {
+                        self.state = State::InsideTag { end, separated: true };
+                        continue;
+                    }
+                    Token::TagEnd => {
+                        self.state = State::Content;
+                        Ok(Some(RawToken::TagClose))
+                    }
+                    Token::EmptyTagEnd if !end => {
+                        self.state = State::Content;
+                        Ok(Some(RawToken::EmptyTagClose))
+                    }
+                    // attributes must be separated from the preceding name or value
+                    Token::Character(_) if !end && separated => {
+                        let name = self.read_name(t)?;
+                        self.state = State::AttributeEquals;
+                        Ok(Some(RawToken::AttributeName(name)))
+                    }
+                    t => Err(self.error(format!("Unexpected token inside a tag: {}", t)))
+                },
+                State::AttributeEquals => match t {
+                    Token::Whitespace(_) => continue,
+                    Token::EqualsSign => {
+                        self.state = State::AttributeValue;
+                        Ok(Some(RawToken::Equals))
+                    }
+                    t => Err(self.error(format!("Unexpected token after an attribute name: {}", t)))
+                },
+                State::AttributeValue => match t {
+                    Token::Whitespace(_) => continue,
+                    Token::SingleQuote | Token::DoubleQuote => {
+                        let value = self.read_value(t)?;
+                        self.state = State::InsideTag { end: false, separated: false };
+                        Ok(Some(RawToken::QuotedValue(value)))
+                    }
+                    t => Err(self.error(format!("Unexpected token instead of an attribute value: {}", t)))
+                }
+            };
+        }
+    }
+
+    fn read_content(&mut self, t: Token) -> Result<RawToken> {
+        match t {
+            Token::OpeningTagStart => {
+                self.state = State::TagName { end: false };
+                Ok(RawToken::StartTagOpen)
+            }
+            Token::ClosingTagStart => {
+                self.state = State::TagName { end: true };
+                Ok(RawToken::EndTagOpen)
+            }
+            Token::CommentStart => {
+                self.lexer.inside_comment();
+                let content = self.read_until(Token::CommentEnd, "a comment");
+                self.lexer.outside_comment();
+                content.map(RawToken::Comment)
+            }
+            Token::CDataStart => {
+                self.lexer.disable_errors();
+                let content = self.read_until(Token::CDataEnd, "a CDATA section");
+                self.lexer.enable_errors();
+                content.map(RawToken::CData)
+            }
+            Token::ProcessingInstructionStart => self.read_processing_instruction(),
+            Token::DoctypeStart => {
+                // the lexer collects the declaration by itself and returns its end only
+                self.lexer.disable_errors();
+                let end = self.lex_in("a document type declaration");
+                self.lexer.enable_errors();
+                match end? {
+                    Token::TagEnd => Ok(RawToken::Doctype(self.lexer.take_doctype())),
+                    t => Err(self.error(format!("Unexpected token inside a document type declaration: {}", t)))
+                }
+            }
+            t => {
+                let mut text = String::new();
+                t.push_to_string(&mut text);
+                while let Some((t, pos)) = self.lex()? {
+                    match t {
+                        Token::OpeningTagStart | Token::ClosingTagStart | Token::CommentStart |
+                        Token::CDataStart | Token::ProcessingInstructionStart | Token::DoctypeStart => {
+                            self.peeked = Some((t, pos));
+                            break;
+                        }
+                        t => t.push_to_string(&mut text)
+                    }
+                }
+                Ok(RawToken::Text(text))
+            }
+        }
+    }
+
+    /// Reads a name which starts with the given token.
+    fn read_name(&mut self, first: Token) -> Result<String> {
+        let mut name = String::new();
+        let mut t = Some((first, self.pos));
+        while let Some((token @ Token::Character(_), _)) | Some((token @ Token::Chunk(_), _)) = t {
+            token.push_to_string(&mut name);
+            t = self.lex()?;
+        }
+        self.peeked = t;
+        let valid = name.starts_with(is_name_start_char) && name.chars().all(is_name_char);
+        if valid {
+            Ok(name)
+        } else {
+            Err((&self.pos, format!("Invalid name: {}", name)).into())
+        }
+    }
+
+    /// Reads the content of a construct up to the given token.
+    fn read_until(&mut self, end: Token, what: &str) -> Result<String> {
+        let mut content = String::new();
+        loop {
+            match self.lex_in(what)? {
+                t if t == end => return Ok(content),
+                t => t.push_to_string(&mut content)
+            }
+        }
+    }
+
+    /// Reads an attribute value which starts after the given quote.
+    fn read_value(&mut self, quote: Token) -> Result<String> {
+        let mut value = String::new();
+        loop {
+            match self.lex_in("an attribute value")? {
+                t if t == quote => return Ok(value),
+                t @ Token::OpeningTagStart | t @ Token::ClosingTagStart | t @ Token::CommentStart |
+                t @ Token::CDataStart | t @ Token::ProcessingInstructionStart | t @ Token::DoctypeStart =>
+                    return Err(self.error(format!("Unexpected token inside an attribute value: {}", t))),
+                t => t.push_to_string(&mut value)
+            }
+        }
+    }
+
+    fn read_processing_instruction(&mut self) -> Result<RawToken> {
+        let mut name = String::new();
+        let separated = loop {
+            match self.lex_in("a processing instruction")? {
+                t @ Token::Character(_) | t @ Token::Chunk(_) => t.push_to_string(&mut name),
+                Token::Whitespace(_) => break true,
+                Token::ProcessingInstructionEnd => break false,
+                t => return Err(self.error(format!("Unexpected token inside a processing instruction: {}", t)))
+            }
+        };
+        if !(name.starts_with(is_name_start_char) && name.chars().all(is_name_char)) {
+            return Err((&self.pos, format!("Invalid processing instruction name: {}", name)).into());
+        }
+        if !separated {
+            return Ok(RawToken::ProcessingInstruction { name, data: None });
+        }
+
+        // the data is arbitrary, so lexing errors are disabled
+        self.lexer.disable_errors();
+        let mut data = String::new();
+        let result = loop {
+            match self.lex_in("a processing instruction") {
+                Ok(Token::Whitespace(_)) if data.is_empty() => {}
+                Ok(Token::ProcessingInstructionEnd) => break Ok(()),
+                Ok(t) => t.push_to_string(&mut data),
+                Err(e) => break Err(e)
+            }
+        };
+        self.lexer.enable_errors();
+        result?;
+        let data = if data.is_empty() { None } else { Some(data) };
+        Ok(RawToken::ProcessingInstruction { name, data })
+    }
+}
+
+impl<R: Read> Position for Tokenizer<R> {
+    /// Returns the position of the last token produced by the tokenizer.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.pos
+    }
+}
+
+impl<R: Read> Iterator for Tokenizer<R> {
+    type Item = Result<RawToken>;
+
+    fn next(&mut self) -> Option<Result<RawToken>> {
+        self.next_token().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::Position;
+
+    use super::{RawToken, Tokenizer};
+
+    fn tokenize(s: &str) -> Vec<(String, RawToken)> {
+        let mut tokenizer = Tokenizer::new(s.as_bytes());
+        let mut tokens = Vec::new();
+        while let Some(t) = tokenizer.next_token().unwrap() {
+            tokens.push((tokenizer.position().to_string(), t));
+        }
+        tokens
+    }
+
+    fn error(s: &str) -> String {
+        let tokenizer = Tokenizer::new(s.as_bytes());
+        match tokenizer.collect::<Result<Vec<_>, _>>() {
+            Ok(tokens) => panic!("Unexpected success: {:?}", tokens),
+            Err(e) => e.to_string()
+        }
+    }
+
+    #[test]
+    fn tokenizing_documents() {
+        let tokens = tokenize("<?xml version=\"1.0\"?>\r\n<!DOCTYPE a [<!ENTITY e 'x>'>]>\
+                               <a x = \"1 > 2\" y='\"'><!-- a - b --><?pi?>text &e; ]] -- ><![CDATA[<b>]]>\
+                               <c-d.e/></a>");
+        let expected = [
+            ("1:1", RawToken::ProcessingInstruction { name: "xml".into(), data: Some("version=\"1.0\"".into()) }),
+            ("1:22", RawToken::Text("\n".into())),
+            ("2:1", RawToken::Doctype(" a [<!ENTITY e 'x>'>]".into())),
+            ("2:32", RawToken::StartTagOpen),
+            ("2:33", RawToken::Name("a".into())),
+            ("2:35", RawToken::AttributeName("x".into())),
+            ("2:37", RawToken::Equals),
+            ("2:39", RawToken::QuotedValue("1 > 2".into())),
+            ("2:47", RawToken::AttributeName("y".into())),
+            ("2:48", RawToken::Equals),
+            ("2:49", RawToken::QuotedValue("\"".into())),
+            ("2:52", RawToken::TagClose),
+            ("2:53", RawToken::Comment(" a - b ".into())),
+            ("2:67", RawToken::ProcessingInstruction { name: "pi".into(), data: None }),
+            ("2:73", RawToken::Text("text &e; ]] -- >".into())),
+            ("2:89", RawToken::CData("<b>".into())),
+            ("2:104", RawToken::StartTagOpen),
+            ("2:105", RawToken::Name("c-d.e".into())),
+            ("2:110", RawToken::EmptyTagClose),
+            ("2:112", RawToken::EndTagOpen),
+            ("2:114", RawToken::Name("a".into())),
+            ("2:115", RawToken::TagClose),
+        ];
+        let tokens: Vec<_> = tokens.iter().map(|(p, t)| (&p[..], t.clone())).collect();
+        assert_eq!(tokens, expected);
+
+        // nesting is not checked
+        assert_eq!(tokenize("</a><b>").len(), 6);
+    }
+
+    #[test]
+    fn tokenizing_errors() {
+        assert_eq!(error("< a>"), "1:2 Invalid name: ");
+        assert_eq!(error("<a b>"), "1:5 Unexpected token after an attribute name: >");
+        assert_eq!(error("<a b='1'c='2'>"), "1:9 Unexpected token inside a tag: c");
+        assert_eq!(error("<a b=1>"), "1:6 Unexpected token instead of an attribute value: 1");
+        assert_eq!(error("<a b='<'>"), "1:7 Unexpected token '<' before '''");
+        assert_eq!(error("<a b='<c'>"), "1:7 Unexpected token inside an attribute value: <");
+        assert_eq!(error("</a b='1'>"), "1:5 Unexpected token inside a tag: b");
+        assert_eq!(error("<a"), "1:3 Unexpected end of stream inside a tag");
+        assert_eq!(error("<!-- a -- b -->"), "1:8 Unexpected token '--' before ' '");
+        assert_eq!(error("<!-- a"), "1:7 Unexpected end of stream inside a comment");
+        assert_eq!(error("<?1 x?>"), "1:1 Invalid processing instruction name: 1");
+    }
+}
@@ -0,0 +1,170 @@
+//! Contains the validation of documents against the declarations of their internal DTD subset.
+
+use attribute::OwnedAttribute;
+use common::is_whitespace_char;
+use name::OwnedName;
+use namespace::{Namespace, NS_XMLNS_PREFIX, NS_XML_PREFIX, NS_XML_URI};
+
+use reader::dtd::{AttributeDefault, AttributeType, ContentModel, Dtd, normalize_tokens};
+use reader::events::XmlEvent;
+
+/// An open element, with the state of matching its content against its content model.
+struct Scope {
+    name: String,
+    // the states of the content automaton, for elements with element content
+    states: Option<Vec<usize>>,
+    // whether the content has already been reported as invalid
+    invalid: bool
+}
+
+impl Scope {
+    fn check_child(&mut self, dtd: &Dtd, child: &str, errors: &mut Vec<String>) {
+        let model = match dtd.element_declarations().get(&self.name) {
+            Some(model) => model,
+            None => return
+        };
+        let allowed = match *model {
+            ContentModel::Any => true,
+            ContentModel::Empty => false,
+            ContentModel::Mixed(ref names) => names.iter().any(|n| n == child),
+            ContentModel::Children(_) => match (self.states.as_mut(), dtd.content_automaton(&self.name)) {
+                (Some(states), Some(automaton)) => {
+                    *states = automaton.step(states, child);
+                    !states.is_empty()
+                }
+                _ => true
+            }
+        };
+        if !allowed && !self.invalid {
+            self.invalid = true;
+            errors.push(format!("Element {} is not allowed in element {} with content model {}",
+                                child, self.name, model));
+        }
+    }
+
+    fn check_text(&mut self, dtd: &Dtd, whitespace: bool, errors: &mut Vec<String>) {
+        let allowed = match dtd.element_declarations().get(&self.name) {
+            Some(&ContentModel::Empty) => false,
+            Some(&ContentModel::Children(_)) => whitespace,
+            _ => true
+        };
+        if !allowed && !self.invalid {
+            self.invalid = true;
+            errors.push(format!("Text is not allowed in element {} with content model {}",
+                                self.name, dtd.element_declarations()[&self.name]));
+        }
+    }
+}
+
+/// Checks the events of a document against the declarations of its DTD, as they are read.
+#[derive(Default)]
+pub struct Validator {
+    scopes: Vec<Scope>
+}
+
+impl Validator {
+    /// Validates the next event of the document, adding default attribute values to start
+    /// elements. Returns the validity errors found in the event.
+    pub fn validate(&mut self, dtd: &Dtd, event: &mut XmlEvent) -> Vec<String> {
+        let mut errors = Vec::new();
+        match *event {
+            XmlEvent::StartElement { ref name, ref mut attributes, ref namespace } => {
+                let name = name.borrow().to_repr();
+                match self.scopes.last_mut() {
+                    Some(parent) => parent.check_child(dtd, &name, &mut errors),
+                    None => match dtd.root_name() {
+                        None => errors.push("Document has no document type declaration".into()),
+                        Some(root) if root != name =>
+                            errors.push(format!("Root element {} does not match the document type {}", name, root)),
+                        _ => {}
+                    }
+                }
+                if !dtd.element_declarations().contains_key(&name) {
+                    errors.push(format!("Element {} is not declared", name));
+                }
+                check_attributes(dtd, &name, attributes, namespace, &mut errors);
+                self.scopes.push(Scope {
+                    states: dtd.content_automaton(&name).map(|a| a.start()),
+                    name,
+                    invalid: false
+                });
+            }
+            XmlEvent::EndElement { .. } => if let Some(scope) = self.scopes.pop() {
+                if let (false, Some(states), Some(automaton)) =
+                        (scope.invalid, scope.states, dtd.content_automaton(&scope.name)) {
+                    if !automaton.accepts(&states) {
+                        errors.push(format!("Content of element {} is incomplete for content model {}",
+                                            scope.name, dtd.element_declarations()[&scope.name]));
+                    }
+                }
+            },
+            XmlEvent::Characters(ref data) | XmlEvent::CData(ref data) => if let Some(scope) = self.scopes.last_mut() {
+                scope.check_text(dtd, data.chars().all(is_whitespace_char), &mut errors);
+            },
+            XmlEvent::Whitespace(_) => if let Some(scope) = self.scopes.last_mut() {
+                scope.check_text(dtd, true, &mut errors);
+            },
+            _ => {}
+        }
+        errors
+    }
+}
+
+fn check_attributes(dtd: &Dtd, element: &str, attributes: &mut Vec<OwnedAttribute>, namespace: &Namespace,
+                    errors: &mut Vec<String>) {
+    let declarations = dtd.attribute_declarations().get(element).map(|d| &d[..]).unwrap_or(&[]);
+    for attribute in attributes.iter_mut() {
+        let name = attribute.name.borrow().to_repr();
+        let declaration = match declarations.iter().find(|d| d.name == name) {
+            Some(declaration) => declaration,
+            None => {
+                errors.push(format!("Attribute {} of element {} is not declared", name, element));
+                continue;
+            }
+        };
+        if declaration.attribute_type != AttributeType::CData {
+            attribute.value = normalize_tokens(&attribute.value);
+        }
+        match declaration.attribute_type {
+            AttributeType::Enumeration(ref values) | AttributeType::Notation(ref values)
+                    if !values.contains(&attribute.value) =>
+                errors.push(format!("Value {:?} of attribute {} is not one of the declared values", attribute.value, name)),
+            _ => {}
+        }
+        if let AttributeDefault::Fixed(ref value) = declaration.default {
+            if *value != attribute.value {
+                errors.push(format!("Attribute {} must have the fixed value {:?}", name, value));
+            }
+        }
+    }
+
+    for declaration in declarations {
+        if attributes.iter().any(|a| a.name.borrow().to_repr() == declaration.name) {
+            continue;
+        }
+        match declaration.default {
+            AttributeDefault::Required =>
+                errors.push(format!("Required attribute {} of element {} is missing", declaration.name, element)),
+            AttributeDefault::Implied => {}
+            AttributeDefault::Fixed(ref value) | AttributeDefault::Value(ref value) =>
+                if let Some(name) = default_attribute_name(&declaration.name, namespace) {
+                    attributes.push(OwnedAttribute::new(name, value.clone()));
+                }
+        }
+    }
+}
+
+/// Resolves the name of a defaulted attribute. Defaults of namespace declarations and of names
+/// with unbound prefixes are not applied.
+fn default_attribute_name(name: &str, namespace: &Namespace) -> Option<OwnedName> {
+    let mut name: OwnedName = name.parse().ok()?;
+    match name.prefix {
+        None => if name.local_name == NS_XMLNS_PREFIX {
+            return None;
+        },
+        Some(ref prefix) if prefix == NS_XMLNS_PREFIX => return None,
+        Some(ref prefix) if prefix == NS_XML_PREFIX => name.namespace = Some(NS_XML_URI.into()),
+        Some(ref prefix) => name.namespace = Some(namespace.get(prefix)?.into())
+    }
+    Some(name)
+}
@@ -0,0 +1,501 @@
+//! Contains a `serde` serializer for XML documents. Available with the `serde` feature.
+//!
+//! `to_string()` and `to_writer()` write any type implementing `serde::Serialize` as
+//! a document, following the same conventions as the deserializer in the `de` module,
+//! so the output can be read back with `de::from_str()`:
+//!
+//! ```rust
+//! # #[macro_use] extern crate serde_derive;
+//! # extern crate xml;
+//! #[derive(Serialize)]
+//! struct Order {
+//!     #[serde(rename = "@id")]
+//!     id: u32,
+//!     customer: Option<String>,
+//!     #[serde(rename = "item")]
+//!     items: Vec<String>
+//! }
+//!
+//! # fn main() {
+//! let order = Order { id: 7, customer: None, items: vec!["a".into(), "b".into()] };
+//! assert_eq!(
+//!     xml::ser::to_string(&order).unwrap(),
+//!     "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Order id=\"7\"><item>a</item><item>b</item></Order>"
+//! );
+//! # }
+//! ```
+//!
+//! Values are mapped to the document as follows:
+//!
+//! * the root value must be a struct, a unit struct, a newtype struct or an enum; the root
+//!   element is named after the struct or after the enum variant;
+//! * struct fields and map entries become child elements named after the field. Fields whose
+//!   names start with `@` become attributes instead, and the `$value` field becomes the text
+//!   content of the element, or its child elements if it contains enums;
+//! * sequences repeat the element of the field for every item, and `None` omits the element
+//!   or the attribute completely;
+//! * enum variants are written as the names of child elements, or as text for unit variants,
+//!   matching the dispatch on element names of the deserializer.
+//!
+//! Values which have no representation in this model, like maps with keys which are not
+//! strings, sequences or structs in attributes, or roots which are not named, e.g. a string
+//! produced by an untagged enum, are reported as errors.
+
+use std::error;
+use std::fmt;
+use std::io::Write;
+
+use serde::ser::{self, Serialize};
+
+use de::{ATTRIBUTE_PREFIX, VALUE_FIELD};
+use writer::{self, EmitterConfig, EventWriter};
+
+/// A serialization error.
+#[derive(Debug)]
+pub enum Error {
+    /// The value cannot be represented as a document; contains a description of the reason.
+    Unsupported(String),
+
+    /// Writing of the document has failed.
+    Writer(writer::Error)
+}
+
+/// A specialized `Result` type where the error is hard-wired to `ser::Error`.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Unsupported(ref msg) => f.write_str(msg),
+            Error::Writer(ref e) => e.fmt(f)
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Unsupported(ref msg) => msg,
+            Error::Writer(_) => "document writing error"
+        }
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::Unsupported(msg.to_string())
+    }
+}
+
+impl From<writer::Error> for Error {
+    fn from(e: writer::Error) -> Error {
+        Error::Writer(e)
+    }
+}
+
+fn unsupported<T>(msg: String) -> Result<T> {
+    Err(Error::Unsupported(msg))
+}
+
+/// Serializes the given value as a document using the default emitter configuration.
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String> {
+    to_string_with_config(value, EmitterConfig::new())
+}
+
+/// Serializes the given value as a document using the given emitter configuration.
+pub fn to_string_with_config<T: ?Sized + Serialize>(value: &T, config: EmitterConfig) -> Result<String> {
+    let mut writer = EventWriter::new_string_with_config(config);
+    to_event_writer(&mut writer, value)?;
+    Ok(writer.into_string())
+}
+
+/// Serializes the given value into the given sink using the default emitter configuration.
+pub fn to_writer<W: Write, T: ?Sized + Serialize>(sink: W, value: &T) -> Result<()> {
+    to_writer_with_config(sink, value, EmitterConfig::new())
+}
+
+/// Serializes the given value into the given sink using the given emitter configuration.
+pub fn to_writer_with_config<W: Write, T: ?Sized + Serialize>(sink: W, value: &T,
+                                                              config: EmitterConfig) -> Result<()> {
+    to_event_writer(&mut EventWriter::new_with_config(sink, config), value)
+}
+
+/// Serializes the given value as an element written to the given writer.
+///
+/// The document declaration is written automatically if the writer is configured to do so,
+/// and the writer is flushed afterwards.
+pub fn to_event_writer<W: Write, T: ?Sized + Serialize>(w: &mut EventWriter<W>, value: &T) -> Result<()> {
+    let mut nodes = value.serialize(Serializer { mode: Mode::Root })?;
+    match (nodes.pop(), nodes.is_empty()) {
+        (Some(Node::Element(root)), true) => {
+            root.write_to(w)?;
+            w.flush()?;
+            Ok(())
+        }
+        _ => unsupported("The value must be serialized as a single root element".into())
+    }
+}
+
+/// An element which is built by the serializer and then written as a whole, so attributes
+/// can follow child elements in the serialized value.
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>
+}
+
+enum Node {
+    Element(Element),
+    Text(String)
+}
+
+impl Element {
+    fn new<S: Into<String>>(name: S) -> Element {
+        Element { name: name.into(), attributes: Vec::new(), children: Vec::new() }
+    }
+
+    fn write_to<W: Write>(&self, w: &mut EventWriter<W>) -> writer::Result<()> {
+        let mut start = w.start_element(&self.name[..]);
+        for (name, value) in &self.attributes {
+            start = start.attr(&name[..], value);
+        }
+        start.write()?;
+        for child in &self.children {
+            match *child {
+                Node::Element(ref e) => e.write_to(w)?,
+                Node::Text(ref s) => w.text(s)?
+            }
+        }
+        w.end_element()
+    }
+}
+
+/// Determines how a value is written.
+#[derive(Clone)]
+enum Mode {
+    /// The value is the root element.
+    Root,
+    /// The value is written as elements with the given name.
+    Element(String),
+    /// The value is the `$value` field: text or elements named after enum variants.
+    Content,
+    /// The value of an attribute.
+    Attribute,
+    /// A map key, which is the name of an element or of an attribute.
+    Key
+}
+
+/// Serializes values into a list of nodes.
+struct Serializer {
+    mode: Mode
+}
+
+impl Serializer {
+    fn text(self, text: String, kind: &str) -> Result<Vec<Node>> {
+        match self.mode {
+            Mode::Root => unsupported(format!("Cannot serialize {} as the root element", kind)),
+            Mode::Element(name) => {
+                let mut element = Element::new(name);
+                element.children.push(Node::Text(text));
+                Ok(vec![Node::Element(element)])
+            }
+            Mode::Content | Mode::Attribute => Ok(vec![Node::Text(text)]),
+            Mode::Key if kind == "a string" || kind == "a character" => Ok(vec![Node::Text(text)]),
+            Mode::Key => unsupported(format!("Map keys must be strings, found {}", kind))
+        }
+    }
+
+    fn unsupported_in<T>(&self, kind: &str) -> Result<T> {
+        match self.mode {
+            Mode::Root => unsupported(format!("Cannot serialize {} as the root element", kind)),
+            Mode::Element(ref name) => unsupported(format!("Cannot serialize {} as element `{}`", kind, name)),
+            Mode::Content => unsupported(format!("Cannot serialize {} as `{}`", kind, VALUE_FIELD)),
+            Mode::Attribute => unsupported(format!("Cannot serialize {} as an attribute value", kind)),
+            Mode::Key => unsupported(format!("Map keys must be strings, found {}", kind))
+        }
+    }
+}
+
+macro_rules! serialize_display {
+    ($($method:ident($ty:ty) => $kind:expr);+ $(;)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Vec<Node>> {
+                self.text(v.to_string(), $kind)
+            }
+        )+
+    }
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Vec<Node>;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = ser::Impossible<Vec<Node>, Error>;
+    type SerializeMap = StructSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = StructSerializer;
+
+    serialize_display! {
+        serialize_bool(bool) => "a boolean";
+        serialize_i8(i8) => "an integer";
+        serialize_i16(i16) => "an integer";
+        serialize_i32(i32) => "an integer";
+        serialize_i64(i64) => "an integer";
+        serialize_u8(u8) => "an integer";
+        serialize_u16(u16) => "an integer";
+        serialize_u32(u32) => "an integer";
+        serialize_u64(u64) => "an integer";
+        serialize_f32(f32) => "a number";
+        serialize_f64(f64) => "a number";
+        serialize_char(char) => "a character";
+        serialize_str(&str) => "a string";
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Vec<Node>> {
+        self.unsupported_in("bytes")
+    }
+
+    fn serialize_none(self) -> Result<Vec<Node>> {
+        match self.mode {
+            Mode::Root | Mode::Key => self.unsupported_in("`None`"),
+            _ => Ok(Vec::new())
+        }
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Vec<Node>> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Vec<Node>> {
+        match self.mode {
+            Mode::Element(name) => Ok(vec![Node::Element(Element::new(name))]),
+            Mode::Content => Ok(Vec::new()),
+            Mode::Attribute => Ok(vec![Node::Text(String::new())]),
+            Mode::Root | Mode::Key => self.unsupported_in("a unit value")
+        }
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Vec<Node>> {
+        match self.mode {
+            Mode::Root => Ok(vec![Node::Element(Element::new(name))]),
+            _ => self.serialize_unit()
+        }
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32,
+                              variant: &'static str) -> Result<Vec<Node>> {
+        match self.mode {
+            Mode::Root | Mode::Content => Ok(vec![Node::Element(Element::new(variant))]),
+            _ => self.text(variant.into(), "a string")
+        }
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, name: &'static str, value: &T) -> Result<Vec<Node>> {
+        match self.mode {
+            Mode::Root => value.serialize(Serializer { mode: Mode::Element(name.into()) }),
+            _ => value.serialize(self)
+        }
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _index: u32,
+                                                        variant: &'static str, value: &T) -> Result<Vec<Node>> {
+        let nodes = value.serialize(Serializer { mode: Mode::Element(variant.into()) });
+        match self.mode {
+            Mode::Root | Mode::Content => nodes,
+            Mode::Element(name) => {
+                let mut element = Element::new(name);
+                element.children = nodes?;
+                Ok(vec![Node::Element(element)])
+            }
+            Mode::Attribute | Mode::Key => self.unsupported_in("an enum variant with data")
+        }
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SeqSerializer> {
+        match self.mode {
+            Mode::Element(_) | Mode::Content => Ok(SeqSerializer { mode: self.mode, nodes: Vec::new() }),
+            _ => self.unsupported_in("a sequence")
+        }
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str,
+                               _len: usize) -> Result<Self::SerializeTupleVariant> {
+        self.unsupported_in("a tuple variant")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<StructSerializer> {
+        match self.mode {
+            Mode::Element(name) => Ok(StructSerializer::new(name, None)),
+            Mode::Root => unsupported("Cannot serialize a map as the root element, it has no name".into()),
+            _ => self.unsupported_in("a map")
+        }
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<StructSerializer> {
+        match self.mode {
+            Mode::Root => Ok(StructSerializer::new(name.into(), None)),
+            Mode::Element(name) => Ok(StructSerializer::new(name, None)),
+            _ => self.unsupported_in("a struct")
+        }
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str,
+                                _len: usize) -> Result<StructSerializer> {
+        match self.mode {
+            Mode::Root | Mode::Content => Ok(StructSerializer::new(variant.into(), None)),
+            Mode::Element(name) => Ok(StructSerializer::new(variant.into(), Some(name))),
+            _ => self.unsupported_in("an enum variant with data")
+        }
+    }
+}
+
+struct SeqSerializer {
+    mode: Mode,
+    nodes: Vec<Node>
+}
+
+impl SeqSerializer {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let nodes = value.serialize(Serializer { mode: self.mode.clone() })?;
+        self.nodes.extend(nodes);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Vec<Node>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<Node>> {
+        Ok(self.nodes)
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Vec<Node>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<Node>> {
+        Ok(self.nodes)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Vec<Node>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<Node>> {
+        Ok(self.nodes)
+    }
+}
+
+struct StructSerializer {
+    element: Element,
+    /// The name of the element which wraps the element of a struct variant.
+    wrapper: Option<String>,
+    key: Option<String>
+}
+
+impl StructSerializer {
+    fn new(name: String, wrapper: Option<String>) -> StructSerializer {
+        StructSerializer { element: Element::new(name), wrapper, key: None }
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        if let Some(name) = key.strip_prefix(ATTRIBUTE_PREFIX) {
+            let value = value.serialize(Serializer { mode: Mode::Attribute }).map_err(|e| match e {
+                Error::Unsupported(msg) => Error::Unsupported(format!("{} (attribute `{}`)", msg, name)),
+                e => e
+            })?;
+            if let Some(Node::Text(value)) = value.into_iter().next() {
+                self.element.attributes.push((name.into(), value));
+            }
+        } else if key == VALUE_FIELD {
+            self.element.children.extend(value.serialize(Serializer { mode: Mode::Content })?);
+        } else {
+            self.element.children.extend(value.serialize(Serializer { mode: Mode::Element(key.into()) })?);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<Node>> {
+        Ok(vec![Node::Element(match self.wrapper {
+            Some(name) => {
+                let mut wrapper = Element::new(name);
+                wrapper.children.push(Node::Element(self.element));
+                wrapper
+            }
+            None => self.element
+        })])
+    }
+}
+
+impl ser::SerializeMap for StructSerializer {
+    type Ok = Vec<Node>;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(Serializer { mode: Mode::Key })?.pop() {
+            Some(Node::Text(key)) => self.key = Some(key),
+            _ => return unsupported("Map keys must be strings".into())
+        }
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = self.key.take().expect("serialize_value is called after serialize_key");
+        self.field(&key, value)
+    }
+
+    fn end(self) -> Result<Vec<Node>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Vec<Node>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Vec<Node>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer {
+    type Ok = Vec<Node>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Vec<Node>> {
+        self.finish()
+    }
+}
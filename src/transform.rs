@@ -0,0 +1,379 @@
+//! Contains a streaming transformer which rewrites a document without building a tree.
+//!
+//! A `Transformer` reads events from an `EventReader`, lets closures drop, replace or modify
+//! them, and writes the result into an `EventWriter`:
+//!
+//! ```rust
+//! use xml::{EmitterConfig, EventReader};
+//! use xml::reader::XmlEvent;
+//! use xml::transform::{Action, Transformer};
+//!
+//! let reader = EventReader::from_str("<doc><comment>internal</comment><p>text</p></doc>");
+//! let writer = EmitterConfig::new().write_document_declaration(false).create_writer(Vec::new());
+//!
+//! let output = Transformer::new(reader, writer)
+//!     .filter_subtree(|_, start| match *start {
+//!         XmlEvent::StartElement { ref name, .. } if name.local_name == "comment" => Action::Skip,
+//!         _ => Action::Keep
+//!     })
+//!     .map_event(|event| match event {
+//!         XmlEvent::StartElement { mut name, attributes, namespace } => {
+//!             if name.local_name == "p" {
+//!                 name.local_name = "para".into();
+//!             }
+//!             Some(XmlEvent::StartElement { name, attributes, namespace })
+//!         }
+//!         event => Some(event)
+//!     })
+//!     .run()
+//!     .unwrap();
+//! assert_eq!(output, b"<doc><para>text</para></doc>");
+//! ```
+//!
+//! End elements are not passed to the closures. They are written automatically for the elements
+//! whose start elements were written, so start and end elements stay paired however the events
+//! are dropped or renamed.
+//!
+//! The `reindent()` function is a ready-made pass which only changes the indentation
+//! of a document.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::io::{Read, Write};
+use std::rc::Rc;
+
+use common::is_whitespace_str;
+use reader::{self, CurrentPath, EventReader, XmlEvent};
+use writer::{self, EmitterConfig, EventWriter};
+
+/// What to do with an element, as decided by a `Transformer::filter_subtree()` closure.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Action {
+    /// Keep the element; its contents are processed as usual.
+    Keep,
+
+    /// Drop the element together with all of its contents.
+    Skip,
+
+    /// Replace the element with the given event.
+    ///
+    /// If the event is a `StartElement`, only the start tag is replaced, e.g. to rename
+    /// the element or to change its attributes, and the contents of the element are processed
+    /// as usual. Any other event replaces the whole element together with its contents.
+    Replace(XmlEvent)
+}
+
+/// An error which occurred during a transformation.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading of the source document has failed.
+    Reader(reader::Error),
+
+    /// Writing of the transformed document has failed.
+    Writer(writer::Error)
+}
+
+/// A specialized `Result` type where the error is hard-wired to `transform::Error`.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Reader(ref e) => e.fmt(f),
+            Error::Writer(ref e) => e.fmt(f)
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Reader(ref e) => e.msg(),
+            Error::Writer(_) => "document writing error"
+        }
+    }
+}
+
+impl From<reader::Error> for Error {
+    fn from(e: reader::Error) -> Error {
+        Error::Reader(e)
+    }
+}
+
+impl From<writer::Error> for Error {
+    fn from(e: writer::Error) -> Error {
+        Error::Writer(e)
+    }
+}
+
+type SubtreeFilter<'f> = Box<dyn for<'p> FnMut(CurrentPath<'p>, &XmlEvent) -> Action + 'f>;
+type EventMap<'f> = Box<dyn FnMut(XmlEvent) -> Option<XmlEvent> + 'f>;
+
+/// A streaming transformation of a document; see the module documentation.
+pub struct Transformer<'f, R: Read, W: Write> {
+    reader: EventReader<R>,
+    writer: EventWriter<W>,
+    filters: Vec<SubtreeFilter<'f>>,
+    maps: Vec<EventMap<'f>>
+}
+
+impl<'f, R: Read, W: Write> Transformer<'f, R, W> {
+    /// Creates a transformer which copies the events of the reader to the writer unchanged.
+    pub fn new(reader: EventReader<R>, writer: EventWriter<W>) -> Transformer<'f, R, W> {
+        Transformer { reader, writer, filters: Vec::new(), maps: Vec::new() }
+    }
+
+    /// Adds a closure which decides what to do with each element.
+    ///
+    /// The closure is called with the path of the element, which includes the element itself
+    /// (see `EventReader::current_path()`), and with its `StartElement` event. The path
+    /// consists of the names in the source document, even if ancestors were renamed.
+    /// Closures are called in the order they were added until one of them returns something
+    /// other than `Action::Keep`; they are not called for the contents of skipped elements
+    /// or for elements replaced with events other than `StartElement`.
+    pub fn filter_subtree<F>(mut self, f: F) -> Transformer<'f, R, W>
+        where F: for<'p> FnMut(CurrentPath<'p>, &XmlEvent) -> Action + 'f
+    {
+        self.filters.push(Box::new(f));
+        self
+    }
+
+    /// Adds a closure which maps every event that is written, after the subtree filters
+    /// are applied; returning `None` drops the event.
+    ///
+    /// The closures are applied in the order they were added. `EndElement` and `EndDocument`
+    /// events are not passed to them. If a `StartElement` event is dropped or mapped to another
+    /// kind of event, the contents of the element are still written, but its end element is not.
+    pub fn map_event<F>(mut self, f: F) -> Transformer<'f, R, W>
+        where F: FnMut(XmlEvent) -> Option<XmlEvent> + 'f
+    {
+        self.maps.push(Box::new(f));
+        self
+    }
+
+    /// Runs the transformation until the end of the document and returns the underlying sink
+    /// of the writer.
+    pub fn run(mut self) -> Result<W> {
+        // for each open element of the source, whether its end element must be written
+        let mut open: Vec<bool> = Vec::new();
+
+        loop {
+            let mut event = match self.reader.next()? {
+                XmlEvent::EndDocument => break,
+                XmlEvent::EndElement { .. } => {
+                    if open.pop() == Some(true) {
+                        self.writer.write(writer::XmlEvent::end_element())?;
+                    }
+                    continue;
+                }
+                event => event
+            };
+
+            if let XmlEvent::StartElement { .. } = event {
+                let action = self.filter(&event);
+                match action {
+                    Action::Keep => {}
+                    Action::Skip => {
+                        self.skip_element()?;
+                        continue;
+                    }
+                    Action::Replace(replacement @ XmlEvent::StartElement { .. }) => event = replacement,
+                    Action::Replace(replacement) => {
+                        self.skip_element()?;
+                        self.write(replacement)?;
+                        continue;
+                    }
+                }
+            }
+
+            let start = event.kind() == reader::XmlEventKind::StartElement;
+            let written = self.write(event)?;
+            if start {
+                open.push(written);
+            }
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer.into_inner())
+    }
+
+    fn filter(&mut self, start: &XmlEvent) -> Action {
+        for f in &mut self.filters {
+            match f(self.reader.current_path(), start) {
+                Action::Keep => {}
+                action => return action
+            }
+        }
+        Action::Keep
+    }
+
+    /// Maps and writes the event. Returns whether a `StartElement` event was written.
+    fn write(&mut self, mut event: XmlEvent) -> Result<bool> {
+        for f in &mut self.maps {
+            event = match f(event) {
+                Some(event) => event,
+                None => return Ok(false)
+            };
+        }
+        if let Some(e) = event.as_writer_event() {
+            self.writer.write(e)?;
+        }
+        Ok(event.kind() == reader::XmlEventKind::StartElement)
+    }
+
+    /// Reads the events of the element whose start element was read last, up to and including
+    /// its end element.
+    fn skip_element(&mut self) -> Result<()> {
+        let depth = self.reader.depth();
+        while self.reader.depth() >= depth {
+            self.reader.next()?;
+        }
+        Ok(())
+    }
+}
+
+/// The maximum number of events `reindent()` reads ahead to find out whether an element has
+/// mixed content.
+pub const REINDENT_LOOKAHEAD: usize = 1024;
+
+/// Reads a document and writes it into the sink with consistent indentation, using `indent`
+/// for each level of depth.
+///
+/// Content, comments and processing instructions are preserved; only whitespace between
+/// markup is replaced. Elements with mixed content, i.e. elements which directly contain
+/// text other than whitespace, are written exactly as they are read, including all of their
+/// descendants, because whitespace added or removed there would change the text. The same
+/// holds for elements with `xml:space="preserve"`.
+///
+/// ```rust
+/// use xml::EventReader;
+/// use xml::transform::reindent;
+///
+/// let reader = EventReader::from_str("<a>\n<b>  <c/></b><p>Some <em>text</em></p></a>");
+/// let output = reindent(reader, Vec::new(), "  ").unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+///      <a>\n  <b>\n    <c />\n  </b>\n  <p>Some <em>text</em></p>\n</a>"
+/// );
+/// ```
+///
+/// The document is streamed: whether an element has mixed content is decided by reading
+/// ahead until text appears in it or until it ends, and at most `REINDENT_LOOKAHEAD` events
+/// are held back for that. If an element is longer than that without text, it is reindented;
+/// should text follow later, e.g. after many child elements, the rest of the element is
+/// written as it is read.
+pub fn reindent<R: Read, W: Write>(mut reader: EventReader<R>, sink: W, indent: &str) -> Result<W> {
+    let writer = EmitterConfig::new()
+        .perform_indent(true)
+        .indent_string(indent.to_owned())
+        .create_writer(sink);
+    let mut reindenter = Reindenter {
+        writer,
+        pending: VecDeque::new(),
+        read_open: Vec::new(),
+        written_open: Vec::new()
+    };
+    loop {
+        let event = reader.next()?;
+        let mixed = match event {
+            XmlEvent::EndDocument => break,
+            XmlEvent::StartElement { .. } => {
+                let mixed = Rc::new(Cell::new(None));
+                reindenter.read_open.push(mixed.clone());
+                Some(mixed)
+            }
+            XmlEvent::EndElement { .. } => {
+                if let Some(mixed) = reindenter.read_open.pop() {
+                    if mixed.get().is_none() {
+                        mixed.set(Some(false));
+                    }
+                }
+                None
+            }
+            XmlEvent::Characters(ref data) if is_whitespace_str(data) => None,
+            XmlEvent::Characters(_) | XmlEvent::CData(_) | XmlEvent::EntityReference(_) => {
+                if let Some(mixed) = reindenter.read_open.last() {
+                    mixed.set(Some(true));
+                }
+                None
+            }
+            _ => None
+        };
+        reindenter.pending.push_back((event, mixed));
+        reindenter.write_pending()?;
+    }
+    reindenter.write_pending()?;
+
+    let mut writer = reindenter.writer;
+    writer.flush()?;
+    Ok(writer.into_inner())
+}
+
+/// Whether an element has mixed content, or `None` while this is not known yet.
+type MixedFlag = Rc<Cell<Option<bool>>>;
+
+/// The state of `reindent()`.
+struct Reindenter<W: Write> {
+    writer: EventWriter<W>,
+    // the events read ahead, with the flags of start elements
+    pending: VecDeque<(XmlEvent, Option<MixedFlag>)>,
+    // the flags of the elements open at the last read event
+    read_open: Vec<MixedFlag>,
+    // the flags of the elements open at the last written event
+    written_open: Vec<MixedFlag>
+}
+
+impl<W: Write> Reindenter<W> {
+    /// Writes the events read ahead for as long as it is known whether they are inside
+    /// mixed content, or for as long as there are too many of them.
+    fn write_pending(&mut self) -> Result<()> {
+        while !self.pending.is_empty() {
+            let undecided = self.written_open.iter().any(|m| m.get().is_none());
+            if undecided && self.pending.len() <= REINDENT_LOOKAHEAD {
+                break;
+            }
+            // elements which are still undecided are not mixed as far as it is known
+            for mixed in &self.written_open {
+                if mixed.get().is_none() {
+                    mixed.set(Some(false));
+                }
+            }
+            let (event, mixed) = self.pending.pop_front().unwrap();
+            self.write(&event)?;
+            match event {
+                XmlEvent::StartElement { .. } => self.written_open.extend(mixed),
+                XmlEvent::EndElement { .. } => {
+                    self.written_open.pop();
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, event: &XmlEvent) -> Result<()> {
+        // the content of elements with mixed content is written as it is read
+        let verbatim = self.written_open.iter().any(|m| m.get() == Some(true));
+        if verbatim {
+            self.writer.mixed_content();
+        }
+        match *event {
+            XmlEvent::StartElement { .. } => self.writer.write(event.as_writer_event().unwrap())?,
+            XmlEvent::EndElement { .. } => self.writer.write(writer::XmlEvent::end_element())?,
+            // whitespace is replaced by indentation, unless it is a part of mixed content
+            XmlEvent::Whitespace(ref data) | XmlEvent::Characters(ref data) if is_whitespace_str(data) =>
+                if verbatim {
+                    self.writer.write(writer::XmlEvent::characters(data))?;
+                } else {
+                    self.writer.write(writer::XmlEvent::Whitespace(data))?;
+                },
+            _ => if let Some(e) = event.as_writer_event() {
+                self.writer.write(e)?;
+            }
+        }
+        Ok(())
+    }
+}
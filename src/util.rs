@@ -2,6 +2,8 @@ use std::io::{self, Read};
 use std::str;
 use std::fmt;
 
+use common::{is_xml_char_reference, XmlVersion};
+
 #[derive(Debug)]
 pub enum CharReadError {
     UnexpectedEof,
@@ -32,63 +34,162 @@ impl fmt::Display for CharReadError {
     }
 }
 
-pub fn next_char_from<R: Read>(source: &mut R) -> Result<Option<char>, CharReadError> {
-    const MAX_CODEPOINT_LEN: usize = 4;
+const MAX_CODEPOINT_LEN: usize = 4;
+
+/// Decodes UTF-8 characters from a stream one by one.
+///
+/// The bytes of a character cut off by the end of the stream are kept, so that they are
+/// completed by the next bytes if the stream continues, like streams read by a parser with
+/// the `ignore_end_of_stream` option do. So is a byte which ends an invalid sequence without
+/// belonging to it, which starts the next character instead.
+#[derive(Default)]
+pub struct CharReader {
+    buf: [u8; MAX_CODEPOINT_LEN],
+    len: usize
+}
 
-    let mut bytes = source.bytes();
-    let mut buf = [0u8; MAX_CODEPOINT_LEN];
-    let mut pos = 0;
+impl CharReader {
+    pub fn new() -> CharReader {
+        CharReader::default()
+    }
 
-    loop {
-        let next = match bytes.next() {
-            Some(Ok(b)) => b,
-            Some(Err(e)) => return Err(e.into()),
-            None if pos == 0 => return Ok(None),
-            None => return Err(CharReadError::UnexpectedEof)
-        };
-        buf[pos] = next;
-        pos += 1;
+    pub fn next_char_from<R: Read>(&mut self, source: &mut R) -> Result<Option<char>, CharReadError> {
+        let mut bytes = source.bytes();
+
+        loop {
+            if self.len > 0 {
+                match str::from_utf8(&self.buf[..self.len]) {
+                    Ok(s) => {
+                        self.len = 0;
+                        return Ok(s.chars().next());  // always Some(..)
+                    }
+                    // a valid prefix of a sequence, which can't be longer than `MAX_CODEPOINT_LEN`
+                    Err(ref e) if e.error_len().is_none() => {}
+                    // invalid bytes, including overlong sequences and surrogates, are rejected
+                    // as soon as they are read, without consuming any bytes after them; the
+                    // bytes after the invalid sequence are decoded by the next call
+                    Err(e) => {
+                        let invalid = e.valid_up_to() + e.error_len().unwrap_or(self.len);
+                        self.buf.copy_within(invalid..self.len, 0);
+                        self.len -= invalid;
+                        return Err(e.into());
+                    }
+                }
+            }
 
-        match str::from_utf8(&buf[..pos]) {
-            Ok(s) => return Ok(s.chars().next()),  // always Some(..)
-            Err(_) if pos < MAX_CODEPOINT_LEN => {},
-            Err(e) => return Err(e.into())
+            let next = match bytes.next() {
+                Some(Ok(b)) => b,
+                Some(Err(e)) => return Err(e.into()),
+                None if self.len == 0 => return Ok(None),
+                None => return Err(CharReadError::UnexpectedEof)
+            };
+            self.buf[self.len] = next;
+            self.len += 1;
         }
     }
 }
 
+/// Parses the code point of a character reference without the leading `&#` and the trailing `;`.
+///
+/// The code point is not necessarily a valid character.
+pub fn parse_char_reference_code(num: &str) -> Option<u32> {
+    // `from_str_radix` accepts a leading sign, which character references may not have
+    match num.strip_prefix('x') {
+        Some(hex) if !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit()) => u32::from_str_radix(hex, 16).ok(),
+        None if !num.is_empty() && num.bytes().all(|b| b.is_ascii_digit()) => num.parse::<u32>().ok(),
+        _ => None
+    }
+}
+
+/// Parses a character reference without the leading `&#` and the trailing `;`.
+pub fn parse_char_reference(num: &str) -> Option<char> {
+    parse_char_reference_code(num).and_then(|code| if code == 0 { None } else { char::from_u32(code) })
+}
+
+/// Parses a character reference without the leading `&#` and the trailing `;`, and checks
+/// that the character is allowed in documents of the given XML version.
+///
+/// Returns an error message naming the reference or the character otherwise.
+pub fn parse_xml_char_reference(num: &str, version: XmlVersion) -> Result<char, String> {
+    match parse_char_reference_code(num).and_then(char::from_u32) {
+        Some(c) if is_xml_char_reference(c, version) => Ok(c),
+        Some(c) => Err(format!("Character U+{:04X} is not allowed in XML documents: &#{};", c as u32, num)),
+        None => Err(format!("Invalid character reference: &#{};", num))
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn test_parse_char_reference_code() {
+        use super::parse_char_reference_code;
+
+        assert_eq!(parse_char_reference_code("65"), Some(65));
+        assert_eq!(parse_char_reference_code("x41"), Some(0x41));
+        assert_eq!(parse_char_reference_code("x10fFfF"), Some(0x10FFFF));
+        assert_eq!(parse_char_reference_code("+65"), None);
+        assert_eq!(parse_char_reference_code("x+41"), None);
+        assert_eq!(parse_char_reference_code("-1"), None);
+        assert_eq!(parse_char_reference_code("X41"), None);
+        assert_eq!(parse_char_reference_code("x"), None);
+        assert_eq!(parse_char_reference_code(""), None);
+        assert_eq!(parse_char_reference_code("99999999999"), None);
+    }
+
     #[test]
     fn test_next_char_from() {
         use std::io;
-        use std::error::Error;
 
         let mut bytes: &[u8] = "correct".as_bytes();    // correct ASCII
-        assert_eq!(super::next_char_from(&mut bytes).unwrap(), Some('c'));
+        assert_eq!(super::CharReader::new().next_char_from(&mut bytes).unwrap(), Some('c'));
 
         let mut bytes: &[u8] = "правильно".as_bytes();  // correct BMP
-        assert_eq!(super::next_char_from(&mut bytes).unwrap(), Some('п'));
+        assert_eq!(super::CharReader::new().next_char_from(&mut bytes).unwrap(), Some('п'));
 
         let mut bytes: &[u8] = "😊".as_bytes();          // correct non-BMP
-        assert_eq!(super::next_char_from(&mut bytes).unwrap(), Some('😊'));
+        assert_eq!(super::CharReader::new().next_char_from(&mut bytes).unwrap(), Some('😊'));
 
         let mut bytes: &[u8] = b"";                     // empty
-        assert_eq!(super::next_char_from(&mut bytes).unwrap(), None);
+        assert_eq!(super::CharReader::new().next_char_from(&mut bytes).unwrap(), None);
 
         let mut bytes: &[u8] = b"\xf0\x9f\x98";         // incomplete code point
-        match super::next_char_from(&mut bytes).unwrap_err() {
+        match super::CharReader::new().next_char_from(&mut bytes).unwrap_err() {
             super::CharReadError::UnexpectedEof => {},
             e => panic!("Unexpected result: {:?}", e)
         };
 
         let mut bytes: &[u8] = b"\xff\x9f\x98\x32";     // invalid code point
-        match super::next_char_from(&mut bytes).unwrap_err() {
+        match super::CharReader::new().next_char_from(&mut bytes).unwrap_err() {
             super::CharReadError::Utf8(_) => {},
             e => panic!("Unexpected result: {:?}", e)
         };
 
 
+        // a character split between reads
+        let mut reader = super::CharReader::new();
+        let mut bytes: &[u8] = b"\xf0\x9f";
+        match reader.next_char_from(&mut bytes).unwrap_err() {
+            super::CharReadError::UnexpectedEof => {},
+            e => panic!("Unexpected result: {:?}", e)
+        };
+        let mut bytes: &[u8] = b"\x98\x8a";
+        assert_eq!(reader.next_char_from(&mut bytes).unwrap(), Some('😊'));
+
+        // an overlong encoding is rejected without reading further
+        let mut bytes: &[u8] = b"\xc0\xaf<";
+        assert!(super::CharReader::new().next_char_from(&mut bytes).is_err());
+        assert_eq!(bytes, b"\xaf<");
+
+        // the byte which ends an invalid sequence starts the next character
+        let mut reader = super::CharReader::new();
+        let mut bytes: &[u8] = b"\xe0A";
+        match reader.next_char_from(&mut bytes).unwrap_err() {
+            super::CharReadError::Utf8(_) => {},
+            e => panic!("Unexpected result: {:?}", e)
+        };
+        assert_eq!(reader.next_char_from(&mut bytes).unwrap(), Some('A'));
+        assert_eq!(reader.next_char_from(&mut bytes).unwrap(), None);
+
         // error during read
         struct ErrorReader;
         impl io::Read for ErrorReader {
@@ -98,9 +199,9 @@ mod tests {
         }
 
         let mut r = ErrorReader;
-        match super::next_char_from(&mut r).unwrap_err() {
+        match super::CharReader::new().next_char_from(&mut r).unwrap_err() {
             super::CharReadError::Io(ref e) if e.kind() == io::ErrorKind::Other &&
-                                               e.description() == "test error" => {},
+                                               e.to_string() == "test error" => {},
             e => panic!("Unexpected result: {:?}", e)
         }
     }
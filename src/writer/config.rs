@@ -2,8 +2,76 @@
 
 use std::io::Write;
 use std::borrow::Cow;
+use std::collections::HashSet;
 
 use writer::EventWriter;
+use escape::QuoteStyle;
+use common::{InvalidCharacterPolicy, XmlVersion};
+#[cfg(feature = "encodings")]
+use encodings::{self, Encoding};
+
+/// Determines what the emitter does when the prefix of a written name is already bound
+/// to another namespace URI.
+///
+/// See `EmitterConfig::namespace_conflict_policy` for more information.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum NamespaceConflictPolicy {
+    /// Bind the prefix to the URI of the name in the current element, shadowing the outer
+    /// binding. If the prefix is already bound to another URI in the current element itself,
+    /// a new prefix is generated instead.
+    Rebind,
+
+    /// Generate a new prefix for the URI of the name.
+    GeneratePrefix,
+
+    /// Return an `EmitterError::NamespacePrefixConflict` error.
+    Error
+}
+
+/// Determines how attributes of a wrapped start tag are aligned.
+///
+/// See `EmitterConfig::max_line_length` for more information.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum AttributeAlignment {
+    /// The first attribute stays on the line with the element name, and the other ones
+    /// are aligned under it.
+    FirstAttribute,
+
+    /// Every attribute is written on its own line, indented one level deeper than the element.
+    ExtraIndent
+}
+
+/// Determines where the end of a wrapped start tag, `>` or `/>`, is written.
+///
+/// See `EmitterConfig::max_line_length` for more information.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TagEndPlacement {
+    /// The end of the tag immediately follows the last attribute.
+    AfterLastAttribute,
+
+    /// The end of the tag is written on its own line, indented like the element.
+    OwnLine
+}
+
+/// Determines how namespace declarations are rendered in the canonical form.
+///
+/// See `EmitterConfig::canonicalization` for more information.
+#[cfg(feature = "c14n")]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Canonicalization {
+    /// [Canonical XML 1.0][1]: every element declares the namespaces in scope which are
+    /// not declared with the same URI by its nearest ancestor.
+    ///
+    /// [1]: https://www.w3.org/TR/xml-c14n/
+    Inclusive,
+
+    /// [Exclusive XML Canonicalization 1.0][1]: every element declares only the namespaces
+    /// which are visibly utilized by its name or the names of its attributes, unless its
+    /// nearest ancestor already declares them with the same URI.
+    ///
+    /// [1]: https://www.w3.org/TR/xml-exc-c14n/
+    Exclusive
+}
 
 /// Emitter configuration structure.
 ///
@@ -11,6 +79,12 @@ use writer::EventWriter;
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct EmitterConfig {
     /// Line separator used to separate lines in formatted output. Default is `"\n"`.
+    ///
+    /// Every line break inserted by the emitter itself, i.e. by the pretty-printer after the
+    /// document declaration and between markup, uses this separator; set it to `"\r\n"` to
+    /// produce CRLF line endings. Line breaks in user-provided content, e.g. in character data,
+    /// CDATA sections or comments, are written as is, regardless of this setting, unless
+    /// `normalize_newlines` is enabled.
     pub line_separator: Cow<'static, str>,
 
     /// A string which will be used for a single level of indentation. Default is `"  "`
@@ -23,11 +97,66 @@ pub struct EmitterConfig {
     /// It is done in stream-like fashion and does not require the knowledge of the whole
     /// document in advance.
     ///
+    /// Every nested element is written on its own line, indented with `indent_string` once
+    /// per level of depth. Elements which contain only character data are kept on one line,
+    /// like `<title>Hi</title>`. Once character data is written into an element, its content is
+    /// considered mixed, and no indentation is added anywhere else inside that element, so
+    /// its text does not change; note, however, that child elements written before the first
+    /// piece of text are indented, because the emitter can't know in advance that text follows.
+    ///
     /// Sometimes, however, automatic indentation is undesirable, e.g. when you want to keep
     /// existing layout when processing an existing XML document. Also the indentiation algorithm
     /// is not thoroughly tested. Hence by default it is disabled.
     pub perform_indent: bool,
 
+    /// Names of elements whose content is never indented. Default is empty.
+    ///
+    /// This option is only meaningful if `perform_indent` is true. In mixed-content vocabularies
+    /// like XHTML, whitespace added around elements like `em` or `a` changes the rendered text.
+    /// Elements whose local name is listed here are positioned within their parent like
+    /// all other elements, but everything inside them, including nested elements, is written
+    /// without any added whitespace, as if it were mixed content:
+    ///
+    /// ```rust
+    /// use xml::writer::{EmitterConfig, XmlEvent};
+    ///
+    /// let mut output = Vec::new();
+    /// {
+    ///     let mut writer = EmitterConfig::new()
+    ///         .write_document_declaration(false)
+    ///         .perform_indent(true)
+    ///         .inline_elements(vec!["p".to_string(), "em".to_string()].into_iter().collect())
+    ///         .create_writer(&mut output);
+    ///     writer.write(XmlEvent::start_element("body")).unwrap();
+    ///     writer.write(XmlEvent::start_element("p")).unwrap();
+    ///     writer.write(XmlEvent::start_element("em")).unwrap();
+    ///     writer.write(XmlEvent::characters("Hi")).unwrap();
+    ///     writer.write(XmlEvent::end_element()).unwrap();
+    ///     writer.write(XmlEvent::end_element()).unwrap();
+    ///     writer.write(XmlEvent::end_element()).unwrap();
+    /// }
+    /// assert_eq!(output, b"<body>\n  <p><em>Hi</em></p>\n</body>");
+    /// ```
+    pub inline_elements: HashSet<String>,
+
+    /// The maximum depth of elements whose content is indented. Default is `None`, that is,
+    /// the content of all elements is indented.
+    ///
+    /// This option is only meaningful if `perform_indent` is true. The root element has depth 0,
+    /// and its children have depth 1. Elements at this depth are still positioned within their
+    /// parent, but their content, including all nested elements, is written without line breaks
+    /// and indentation, like the content of `inline_elements`. This keeps the overall structure
+    /// of huge documents readable without spending most of the output on whitespace. For example,
+    /// with `max_indent_depth` set to 1:
+    ///
+    /// ```xml
+    /// <root>
+    ///   <item><name>a</name><tags><tag /></tags></item>
+    ///   <item><name>b</name></item>
+    /// </root>
+    /// ```
+    pub max_indent_depth: Option<usize>,
+
     /// Whether or not characters in output events will be escaped. Default is true.
     ///
     /// The emitter can automatically escape characters which can't appear in PCDATA sections
@@ -43,13 +172,53 @@ pub struct EmitterConfig {
     /// Default is true.
     ///
     /// This option controls whether the document declaration should be emitted automatically
-    /// before a root element is written if it was not emitted explicitly by the user.
+    /// before a root element is written if it was not emitted explicitly by the user. The
+    /// automatic declaration looks like `<?xml version="1.0" encoding="UTF-8"?>`.
+    ///
+    /// When this option is disabled, no declaration is written at all, even if a `StartDocument`
+    /// event is written explicitly, which is useful for generating markup to be embedded into
+    /// other documents. Such an event still determines the XML version of the document,
+    /// see `EventWriter::xml_version()`.
     pub write_document_declaration: bool,
 
+    /// The XML version declared in the automatically written document declaration.
+    /// Default is `XmlVersion::Version10`.
+    ///
+    /// An explicitly written `StartDocument` event declares its own version instead.
+    pub xml_version: XmlVersion,
+
+    /// The standalone flag declared in the automatically written document declaration.
+    /// Default is `None`, that is, the `standalone` pseudo-attribute is omitted.
+    ///
+    /// An explicitly written `StartDocument` event declares its own flag instead.
+    pub standalone: Option<bool>,
+
+    /// Whether or not the `encoding` pseudo-attribute is written in the document declaration.
+    /// Default is true.
+    ///
+    /// When this option is disabled, the declaration only contains the version and, if enabled,
+    /// the standalone flag, e.g. `<?xml version="1.0"?>`, even if a `StartDocument` event
+    /// with an encoding is written explicitly. Documents without a declared encoding must be
+    /// encoded in UTF-8 or UTF-16, so this option should only be disabled for such outputs.
+    pub declare_encoding: bool,
+
+    /// Whether or not the `standalone` pseudo-attribute is written in the document declaration.
+    /// Default is true.
+    ///
+    /// When this option is disabled, the standalone flag is omitted from the declaration, even
+    /// if it is set by the `standalone` option or in an explicitly written `StartDocument` event.
+    /// The pseudo-attributes which are written always keep the order required by the XML
+    /// specification: `version`, `encoding`, `standalone`.
+    pub declare_standalone: bool,
+
     /// Whether or not to convert elements with empty content to empty elements. Default is true.
     ///
     /// This option allows turning elements like `<a></a>` (an element with empty content)
-    /// into `<a />` (an empty element).
+    /// into `<a />` (an empty element). To make this possible, the closing `>` of a start tag
+    /// is only written when the next event is known. An element is only collapsed if no event
+    /// at all was written between its start and end; even empty character data, written as
+    /// `Characters("")`, makes the element non-empty, so `<a></a>` can still be produced
+    /// when needed.
     pub normalize_empty_elements: bool,
 
     /// Whether or not to emit CDATA events as plain characters. Default is false.
@@ -59,11 +228,26 @@ pub struct EmitterConfig {
     /// for feeding the document into incorrect parsers which do not support CDATA.
     pub cdata_to_characters: bool,
 
+    /// Whether or not to emit character data which needs escaping as CDATA sections.
+    /// Default is false.
+    ///
+    /// When this option is enabled, `Characters` events whose content contains markup characters
+    /// like `<` or `&` are written as CDATA sections instead of being escaped. This only happens
+    /// if `perform_escaping` is also enabled. Content with characters which cannot be represented
+    /// in the output encoding is escaped as usual, which is noted as a warning that can be
+    /// retrieved with `EventWriter::take_warnings()`.
+    pub characters_to_cdata: bool,
+
     /// Whether or not to keep element names to support `EndElement` events without explicit names.
     /// Default is true.
     ///
     /// This option makes the emitter to keep names of written elements in order to allow
     /// omitting names when writing closing element tags. This could incur some memory overhead.
+    ///
+    /// The names are also used to check that closing element tags match the opening ones,
+    /// and are available through `EventWriter::current_element()` and
+    /// `EventWriter::element_names()`. When this option is disabled, names of closing
+    /// element tags must always be specified and are not checked.
     pub keep_element_names_stack: bool,
 
     /// Whether or not to automatically insert leading and trailing spaces in emitted comments,
@@ -72,6 +256,10 @@ pub struct EmitterConfig {
     /// This is a convenience option in order for the user not to append spaces before and after
     /// comments text in order to get more pretty comments: `<!-- something -->` instead of
     /// `<!--something-->`.
+    ///
+    /// When this option is enabled, the content of comments is also made valid with
+    /// `escape::escape_str_comment()`, that is, spaces are inserted between adjacent dashes and
+    /// after a trailing dash. When it is disabled, writing such a comment is an error.
     pub autopad_comments: bool,
 
     /// Whether or not to automatically insert spaces before the trailing `/>` in self-closing
@@ -82,6 +270,210 @@ pub struct EmitterConfig {
     /// this option is also true, the same element would appear `<a />`. If this option is false,
     /// then the same element would appear `<a/>`.
     pub pad_self_closing: bool,
+
+    /// What to do with characters which are not allowed in XML documents. Default is
+    /// `InvalidCharacterPolicy::Error`.
+    ///
+    /// Characters which do not match the `Char` production of the XML 1.0 specification,
+    /// like U+0000 or most of the C0 control characters, cannot be represented in XML documents
+    /// at all, not even with character references, and no parser would accept a document
+    /// containing them. This policy applies to such characters in character data, CDATA
+    /// sections and attribute values: the emitter either returns an
+    /// `EmitterError::InvalidCharacter` error, or strips the characters, or writes a replacement
+    /// character instead of them.
+    pub invalid_character_policy: InvalidCharacterPolicy,
+
+    /// Whether or not to write a byte order mark at the beginning of the output. Default is false.
+    ///
+    /// When this option is enabled, the byte order mark of the output encoding (e.g. `EF BB BF`
+    /// for UTF-8) is written before anything else, in particular, before the XML declaration.
+    /// Encodings which are not able to represent all of Unicode have no byte order mark, and
+    /// this option does nothing for them.
+    pub write_bom: bool,
+
+    /// Whether or not to flush the output stream after each written event. Default is false.
+    ///
+    /// By default the emitter never flushes the underlying stream by itself, so when it is
+    /// buffered, e.g. with `BufWriter`, written events may stay in the buffer indefinitely.
+    /// This is fine for writing files, but not for long-lived streams like network connections,
+    /// where the other side expects to receive events as soon as they are written. Enabling this
+    /// option makes the emitter call `flush()` on the stream after every event. Note that this
+    /// defeats the purpose of buffering and may considerably slow down writing large documents,
+    /// because every event results in a separate write to the underlying device; consider
+    /// calling `EventWriter::flush()` explicitly at suitable points instead.
+    pub autoflush: bool,
+
+    /// The quote character used to delimit attribute values. Default is `QuoteStyle::Double`.
+    ///
+    /// Only the active quote character is escaped inside attribute values, e.g. with
+    /// `QuoteStyle::Single` the value `it's "ok"` is written as `'it&apos;s "ok"'`.
+    /// `QuoteStyle::Smart` picks the quote character separately for each value so that
+    /// escaping is avoided where possible. Namespace declarations are delimited the same way.
+    pub attribute_quote: QuoteStyle,
+
+    /// What to do when the prefix of an element or attribute name is bound to another namespace
+    /// URI than the one of the name. Default is `NamespaceConflictPolicy::Rebind`.
+    ///
+    /// The emitter makes sure that names with a namespace URI are written with a prefix bound
+    /// to this URI, declaring namespaces automatically where necessary; see
+    /// `writer::XmlEvent::StartElement` for details. This option controls what happens if the
+    /// requested prefix is already bound to another URI, either in an enclosing element or in
+    /// the current one.
+    pub namespace_conflict_policy: NamespaceConflictPolicy,
+
+    /// The maximum length of a line with a start tag. Default is `None`, that is, start tags
+    /// are never wrapped.
+    ///
+    /// This option is only meaningful if `perform_indent` is true. When a start tag, including
+    /// its indentation and the closing `>`, is longer than this number of characters, each of its
+    /// attributes and namespace declarations is written on its own line, as determined by
+    /// `wrapped_attribute_alignment` and `wrapped_tag_end`:
+    ///
+    /// ```xml
+    /// <element
+    ///   first="1"
+    ///   second="2">
+    /// ```
+    ///
+    /// Note that whether an element is empty, and so whether its start tag ends with `/>`, is
+    /// not known at the time it is written, so the length is always computed with `>`.
+    pub max_line_length: Option<usize>,
+
+    /// How the attributes of a wrapped start tag are aligned. Default is
+    /// `AttributeAlignment::ExtraIndent`.
+    ///
+    /// See `max_line_length` for more information.
+    pub wrapped_attribute_alignment: AttributeAlignment,
+
+    /// Where the end of a wrapped start tag is written. Default is
+    /// `TagEndPlacement::AfterLastAttribute`.
+    ///
+    /// When the end of the tag is written on its own line, `pad_self_closing` has no effect
+    /// on it. See `max_line_length` for more information.
+    pub wrapped_tag_end: TagEndPlacement,
+
+    /// Whether or not to sort attributes of each element by their qualified names.
+    /// Default is false, that is, attributes are written in the order they were added.
+    ///
+    /// Sorting makes the output independent of the order in which the attributes were added,
+    /// which is useful for diffing. The sort is stable, and namespace declarations, which are
+    /// sorted by prefix anyway, are written before all other attributes.
+    pub sort_attributes: bool,
+
+    /// Whether or not to sort attributes of each element by their namespace URIs and then
+    /// by their local names. Default is false.
+    ///
+    /// This is the attribute order of [Canonical XML][1], where attributes without a namespace
+    /// come first; unlike `sort_attributes`, the result does not depend on the prefixes
+    /// chosen for the namespaces. Note that this is only the attribute ordering part of
+    /// the canonical form, not full canonicalization; see `canonical` for the latter,
+    /// which implies this option. Takes precedence over `sort_attributes`.
+    ///
+    /// [1]: https://www.w3.org/TR/xml-c14n11/#DocumentOrder
+    pub sort_attributes_by_namespace: bool,
+
+    /// Whether or not to write the document in the canonical form. Default is false.
+    ///
+    /// When enabled, the output follows [Canonical XML][1], which makes it deterministic
+    /// and suitable for signing and diffing: the XML and document type declarations are
+    /// omitted, attributes are sorted by namespace URI and local name, empty elements are
+    /// written as start/end tag pairs, CDATA sections are written as escaped characters,
+    /// characters are escaped according to canonical rules (e.g. carriage returns become
+    /// `&#xD;`), comments are written without padding, and comments and processing instructions
+    /// outside of the root element are separated from it by line feeds. The output is
+    /// always UTF-8 without a byte order mark.
+    ///
+    /// This option overrides the conflicting options, like `perform_indent`,
+    /// `normalize_empty_elements`, `cdata_to_characters` or `attribute_quote`. Note that
+    /// the namespace rendering rules of the specification are not applied: namespace
+    /// declarations are written as given, sorted by prefix. Use `canonicalization`,
+    /// which requires the `c14n` feature, to apply them.
+    ///
+    /// [1]: https://www.w3.org/TR/xml-c14n/
+    pub canonical: bool,
+
+    /// Whether or not to allow writing document fragments. Default is false.
+    ///
+    /// By default the emitter refuses to write anything which would make the document
+    /// not well-formed: a second root element, non-whitespace character data, CDATA sections
+    /// or entity references outside of the root element are errors, e.g.
+    /// `EmitterError::MultipleRootElements`. Enabling this option allows them, so that
+    /// sequences of elements and text can be written, e.g. to be embedded into another document.
+    /// Mismatched end elements, invalid names and duplicate attributes are errors regardless
+    /// of this option.
+    pub allow_fragments: bool,
+
+    /// Additional replacements for characters in character data. Default is empty.
+    ///
+    /// Every character of character data written by the emitter which is listed here is
+    /// replaced with the corresponding string, which is written verbatim, so it should
+    /// be a reference, e.g. `('\u{A0}', "&#160;")` to make non-breaking spaces visible.
+    /// These replacements take precedence over the built-in escaping; see
+    /// `escape::escape_str_pcdata_with_extra()`. They are only applied if `perform_escaping`
+    /// is enabled and `canonical` is disabled, and never inside CDATA sections, which are
+    /// written as is; with `characters_to_cdata`, only characters escaped by the built-in
+    /// rules make character data be written as a CDATA section.
+    pub extra_pcdata_escapes: Cow<'static, [(char, &'static str)]>,
+
+    /// Additional replacements for characters in attribute values. Default is empty.
+    ///
+    /// This works like `extra_pcdata_escapes`, but for attribute values; the replacements take
+    /// precedence over the escaping of the active quote character too. See
+    /// `escape::escape_str_attribute_quoted_with_extra()`.
+    pub extra_attribute_escapes: Cow<'static, [(char, &'static str)]>,
+
+    /// Whether or not to normalize line breaks in character data and attribute values.
+    /// Default is false, that is, line breaks are written exactly as given.
+    ///
+    /// Conforming XML parsers replace `\r\n` and lone `\r` line breaks with `\n`, and literal
+    /// line breaks in attribute values with spaces, so text from sources with Windows or old Mac
+    /// line endings is read differently by different parsers. When this option is enabled, every line break
+    /// in character data, whether `\r\n`, `\r` or `\n`, is written as `line_separator`, and
+    /// every line break in attribute values is written as a `&#xA;` reference, so the reader
+    /// reports the value with `\n` line breaks. CDATA sections, comments and processing
+    /// instructions are not affected.
+    pub normalize_newlines: bool,
+
+    /// The encoding of the emitted document. Default is UTF-8.
+    ///
+    /// All output of the emitter is converted to this encoding, and the encoding name is used
+    /// in the XML declaration. Characters which cannot be represented in the output encoding
+    /// are written as character references in character data and attribute values; this is not
+    /// possible inside names, comments, CDATA sections, processing instructions and the document
+    /// type declaration, so writing such characters there results in an error.
+    ///
+    /// Encodings follow the [Encoding Standard][1], so the `iso-8859-1` label, for example,
+    /// corresponds to `windows-1252`, its superset, which is also the name declared in the document.
+    ///
+    /// [1]: https://encoding.spec.whatwg.org/
+    #[cfg(feature = "encodings")]
+    pub output_encoding: &'static Encoding,
+
+    /// The namespace rendering rules of the canonical form. Default is `None`.
+    ///
+    /// Setting this option implies `canonical`. Instead of writing the namespace declarations
+    /// as given, the emitter then writes the declarations which the chosen [`Canonicalization`]
+    /// method requires at each element, sorted by prefix with the default namespace first, and
+    /// omits the redundant ones. An `xmlns=""` declaration is written where an element
+    /// is not in the default namespace of its nearest ancestor which declares one.
+    ///
+    /// ```rust
+    /// use xml::writer::{Canonicalization, EmitterConfig, XmlEvent};
+    ///
+    /// let mut output = Vec::new();
+    /// {
+    ///     let mut writer = EmitterConfig::new()
+    ///         .canonicalization(Canonicalization::Exclusive)
+    ///         .create_writer(&mut output);
+    ///     writer.write(XmlEvent::start_element("a:doc").ns("a", "urn:a").ns("b", "urn:b")).unwrap();
+    ///     writer.write(XmlEvent::start_element("b:item").ns("b", "urn:b").attr("a:id", "1")).unwrap();
+    ///     writer.write(XmlEvent::end_element()).unwrap();
+    ///     writer.write(XmlEvent::end_element()).unwrap();
+    /// }
+    /// assert_eq!(output, &b"<a:doc xmlns:a=\"urn:a\"><b:item xmlns:b=\"urn:b\" a:id=\"1\"></b:item></a:doc>"[..]);
+    /// ```
+    #[cfg(feature = "c14n")]
+    pub canonicalization: Option<Canonicalization>,
 }
 
 impl EmitterConfig {
@@ -103,13 +495,39 @@ impl EmitterConfig {
             line_separator: "\n".into(),
             indent_string: "  ".into(),  // two spaces
             perform_indent: false,
+            inline_elements: HashSet::new(),
+            max_indent_depth: None,
             perform_escaping: true,
             write_document_declaration: true,
+            xml_version: XmlVersion::Version10,
+            standalone: None,
+            declare_encoding: true,
+            declare_standalone: true,
             normalize_empty_elements: true,
             cdata_to_characters: false,
+            characters_to_cdata: false,
             keep_element_names_stack: true,
             autopad_comments: true,
-            pad_self_closing: true
+            pad_self_closing: true,
+            invalid_character_policy: InvalidCharacterPolicy::Error,
+            write_bom: false,
+            autoflush: false,
+            attribute_quote: QuoteStyle::Double,
+            namespace_conflict_policy: NamespaceConflictPolicy::Rebind,
+            max_line_length: None,
+            wrapped_attribute_alignment: AttributeAlignment::ExtraIndent,
+            wrapped_tag_end: TagEndPlacement::AfterLastAttribute,
+            sort_attributes: false,
+            sort_attributes_by_namespace: false,
+            canonical: false,
+            allow_fragments: false,
+            extra_pcdata_escapes: Cow::Borrowed(&[]),
+            extra_attribute_escapes: Cow::Borrowed(&[]),
+            normalize_newlines: false,
+            #[cfg(feature = "encodings")]
+            output_encoding: encodings::UTF_8,
+            #[cfg(feature = "c14n")]
+            canonicalization: None
         }
     }
 
@@ -148,10 +566,42 @@ gen_setters!(EmitterConfig,
     line_separator: into Cow<'static, str>,
     indent_string: into Cow<'static, str>,
     perform_indent: val bool,
+    inline_elements: val HashSet<String>,
+    max_indent_depth: into Option<usize>,
     write_document_declaration: val bool,
+    xml_version: val XmlVersion,
+    standalone: into Option<bool>,
+    declare_encoding: val bool,
+    declare_standalone: val bool,
     normalize_empty_elements: val bool,
     cdata_to_characters: val bool,
+    characters_to_cdata: val bool,
     keep_element_names_stack: val bool,
     autopad_comments: val bool,
-    pad_self_closing: val bool
+    pad_self_closing: val bool,
+    invalid_character_policy: val InvalidCharacterPolicy,
+    write_bom: val bool,
+    autoflush: val bool,
+    attribute_quote: val QuoteStyle,
+    namespace_conflict_policy: val NamespaceConflictPolicy,
+    max_line_length: into Option<usize>,
+    wrapped_attribute_alignment: val AttributeAlignment,
+    wrapped_tag_end: val TagEndPlacement,
+    sort_attributes: val bool,
+    sort_attributes_by_namespace: val bool,
+    canonical: val bool,
+    allow_fragments: val bool,
+    extra_pcdata_escapes: into Cow<'static, [(char, &'static str)]>,
+    extra_attribute_escapes: into Cow<'static, [(char, &'static str)]>,
+    normalize_newlines: val bool
+);
+
+#[cfg(feature = "encodings")]
+gen_setters!(EmitterConfig,
+    output_encoding: val &'static Encoding
+);
+
+#[cfg(feature = "c14n")]
+gen_setters!(EmitterConfig,
+    canonicalization: into Option<Canonicalization>
 );
@@ -1,6 +1,8 @@
 use std::io;
 use std::io::prelude::*;
 use std::fmt;
+use std::mem;
+use std::str;
 use std::result;
 use std::borrow::Cow;
 use std::error::Error;
@@ -8,11 +10,17 @@ use std::error::Error;
 use common;
 use name::{Name, OwnedName};
 use attribute::Attribute;
-use escape::{escape_str_attribute, escape_str_pcdata};
-use common::XmlVersion;
-use namespace::{NamespaceStack, NS_NO_PREFIX, NS_EMPTY_URI, NS_XMLNS_PREFIX, NS_XML_PREFIX};
-
-use writer::config::EmitterConfig;
+use escape::{escape_str_attribute_quoted, escape_str_pcdata, escape_str_comment};
+use escape::{escape_str_attribute_quoted_with_extra, escape_str_pcdata_with_extra};
+use escape::{escape_str_canonical_attribute, escape_str_canonical_pcdata};
+use common::{XmlVersion, XmlSpace, InvalidCharacterPolicy};
+use namespace::{NamespaceStack, NS_NO_PREFIX, NS_EMPTY_URI, NS_XMLNS_PREFIX, NS_XML_PREFIX, NS_XML_URI};
+
+use writer::config::{
+    EmitterConfig, NamespaceConflictPolicy, AttributeAlignment, TagEndPlacement
+};
+#[cfg(feature = "encodings")]
+use encodings;
 
 /// An error which may be returned by `XmlWriter` when writing XML events.
 #[derive(Debug)]
@@ -28,11 +36,83 @@ pub enum EmitterError {
 
     /// The name of the last opening element is not equal to the name of the provided
     /// closing element.
-    EndElementNameIsNotEqualToLastStartElementName,
+    EndElementNameIsNotEqualToLastStartElementName {
+        /// The name of the innermost open element.
+        expected: String,
+        /// The name of the closing element.
+        found: String,
+        /// The number of open elements.
+        depth: usize
+    },
 
     /// End element name is not specified when it is needed, for example, when automatic
     /// closing is not enabled in configuration.
-    EndElementNameIsNotSpecified
+    EndElementNameIsNotSpecified,
+
+    /// An end element is written when there are no open elements.
+    UnexpectedEndElement,
+
+    /// The content of a comment contains `--` or ends with `-`, and `autopad_comments`
+    /// is disabled in configuration.
+    InvalidComment,
+
+    /// A CDATA section is written outside of the root element, and `allow_fragments`
+    /// is disabled in configuration.
+    CDataOutsideOfRootElement,
+
+    /// Character data which is not whitespace is written outside of the root element, and
+    /// `allow_fragments` is disabled in configuration.
+    CharactersOutsideOfRootElement,
+
+    /// An element is written after the root element was closed, and `allow_fragments`
+    /// is disabled in configuration. Contains the name of the element.
+    MultipleRootElements(String),
+
+    /// An element has two attributes with the same qualified name, or with the same local
+    /// name and namespace URI. Contains the name of the attribute and the name of the element.
+    DuplicateAttribute(String, String),
+
+    /// An entity reference is written outside of the root element, and `allow_fragments`
+    /// is disabled in configuration.
+    EntityReferenceOutsideOfRootElement,
+
+    /// The content contains a character which is not allowed in XML documents.
+    InvalidCharacter(char),
+
+    /// The target of a processing instruction is not a valid name, or it is reserved,
+    /// like `xml`.
+    InvalidProcessingInstructionName(String),
+
+    /// The data of a processing instruction contains `?>`.
+    InvalidProcessingInstructionData,
+
+    /// Document type declaration has already been written to the output stream.
+    DoctypeAlreadyEmitted,
+
+    /// Document type declaration is written after the root element was started.
+    DoctypeAfterRootElement,
+
+    /// Document type declaration is malformed; the string describes the problem.
+    InvalidDoctype(String),
+
+    /// Raw content is written before the document was started.
+    RawBeforeDocumentStart,
+
+    /// The prefix of a written name is bound to another namespace URI, and
+    /// `namespace_conflict_policy` is `NamespaceConflictPolicy::Error`.
+    NamespacePrefixConflict(String),
+
+    /// A character which cannot be represented in the output encoding is written where
+    /// character references are not allowed, e.g. in a name or a comment.
+    UnencodableCharacter(char),
+
+    /// The name of an element, of an attribute or of an entity reference is not a valid
+    /// XML name.
+    InvalidName(String),
+
+    /// The writer is finished while there are open elements. Contains the names of the open
+    /// elements, outermost first, if `keep_element_names_stack` is enabled.
+    UnclosedElements(Vec<String>)
 }
 
 impl From<io::Error> for EmitterError {
@@ -48,6 +128,27 @@ impl fmt::Display for EmitterError {
         match *self {
             EmitterError::Io(ref e) =>
                 write!(f, "I/O error: {}", e),
+            EmitterError::InvalidCharacter(c) =>
+                write!(f, "character U+{:04X} is not allowed in XML documents", c as u32),
+            EmitterError::UnencodableCharacter(c) =>
+                write!(f, "character U+{:04X} cannot be represented in the output encoding", c as u32),
+            EmitterError::InvalidName(ref name) =>
+                write!(f, "invalid name: {:?}", name),
+            EmitterError::InvalidProcessingInstructionName(ref name) =>
+                write!(f, "invalid processing instruction name: {:?}", name),
+            EmitterError::InvalidDoctype(ref problem) =>
+                write!(f, "invalid document type declaration: {}", problem),
+            EmitterError::NamespacePrefixConflict(ref prefix) =>
+                write!(f, "namespace prefix {:?} is already bound to another URI", prefix),
+            EmitterError::EndElementNameIsNotEqualToLastStartElementName { ref expected, ref found, depth } =>
+                write!(f, "end element name {} is not equal to last start element name {} at depth {}",
+                       found, expected, depth),
+            EmitterError::MultipleRootElements(ref name) =>
+                write!(f, "element {} is written after the root element", name),
+            EmitterError::DuplicateAttribute(ref attribute, ref element) =>
+                write!(f, "attribute {} is written twice in element {}", attribute, element),
+            EmitterError::UnclosedElements(ref names) if !names.is_empty() =>
+                write!(f, "document is finished with unclosed elements: {}", names.join(", ")),
             ref other =>
                 write!(f, "{}", other.description()),
         }
@@ -63,10 +164,46 @@ impl Error for EmitterError {
                 "document start event has already been emitted",
             EmitterError::LastElementNameNotAvailable =>
                 "last element name is not available",
-            EmitterError::EndElementNameIsNotEqualToLastStartElementName =>
+            EmitterError::EndElementNameIsNotEqualToLastStartElementName { .. } =>
                 "end element name is not equal to last start element name",
             EmitterError::EndElementNameIsNotSpecified =>
                 "end element name is not specified and can't be inferred",
+            EmitterError::UnexpectedEndElement =>
+                "end element is written but there are no open elements",
+            EmitterError::InvalidComment =>
+                "comment contains '--' or ends with '-'",
+            EmitterError::CDataOutsideOfRootElement =>
+                "CDATA section is written outside of the root element",
+            EmitterError::CharactersOutsideOfRootElement =>
+                "character data is written outside of the root element",
+            EmitterError::MultipleRootElements(_) =>
+                "element is written after the root element",
+            EmitterError::DuplicateAttribute(..) =>
+                "attribute is written twice in an element",
+            EmitterError::EntityReferenceOutsideOfRootElement =>
+                "entity reference is written outside of the root element",
+            EmitterError::InvalidCharacter(_) =>
+                "character is not allowed in XML documents",
+            EmitterError::InvalidProcessingInstructionName(_) =>
+                "processing instruction name is not a valid name or is reserved",
+            EmitterError::InvalidProcessingInstructionData =>
+                "processing instruction data contains '?>'",
+            EmitterError::DoctypeAlreadyEmitted =>
+                "document type declaration has already been emitted",
+            EmitterError::DoctypeAfterRootElement =>
+                "document type declaration is emitted after the root element",
+            EmitterError::InvalidDoctype(_) =>
+                "document type declaration is invalid",
+            EmitterError::RawBeforeDocumentStart =>
+                "raw content is written before the document start",
+            EmitterError::NamespacePrefixConflict(_) =>
+                "namespace prefix is already bound to another URI",
+            EmitterError::UnencodableCharacter(_) =>
+                "character cannot be represented in the output encoding",
+            EmitterError::InvalidName(_) =>
+                "name is not a valid XML name",
+            EmitterError::UnclosedElements(_) =>
+                "document is finished with unclosed elements",
         }
     }
 }
@@ -83,27 +220,58 @@ pub struct Emitter {
 
     indent_level: usize,
     indent_stack: Vec<IndentFlags>,
+    // xml:space in effect for every open element
+    space_stack: Vec<XmlSpace>,
 
     element_names: Vec<OwnedName>,
-
+    // prefixes which the open elements were written with, if they differ from their names
+    element_prefixes: Vec<Option<String>>,
+    // namespace mappings which the next start element must declare
+    forced_namespaces: Vec<(String, String)>,
+    // namespace declarations written by the open elements in canonicalization mode
+    #[cfg(feature = "c14n")]
+    rendered_namespaces: NamespaceStack,
+
+    // the version of the document, as declared or configured
+    version: XmlVersion,
+
+    bom_emitted: bool,
     start_document_emitted: bool,
-    just_wrote_start_element: bool
+    doctype_emitted: bool,
+    root_element_started: bool,
+    just_wrote_start_element: bool,
+    start_tag_wrapped: bool,
+
+    // recoverable problems noted since the last call of `take_warnings()`
+    warnings: Vec<EmitterError>
 }
 
 impl Emitter {
     pub fn new(config: EmitterConfig) -> Emitter {
         Emitter {
+            version: config.xml_version,
             config,
 
             nst: NamespaceStack::empty(),
 
             indent_level: 0,
             indent_stack: vec![IndentFlags::WroteNothing],
+            space_stack: Vec::new(),
 
             element_names: Vec::new(),
+            element_prefixes: Vec::new(),
+            forced_namespaces: Vec::new(),
+            #[cfg(feature = "c14n")]
+            rendered_namespaces: NamespaceStack::empty(),
 
+            bom_emitted: false,
             start_document_emitted: false,
-            just_wrote_start_element: false
+            doctype_emitted: false,
+            root_element_started: false,
+            just_wrote_start_element: false,
+            start_tag_wrapped: false,
+
+            warnings: Vec::new()
         }
     }
 }
@@ -122,6 +290,37 @@ impl Emitter {
         &mut self.nst
     }
 
+    /// Returns the number of open elements.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.indent_level
+    }
+
+    /// Returns the warnings noted so far, clearing the list.
+    #[inline]
+    pub fn take_warnings(&mut self) -> Vec<EmitterError> {
+        mem::take(&mut self.warnings)
+    }
+
+    /// Returns the version of the document.
+    #[inline]
+    pub fn xml_version(&self) -> XmlVersion {
+        self.version
+    }
+
+    /// Returns the names of the open elements, outermost first.
+    #[inline]
+    pub fn element_names(&self) -> &[OwnedName] {
+        &self.element_names
+    }
+
+    /// Notes that the content of the current element is mixed, so that no indentation
+    /// is written in it from now on, as if text had been written.
+    #[inline]
+    pub fn set_mixed_content(&mut self) {
+        self.set_wrote_text();
+    }
+
     #[inline]
     fn wrote_text(&self) -> bool {
         *self.indent_stack.last().unwrap() == IndentFlags::WroteText
@@ -137,9 +336,14 @@ impl Emitter {
         *self.indent_stack.last_mut().unwrap() = IndentFlags::WroteText;
     }
 
+    /// Notes that markup was written at the current level, unless text was written there
+    /// before; once an element contains text, its content is mixed and is not indented anymore.
     #[inline]
     fn set_wrote_markup(&mut self) {
-        *self.indent_stack.last_mut().unwrap() = IndentFlags::WroteMarkup;
+        let flags = self.indent_stack.last_mut().unwrap();
+        if *flags != IndentFlags::WroteText {
+            *flags = IndentFlags::WroteMarkup;
+        }
     }
 
     #[inline]
@@ -155,8 +359,15 @@ impl Emitter {
         Ok(())
     }
 
+    /// Checks whether `xml:space="preserve"` is in effect for the current element, so
+    /// no whitespace may be added or removed.
+    #[inline]
+    fn preserving_space(&self) -> bool {
+        self.space_stack.last() == Some(&XmlSpace::Preserve)
+    }
+
     fn before_markup<W: Write>(&mut self, target: &mut W) -> Result<()> {
-        if self.config.perform_indent && !self.wrote_text() &&
+        if self.config.perform_indent && !self.wrote_text() && !self.preserving_space() &&
            (self.indent_level > 0 || self.wrote_markup()) {
             let indent_level = self.indent_level;
             self.write_newline(target, indent_level)?;
@@ -171,9 +382,15 @@ impl Emitter {
         self.set_wrote_markup();
     }
 
-    fn before_start_element<W: Write>(&mut self, target: &mut W) -> Result<()> {
+    fn before_start_element<W: Write>(&mut self, target: &mut W, name: Name) -> Result<()> {
         self.before_markup(target)?;
-        self.indent_stack.push(IndentFlags::WroteNothing);
+        // whitespace inside children of an element with mixed content would become a part
+        // of its text, so indentation is suppressed for them as well; the content of inline
+        // elements and of elements at the maximum indentation depth is treated as mixed from the start
+        let inline = self.config.inline_elements.contains(name.local_name) ||
+                     self.config.max_indent_depth.is_some_and(|depth| self.indent_level >= depth);
+        let flags = if inline || self.wrote_text() { IndentFlags::WroteText } else { IndentFlags::WroteNothing };
+        self.indent_stack.push(flags);
         Ok(())
     }
 
@@ -184,7 +401,7 @@ impl Emitter {
 
     fn before_end_element<W: Write>(&mut self, target: &mut W) -> Result<()> {
         if self.config.perform_indent && self.indent_level > 0 && self.wrote_markup() &&
-           !self.wrote_text() {
+           !self.wrote_text() && !self.preserving_space() {
             let indent_level = self.indent_level;
             self.write_newline(target, indent_level - 1)
         } else {
@@ -196,6 +413,7 @@ impl Emitter {
         if self.indent_level > 0 {
             self.indent_level -= 1;
             self.indent_stack.pop();
+            self.space_stack.pop();
         }
         self.set_wrote_markup();
     }
@@ -204,6 +422,62 @@ impl Emitter {
         self.set_wrote_text();
     }
 
+    /// Checks whether the output encoding is able to represent all of Unicode.
+    #[cfg(feature = "encodings")]
+    fn unicode_output(&self) -> bool {
+        encodings::is_unicode(self.config.output_encoding)
+    }
+
+    #[cfg(not(feature = "encodings"))]
+    fn unicode_output(&self) -> bool {
+        true
+    }
+
+    /// Checks that the given content, which is written where character references are
+    /// not allowed, can be represented in the output encoding.
+    #[cfg(feature = "encodings")]
+    fn check_encodable(&self, content: &str) -> Result<()> {
+        match encodings::find_unencodable(self.config.output_encoding, content) {
+            Some(c) => Err(EmitterError::UnencodableCharacter(c)),
+            None => Ok(())
+        }
+    }
+
+    #[cfg(not(feature = "encodings"))]
+    fn check_encodable(&self, content: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn check_name_encodable(&self, name: Name) -> Result<()> {
+        self.check_encodable(name.local_name)?;
+        self.check_encodable(name.prefix.unwrap_or(NS_NO_PREFIX))
+    }
+
+    /// Returns the encoding name which should be written in the document declaration.
+    #[cfg(feature = "encodings")]
+    fn declared_encoding<'a>(&self, requested: &'a str) -> &'a str {
+        if self.config.output_encoding == encodings::UTF_8 {
+            requested
+        } else {
+            self.config.output_encoding.name()
+        }
+    }
+
+    #[cfg(not(feature = "encodings"))]
+    fn declared_encoding<'a>(&self, requested: &'a str) -> &'a str {
+        requested
+    }
+
+    fn check_bom_emitted<W: Write>(&mut self, target: &mut W) -> Result<()> {
+        if self.config.write_bom && !self.bom_emitted && self.unicode_output() {
+            self.bom_emitted = true;
+            // the output is converted to the target encoding afterwards, if necessary,
+            // so U+FEFF in UTF-8 becomes the correct byte order mark
+            target.write_all(b"\xEF\xBB\xBF")?;
+        }
+        Ok(())
+    }
+
     pub fn emit_start_document<W: Write>(&mut self, target: &mut W,
                                          version: XmlVersion,
                                          encoding: &str,
@@ -212,11 +486,23 @@ impl Emitter {
             return Err(EmitterError::DocumentStartAlreadyEmitted);
         }
         self.start_document_emitted = true;
+        self.version = version;
+        self.check_bom_emitted(target)?;
+        // the canonical form has no XML declaration either
+        if !self.config.write_document_declaration || self.config.canonical {
+            return Ok(());
+        }
+        let encoding = Some(self.declared_encoding(encoding)).filter(|_| self.config.declare_encoding);
+        let standalone = standalone.filter(|_| self.config.declare_standalone);
 
         self.before_markup(target)?;
         let result = {
             let mut write = move || {
-                write!(target, "<?xml version=\"{}\" encoding=\"{}\"", version, encoding)?;
+                write!(target, "<?xml version=\"{}\"", version)?;
+
+                if let Some(encoding) = encoding {
+                    write!(target, " encoding=\"{}\"", encoding)?;
+                }
 
                 if let Some(standalone) = standalone {
                     write!(target, " standalone=\"{}\"", if standalone { "yes" } else { "no" })?;
@@ -235,31 +521,112 @@ impl Emitter {
 
     fn check_document_started<W: Write>(&mut self, target: &mut W) -> Result<()> {
         if !self.start_document_emitted && self.config.write_document_declaration {
-            self.emit_start_document(target, common::XmlVersion::Version10, "utf-8", None)
+            let (version, standalone) = (self.config.xml_version, self.config.standalone);
+            self.emit_start_document(target, version, "UTF-8", standalone)
         } else {
-            Ok(())
+            self.check_bom_emitted(target)
         }
     }
 
     fn fix_non_empty_element<W: Write>(&mut self, target: &mut W) -> Result<()> {
         if self.config.normalize_empty_elements && self.just_wrote_start_element {
             self.just_wrote_start_element = false;
-            target.write_all(b">").map_err(From::from)
+            self.write_start_tag_end(target, ">")
         } else {
             Ok(())
         }
     }
 
+    /// Writes the end of the last start tag if it is still pending, so that anything written
+    /// to the output stream afterwards becomes the content of the element.
+    #[inline]
+    pub fn close_start_tag<W: Write>(&mut self, target: &mut W) -> Result<()> {
+        self.fix_non_empty_element(target)
+    }
+
+    /// Writes the end of the last start tag, moving it to its own line if the tag was wrapped
+    /// and the configuration requires so.
+    fn write_start_tag_end<W: Write>(&mut self, target: &mut W, end: &str) -> Result<()> {
+        if self.start_tag_wrapped {
+            self.start_tag_wrapped = false;
+            if self.config.wrapped_tag_end == TagEndPlacement::OwnLine {
+                let indent_level = self.indent_level - 1;
+                self.write_newline(target, indent_level)?;
+                return target.write_all(end.trim_start().as_bytes()).map_err(From::from);
+            }
+        }
+        target.write_all(end.as_bytes()).map_err(From::from)
+    }
+
+    pub fn emit_doctype<W: Write>(&mut self, target: &mut W,
+                                  name: &str,
+                                  public_id: Option<&str>,
+                                  system_id: Option<&str>,
+                                  internal_subset: Option<&str>) -> Result<()> {
+        if self.doctype_emitted {
+            return Err(EmitterError::DoctypeAlreadyEmitted);
+        }
+        if self.root_element_started {
+            return Err(EmitterError::DoctypeAfterRootElement);
+        }
+        if !name.starts_with(common::is_name_start_char) || !name.chars().all(common::is_name_char) {
+            return Err(EmitterError::InvalidDoctype(format!("invalid root element name {:?}", name)));
+        }
+        if public_id.is_some() && system_id.is_none() {
+            return Err(EmitterError::InvalidDoctype("public identifier without system identifier".into()));
+        }
+        for part in [Some(name), public_id, system_id, internal_subset].iter().flatten() {
+            self.check_encodable(part)?;
+        }
+        let public_quote = public_id.map(quote_for_literal).unwrap_or(Ok('"'))?;
+        let system_quote = system_id.map(quote_for_literal).unwrap_or(Ok('"'))?;
+
+        self.check_document_started(target)?;
+        self.doctype_emitted = true;
+        if self.config.canonical {
+            // the canonical form has no document type declaration
+            return Ok(());
+        }
+        self.before_markup(target)?;
+
+        write!(target, "<!DOCTYPE {}", name)?;
+        match (public_id, system_id) {
+            (Some(public_id), Some(system_id)) =>
+                write!(target, " PUBLIC {q}{}{q} {s}{}{s}", public_id, system_id, q = public_quote, s = system_quote)?,
+            (None, Some(system_id)) =>
+                write!(target, " SYSTEM {s}{}{s}", system_id, s = system_quote)?,
+            _ => {}
+        }
+        if let Some(internal_subset) = internal_subset {
+            write!(target, " [{}]", internal_subset)?;
+        }
+        target.write_all(b">")?;
+
+        self.after_markup();
+        Ok(())
+    }
+
     pub fn emit_processing_instruction<W: Write>(&mut self,
                                                  target: &mut W,
                                                  name: &str,
                                                  data: Option<&str>) -> Result<()> {
+        let valid_name = name.starts_with(common::is_name_start_char) && name.chars().all(common::is_name_char);
+        if !valid_name || name.eq_ignore_ascii_case("xml") {
+            return Err(EmitterError::InvalidProcessingInstructionName(name.into()));
+        }
+        if matches!(data, Some(data) if data.contains("?>")) {
+            return Err(EmitterError::InvalidProcessingInstructionData);
+        }
+        self.check_encodable(name)?;
+        self.check_encodable(data.unwrap_or(""))?;
+
         self.check_document_started(target)?;
         self.fix_non_empty_element(target)?;
 
         self.before_markup(target)?;
+        self.before_top_level_node(target)?;
 
-        let result = {
+        let result: Result<()> = {
             let mut write = || {
                 write!(target, "<?{}", name)?;
 
@@ -273,10 +640,28 @@ impl Emitter {
             };
             write()
         };
+        result?;
 
         self.after_markup();
+        self.after_top_level_node(target)
+    }
 
-        result
+    /// Separates a comment or a processing instruction after the root element from it with
+    /// a line feed in canonical mode.
+    fn before_top_level_node<W: Write>(&mut self, target: &mut W) -> Result<()> {
+        if self.config.canonical && self.indent_level == 0 && self.root_element_started {
+            target.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Separates a comment or a processing instruction before the root element from it with
+    /// a line feed in canonical mode.
+    fn after_top_level_node<W: Write>(&mut self, target: &mut W) -> Result<()> {
+        if self.config.canonical && self.indent_level == 0 && !self.root_element_started {
+            target.write_all(b"\n")?;
+        }
+        Ok(())
     }
 
     fn emit_start_element_initial<W>(&mut self, target: &mut W,
@@ -284,30 +669,154 @@ impl Emitter {
                                      attributes: &[Attribute]) -> Result<()>
         where W: Write
     {
+        // namespace bindings must be known before anything is written; they only change
+        // the scope of this element, which is discarded if they conflict
+        for (prefix, uri) in &self.forced_namespaces {
+            self.nst.put(prefix.clone(), uri.clone());
+        }
+        let prefix = self.resolve_prefix(name, false)?;
+        let attribute_prefixes = if attributes.iter().any(|a| a.name.namespace.is_some()) {
+            attributes.iter().map(|a| self.resolve_prefix(a.name, true)).collect::<Result<Vec<_>>>()?
+        } else {
+            Vec::new()
+        };
+        self.forced_namespaces.clear();
+
         self.check_document_started(target)?;
         self.fix_non_empty_element(target)?;
-        self.before_start_element(target)?;
-        write!(target, "<{}", name.repr_display())?;
-        self.emit_current_namespace_attributes(target)?;
-        self.emit_attributes(target, attributes)?;
+        self.before_start_element(target, name)?;
+
+        target.write_all(b"<")?;
+        if self.config.perform_indent && self.config.max_line_length.is_some() {
+            let mut written_name = Vec::new();
+            write_name(&mut written_name, name, prefix.as_ref().map(|p| &p[..]))?;
+            target.write_all(&written_name)?;
+            let name_length = str::from_utf8(&written_name).unwrap().chars().count();
+            self.emit_wrapped_attributes(target, name_length, attributes, &attribute_prefixes)?;
+        } else {
+            write_name(target, name, prefix.as_ref().map(|p| &p[..]))?;
+            #[cfg(feature = "c14n")]
+            {
+                if self.config.canonicalization.is_some() {
+                    self.emit_canonical_namespace_attributes(target, name, prefix.as_ref().map(|p| &p[..]),
+                                                             attributes, &attribute_prefixes)?;
+                } else {
+                    self.emit_current_namespace_attributes(target)?;
+                }
+            }
+            #[cfg(not(feature = "c14n"))]
+            self.emit_current_namespace_attributes(target)?;
+            self.emit_attributes(target, attributes, &attribute_prefixes)?;
+        }
+        self.element_prefixes.push(prefix);
+
+        let inherited = self.space_stack.last().cloned().unwrap_or(XmlSpace::Default);
+        let space = attributes.iter()
+            .find(|a| a.name.local_name == "space" &&
+                      (a.name.prefix == Some(NS_XML_PREFIX) || a.name.namespace == Some(NS_XML_URI)))
+            .and_then(|a| XmlSpace::from_attribute_value(a.value))
+            .unwrap_or(inherited);
+        self.space_stack.push(space);
+
         self.after_start_element();
         Ok(())
     }
 
+    /// Makes sure that the namespace of the given name is bound to the prefix it is written
+    /// with, declaring it in the current element if necessary.
+    ///
+    /// Returns the prefix which the name must be written with if it differs from the prefix
+    /// of the name; an empty prefix means that the name must be written without a prefix.
+    /// Names without a namespace are written as they are.
+    fn resolve_prefix(&mut self, name: Name, is_attribute: bool) -> Result<Option<String>> {
+        let uri = match name.namespace {
+            Some(uri) if uri != NS_EMPTY_URI => uri,
+            _ => return Ok(None)
+        };
+        Ok(match name.prefix {
+            // reserved prefixes are always bound
+            Some(NS_XML_PREFIX) | Some(NS_XMLNS_PREFIX) => None,
+            // the default namespace does not apply to attributes
+            Some(prefix) if !(is_attribute && prefix == NS_NO_PREFIX) => {
+                if self.nst.get(prefix) == Some(uri) {
+                    None
+                } else if self.nst.get(prefix).is_none() {
+                    self.nst.put(prefix, uri);
+                    None
+                } else {
+                    match self.config.namespace_conflict_policy {
+                        NamespaceConflictPolicy::Error =>
+                            return Err(EmitterError::NamespacePrefixConflict(prefix.into())),
+                        // the prefix is bound to another URI in an ancestor
+                        NamespaceConflictPolicy::Rebind if !self.nst.peek().contains(prefix) => {
+                            self.nst.put(prefix, uri);
+                            None
+                        }
+                        // the prefix is bound to another URI in this very element
+                        _ => Some(self.bind_generated_prefix(uri))
+                    }
+                }
+            }
+            _ if !is_attribute && self.nst.get(NS_NO_PREFIX) == Some(uri) => None,
+            _ => {
+                let nst = &self.nst;
+                let existing = nst.iter()
+                    .find(|&(p, u)| p != NS_NO_PREFIX && u == uri)
+                    .map(|(p, _)| p.to_owned());
+                Some(existing.unwrap_or_else(|| self.bind_generated_prefix(uri)))
+            }
+        })
+    }
+
+    pub fn force_namespace_declaration(&mut self, prefix: String, uri: String) {
+        self.forced_namespaces.push((prefix, uri));
+    }
+
+    /// Binds the given URI to a new prefix of the form `nsN` which is not used in scope.
+    fn bind_generated_prefix(&mut self, uri: &str) -> String {
+        let nst = &self.nst;
+        let prefix = (0..).map(|i| format!("ns{}", i)).find(|p| nst.get(p).is_none()).unwrap();
+        self.nst.put(prefix.clone(), uri);
+        prefix
+    }
+
+    /// Checks that an element with the given name and attributes may be written at this point,
+    /// before anything is written or the state is changed.
+    pub fn check_start_element(&self, name: Name, attributes: &[Attribute]) -> Result<()> {
+        if self.indent_level == 0 && self.root_element_started && !self.config.allow_fragments {
+            return Err(EmitterError::MultipleRootElements(name.to_repr()));
+        }
+        check_name(name)?;
+        self.check_name_encodable(name)?;
+        for (i, attr) in attributes.iter().enumerate() {
+            check_name(attr.name)?;
+            self.check_name_encodable(attr.name)?;
+            let duplicate = attributes[..i].iter().any(|other| {
+                other.name.local_name == attr.name.local_name &&
+                    (other.name.prefix == attr.name.prefix ||
+                     other.name.namespace.is_some() && other.name.namespace == attr.name.namespace)
+            });
+            if duplicate {
+                return Err(EmitterError::DuplicateAttribute(attr.name.to_repr(), name.to_repr()));
+            }
+        }
+        Ok(())
+    }
+
     pub fn emit_start_element<W>(&mut self, target: &mut W,
                                  name: Name,
                                  attributes: &[Attribute]) -> Result<()>
         where W: Write
     {
+        self.emit_start_element_initial(target, name, attributes)?;
         if self.config.keep_element_names_stack {
             self.element_names.push(name.to_owned());
         }
-
-        self.emit_start_element_initial(target, name, attributes)?;
+        self.root_element_started = true;
         self.just_wrote_start_element = true;
 
         if !self.config.normalize_empty_elements {
-            write!(target, ">")?;
+            self.write_start_tag_end(target, ">")?;
         }
 
         Ok(())
@@ -317,63 +826,232 @@ impl Emitter {
         where W: Write
     {
         for (prefix, uri) in self.nst.peek() {
-            match prefix {
-                // internal namespaces are not emitted
-                NS_XMLNS_PREFIX | NS_XML_PREFIX => Ok(()),
-                //// there is already a namespace binding with this prefix in scope
-                //prefix if self.nst.get(prefix) == Some(uri) => Ok(()),
-                // emit xmlns only if it is overridden
-                NS_NO_PREFIX => if uri != NS_EMPTY_URI {
-                    write!(target, " xmlns=\"{}\"", uri)
-                } else { Ok(()) },
-                // everything else
-                prefix => write!(target, " xmlns:{}=\"{}\"", prefix, uri)
-            }?;
+            let quote = self.config.attribute_quote.quote_for(uri);
+            write_namespace_attribute(target, prefix, uri, quote)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the namespace declarations required by the canonicalization method at the start
+    /// element being written, given the prefixes which the element and the attributes are
+    /// written with.
+    #[cfg(feature = "c14n")]
+    fn emit_canonical_namespace_attributes<W: Write>(&mut self, target: &mut W,
+                                                     name: Name, prefix: Option<&str>,
+                                                     attributes: &[Attribute],
+                                                     attribute_prefixes: &[Option<String>]) -> Result<()> {
+        use std::collections::BTreeSet;
+        use writer::config::Canonicalization;
+
+        // the default namespace can only be undeclared, so it is always a candidate
+        let mut prefixes = BTreeSet::new();
+        prefixes.insert(NS_NO_PREFIX.to_owned());
+        match self.config.canonicalization {
+            Some(Canonicalization::Exclusive) => {
+                let written_prefix = |name: Name<'_>, prefix: Option<&str>| prefix.or(name.prefix).unwrap_or(NS_NO_PREFIX).to_owned();
+                prefixes.insert(written_prefix(name, prefix));
+                for (i, attr) in attributes.iter().enumerate() {
+                    let prefix = attribute_prefixes.get(i).and_then(|p| p.as_ref()).map(|p| &p[..]);
+                    let prefix = written_prefix(attr.name, prefix);
+                    // unprefixed attributes are in no namespace at all
+                    if prefix != NS_NO_PREFIX {
+                        prefixes.insert(prefix);
+                    }
+                }
+            }
+            _ => prefixes.extend((&self.nst).into_iter().map(|(p, _)| p.to_owned()))
+        }
+
+        self.rendered_namespaces.push_empty();
+        for prefix in prefixes {
+            if prefix == NS_XML_PREFIX || prefix == NS_XMLNS_PREFIX {
+                continue;
+            }
+            let uri = self.nst.get(&prefix).unwrap_or(NS_EMPTY_URI).to_owned();
+            if self.rendered_namespaces.get(&prefix).unwrap_or(NS_EMPTY_URI) == uri {
+                continue;
+            }
+            if prefix == NS_NO_PREFIX {
+                write!(target, " xmlns=\"{}\"", escape_str_canonical_attribute(&uri))?;
+            } else {
+                write!(target, " xmlns:{}=\"{}\"", prefix, escape_str_canonical_attribute(&uri))?;
+            }
+            self.rendered_namespaces.put(prefix, uri);
+        }
+        Ok(())
+    }
+
+    /// Writes attributes of a start tag, wrapping them if the tag does not fit
+    /// into `max_line_length`.
+    fn emit_wrapped_attributes<W: Write>(&mut self, target: &mut W,
+                                         name_length: usize,
+                                         attributes: &[Attribute],
+                                         prefixes: &[Option<String>]) -> Result<()> {
+        // every part starts with a space separating it from the previous one
+        let mut parts = Vec::new();
+        for (prefix, uri) in self.nst.peek() {
+            let mut part = Vec::new();
+            write_namespace_attribute(&mut part, prefix, uri, self.config.attribute_quote.quote_for(uri))?;
+            if !part.is_empty() {
+                parts.push(part);
+            }
+        }
+        for i in self.attribute_order(attributes, prefixes) {
+            let mut part = Vec::new();
+            self.emit_attribute(&mut part, &attributes[i], prefixes.get(i).and_then(|p| p.as_ref()).map(|p| &p[..]))?;
+            parts.push(part);
+        }
+
+        let indent = self.indent_level * self.config.indent_string.chars().count();
+        let length = indent + 1 + name_length + 1 +
+            parts.iter().map(|p| str::from_utf8(p).unwrap().chars().count()).sum::<usize>();
+        if parts.is_empty() || length <= self.config.max_line_length.unwrap_or(usize::MAX) {
+            for part in &parts {
+                target.write_all(part)?;
+            }
+            return Ok(());
+        }
+
+        self.start_tag_wrapped = true;
+        match self.config.wrapped_attribute_alignment {
+            AttributeAlignment::FirstAttribute => {
+                target.write_all(&parts[0])?;
+                let column = indent + 1 + name_length + 1;
+                for part in &parts[1..] {
+                    target.write_all(self.config.line_separator.as_bytes())?;
+                    for _ in 0..column {
+                        target.write_all(b" ")?;
+                    }
+                    target.write_all(&part[1..])?;
+                }
+            }
+            AttributeAlignment::ExtraIndent => {
+                let indent_level = self.indent_level + 1;
+                for part in &parts {
+                    self.write_newline(target, indent_level)?;
+                    target.write_all(&part[1..])?;
+                }
+            }
         }
         Ok(())
     }
 
     pub fn emit_attributes<W: Write>(&mut self, target: &mut W,
-                                      attributes: &[Attribute]) -> Result<()> {
-        for attr in attributes.iter() {
-            write!(
-                target, " {}=\"{}\"",
-                attr.name.repr_display(),
-                if self.config.perform_escaping { escape_str_attribute(attr.value) } else { Cow::Borrowed(attr.value) }
-            )?
+                                      attributes: &[Attribute],
+                                      prefixes: &[Option<String>]) -> Result<()> {
+        if !self.config.canonical && !self.config.sort_attributes && !self.config.sort_attributes_by_namespace {
+            for (i, attr) in attributes.iter().enumerate() {
+                self.emit_attribute(target, attr, prefixes.get(i).and_then(|p| p.as_ref()).map(|p| &p[..]))?;
+            }
+            return Ok(());
+        }
+        for i in self.attribute_order(attributes, prefixes) {
+            self.emit_attribute(target, &attributes[i], prefixes.get(i).and_then(|p| p.as_ref()).map(|p| &p[..]))?;
         }
         Ok(())
     }
 
-    pub fn emit_end_element<W: Write>(&mut self, target: &mut W,
-                                      name: Option<Name>) -> Result<()> {
-        let owned_name = if self.config.keep_element_names_stack {
-            Some(self.element_names.pop().ok_or(EmitterError::LastElementNameNotAvailable)?)
+    /// Returns the indices of the given attributes in the order they must be written.
+    fn attribute_order(&self, attributes: &[Attribute], prefixes: &[Option<String>]) -> Vec<usize> {
+        // in canonical mode attributes are sorted by namespace URI and then by local name
+        let mut order: Vec<usize> = (0..attributes.len()).collect();
+        if self.config.canonical || self.config.sort_attributes_by_namespace {
+            order.sort_by_key(|&i| {
+                let name = attributes[i].name;
+                (name.namespace.unwrap_or(NS_EMPTY_URI), name.local_name)
+            });
+        } else if self.config.sort_attributes {
+            // the names are compared as they are written, i.e. with the resolved prefixes
+            order.sort_by_cached_key(|&i| {
+                let name = attributes[i].name;
+                match prefixes.get(i).and_then(|p| p.as_ref()).map(|p| &p[..]).or(name.prefix) {
+                    Some(prefix) if !prefix.is_empty() => format!("{}:{}", prefix, name.local_name),
+                    _ => name.local_name.to_owned()
+                }
+            });
+        }
+        order
+    }
+
+    fn emit_attribute<W: Write>(&mut self, target: &mut W, attr: &Attribute, prefix: Option<&str>) -> Result<()> {
+        let value = self.replace_invalid_characters(attr.value)?;
+        let value = if self.config.normalize_newlines {
+            // escaping turns line feeds into references, which survive attribute value normalization
+            let separator = if self.config.perform_escaping || self.config.canonical { "\n" } else { "&#xA;" };
+            normalize_newlines(value, separator)
         } else {
-            None
+            value
         };
+        let quote = self.config.attribute_quote.quote_for(&value);
+        target.write_all(b" ")?;
+        write_name(target, attr.name, prefix)?;
+        write!(
+            target, "={}{}{}",
+            quote,
+            if self.config.canonical {
+                escape_str_canonical_attribute(&value)
+            } else if self.config.perform_escaping && !self.config.extra_attribute_escapes.is_empty() {
+                escape_str_attribute_quoted_with_extra(&value, quote, &self.config.extra_attribute_escapes)
+            } else if self.config.perform_escaping {
+                escape_str_attribute_quoted(&value, quote)
+            } else {
+                Cow::Borrowed(&value[..])
+            },
+            quote
+        ).map_err(From::from)
+    }
+
+    pub fn emit_end_element<W: Write>(&mut self, target: &mut W,
+                                      name: Option<Name>) -> Result<()> {
+        if self.indent_level == 0 {
+            return Err(EmitterError::UnexpectedEndElement);
+        }
 
-        // Check that last started element name equals to the provided name, if there are both
-        if let Some(ref last_name) = owned_name {
+        // the end element is checked before the state is changed, so that
+        // a failed write can be corrected
+        if self.config.keep_element_names_stack {
+            let last_name = self.element_names.last().ok_or(EmitterError::LastElementNameNotAvailable)?;
+            // Check that last started element name equals to the provided name, if there are both
             if let Some(ref name) = name {
-                if last_name.borrow() != *name {
-                    return Err(EmitterError::EndElementNameIsNotEqualToLastStartElementName);
+                if last_name.borrow() != *name || last_name.prefix_ref() != name.prefix {
+                    return Err(EmitterError::EndElementNameIsNotEqualToLastStartElementName {
+                        expected: last_name.borrow().to_repr(),
+                        found: name.to_repr(),
+                        depth: self.indent_level
+                    });
                 }
             }
+        } else if name.is_none() {
+            return Err(EmitterError::EndElementNameIsNotSpecified);
+        }
+
+        let prefix = self.element_prefixes.pop().and_then(|p| p);
+        #[cfg(feature = "c14n")]
+        {
+            if self.config.canonicalization.is_some() {
+                self.rendered_namespaces.try_pop();
+            }
         }
+        let owned_name = if self.config.keep_element_names_stack {
+            self.element_names.pop()
+        } else {
+            None
+        };
 
         if let Some(name) = owned_name.as_ref().map(|n| n.borrow()).or(name) {
             if self.config.normalize_empty_elements && self.just_wrote_start_element {
                 self.just_wrote_start_element = false;
                 let termination = if self.config.pad_self_closing { " />" } else { "/>" };
-                let result = target.write_all(termination.as_bytes()).map_err(From::from);
+                let result = self.write_start_tag_end(target, termination);
                 self.after_end_element();
                 result
             } else {
                 self.just_wrote_start_element = false;
 
                 self.before_end_element(target)?;
-                let result = write!(target, "</{}>", name.repr_display()).map_err(From::from);
+                let result = target.write_all(b"</").map_err(From::from)
+                    .and_then(|_| write_name(target, name, prefix.as_ref().map(|p| &p[..])))
+                    .and_then(|_| target.write_all(b">").map_err(From::from));
                 self.after_end_element();
 
                 result
@@ -383,43 +1061,164 @@ impl Emitter {
         }
     }
 
+    pub fn emit_raw<W: Write>(&mut self, target: &mut W, content: &str) -> Result<()> {
+        if !self.start_document_emitted && !self.root_element_started {
+            return Err(EmitterError::RawBeforeDocumentStart);
+        }
+        self.check_document_started(target)?;
+        self.fix_non_empty_element(target)?;
+        target.write_all(content.as_bytes())?;
+        // the content may contain text, so no indentation may be inserted around it
+        self.after_text();
+        Ok(())
+    }
+
+    /// Checks that nothing is left pending, i.e. that all elements are closed.
+    pub fn check_document_finished(&self) -> Result<()> {
+        if self.indent_level > 0 {
+            let names = self.element_names.iter().map(|name| name.borrow().to_repr()).collect();
+            return Err(EmitterError::UnclosedElements(names));
+        }
+        Ok(())
+    }
+
     pub fn emit_cdata<W: Write>(&mut self, target: &mut W, content: &str) -> Result<()> {
+        if self.indent_level == 0 && !self.config.allow_fragments {
+            return Err(EmitterError::CDataOutsideOfRootElement);
+        }
+        let content = self.replace_invalid_characters(content)?;
+        if !self.config.cdata_to_characters {
+            self.check_encodable(&content)?;
+        }
+        self.check_bom_emitted(target)?;
         self.fix_non_empty_element(target)?;
         if self.config.cdata_to_characters {
-            self.emit_characters(target, content)
+            self.check_document_started(target)?;
+            target.write_all(self.escape_pcdata(&content).as_bytes())?;
         } else {
-            // TODO: escape ']]>' characters in CDATA as two adjacent CDATA blocks
-            target.write_all(b"<![CDATA[")?;
-            target.write_all(content.as_bytes())?;
-            target.write_all(b"]]>")?;
+            self.write_cdata(target, &content)?;
+        }
+        self.after_text();
+        Ok(())
+    }
 
-            self.after_text();
+    /// Applies the invalid character policy to the given content.
+    fn replace_invalid_characters<'a>(&self, content: &'a str) -> Result<Cow<'a, str>> {
+        if content.chars().all(common::is_xml10_char) {
+            return Ok(Cow::Borrowed(content));
+        }
+        match self.config.invalid_character_policy {
+            InvalidCharacterPolicy::Error =>
+                Err(EmitterError::InvalidCharacter(content.chars().find(|&c| !common::is_xml10_char(c)).unwrap())),
+            InvalidCharacterPolicy::Strip =>
+                Ok(Cow::Owned(content.chars().filter(|&c| common::is_xml10_char(c)).collect())),
+            InvalidCharacterPolicy::ReplaceWith(r) =>
+                Ok(Cow::Owned(content.chars().map(|c| if common::is_xml10_char(c) { c } else { r }).collect()))
+        }
+    }
 
-            Ok(())
+    fn write_cdata<W: Write>(&mut self, target: &mut W, content: &str) -> Result<()> {
+        target.write_all(b"<![CDATA[")?;
+        // ']]>' cannot appear inside CDATA, so it is split between two adjacent sections
+        for (i, part) in content.split("]]>").enumerate() {
+            if i > 0 {
+                target.write_all(b"]]]]><![CDATA[>")?;
+            }
+            target.write_all(part.as_bytes())?;
         }
+        target.write_all(b"]]>")?;
+        Ok(())
     }
 
     pub fn emit_characters<W: Write>(&mut self, target: &mut W,
                                       content: &str) -> Result<()> {
+        if self.indent_level == 0 && !self.config.allow_fragments && !common::is_whitespace_str(content) {
+            return Err(EmitterError::CharactersOutsideOfRootElement);
+        }
         self.check_document_started(target)?;
         self.fix_non_empty_element(target)?;
-        target.write_all(
-            (if self.config.perform_escaping {
-                escape_str_pcdata(content)
-            } else {
-                Cow::Borrowed(content)
-            }).as_bytes()
-        )?;
+        let content = self.replace_invalid_characters(content)?;
+        let content = if self.config.normalize_newlines {
+            normalize_newlines(content, &self.config.line_separator)
+        } else {
+            content
+        };
+        let content = &content[..];
+        if self.config.perform_escaping {
+            let escaped = self.escape_pcdata(content);
+            // replacements from `extra_pcdata_escapes` alone do not make CDATA necessary
+            if let Cow::Owned(_) = escaped {
+                let markup = content.contains(&['<', '&'][..]) || content.contains("]]>");
+                // characters which cannot be encoded may only be written as references
+                if self.config.characters_to_cdata && markup {
+                    match self.check_encodable(content) {
+                        Ok(()) => {
+                            self.write_cdata(target, content)?;
+                            self.after_text();
+                            return Ok(());
+                        }
+                        Err(e) => self.warnings.push(e)
+                    }
+                }
+            }
+            target.write_all(escaped.as_bytes())?;
+        } else {
+            target.write_all(content.as_bytes())?;
+        }
         self.after_text();
         Ok(())
     }
 
-    pub fn emit_comment<W: Write>(&mut self, target: &mut W, content: &str) -> Result<()> {
+    fn escape_pcdata<'a>(&self, content: &'a str) -> Cow<'a, str> {
+        if self.config.canonical {
+            escape_str_canonical_pcdata(content)
+        } else if !self.config.extra_pcdata_escapes.is_empty() {
+            escape_str_pcdata_with_extra(content, &self.config.extra_pcdata_escapes)
+        } else {
+            escape_str_pcdata(content)
+        }
+    }
+
+    pub fn emit_entity_reference<W: Write>(&mut self, target: &mut W, name: &str) -> Result<()> {
+        let valid_name = name.starts_with(common::is_name_start_char) && name.chars().all(common::is_name_char);
+        if !valid_name {
+            return Err(EmitterError::InvalidName(name.into()));
+        }
+        if self.indent_level == 0 && !self.config.allow_fragments {
+            return Err(EmitterError::EntityReferenceOutsideOfRootElement);
+        }
+        self.check_encodable(name)?;
+
+        self.check_document_started(target)?;
         self.fix_non_empty_element(target)?;
+        write!(target, "&{};", name)?;
+        // a reference stands for text, so no indentation may be inserted around it
+        self.after_text();
+        Ok(())
+    }
 
-        // TODO: add escaping dashes at the end of the comment
+    pub fn emit_whitespace<W: Write>(&mut self, target: &mut W, content: &str) -> Result<()> {
+        // indentation replaces insignificant whitespace
+        if self.config.perform_indent && !self.preserving_space() && common::is_whitespace_str(content) {
+            return Ok(());
+        }
+        self.emit_characters(target, content)
+    }
 
+    pub fn emit_comment<W: Write>(&mut self, target: &mut W, content: &str) -> Result<()> {
         let autopad_comments = self.config.autopad_comments;
+        let content = if autopad_comments {
+            escape_str_comment(content)
+        } else if content.contains("--") || content.ends_with('-') {
+            return Err(EmitterError::InvalidComment);
+        } else {
+            Cow::Borrowed(content)
+        };
+        self.check_encodable(&content)?;
+
+        self.check_document_started(target)?;
+        self.fix_non_empty_element(target)?;
+
         let write = |target: &mut W| -> Result<()> {
             target.write_all(b"<!--")?;
 
@@ -439,9 +1238,66 @@ impl Emitter {
         };
 
         self.before_markup(target)?;
-        let result = write(target);
+        self.before_top_level_node(target)?;
+        write(target)?;
         self.after_markup();
+        self.after_top_level_node(target)
+    }
+}
 
-        result
+/// Replaces all line breaks in the given string, that is, `\r\n`, `\r` and `\n`,
+/// with the given separator.
+fn normalize_newlines<'a>(content: Cow<'a, str>, separator: &str) -> Cow<'a, str> {
+    if !content.contains('\r') && (separator == "\n" || !content.contains('\n')) {
+        return content;
+    }
+    Cow::Owned(content.replace("\r\n", "\n").replace('\r', "\n").replace('\n', separator))
+}
+
+/// Chooses the quote character for a literal in a document type declaration.
+fn quote_for_literal(literal: &str) -> Result<char> {
+    match (literal.contains('"'), literal.contains('\'')) {
+        (false, _) => Ok('"'),
+        (true, false) => Ok('\''),
+        (true, true) => Err(EmitterError::InvalidDoctype(
+            format!("identifier {:?} contains both quote characters", literal)
+        ))
+    }
+}
+
+/// Writes the given name, replacing its prefix with the given one, if any.
+fn write_name<W: Write>(target: &mut W, name: Name, prefix: Option<&str>) -> Result<()> {
+    match prefix.or(name.prefix) {
+        Some(NS_NO_PREFIX) | None => write!(target, "{}", name.local_name),
+        Some(prefix) => write!(target, "{}:{}", prefix, name.local_name)
+    }.map_err(From::from)
+}
+
+/// Writes a namespace declaration attribute for the given mapping, unless it does not need
+/// to be declared.
+fn write_namespace_attribute<W: Write>(target: &mut W, prefix: &str, uri: &str, quote: char) -> Result<()> {
+    match prefix {
+        // internal namespaces are not emitted
+        NS_XMLNS_PREFIX | NS_XML_PREFIX => Ok(()),
+        //// there is already a namespace binding with this prefix in scope
+        //prefix if self.nst.get(prefix) == Some(uri) => Ok(()),
+        // emit xmlns only if it is overridden
+        NS_NO_PREFIX => if uri != NS_EMPTY_URI {
+            write!(target, " xmlns={}{}{}", quote, escape_str_attribute_quoted(uri, quote), quote)
+        } else { Ok(()) },
+        // everything else
+        prefix => write!(target, " xmlns:{}={}{}{}", prefix, quote, escape_str_attribute_quoted(uri, quote), quote)
+    }.map_err(From::from)
+}
+
+/// Checks that the local name and the prefix of the given name are valid XML names
+/// without colons. The prefix may be empty, which denotes the default namespace.
+fn check_name(name: Name) -> Result<()> {
+    let valid = |s: &str| s.starts_with(common::is_name_start_char) && !s.contains(':') &&
+                          s.chars().all(common::is_name_char);
+    if valid(name.local_name) && name.prefix.is_none_or(|p| p.is_empty() || valid(p)) {
+        Ok(())
+    } else {
+        Err(EmitterError::InvalidName(name.to_repr()))
     }
 }
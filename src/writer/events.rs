@@ -1,9 +1,10 @@
 //! Contains `XmlEvent` datatype, instances of which are consumed by the writer.
 
 use std::borrow::Cow;
+use std::convert::TryFrom;
 
 use name::Name;
-use attribute::Attribute;
+use attribute::{Attribute, ParseAttributeError};
 use common::XmlVersion;
 use namespace::{Namespace, NS_NO_PREFIX};
 
@@ -35,7 +36,33 @@ pub enum XmlEvent<'a> {
         standalone: Option<bool>
     },
 
+    /// Denotes a document type declaration, e.g.
+    /// `<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "DTD/xhtml1-strict.dtd">`.
+    ///
+    /// The declaration can only be written once, before the root element; it is written after
+    /// the document declaration, which is emitted automatically if the corresponding option
+    /// is set in the configuration. The identifiers are quoted with `"`, or with `'` if they
+    /// contain `"`; an identifier containing both quote characters is an error, as well as
+    /// a public identifier without a system one. The internal subset is written as is.
+    DoctypeDecl {
+        /// The name of the root element.
+        name: &'a str,
+
+        /// The public identifier of the external subset.
+        public_id: Option<&'a str>,
+
+        /// The system identifier of the external subset.
+        system_id: Option<&'a str>,
+
+        /// The internal subset, without the enclosing brackets.
+        internal_subset: Option<&'a str>
+    },
+
     /// Denotes an XML processing instruction.
+    ///
+    /// The target must be a valid name other than `xml` in any case, and the data must not
+    /// contain `?>`; otherwise an error is returned by the write operation. The data is written
+    /// exactly as it is, separated from the target by a single space.
     ProcessingInstruction {
         /// Processing instruction target.
         name: &'a str,
@@ -45,15 +72,24 @@ pub enum XmlEvent<'a> {
     },
 
     /// Denotes a beginning of an XML element.
+    ///
+    /// Names of the element and its attributes which have a namespace URI are written with
+    /// a prefix bound to that URI. If the requested prefix is not bound in scope or is bound to
+    /// another URI, it is declared on this element. Names without a prefix use the default
+    /// namespace, if it is bound to their URI, or an existing prefix bound to it, and otherwise
+    /// a new prefix of the form `nsN` is generated and declared. A name with an empty prefix
+    /// binds the default namespace; attributes are never put into the default namespace, though,
+    /// because it does not apply to them.
     StartElement {
         /// Qualified name of the element.
         name: Name<'a>,
 
         /// A list of attributes associated with the element.
         ///
-        /// Currently attributes are not checked for duplicates (TODO). Attribute values
-        /// will be escaped, and all characters invalid for attribute values like `"` or `<`
-        /// will be changed into character entities.
+        /// Writing two attributes with the same qualified name, or with the same local name and
+        /// namespace URI, is an error, as well as writing names which are not valid XML names.
+        /// Attribute values will be escaped, and all characters invalid for attribute values
+        /// like `"` or `<` will be changed into character entities.
         attributes: Cow<'a, [Attribute<'a>]>,
 
         /// Contents of the namespace mapping at this point of the document.
@@ -77,20 +113,44 @@ pub enum XmlEvent<'a> {
     /// Denotes CDATA content.
     ///
     /// This event contains unparsed data, and no escaping will be performed when writing it
-    /// to the output stream.
+    /// to the output stream. Occurrences of `]]>` are written by splitting the content into
+    /// two adjacent CDATA sections. Characters which are not allowed in XML documents are
+    /// handled according to `EmitterConfig::invalid_character_policy`. CDATA sections are
+    /// only allowed inside the root element; writing this event outside of it is an error.
     CData(&'a str),
 
     /// Denotes a comment.
     ///
-    /// The string will be checked for invalid sequences and error will be returned by the
-    /// write operation
+    /// Comments can't contain `--` or end with `-`. Such content is fixed by inserting spaces
+    /// if `EmitterConfig::autopad_comments` is enabled; otherwise an error is returned by the
+    /// write operation. Comments may be written before, inside and after the root element; like
+    /// any other markup, a comment written first causes the document declaration to be written
+    /// before it, if it is enabled.
     Comment(&'a str),
 
     /// Denotes character data outside of tags.
     ///
     /// Contents of this event will be escaped if `perform_escaping` option is enabled,
     /// that is, every character invalid for PCDATA will appear as a character entity.
-    Characters(&'a str)
+    Characters(&'a str),
+
+    /// Denotes a chunk of insignificant whitespace outside of tags.
+    ///
+    /// If `perform_indent` option is enabled, this event is not written at all, because
+    /// indentation replaces the original whitespace, unless `xml:space="preserve"` is in effect
+    /// for the current element; otherwise it is written in the same way as `Characters`; it is
+    /// also written like `Characters` if it contains anything besides whitespace. Note that
+    /// the emitter does not indent the content of elements with `xml:space="preserve"` in scope.
+    Whitespace(&'a str),
+
+    /// Denotes a reference to a general entity, e.g. `&custom;`, given by the name of the entity.
+    ///
+    /// The reference is written as is, so the entity must be declared in the document type
+    /// declaration for the document to be well-formed, unless it is one of the predefined
+    /// entities. Like character data, references are only allowed inside the root element;
+    /// writing this event outside of it, or with a name which is not a valid XML name,
+    /// is an error.
+    EntityReference(&'a str)
 }
 
 impl<'a> XmlEvent<'a> {
@@ -125,8 +185,8 @@ impl<'a> XmlEvent<'a> {
 
     /// Returns a CDATA event.
     ///
-    /// Naturally, the provided string won't be escaped, except for closing CDATA token `]]>`
-    /// (depending on the configuration).
+    /// Naturally, the provided string won't be escaped, except for closing CDATA token `]]>`,
+    /// which is split between two adjacent CDATA sections.
     #[inline]
     pub fn cdata(data: &'a str) -> XmlEvent<'a> { XmlEvent::CData(data) }
 
@@ -146,6 +206,30 @@ impl<'a> From<&'a str> for XmlEvent<'a> {
     fn from(s: &'a str) -> XmlEvent<'a> { XmlEvent::Characters(s) }
 }
 
+/// A value which can be written by `EventWriter::write_all()`.
+///
+/// This trait is implemented for everything convertible into writer events, and for
+/// references to reader events, so recorded events of both kinds can be replayed.
+pub trait IntoWriterEvent<'a> {
+    /// Converts this value into a writer event, or returns `None` if it has
+    /// no writer counterpart and must be skipped.
+    fn into_writer_event(self) -> Option<XmlEvent<'a>>;
+}
+
+impl<'a, E> IntoWriterEvent<'a> for E where E: Into<XmlEvent<'a>> {
+    #[inline]
+    fn into_writer_event(self) -> Option<XmlEvent<'a>> {
+        Some(self.into())
+    }
+}
+
+impl<'a> IntoWriterEvent<'a> for &'a ::reader::XmlEvent {
+    #[inline]
+    fn into_writer_event(self) -> Option<XmlEvent<'a>> {
+        self.as_writer_event()
+    }
+}
+
 pub struct EndElementBuilder<'a> {
     name: Option<Name<'a>>
 }
@@ -197,6 +281,23 @@ impl<'a> StartElementBuilder<'a> {
         self
     }
 
+    /// Parses an attribute written as in a start tag and adds it to this element.
+    ///
+    /// See `Attribute::try_from()` for the accepted syntax; for example, `.try_attr("a='1'")`
+    /// is equivalent to `.attr("a", "1")`. The value must not contain references, so characters
+    /// which must be escaped can only be added with `attr()`, which escapes them.
+    ///
+    /// ```rust
+    /// use xml::writer::XmlEvent;
+    ///
+    /// assert!(XmlEvent::start_element("a").try_attr("href=\"/\"").is_ok());
+    /// assert!(XmlEvent::start_element("a").try_attr("1href=\"/\"").is_err());
+    /// ```
+    pub fn try_attr(self, attribute: &'a str) -> Result<StartElementBuilder<'a>, ParseAttributeError> {
+        let attribute = Attribute::try_from(attribute)?;
+        Ok(self.attr(attribute.name, attribute.value))
+    }
+
     /// Adds a namespace to the current namespace context.
     ///
     /// If no namespace URI was bound to the provided prefix at this point of the document,
@@ -5,22 +5,42 @@
 
 pub use self::emitter::Result;
 pub use self::emitter::EmitterError as Error;
-pub use self::config::EmitterConfig;
-pub use self::events::XmlEvent;
+pub use self::config::{
+    EmitterConfig, NamespaceConflictPolicy, AttributeAlignment, TagEndPlacement
+};
+#[cfg(feature = "c14n")]
+pub use self::config::Canonicalization;
+pub use self::events::{XmlEvent, IntoWriterEvent, StartElementBuilder};
+pub use self::sink::FmtWriter;
+pub use escape::QuoteStyle;
+pub use common::InvalidCharacterPolicy;
 
 use self::emitter::Emitter;
 
+use common::XmlVersion;
+use name::{Name, OwnedName};
+
 use std::io::prelude::*;
+use std::result;
+#[cfg(feature = "encodings")]
+use std::str;
+
+#[cfg(feature = "encodings")]
+use encodings::{self, OutputEncoder};
 
 mod emitter;
 mod config;
+mod sink;
 pub mod events;
 
 /// A wrapper around an `std::io::Write` instance which emits XML document according to provided
 /// events.
 pub struct EventWriter<W> {
     sink: W,
-    emitter: Emitter
+    emitter: Emitter,
+    autoflush: bool,
+    #[cfg(feature = "encodings")]
+    encoder: Option<OutputEncoder>
 }
 
 impl<W: Write> EventWriter<W> {
@@ -35,8 +55,18 @@ impl<W: Write> EventWriter<W> {
     /// configuration.
     #[inline]
     pub fn new_with_config(sink: W, config: EmitterConfig) -> EventWriter<W> {
+        #[cfg(feature = "c14n")]
+        let config = EmitterConfig { canonical: config.canonical || config.canonicalization.is_some(), ..config };
+        let config = if config.canonical { canonical_config(config) } else { config };
         EventWriter {
             sink,
+            autoflush: config.autoflush,
+            #[cfg(feature = "encodings")]
+            encoder: if config.output_encoding == encodings::UTF_8 {
+                None
+            } else {
+                Some(OutputEncoder::new(config.output_encoding))
+            },
             emitter: Emitter::new(config)
         }
     }
@@ -48,28 +78,315 @@ impl<W: Write> EventWriter<W> {
     /// correspond to a separate closing element or it may cause writing an empty element.
     /// Another example is that `XmlEvent::CData` may be represented as characters in
     /// the output stream.
+    ///
+    /// If the `autoflush` configuration option is enabled, the output stream is flushed
+    /// after the event is written.
     pub fn write<'a, E>(&mut self, event: E) -> Result<()> where E: Into<XmlEvent<'a>> {
-        match event.into() {
-            XmlEvent::StartDocument { version, encoding, standalone } =>
-                self.emitter.emit_start_document(&mut self.sink, version, encoding.unwrap_or("UTF-8"), standalone),
-            XmlEvent::ProcessingInstruction { name, data } =>
-                self.emitter.emit_processing_instruction(&mut self.sink, name, data),
-            XmlEvent::StartElement { name, attributes, namespace } => {
-                self.emitter.namespace_stack_mut().push_empty().checked_target().extend(namespace.as_ref());
-                self.emitter.emit_start_element(&mut self.sink, name, &attributes)
+        self.write_unflushed(event.into())?;
+        if self.autoflush {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes all of the given events, stopping at the first error.
+    ///
+    /// The events may be anything `write()` accepts, or references to reader events, which
+    /// are converted with `as_writer_event()`; reader events without a writer counterpart
+    /// are skipped. This makes replaying a recorded document a one-liner:
+    ///
+    /// ```rust
+    /// use xml::reader::{self, ParserConfig};
+    /// use xml::writer::EmitterConfig;
+    ///
+    /// let events = reader::parse_str("<a><b>text</b></a>", &ParserConfig::new()).unwrap();
+    ///
+    /// let mut output = Vec::new();
+    /// {
+    ///     let mut writer = EmitterConfig::new().create_writer(&mut output);
+    ///     assert_eq!(writer.write_all(&events).unwrap(), events.len());
+    /// }
+    /// assert_eq!(output, &b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><a><b>text</b></a>"[..]);
+    /// ```
+    ///
+    /// Returns the number of events taken from the iterator. If writing an event fails,
+    /// returns the number of events taken before the failed one together with the error.
+    pub fn write_all<'a, I>(&mut self, events: I) -> result::Result<usize, (usize, Error)>
+        where I: IntoIterator, I::Item: IntoWriterEvent<'a>
+    {
+        let mut count = 0;
+        for event in events {
+            if let Some(event) = event.into_writer_event() {
+                self.write_unflushed(event).map_err(|e| (count, e))?;
             }
-            XmlEvent::EndElement { name } => {
-                let r = self.emitter.emit_end_element(&mut self.sink, name);
-                self.emitter.namespace_stack_mut().try_pop();
-                r
+            count += 1;
+        }
+        if self.autoflush {
+            self.flush().map_err(|e| (count, e))?;
+        }
+        Ok(count)
+    }
+
+    fn write_unflushed(&mut self, event: XmlEvent) -> Result<()> {
+        self.emit(|emitter, mut sink| write_event(emitter, &mut sink, event))
+    }
+
+    /// Runs the given emitter operation on the output stream, converting the output to
+    /// the output encoding.
+    fn emit<F>(&mut self, f: F) -> Result<()> where F: FnOnce(&mut Emitter, &mut dyn Write) -> Result<()> {
+        #[cfg(feature = "encodings")]
+        {
+            if let Some(ref mut encoder) = self.encoder {
+                // the emitter always produces UTF-8, which is then converted to the output encoding
+                let mut buf = Vec::new();
+                let result = f(&mut self.emitter, &mut buf);
+                let mut encoded = Vec::with_capacity(buf.len());
+                encoder.encode(str::from_utf8(&buf).unwrap(), &mut encoded);
+                self.sink.write_all(&encoded)?;
+                return result;
             }
-            XmlEvent::Comment(content) =>
-                self.emitter.emit_comment(&mut self.sink, content),
-            XmlEvent::CData(content) =>
-                self.emitter.emit_cdata(&mut self.sink, content),
-            XmlEvent::Characters(content) =>
-                self.emitter.emit_characters(&mut self.sink, content)
         }
+
+        f(&mut self.emitter, &mut self.sink)
+    }
+
+    /// Makes the next written start element declare the given namespace mapping, even if
+    /// the same mapping is already in scope.
+    ///
+    /// Namespace mappings added to a start element with `ns()` or `default_ns()`, as well as
+    /// the ones declared automatically, are only written if they are not already in scope.
+    /// This method can be used to repeat a declaration at a chosen element, for example,
+    /// at the root of a subtree which may be extracted from the document later:
+    ///
+    /// ```rust
+    /// use xml::writer::{EmitterConfig, XmlEvent};
+    ///
+    /// let mut output = Vec::new();
+    /// {
+    ///     let mut writer = EmitterConfig::new().write_document_declaration(false).create_writer(&mut output);
+    ///     writer.write(XmlEvent::start_element("a").ns("p", "urn:p")).unwrap();
+    ///     writer.force_namespace_declaration("p", "urn:p");
+    ///     writer.write(XmlEvent::start_element("p:b")).unwrap();
+    ///     writer.write(XmlEvent::end_element()).unwrap();
+    ///     writer.write(XmlEvent::end_element()).unwrap();
+    /// }
+    /// assert_eq!(output, &b"<a xmlns:p=\"urn:p\"><p:b xmlns:p=\"urn:p\" /></a>"[..]);
+    /// ```
+    pub fn force_namespace_declaration<P, U>(&mut self, prefix: P, uri: U)
+        where P: Into<String>, U: Into<String>
+    {
+        self.emitter.force_namespace_declaration(prefix.into(), uri.into());
+    }
+
+    /// Writes a comment with the given content.
+    ///
+    /// This is a shorthand for writing `XmlEvent::comment(content)`. If `autopad_comments`
+    /// is enabled, the content is padded with spaces and `--` sequences are split, otherwise
+    /// an error is returned for content which is not allowed in comments.
+    #[inline]
+    pub fn comment(&mut self, content: &str) -> Result<()> {
+        self.write(XmlEvent::comment(content))
+    }
+
+    /// Writes a processing instruction with the given target and data.
+    ///
+    /// This is a shorthand for writing `XmlEvent::processing_instruction(name, data)`. An error
+    /// is returned if the target is not a valid name or is reserved, or if the data contains `?>`.
+    #[inline]
+    pub fn processing_instruction(&mut self, name: &str, data: Option<&str>) -> Result<()> {
+        self.write(XmlEvent::processing_instruction(name, data))
+    }
+
+    /// Writes a CDATA section with the given content.
+    ///
+    /// This is a shorthand for writing `XmlEvent::cdata(content)`. Occurrences of `]]>` are
+    /// split between adjacent CDATA sections, so any content can be written.
+    ///
+    /// ```rust
+    /// use xml::writer::{EmitterConfig, XmlEvent};
+    ///
+    /// let mut output = Vec::new();
+    /// {
+    ///     let mut writer = EmitterConfig::new().write_document_declaration(false).create_writer(&mut output);
+    ///     writer.write(XmlEvent::start_element("a")).unwrap();
+    ///     writer.comment("note").unwrap();
+    ///     writer.processing_instruction("pi", Some("data")).unwrap();
+    ///     writer.cdata("x]]>y").unwrap();
+    ///     writer.write(XmlEvent::end_element()).unwrap();
+    /// }
+    /// assert_eq!(output, &b"<a><!-- note --><?pi data?><![CDATA[x]]]]><![CDATA[>y]]></a>"[..]);
+    /// ```
+    #[inline]
+    pub fn cdata(&mut self, content: &str) -> Result<()> {
+        self.write(XmlEvent::cdata(content))
+    }
+
+    /// Starts writing a start element with the given name.
+    ///
+    /// The returned builder collects attributes and namespace declarations of the element
+    /// and writes it with `write()`; together with `end_element()` and `text()` this allows
+    /// writing documents without constructing events by hand:
+    ///
+    /// ```rust
+    /// use xml::writer::EmitterConfig;
+    ///
+    /// let mut output = Vec::new();
+    /// {
+    ///     let mut writer = EmitterConfig::new().write_document_declaration(false).create_writer(&mut output);
+    ///     writer.start_element("person").attr("id", "42").attr_ns("urn:x", "lang", "en").write().unwrap();
+    ///     writer.text("Jane").unwrap();
+    ///     writer.end_element().unwrap();
+    /// }
+    /// assert_eq!(output, &b"<person xmlns:ns0=\"urn:x\" id=\"42\" ns0:lang=\"en\">Jane</person>"[..]);
+    /// ```
+    ///
+    /// The names are checked when the element is written; unlike with `write()`, an
+    /// `EmitterError::InvalidName` error is returned for names which are not valid XML names.
+    #[inline]
+    pub fn start_element<'s, 'a, N>(&'s mut self, name: N) -> StartElementWriter<'s, 'a, W>
+        where N: Into<Name<'a>>
+    {
+        StartElementWriter { writer: self, builder: XmlEvent::start_element(name) }
+    }
+
+    /// Writes an end element for the innermost open element.
+    ///
+    /// This is a shorthand for writing `XmlEvent::end_element()`.
+    #[inline]
+    pub fn end_element(&mut self) -> Result<()> {
+        self.write(XmlEvent::end_element())
+    }
+
+    /// Writes the given text as character data, escaping it according to the configuration.
+    ///
+    /// This is a shorthand for writing `XmlEvent::characters(text)`.
+    #[inline]
+    pub fn text(&mut self, text: &str) -> Result<()> {
+        self.write(XmlEvent::characters(text))
+    }
+
+    /// Writes the given string to the output stream verbatim, e.g. an already serialized
+    /// fragment of a document.
+    ///
+    /// The string is written at the current position without any escaping, validation or
+    /// encoding checks; **the caller is responsible for the well-formedness of the fragment**
+    /// and of the resulting document. Otherwise, the string is treated like character data:
+    /// an unfinished start tag is closed before it, the current element is not written as
+    /// an empty element afterwards, and no indentation is inserted around it.
+    ///
+    /// Returns an error if the document was not started yet, i.e. if neither the document
+    /// declaration nor any element was written before. The declaration is not written
+    /// automatically before raw content, even if `write_document_declaration` is enabled.
+    ///
+    /// ```rust
+    /// use xml::writer::{EmitterConfig, XmlEvent};
+    ///
+    /// let mut output = Vec::new();
+    /// {
+    ///     let mut writer = EmitterConfig::new().write_document_declaration(false).create_writer(&mut output);
+    ///     writer.write(XmlEvent::start_element("a")).unwrap();
+    ///     writer.write_raw("<cached>subtree</cached>").unwrap();
+    ///     writer.write(XmlEvent::end_element()).unwrap();
+    /// }
+    /// assert_eq!(output, b"<a><cached>subtree</cached></a>");
+    /// ```
+    pub fn write_raw(&mut self, s: &str) -> Result<()> {
+        self.emit(|emitter, mut sink| emitter.emit_raw(&mut sink, s))?;
+        if self.autoflush {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the XML version of the document being written.
+    ///
+    /// This is the version of the written `StartDocument` event, even if no declaration was
+    /// written because `write_document_declaration` is disabled, or `EmitterConfig::xml_version`
+    /// if no such event was written.
+    #[inline]
+    pub fn xml_version(&self) -> XmlVersion {
+        self.emitter.xml_version()
+    }
+
+    /// Returns the warnings noted by the emitter since the last call of this method.
+    ///
+    /// Warnings are issued for recoverable problems with the written events, for example, for
+    /// character data which could not be written as a CDATA section because of the
+    /// `characters_to_cdata` configuration option, as it contains characters which cannot be
+    /// represented in the output encoding, and which was escaped instead. Warnings accumulate
+    /// until they are taken with this method.
+    #[inline]
+    pub fn take_warnings(&mut self) -> Vec<Error> {
+        self.emitter.take_warnings()
+    }
+
+    /// Returns the number of elements which are currently open.
+    ///
+    /// This is zero before the root element is started and after it is closed.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.emitter.depth()
+    }
+
+    /// Returns the name of the innermost open element, if any.
+    ///
+    /// This can be used to check that the element about to be closed is the expected one.
+    /// Always returns `None` if `keep_element_names_stack` is disabled.
+    ///
+    /// ```rust
+    /// use xml::writer::{EmitterConfig, XmlEvent};
+    ///
+    /// let mut writer = EmitterConfig::new().create_writer(Vec::new());
+    /// writer.write(XmlEvent::start_element("a")).unwrap();
+    /// writer.write(XmlEvent::start_element("p:b").ns("p", "urn:p")).unwrap();
+    /// assert_eq!(writer.depth(), 2);
+    /// assert_eq!(writer.current_element().map(|n| n.to_repr()), Some("p:b".into()));
+    /// ```
+    #[inline]
+    pub fn current_element(&self) -> Option<Name<'_>> {
+        self.emitter.element_names().last().map(|name| name.borrow())
+    }
+
+    /// Returns the names of all open elements, outermost first.
+    ///
+    /// Always returns an empty slice if `keep_element_names_stack` is disabled.
+    #[inline]
+    pub fn element_names(&self) -> &[OwnedName] {
+        self.emitter.element_names()
+    }
+
+    /// Treats the content of the current element as mixed from now on, as if text had been
+    /// written into it.
+    ///
+    /// With `perform_indent` enabled, no indentation is written inside an element once it
+    /// contains text, because the whitespace would become a part of that text. This method
+    /// does the same for content which is known to be mixed before any of its text is written,
+    /// e.g. when the text only follows a child element. It writes nothing by itself.
+    ///
+    /// ```rust
+    /// use xml::writer::{EmitterConfig, XmlEvent};
+    ///
+    /// let mut writer = EmitterConfig::new().perform_indent(true).write_document_declaration(false)
+    ///     .create_writer(Vec::new());
+    /// writer.write(XmlEvent::start_element("p")).unwrap();
+    /// writer.mixed_content();
+    /// writer.write(XmlEvent::start_element("b")).unwrap();
+    /// writer.write(XmlEvent::characters("bold")).unwrap();
+    /// writer.write(XmlEvent::end_element()).unwrap();
+    /// writer.write(XmlEvent::characters(" text")).unwrap();
+    /// writer.write(XmlEvent::end_element()).unwrap();
+    /// assert_eq!(writer.into_inner(), b"<p><b>bold</b> text</p>");
+    /// ```
+    #[inline]
+    pub fn mixed_content(&mut self) {
+        self.emitter.set_mixed_content();
+    }
+
+    /// Flushes the underlying output stream, ensuring that everything written so far
+    /// reaches its destination.
+    #[inline]
+    pub fn flush(&mut self) -> Result<()> {
+        self.sink.flush().map_err(From::from)
     }
 
     /// Returns a mutable reference to the underlying `Writer`.
@@ -87,7 +404,213 @@ impl<W: Write> EventWriter<W> {
     /// Note that this is a destructive operation: unwrapping a writer and then wrapping
     /// it again with `EventWriter::new()` will create a fresh writer whose state will be
     /// blank; for example, accumulated namespaces will be reset.
-    pub fn into_inner(self) -> W {
+    ///
+    /// The writer is returned even if the document is not complete. With
+    /// `normalize_empty_elements` enabled, the last start tag is left without its closing `>`
+    /// until the next event is known, so this `>` is written first; the content written
+    /// to the returned stream afterwards then becomes the content of that element:
+    ///
+    /// ```rust
+    /// use std::io::Write;
+    /// use xml::writer::{EmitterConfig, XmlEvent};
+    ///
+    /// let mut writer = EmitterConfig::new().write_document_declaration(false).create_writer(Vec::new());
+    /// writer.write(XmlEvent::start_element("a")).unwrap();
+    /// let mut output = writer.into_inner();
+    /// output.write_all(b"text").unwrap();
+    /// assert_eq!(output, b"<a>text");
+    /// ```
+    ///
+    /// The output stream is not flushed, and an error while writing the `>` is ignored,
+    /// since there is no way to return it; use `finish()` to check that the document is
+    /// complete and that all output is written successfully.
+    pub fn into_inner(mut self) -> W {
+        let _ = self.close_start_tag();
         self.sink
     }
+
+    /// Writes the end of the last start tag, if it is still pending.
+    fn close_start_tag(&mut self) -> Result<()> {
+        self.emit(|emitter, mut sink| emitter.close_start_tag(&mut sink))
+    }
+
+    /// Checks that the written document is complete, flushes the output stream and
+    /// returns the underlying writer.
+    ///
+    /// Returns an error if some elements are still open, because their start tags may not
+    /// have been finished yet and writing anything else to the output stream would corrupt
+    /// the document. Otherwise, the writer holds no pending output, and further data may be
+    /// written to the returned stream directly:
+    ///
+    /// ```rust
+    /// use std::io::Write;
+    /// use xml::writer::{EmitterConfig, XmlEvent};
+    ///
+    /// let mut writer = EmitterConfig::new().write_document_declaration(false).create_writer(Vec::new());
+    /// writer.write(XmlEvent::start_element("a")).unwrap();
+    /// writer.write(XmlEvent::end_element()).unwrap();
+    /// let mut output = writer.finish().unwrap();
+    /// output.write_all(b"\n--boundary--").unwrap();
+    /// assert_eq!(output, b"<a />\n--boundary--");
+    /// ```
+    pub fn finish(mut self) -> Result<W> {
+        self.emitter.check_document_finished()?;
+        self.flush()?;
+        Ok(self.sink)
+    }
+}
+
+/// A builder of a start element which writes it into an `EventWriter`, returned by
+/// `EventWriter::start_element()`.
+///
+/// Nothing is written until `write()` is called.
+pub struct StartElementWriter<'w, 'a, W: 'w> {
+    writer: &'w mut EventWriter<W>,
+    builder: StartElementBuilder<'a>
+}
+
+impl<'w, 'a, W: Write> StartElementWriter<'w, 'a, W> {
+    /// Adds an attribute with the given name to the element.
+    ///
+    /// See `StartElementBuilder::attr()` for the details.
+    #[inline]
+    pub fn attr<N>(mut self, name: N, value: &'a str) -> StartElementWriter<'w, 'a, W>
+        where N: Into<Name<'a>>
+    {
+        self.builder = self.builder.attr(name, value);
+        self
+    }
+
+    /// Adds an attribute with the given namespace URI and local name to the element.
+    ///
+    /// The attribute is written with a prefix bound to the namespace; if there is no such
+    /// prefix in scope, one is declared on the element automatically.
+    #[inline]
+    pub fn attr_ns(self, namespace: &'a str, local_name: &'a str, value: &'a str) -> StartElementWriter<'w, 'a, W> {
+        self.attr(Name::qualified(local_name, namespace, None), value)
+    }
+
+    /// Declares a namespace mapping on the element; see `StartElementBuilder::ns()`.
+    #[inline]
+    pub fn ns_decl(mut self, prefix: &str, uri: &str) -> StartElementWriter<'w, 'a, W> {
+        self.builder = self.builder.ns(prefix, uri);
+        self
+    }
+
+    /// Declares the default namespace on the element; see `StartElementBuilder::default_ns()`.
+    #[inline]
+    pub fn default_ns(mut self, uri: &str) -> StartElementWriter<'w, 'a, W> {
+        self.builder = self.builder.default_ns(uri);
+        self
+    }
+
+    /// Writes the element.
+    ///
+    /// Like for every start element, the names of the element and of its attributes are checked
+    /// before anything is written; see `EmitterError::InvalidName`.
+    #[inline]
+    pub fn write(self) -> Result<()> {
+        self.writer.write(self.builder)
+    }
+}
+
+impl EventWriter<FmtWriter<String>> {
+    /// Creates a new `EventWriter` which writes into a `String`, using the default
+    /// configuration.
+    ///
+    /// ```rust
+    /// use xml::writer::{EventWriter, XmlEvent};
+    ///
+    /// let mut writer = EventWriter::new_string();
+    /// writer.write(XmlEvent::start_element("a")).unwrap();
+    /// writer.write(XmlEvent::end_element()).unwrap();
+    /// assert_eq!(writer.into_string(), "<?xml version=\"1.0\" encoding=\"UTF-8\"?><a />");
+    /// ```
+    #[inline]
+    pub fn new_string() -> EventWriter<FmtWriter<String>> {
+        EventWriter::new_string_with_config(EmitterConfig::new())
+    }
+
+    /// Creates a new `EventWriter` which writes into a `String`, using the provided
+    /// configuration.
+    ///
+    /// The output encoding is always UTF-8, regardless of the configuration.
+    #[inline]
+    pub fn new_string_with_config(config: EmitterConfig) -> EventWriter<FmtWriter<String>> {
+        #[cfg(feature = "encodings")]
+        let config = EmitterConfig { output_encoding: encodings::UTF_8, ..config };
+        EventWriter::new_with_config(FmtWriter::new(String::new()), config)
+    }
+
+    /// Returns the string written so far.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        self.sink.get_ref()
+    }
+
+    /// Unwraps this `EventWriter`, returning the written string.
+    #[inline]
+    pub fn into_string(self) -> String {
+        self.sink.into_inner()
+    }
+}
+
+/// Overrides the options which conflict with the canonical form.
+fn canonical_config(mut config: EmitterConfig) -> EmitterConfig {
+    config.line_separator = "\n".into();
+    config.perform_indent = false;
+    config.perform_escaping = true;
+    config.write_document_declaration = false;
+    config.normalize_empty_elements = false;
+    config.cdata_to_characters = true;
+    config.characters_to_cdata = false;
+    config.autopad_comments = false;
+    config.write_bom = false;
+    config.attribute_quote = QuoteStyle::Double;
+    #[cfg(feature = "encodings")]
+    {
+        config.output_encoding = encodings::UTF_8;
+    }
+    config
+}
+
+fn write_event<W: Write>(emitter: &mut Emitter, sink: &mut W, event: XmlEvent) -> Result<()> {
+    match event {
+        XmlEvent::StartDocument { version, encoding, standalone } =>
+            emitter.emit_start_document(sink, version, encoding.unwrap_or("UTF-8"), standalone),
+        XmlEvent::DoctypeDecl { name, public_id, system_id, internal_subset } =>
+            emitter.emit_doctype(sink, name, public_id, system_id, internal_subset),
+        XmlEvent::ProcessingInstruction { name, data } =>
+            emitter.emit_processing_instruction(sink, name, data),
+        XmlEvent::StartElement { name, attributes, namespace } => {
+            emitter.check_start_element(name, &attributes)?;
+            let depth = emitter.depth();
+            emitter.namespace_stack_mut().push_empty().checked_target().extend(namespace.as_ref());
+            let r = emitter.emit_start_element(sink, name, &attributes);
+            // the element is not opened if its namespaces were rejected
+            if emitter.depth() == depth {
+                emitter.namespace_stack_mut().try_pop();
+            }
+            r
+        }
+        XmlEvent::EndElement { name } => {
+            let depth = emitter.depth();
+            let r = emitter.emit_end_element(sink, name);
+            // the element is not closed if the end element was rejected
+            if emitter.depth() < depth {
+                emitter.namespace_stack_mut().try_pop();
+            }
+            r
+        }
+        XmlEvent::Comment(content) =>
+            emitter.emit_comment(sink, content),
+        XmlEvent::CData(content) =>
+            emitter.emit_cdata(sink, content),
+        XmlEvent::Characters(content) =>
+            emitter.emit_characters(sink, content),
+        XmlEvent::Whitespace(content) =>
+            emitter.emit_whitespace(sink, content),
+        XmlEvent::EntityReference(name) =>
+            emitter.emit_entity_reference(sink, name)
+    }
 }
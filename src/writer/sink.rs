@@ -0,0 +1,58 @@
+//! Contains an adapter which allows writing XML documents into `std::fmt::Write` instances.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::str;
+
+/// An adapter which implements `std::io::Write` on top of a `std::fmt::Write` instance,
+/// e.g. a `String`.
+///
+/// The emitter only produces valid UTF-8, so the written bytes are passed to the
+/// underlying writer as strings without copying. Writing bytes which are not valid UTF-8,
+/// e.g. output converted to another encoding with the `encodings` feature, fails with
+/// an `io::ErrorKind::InvalidData` error; errors of the underlying writer are returned
+/// as `io::ErrorKind::Other` errors wrapping the original `fmt::Error`.
+///
+/// `EventWriter::new_string()` creates a writer which emits into a `String` using this adapter.
+pub struct FmtWriter<F> {
+    inner: F
+}
+
+impl<F: fmt::Write> FmtWriter<F> {
+    /// Creates a new adapter around the given `std::fmt::Write` instance.
+    #[inline]
+    pub fn new(inner: F) -> FmtWriter<F> {
+        FmtWriter { inner }
+    }
+
+    /// Returns a reference to the underlying writer.
+    #[inline]
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+
+    /// Unwraps this adapter, returning the underlying writer.
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F: fmt::Write> Write for FmtWriter<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let s = str::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.inner.write_str(s).map_err(io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+#![forbid(unsafe_code)]
+
+extern crate xml;
+
+use xml::compare::{compare, CompareOptions, ComparisonResult};
+
+fn check(a: &str, b: &str, options: &CompareOptions) -> ComparisonResult {
+    compare(a.as_bytes(), b.as_bytes(), options).unwrap()
+}
+
+fn description(a: &str, b: &str, options: &CompareOptions) -> String {
+    check(a, b, options).difference().expect("documents are equal").to_string()
+}
+
+#[test]
+fn comparing_equivalent_documents() {
+    let options = CompareOptions::new();
+    assert!(check(
+        "<?xml version='1.0'?><a x='1'><b/>te&#x78;t &amp; <c/></a>",
+        "<a x=\"1\"><b></b>text &amp; <c></c></a>",
+        &options
+    ).is_equal());
+    assert!(check("<a>split<!-- x -->text</a>", "<a>split<!-- x -->text</a>", &options).is_equal());
+}
+
+#[test]
+fn comparing_different_documents() {
+    let options = CompareOptions::new();
+    assert_eq!(description("<a><b/></a>", "<a>\n  <c/></a>", &options),
+               "Expected element `b`, found text \"\\n  \" (at 1:4 and 1:4)");
+    assert_eq!(description("<a><b/></a>", "<a><c/></a>", &options),
+               "Element names differ: `b` and `c` (at 1:4 and 1:4)");
+    assert_eq!(description("<a>x</a>", "<a>y</a>", &options),
+               "Expected text \"x\", found text \"y\" (at 1:4 and 1:4)");
+    assert_eq!(description("<a><b/></a>", "<a><b/><b/></a>", &options),
+               "Expected end of element `a`, found element `b` (at 1:8 and 1:8)");
+    assert_eq!(description("<a x='1'/>", "<a x='2'/>", &options),
+               "Attributes of element `a` differ: x=\"1\" and x=\"2\" (at 1:1 and 1:1)");
+    assert_eq!(description("<a><?pi?></a>", "<a><?pj?></a>", &options),
+               "Expected processing instruction `pi`, found processing instruction `pj` (at 1:4 and 1:4)");
+
+    assert!(compare("<a>".as_bytes(), "<a/>".as_bytes(), &options).is_err());
+    assert!(compare("<a/>".as_bytes(), "<a></b>".as_bytes(), &options).is_err());
+}
+
+#[test]
+fn comparing_with_attribute_order() {
+    let (a, b) = ("<a x='1' y='2'/>", "<a y='2' x='1'/>");
+    assert_eq!(description(a, b, &CompareOptions::new()),
+               "Attributes of element `a` differ: x=\"1\" y=\"2\" and y=\"2\" x=\"1\" (at 1:1 and 1:1)");
+    let options = CompareOptions::new().ignore_attribute_order(true);
+    assert!(check(a, b, &options).is_equal());
+    assert!(!check("<a x='1' y='2'/>", "<a y='2' x='2'/>", &options).is_equal());
+    assert!(!check("<a x='1' y='2'/>", "<a x='1'/>", &options).is_equal());
+}
+
+#[test]
+fn comparing_with_comments() {
+    let (a, b) = ("<a><!-- note --><b/></a>", "<a><b/><!-- other --></a>");
+    assert_eq!(description(a, b, &CompareOptions::new()),
+               "Expected comment \" note \", found element `b` (at 1:4 and 1:4)");
+    let options = CompareOptions::new().ignore_comments(true);
+    assert!(check(a, b, &options).is_equal());
+    // the text around an ignored comment is compared as a whole
+    assert!(check("<a>ab<!-- x --></a>", "<a>a<!-- y -->b</a>", &options).is_equal());
+}
+
+#[test]
+fn comparing_with_whitespace() {
+    let (a, b) = ("<a><b/><c/></a>", "<a>\n  <b/>\n  <c/>\n</a>");
+    assert_eq!(description(a, b, &CompareOptions::new()),
+               "Expected element `b`, found text \"\\n  \" (at 1:4 and 1:4)");
+    let options = CompareOptions::new().normalize_whitespace(true);
+    assert!(check(a, b, &options).is_equal());
+    // whitespace in other text is still significant
+    assert!(!check("<a>x</a>", "<a> x </a>", &options).is_equal());
+}
+
+#[test]
+fn comparing_with_cdata() {
+    let (a, b) = ("<a>&lt;b&gt;</a>", "<a><![CDATA[<b>]]></a>");
+    assert_eq!(description(a, b, &CompareOptions::new()),
+               "Expected text \"<b>\", found CDATA section \"<b>\" (at 1:13 and 1:4)");
+    let options = CompareOptions::new().cdata_as_text(true);
+    assert!(check(a, b, &options).is_equal());
+    assert!(check("<a>x<![CDATA[y]]>z</a>", "<a>xyz</a>", &options).is_equal());
+}
+
+#[test]
+fn comparing_with_namespaces() {
+    let (a, b) = ("<p:a xmlns:p='urn:x' p:attr='1'/>", "<q:a xmlns:q='urn:x' q:attr='1'/>");
+    assert_eq!(description(a, b, &CompareOptions::new()),
+               "Element names differ: `{urn:x}p:a` and `{urn:x}q:a` (at 1:1 and 1:1)");
+    let options = CompareOptions::new().compare_namespaces_by_uri(true);
+    assert!(check(a, b, &options).is_equal());
+    assert!(check("<a xmlns='urn:x'/>", "<p:a xmlns:p='urn:x'/>", &options).is_equal());
+    // the namespace URIs are always compared
+    assert!(!check("<p:a xmlns:p='urn:x'/>", "<p:a xmlns:p='urn:y'/>", &options).is_equal());
+    assert!(!check(a, "<a q:attr='1' xmlns:q='urn:y' xmlns='urn:x'/>", &options).is_equal());
+}
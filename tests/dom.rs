@@ -0,0 +1,334 @@
+#![forbid(unsafe_code)]
+#![cfg(feature = "dom")]
+
+extern crate xml;
+
+use std::str;
+
+use xml::dom::{read_to_tree, Element, Node, Query};
+use xml::common::Position;
+use xml::name::OwnedName;
+use xml::namespace::Namespace;
+use xml::reader::{ErrorKind, EventReader, ParserConfig};
+use xml::writer::EmitterConfig;
+
+fn parse(document: &str) -> Element {
+    let reader = ParserConfig::new()
+        .ignore_comments(false)
+        .create_reader(document.as_bytes());
+    read_to_tree(reader).unwrap()
+}
+
+#[test]
+fn reading_tree() {
+    let root = parse(
+        "<?xml version=\"1.0\"?>\n\
+         <!-- before -->\n\
+         <root xmlns:p=\"urn:p\" a=\"1\" p:b=\"2\">\
+           <p:child>text &amp; <![CDATA[<cdata>]]></p:child>\
+           <!-- comment --><?pi data?>\
+           <empty/> \
+         </root>\n\
+         <?after?>"
+    );
+
+    assert_eq!(root.name, OwnedName::local("root"));
+    assert_eq!(root.attribute("a"), Some("1"));
+    assert_eq!(root.attribute("b"), None);
+    assert_eq!(root.attributes[1].name.namespace.as_ref().map(|s| &s[..]), Some("urn:p"));
+    assert_eq!(root.namespace.get("p"), Some("urn:p"));
+
+    assert_eq!(root.children.len(), 5);
+    let child = match root.children[0] {
+        Node::Element(ref e) => e,
+        ref other => panic!("Unexpected node: {:?}", other)
+    };
+    assert_eq!(child.name.prefix.as_ref().map(|s| &s[..]), Some("p"));
+    assert_eq!(child.name.namespace.as_ref().map(|s| &s[..]), Some("urn:p"));
+    assert_eq!(child.children, vec![Node::Text("text & ".into()), Node::CData("<cdata>".into())]);
+    assert_eq!(root.children[1], Node::Comment(" comment ".into()));
+    assert_eq!(root.children[2], Node::ProcessingInstruction { name: "pi".into(), data: Some("data".into()) });
+    assert_eq!(root.children[3], Node::Element(Element {
+        name: OwnedName::local("empty"),
+        attributes: Vec::new(),
+        namespace: root.namespace.clone(),
+        children: Vec::new()
+    }));
+    assert_eq!(root.children[4], Node::Text(" ".into()));
+
+    let names: Vec<_> = root.child_elements().map(|e| &e.name.local_name[..]).collect();
+    assert_eq!(names, ["child", "empty"]);
+    assert_eq!(root.get_child("empty").map(|e| e.children.len()), Some(0));
+    assert!(root.get_child("missing").is_none());
+    assert_eq!(root.text(), "text & <cdata> ");
+}
+
+#[test]
+fn reading_tree_errors() {
+    let err = read_to_tree(EventReader::from_str("<a><b></a>")).unwrap_err();
+    match *err.kind() {
+        ErrorKind::Syntax(_) => {}
+        ref other => panic!("Unexpected error: {:?}", other)
+    }
+
+    assert!(read_to_tree(EventReader::from_str("<!-- no root -->")).is_err());
+}
+
+#[test]
+fn writing_tree() {
+    let document = "<root xmlns:p=\"urn:p\" a=\"1\"><p:child p:b=\"2\">text &amp; <![CDATA[x]]></p:child>\
+                    <!-- comment --><?pi data?><empty /></root>";
+    let root = parse(document);
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .create_writer(&mut b);
+        root.write_to(&mut w).unwrap();
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), document);
+    assert_eq!(parse(str::from_utf8(&b).unwrap()), root);
+
+    let mut tree = Element::new(OwnedName::local("a"));
+    tree.children.push(Node::Text("<text>".into()));
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .create_writer(&mut b);
+        tree.write_to(&mut w).unwrap();
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<a>&lt;text></a>");
+}
+
+#[test]
+fn tree_round_trip() {
+    use xml::dom::write_tree;
+
+    let document = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                    <root z=\"1\" a=\"&lt;2&gt;\" m=\"&quot;3&quot;\">\n  \
+                      <item id=\"x\">one &amp; two</item>\n  \
+                      <item id=\"y\">\n    \
+                        <nested />\n  \
+                      </item>\n  \
+                      <pre xml:space=\"preserve\">  keep  <b> </b></pre>\n\
+                    </root>";
+    let root = parse(document);
+    let names: Vec<_> = root.attributes.iter().map(|a| &a.name.local_name[..]).collect();
+    assert_eq!(names, ["z", "a", "m"]);
+
+    let write = |root: &Element, indent: bool| {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .perform_indent(indent)
+                .create_writer(&mut b);
+            write_tree(&mut w, root).unwrap();
+        }
+        String::from_utf8(b).unwrap()
+    };
+
+    // without indentation the document is reproduced exactly, apart from the declaration
+    let written = write(&root, false);
+    assert_eq!(written, document.replace("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                                         "<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+    assert_eq!(parse(&written), root);
+
+    // with indentation the original whitespace is replaced, which leads to the same document
+    let written = write(&root, true);
+    assert_eq!(written, document);
+    assert_eq!(parse(&written), root);
+
+    // trees stripped of insignificant whitespace are indented by the writer
+    let mut stripped = root.clone();
+    stripped.children.retain(|n| match *n {
+        Node::Text(ref s) => !s.trim().is_empty(),
+        _ => true
+    });
+    assert_eq!(write(&stripped, true), document);
+}
+
+#[test]
+fn finding_elements() {
+    let root = parse(
+        "<root xmlns:p=\"urn:p\" version=\"2\">\
+           <group name=\"first\"><item id=\"1\">one</item><item>two</item></group>\
+           <group name=\"second\"><p:item id=\"3\">three</p:item><other id=\"4\"/></group>\
+           <item id=\"5\">five</item>\
+         </root>"
+    );
+
+    let texts = |elements: Vec<&Element>| -> Vec<String> { elements.iter().map(|e| e.text()).collect() };
+
+    assert_eq!(root.find("group/item").map(|e| e.text()), Some("one".into()));
+    assert_eq!(texts(root.find_all("group/item")), ["one", "two", "three"]);
+    assert_eq!(texts(root.find_all("group/p:item")), ["three"]);
+    assert_eq!(texts(root.find_all("item")), ["five"]);
+    assert_eq!(texts(root.find_all("*/*")), ["one", "two", "three", ""]);
+    assert_eq!(root.find_all("*").len(), 3);
+    assert_eq!(root.find("group").and_then(|e| e.attribute("name")), Some("first"));
+
+    assert_eq!(root.find_attribute("@version"), Some("2"));
+    assert_eq!(root.find_attribute("group/@name"), Some("first"));
+    assert_eq!(root.find_attribute("group/item/@id"), Some("1"));
+    assert_eq!(root.find_attribute("*/other/@id"), Some("4"));
+    assert_eq!(root.find_attribute("item/@id"), Some("5"));
+
+    // missing paths
+    assert!(root.find("missing").is_none());
+    assert!(root.find("group/missing").is_none());
+    assert!(root.find("group/item/missing").is_none());
+    assert!(root.find("item/*").is_none());
+    assert!(root.find_all("group/missing/item").is_empty());
+    assert_eq!(root.find_attribute("@missing"), None);
+    assert_eq!(root.find_attribute("group/@missing"), None);
+    assert_eq!(root.find_attribute("missing/@name"), None);
+
+    // malformed paths
+    assert!(root.find("").is_none());
+    assert!(root.find("/group").is_none());
+    assert!(root.find("group//item").is_none());
+    assert!(root.find("group/").is_none());
+    assert!(root.find("group/@name").is_none());
+    assert_eq!(root.find_attribute("group"), None);
+    assert_eq!(root.find_attribute("group/item"), None);
+}
+
+#[test]
+fn parsing_navigating_and_editing_tree() {
+    let reader = EventReader::new(&include_bytes!("documents/sample_1.xml")[..]);
+    let mut root = Element::parse(reader).unwrap();
+
+    assert_eq!(root.name.local_name, "project");
+    assert_eq!(root.attribute("name"), Some("project-name"));
+    let modules = root.children_named("module");
+    assert_eq!(modules.iter().map(|m| m.attribute("name").unwrap()).collect::<Vec<_>>(), ["module-1", "module-2"]);
+    let files = modules[0].get_child("files").unwrap().children_named("file");
+    assert_eq!(files.len(), 3);
+    assert_eq!(files[0].text().trim(), "Some <java> class");
+    assert!(root.children_named("missing").is_empty());
+
+    // editing
+    {
+        let libraries = root.get_child_mut("libraries").unwrap();
+        let mut library = Element::new(OwnedName::local("library"));
+        assert_eq!(library.set_attribute("groupId", "org.example"), None);
+        assert_eq!(library.set_attribute("artifactId", "new"), None);
+        assert_eq!(library.set_attribute("artifactId", "newer"), Some("new".into()));
+        library.push_child(Node::Text("text".into()));
+        libraries.push_child(library);
+        assert_eq!(libraries.children_named("library").len(), 3);
+    }
+    assert_eq!(root.set_attribute("name", "renamed"), Some("project-name".into()));
+    assert_eq!(root.remove_attribute("name"), Some("renamed".into()));
+    assert_eq!(root.remove_attribute("name"), None);
+    assert!(root.attributes.is_empty());
+
+    // the edited tree is written and read back
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .create_writer(&mut b);
+        root.write_to(&mut w).unwrap();
+    }
+    let reread = Element::parse(EventReader::new(&b[..])).unwrap();
+    let library = reread.get_child("libraries").unwrap().children_named("library")[2];
+    assert_eq!(library.attribute("groupId"), Some("org.example"));
+    assert_eq!(library.attribute("artifactId"), Some("newer"));
+    assert_eq!(library.text(), "text");
+    assert_eq!(reread.attribute("name"), None);
+}
+
+#[test]
+fn parsing_tree_with_limits() {
+    let document = "<a><b><c><d/></c></b></a>";
+
+    let parse = |config: ParserConfig| Element::parse(config.create_reader(document.as_bytes()));
+    assert!(parse(ParserConfig::new().max_element_depth(4)).is_ok());
+    let err = parse(ParserConfig::new().max_element_depth(3)).unwrap_err();
+    assert_eq!(err.msg(), "Element depth limit exceeded: more than 3 levels");
+    assert_eq!(err.position().to_string(), "1:12");
+
+    assert!(parse(ParserConfig::new().max_document_length(10)).is_err());
+}
+
+#[test]
+fn querying_elements() {
+    let root = parse(
+        "<library xmlns:x=\"urn:x\">\
+           <book id=\"1\"><author lang=\"en\">A</author><author lang=\"de\">B</author></book>\
+           <book id=\"2\" x:lang=\"fr\"><author lang=\"en\" primary=\"\">C</author><author>D</author></book>\
+           <x:book id=\"3\"><author lang=\"en\">E</author></x:book>\
+         </library>"
+    );
+    let select = |query: &str| -> Vec<String> {
+        root.select(&Query::parse(query).unwrap()).iter().map(|e| e.text()).collect()
+    };
+
+    assert_eq!(select("book/author[@lang='en']"), ["A", "C", "E"]);
+    assert_eq!(select("book/author[@lang=\"de\"]"), ["B"]);
+    assert_eq!(select("book/author[@lang]"), ["A", "B", "C", "E"]);
+    assert_eq!(select("x:book/author"), ["E"]);
+    assert_eq!(select("*/author[2]"), ["B", "D"]);
+    assert_eq!(select("book[2]/*"), ["C", "D"]);
+    assert_eq!(select("book[@id='2']/author[1]"), ["C"]);
+    assert_eq!(select("book[@x:lang='fr']/author"), ["C", "D"]);
+
+    // predicates are applied in order
+    assert_eq!(select("book/author[@lang='en'][@primary]"), ["C"]);
+    assert_eq!(select("book/author[@lang='en'][1]"), ["A", "C", "E"]);
+    assert_eq!(select("book/author[1][@lang='de']"), Vec::<String>::new());
+    assert_eq!(select("book/author[2][@lang='de']"), ["B"]);
+
+    // missing matches
+    assert!(select("book/author[@lang='it']").is_empty());
+    assert!(select("book/author[@missing]").is_empty());
+    assert!(select("book[4]").is_empty());
+    assert!(select("book/author/*").is_empty());
+    assert!(select("book[@lang='fr']").is_empty());
+
+    assert_eq!(root.select_first(&Query::parse("*[@id='3']").unwrap()).map(|e| e.text()), Some("E".into()));
+    assert!(root.select_first(&Query::parse("missing").unwrap()).is_none());
+
+    // find() and find_all() accept the same syntax
+    assert_eq!(root.find("book[2]/author").map(|e| e.text()), Some("C".into()));
+    assert_eq!(root.find_all("*/author[@lang='en']").len(), 3);
+    assert!(root.find_all("book[").is_empty());
+
+    // prefixes are resolved with the given mapping, whatever prefixes the document uses
+    let mut namespaces = Namespace::empty();
+    namespaces.put("y", "urn:x");
+    let query = Query::parse_with_namespaces("y:book/author", &namespaces).unwrap();
+    assert_eq!(root.select(&query).iter().map(|e| e.text()).collect::<Vec<_>>(), ["E"]);
+    let query = Query::parse_with_namespaces("*[@y:lang='fr']", &namespaces).unwrap();
+    assert_eq!(root.select(&query).iter().map(|e| e.attribute("id")).collect::<Vec<_>>(), [Some("2")]);
+    assert!(root.select(&Query::parse("y:book").unwrap()).is_empty());
+}
+
+#[test]
+fn query_errors() {
+    let error = |query: &str| {
+        let e = Query::parse(query).unwrap_err();
+        (e.offset(), e.to_string())
+    };
+
+    assert_eq!(error(""), (0, "Unexpected end of query, expected an element name or `*` at position 1".into()));
+    assert_eq!(error("/a"), (0, "Unexpected `/`, expected an element name or `*` at position 1".into()));
+    assert_eq!(error("a//b"), (2, "Unexpected `/`, expected an element name or `*` at position 3".into()));
+    assert_eq!(error("a/"), (2, "Unexpected end of query, expected an element name or `*` at position 3".into()));
+    assert_eq!(error("a/@id"), (2, "Unexpected `@`, expected an element name or `*` at position 3".into()));
+    assert_eq!(error("a b"), (1, "Unexpected ` `, expected `/` at position 2".into()));
+    assert_eq!(error("a[@id"), (5, "Unexpected end of query, expected `]` at position 6".into()));
+    assert_eq!(error("a[id]"), (2, "Unexpected `i`, expected `@` or a position at position 3".into()));
+    assert_eq!(error("a[@id=1]"), (6, "Unexpected `1`, expected a quoted value at position 7".into()));
+    assert_eq!(error("a[@id='1]"), (6, "Unterminated value at position 7".into()));
+    assert_eq!(error("a[0]"), (2, "Positions start at 1 at position 3".into()));
+    assert_eq!(error("a[99999999999999999999999]"), (2, "Position `99999999999999999999999` is too large at position 3".into()));
+    assert_eq!(error("a:"), (2, "Unexpected end of query, expected a local name at position 3".into()));
+    assert_eq!(error("ä/b[@ö='x'] c"), (11, "Unexpected ` `, expected `/` at position 12".into()));
+
+    let e = Query::parse_with_namespaces("a/p:b", &Namespace::empty()).unwrap_err();
+    assert_eq!((e.offset(), e.msg()), (2, "Unknown namespace prefix `p`"));
+}
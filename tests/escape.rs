@@ -0,0 +1,63 @@
+#![forbid(unsafe_code)]
+
+extern crate proptest;
+extern crate xml;
+
+use proptest::prelude::*;
+
+use xml::common::is_xml10_char;
+use xml::escape::{escape_str_attribute, escape_str_pcdata};
+use xml::reader::{EventReader, XmlEvent};
+
+/// Generates strings of characters allowed in XML 1.0 documents, with markup characters
+/// and whitespace being much more likely than in uniformly distributed Unicode.
+fn xml_string() -> impl Strategy<Value = String> {
+    const INTERESTING: &[char] = &['<', '>', '&', '"', '\'', ';', '#', ']', ' ', '\t', '\n', '\r'];
+
+    let c = prop_oneof![
+        1 => prop::sample::select(INTERESTING),
+        1 => prop::char::range(' ', '~'),
+        2 => any::<char>().prop_filter("not allowed in XML 1.0", |&c| is_xml10_char(c))
+    ];
+    prop::collection::vec(c, 0..40).prop_map(|chars| chars.into_iter().collect())
+}
+
+fn parse(document: &str) -> Vec<XmlEvent> {
+    EventReader::new(document.as_bytes()).into_iter()
+        .map(|e| e.unwrap_or_else(|e| panic!("{} in {:?}", e, document)))
+        .collect()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn escaped_pcdata_round_trips(s in xml_string()) {
+        let escaped = escape_str_pcdata(&s);
+        prop_assert!(!escaped.contains("]]>"), "escaped: {:?}", escaped);
+        let document = format!("<a>{}</a>", escaped);
+        let text: String = parse(&document).into_iter()
+            .filter_map(|e| match e {
+                XmlEvent::Characters(t) | XmlEvent::Whitespace(t) => Some(t),
+                _ => None
+            })
+            .collect();
+        // carriage returns are not escaped in character data, so the reader normalizes them
+        let expected = s.replace("\r\n", "\n").replace('\r', "\n");
+        prop_assert_eq!(text, expected, "document: {:?}", document);
+    }
+
+    #[test]
+    fn escaped_attribute_values_round_trip(s in xml_string()) {
+        for &quote in &['"', '\''] {
+            let document = format!("<a v={q}{}{q}/>", escape_str_attribute(&s), q = quote);
+            let value = parse(&document).into_iter()
+                .filter_map(|e| match e {
+                    XmlEvent::StartElement { mut attributes, .. } => Some(attributes.remove(0).value),
+                    _ => None
+                })
+                .next();
+            prop_assert_eq!(value, Some(s.clone()), "document: {:?}", document);
+        }
+    }
+}
@@ -4,15 +4,19 @@ extern crate xml;
 #[macro_use]
 extern crate lazy_static;
 
+use std::cell::Cell;
 use std::env;
 use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Write, stderr};
+use std::io::{BufRead, BufReader, Read, Write, stderr};
 use std::path::Path;
+use std::rc::Rc;
 
+use xml::attribute::OwnedAttribute;
 use xml::name::OwnedName;
-use xml::common::Position;
-use xml::reader::{Result, XmlEvent, ParserConfig, EventReader};
+use xml::namespace::{self, Namespace};
+use xml::common::{Position, XmlVersion};
+use xml::reader::{self, Result, XmlEvent, XmlEventKind, ParserConfig, EventReader};
 
 /// Dummy function that opens a file, parses it, and returns a `Result`.
 /// There can be IO errors (from `File::open`) and XML errors (from the parser).
@@ -465,6 +469,1860 @@ fn issue_replacement_character_entity_reference() {
     );
 }
 
+#[test]
+fn convenience_constructors() {
+    let doc = r#"<a x="1"><b>text</b></a>"#;
+
+    let from_str: Vec<_> = EventReader::from_str(doc).into_iter().map(|e| e.unwrap()).collect();
+    let bytes = doc.as_bytes().to_vec();
+    let from_bytes: Vec<_> = EventReader::from_bytes(&bytes).into_iter().map(|e| e.unwrap()).collect();
+
+    assert_eq!(from_str.len(), 7);
+    assert_eq!(from_str, from_bytes);
+
+    let config = ParserConfig::new().ignore_comments(false);
+    let doc = "<a><!--comment--></a>";
+    let from_str: Vec<_> = EventReader::from_str_with_config(doc, config.clone())
+        .into_iter().map(|e| e.unwrap()).collect();
+    let from_bytes: Vec<_> = EventReader::from_bytes_with_config(doc.as_bytes(), config)
+        .into_iter().map(|e| e.unwrap()).collect();
+
+    assert_eq!(from_str[2], XmlEvent::Comment("comment".into()));
+    assert_eq!(from_str, from_bytes);
+}
+
+#[test]
+fn current_path_follows_element_events() {
+    fn path<R: std::io::Read>(reader: &EventReader<R>) -> String {
+        reader.current_path().map(|n| n.local_name).collect::<Vec<_>>().join("/")
+    }
+
+    let mut reader = EventReader::from_str("<root><item><title>a</title><empty/></item><item/></root>");
+
+    assert_eq!(reader.depth(), 0);
+    loop {
+        let e = reader.next().unwrap();
+        match e {
+            XmlEvent::StartDocument { .. } => assert_eq!(path(&reader), ""),
+            XmlEvent::Characters(_) => assert_eq!(path(&reader), "root/item/title"),
+            XmlEvent::StartElement { ref name, .. } if name.local_name == "empty" =>
+                assert_eq!(path(&reader), "root/item/empty"),
+            XmlEvent::EndElement { ref name } if name.local_name == "empty" =>
+                assert_eq!(path(&reader), "root/item"),
+            XmlEvent::EndElement { ref name } if name.local_name == "title" =>
+                assert_eq!(path(&reader), "root/item"),
+            XmlEvent::EndElement { ref name } if name.local_name == "root" =>
+                assert_eq!(reader.depth(), 0),
+            XmlEvent::EndDocument => break,
+            _ => {}
+        }
+        assert_eq!(reader.depth(), reader.current_path().len());
+    }
+}
+
+#[test]
+fn multiple_root_elements() {
+    test(
+        br#"<a/> <b>x</b>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a)
+            |EndElement(a)
+            |1:6 Unexpected element after the root element
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<a/> <b>x</b>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a)
+            |EndElement(a)
+            |StartElement(b)
+            |Characters("x")
+            |EndElement(b)
+            |EndDocument
+        "#,
+        ParserConfig::new()
+            .allow_multiple_root_elements(true),
+        false
+    );
+}
+
+/// An endless stream consisting of the prefix followed by the repeated chunk.
+struct Repeat {
+    prefix: &'static [u8],
+    chunk: &'static [u8],
+    pos: usize
+}
+
+impl std::io::Read for Repeat {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        for b in buf.iter_mut() {
+            *b = if self.pos < self.prefix.len() {
+                self.prefix[self.pos]
+            } else {
+                self.chunk[(self.pos - self.prefix.len()) % self.chunk.len()]
+            };
+            self.pos += 1;
+        }
+        Ok(buf.len())
+    }
+}
+
+#[test]
+fn data_length_limit() {
+    let source = Repeat { prefix: b"<a>", chunk: b"text ", pos: 0 };
+    let mut reader = ParserConfig::new().max_data_length(1000).create_reader(source);
+
+    match reader.next() { Ok(XmlEvent::StartDocument { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+    match reader.next() { Ok(XmlEvent::StartElement { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+    let e = reader.next().unwrap_err();
+    assert_eq!(e.msg(), "Data length limit exceeded: more than 1000 bytes");
+    assert_eq!(e.position().column, 1003);
+
+    let source = Repeat { prefix: b"<a>", chunk: b"<!--comment-->", pos: 0 };
+    let mut reader = ParserConfig::new().ignore_comments(false).max_data_length(1000).create_reader(source);
+    for _ in 0..10 {
+        assert!(reader.next().is_ok());
+    }
+}
+
+#[test]
+fn document_length_limit() {
+    let source = Repeat { prefix: b"<a>", chunk: b"<b>text</b>", pos: 0 };
+    let mut reader = ParserConfig::new().max_document_length(10000u64).create_reader(source);
+
+    let mut events = 0;
+    let e = loop {
+        match reader.next() {
+            Ok(_) => events += 1,
+            Err(e) => break e
+        }
+    };
+    assert!(events > 2000);
+    assert_eq!(e.msg(), "Document length limit exceeded: more than 10000 bytes");
+}
+
+#[test]
+fn parameter_entities_in_internal_subset() {
+    test(
+        br#"<!DOCTYPE doc [
+            <!ENTITY % common "<!ENTITY greeting 'Hello, &who;!'> <!ENTITY who 'world'>">
+            %common;
+        ]>
+        <doc a="&greeting;">&greeting; &#x41;&amp;</doc>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(doc [a="Hello, world!"])
+            |Characters("Hello, world! A&")
+            |EndElement(doc)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<!DOCTYPE doc [
+            <!ENTITY % name "code">
+            <!ELEMENT %name; (#PCDATA)>
+        ]><doc/>"#,
+        br#"
+            |3:13 Parameter entity references are not allowed within markup declarations in the internal subset
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<!DOCTYPE doc [
+            <!ENTITY % defined "<!ENTITY a 'b'>">
+            %defined; %undefined;
+        ]><doc/>"#,
+        br#"
+            |3:23 Undeclared parameter entity: %undefined;
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<!DOCTYPE doc [
+            <!ENTITY a "&b;&b;&b;&b;&b;&b;&b;&b;&b;&b;">
+            <!ENTITY b "&c;&c;&c;&c;&c;&c;&c;&c;&c;&c;">
+            <!ENTITY c "lol">
+        ]><doc>&a;</doc>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(doc)
+            |5:18 Entity expansion length limit exceeded: more than 100 bytes
+        "#,
+        ParserConfig::new().max_entity_expansion_length(100),
+        false
+    );
+}
+
+#[test]
+fn byte_offset_allows_slicing_elements() {
+    let source = "<root><sig a=\"é\">x</sig></root>".as_bytes();
+    let mut reader = EventReader::new_buffered(source);
+
+    let mut start = 0;
+    let raw = loop {
+        let event = reader.next().unwrap();
+        match event {
+            XmlEvent::StartElement { ref name, .. } if name.local_name == "root" =>
+                start = reader.byte_offset() as usize,
+            XmlEvent::EndElement { ref name } if name.local_name == "sig" =>
+                break &source[start..reader.byte_offset() as usize],
+            _ => {}
+        }
+    };
+    assert_eq!(raw, "<sig a=\"é\">x</sig>".as_bytes());
+}
+
+#[test]
+fn reading_raw_element_bytes() {
+    use std::io::Cursor;
+
+    let data = "prefix<doc><a x=\"é\"><b/><!-- <c> --><![CDATA[</a>]]></a><e/></doc>".as_bytes();
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(6);
+    let mut reader = EventReader::new(cursor);
+
+    assert!(reader.read_element_raw().is_err());
+    match reader.next() { Ok(XmlEvent::StartDocument { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+    match reader.next() { Ok(XmlEvent::StartElement { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+    match reader.next() { Ok(XmlEvent::StartElement { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+    assert_eq!(reader.read_element_raw().unwrap(),
+               "<a x=\"é\"><b/><!-- <c> --><![CDATA[</a>]]></a>".as_bytes());
+
+    match reader.next() { Ok(XmlEvent::StartElement { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+    assert_eq!(reader.read_element_raw().unwrap(), b"<e/>");
+    assert_eq!(reader.depth(), 1);
+    assert_eq!(reader.read_element_raw().unwrap(), &data[6..]);
+    match reader.next() { Ok(XmlEvent::EndDocument) => {}, e => panic!("Unexpected event: {:?}", e) }
+
+    let mut reader = EventReader::new(Cursor::new(&b"<doc><a></b></doc>"[..]));
+    reader.next().unwrap();
+    reader.next().unwrap();
+    reader.next().unwrap();
+    assert!(reader.read_element_raw().is_err());
+}
+
+#[test]
+fn malformed_xml_declarations() {
+    test(
+        br#"<?xml encoding="UTF-8" version="1.0"?><a/>"#,
+        br#"
+            |1:7 Unexpected token inside XML declaration: e
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<?xml version="1.0" standalone="yes" encoding="UTF-8"?><a/>"#,
+        br#"
+            |1:38 Unexpected token inside XML declaration: e
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<?xml version="2.0"?><a/>"#,
+        br#"
+            |1:19 Unexpected XML version value: 2.0
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<?xml version="1."?><a/>"#,
+        br#"
+            |1:18 Unexpected XML version value: 1.
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<?xml version="1.0" encoding="8bit"?><a/>"#,
+        br#"
+            |1:35 Invalid encoding name: 8bit
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<?xml version="1.0" standalone="maybe"?><a/>"#,
+        br#"
+            |1:38 Invalid standalone declaration value: maybe
+        "#,
+        ParserConfig::new(),
+        false
+    );
+}
+
+#[test]
+fn xml_declaration_fields() {
+    let mut reader = EventReader::from_str("<?xml version='1.5' encoding='latin1' standalone='yes'?><a/>");
+    assert_eq!(reader.next().unwrap(), XmlEvent::StartDocument {
+        version: XmlVersion::Version10,
+        encoding: Some("latin1".into()),
+        effective_encoding: "ISO-8859-1".into(),
+        standalone: Some(true)
+    });
+
+    let mut reader = EventReader::from_str("<a/>");
+    assert_eq!(reader.next().unwrap(), XmlEvent::StartDocument {
+        version: XmlVersion::Version10,
+        encoding: None,
+        effective_encoding: "UTF-8".into(),
+        standalone: None
+    });
+}
+
+#[test]
+fn invalid_character_policy() {
+    use xml::reader::InvalidCharacterPolicy;
+
+    fn read(policy: InvalidCharacterPolicy) -> (Vec<String>, Vec<String>) {
+        let mut reader = ParserConfig::new()
+            .trim_whitespace(true)
+            .cdata_to_characters(true)
+            .invalid_character_policy(policy)
+            .create_reader(&include_bytes!("documents/invalid_characters.xml")[..]);
+        let mut texts = Vec::new();
+        loop {
+            match reader.next().unwrap() {
+                XmlEvent::StartElement { attributes, .. } =>
+                    texts.extend(attributes.into_iter().map(|a| a.value)),
+                XmlEvent::Characters(s) => texts.push(s),
+                XmlEvent::EndDocument => break,
+                _ => {}
+            }
+        }
+        let warnings = reader.take_warnings().iter().map(|w| w.to_string()).collect();
+        assert!(reader.take_warnings().is_empty());
+        (texts, warnings)
+    }
+
+    let expected_warnings = vec![
+        "3:16 Character U+000B is not allowed in XML documents".to_owned(),
+        "3:23 Character U+000B is not allowed in XML documents".to_owned(),
+        "3:27 Character U+0001 is not allowed in XML documents".to_owned(),
+        "4:11 Character U+001F is not allowed in XML documents".to_owned(),
+        "4:30 Character U+000C is not allowed in XML documents".to_owned(),
+    ];
+
+    let (texts, warnings) = read(InvalidCharacterPolicy::ReplaceWith('\u{fffd}'));
+    assert_eq!(texts, vec!["a\u{fffd}b", "one\u{fffd}two\u{fffd}", "\u{fffd}threefour\u{fffd}five"]);
+    assert_eq!(warnings, expected_warnings);
+
+    let (texts, warnings) = read(InvalidCharacterPolicy::Strip);
+    assert_eq!(texts, vec!["ab", "onetwo", "threefourfive"]);
+    assert_eq!(warnings, expected_warnings);
+
+    test(
+        include_bytes!("documents/invalid_characters.xml"),
+        br#"
+            |StartDocument(1.0, utf-8)
+            |StartElement(records)
+            |Whitespace("\n  ")
+            |3:16 Character U+000B is not allowed in XML documents
+        "#,
+        ParserConfig::new(),
+        false
+    );
+}
+
+#[test]
+fn characters_outside_of_char_production() {
+    test(
+        "<a>form\u{c}feed</a>".as_bytes(),
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a)
+            |1:8 Character U+000C is not allowed in XML documents
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        "<a b='\u{fffe}'/>".as_bytes(),
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |1:7 Character U+FFFE is not allowed in XML documents
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        "<a b='\t\n\r'>\t\n\r\u{7f}<!--\u{10ffff}--></a>".as_bytes(),
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a [b="\t\n\n"])
+            |Characters("\t\n\n\u{7f}")
+            |EndElement(a)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        "<?xml version='1.1'?><a>\u{7f}</a>".as_bytes(),
+        br#"
+            |StartDocument(1.1, UTF-8)
+            |StartElement(a)
+            |1:25 Character U+007F is not allowed in XML documents
+        "#,
+        ParserConfig::new(),
+        false
+    );
+}
+
+#[test]
+fn character_references_outside_of_char_production() {
+    test(
+        b"<a>&#1;</a>",
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a)
+            |1:7 Character U+0001 is not allowed in XML documents: &#1;
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        b"<a>&#xFFFE;</a>",
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a)
+            |1:11 Character U+FFFE is not allowed in XML documents: &#xFFFE;
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        b"<a b='&#1;'/>",
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |1:10 Character U+0001 is not allowed in XML documents: &#1;
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        b"<!DOCTYPE a [<!ENTITY e '&#1;'>]><a>&e;</a>",
+        br#"
+            |1:14 Character U+0001 is not allowed in XML documents: &#1;
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        b"<!DOCTYPE a [<!ENTITY e '&#38;#1;'>]><a>&e;</a>",
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a)
+            |1:43 Character U+0001 is not allowed in XML documents: &#1;
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        b"<!DOCTYPE a [<!ATTLIST a b CDATA '&#1;'>]><a/>",
+        br#"
+            |1:14 Character U+0001 is not allowed in XML documents: &#1;
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        b"<a>&#9;&#x10FFFF;</a>",
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a)
+            |Characters("\t\u{10ffff}")
+            |EndElement(a)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    // restricted characters of XML 1.1 are allowed in references
+    test(
+        b"<?xml version='1.1'?><a>&#1;&#x7F;&#0;</a>",
+        br#"
+            |StartDocument(1.1, UTF-8)
+            |StartElement(a)
+            |1:38 Character U+0000 is not allowed in XML documents: &#0;
+        "#,
+        ParserConfig::new(),
+        false
+    );
+}
+
+#[test]
+fn parsing_whole_documents() {
+    fn ns(extra: &[(&str, &str)]) -> Namespace {
+        let mut ns = Namespace::empty();
+        ns.put(namespace::NS_NO_PREFIX, namespace::NS_EMPTY_URI);
+        ns.put(namespace::NS_XML_PREFIX, namespace::NS_XML_URI);
+        ns.put(namespace::NS_XMLNS_PREFIX, namespace::NS_XMLNS_URI);
+        for &(prefix, uri) in extra {
+            ns.put(prefix, uri);
+        }
+        ns
+    }
+
+    fn start_document(encoding: Option<&str>) -> XmlEvent {
+        XmlEvent::StartDocument {
+            version: XmlVersion::Version10,
+            encoding: encoding.map(|e| e.into()),
+            effective_encoding: "UTF-8".into(),
+            standalone: None
+        }
+    }
+
+    let config = ParserConfig::new();
+
+    assert_eq!(reader::parse_str("<a/>", &config).unwrap(), vec![
+        start_document(None),
+        XmlEvent::StartElement { name: OwnedName::local("a"), attributes: vec![], namespace: ns(&[]) },
+        XmlEvent::EndElement { name: OwnedName::local("a") },
+        XmlEvent::EndDocument
+    ]);
+
+    let doc = "<?xml version='1.0' encoding='utf-8'?>\n\
+               <a xmlns:p='urn:p' x='1'><p:b>t &amp; u</p:b><![CDATA[<c>]]></a>\n";
+    assert_eq!(reader::parse_str(doc, &config).unwrap(), vec![
+        start_document(Some("utf-8")),
+        XmlEvent::StartElement {
+            name: OwnedName::local("a"),
+            attributes: vec![OwnedAttribute::new(OwnedName::local("x"), "1")],
+            namespace: ns(&[("p", "urn:p")])
+        },
+        XmlEvent::StartElement {
+            name: OwnedName::qualified("b", "urn:p", Some("p")),
+            attributes: vec![],
+            namespace: ns(&[("p", "urn:p")])
+        },
+        XmlEvent::Characters("t & u".into()),
+        XmlEvent::EndElement { name: OwnedName::qualified("b", "urn:p", Some("p")) },
+        XmlEvent::CData("<c>".into()),
+        XmlEvent::EndElement { name: OwnedName::local("a") },
+        XmlEvent::EndDocument
+    ]);
+
+    let config = ParserConfig::new().ignore_comments(false);
+    let doc = b"<a>x</a><?pi data?> <!--after-->";
+    assert_eq!(reader::parse_bytes(doc, &config).unwrap(), vec![
+        start_document(None),
+        XmlEvent::StartElement { name: OwnedName::local("a"), attributes: vec![], namespace: ns(&[]) },
+        XmlEvent::Characters("x".into()),
+        XmlEvent::EndElement { name: OwnedName::local("a") },
+        XmlEvent::ProcessingInstruction { name: "pi".into(), data: Some("data".into()) },
+        XmlEvent::Comment("after".into()),
+        XmlEvent::EndDocument
+    ]);
+
+    for doc in &["<a/>trailing", "<a/><b/>", "<a>", ""] {
+        assert!(reader::parse_str(doc, &config).is_err(), "{:?} should be rejected", doc);
+    }
+}
+
+#[test]
+fn reading_event_kinds() {
+    let mut reader = EventReader::from_str("<a><!--c--><b x='1'/>text<![CDATA[d]]><?pi?></a>");
+    let mut kinds = Vec::new();
+    loop {
+        match reader.next_kind().unwrap() {
+            XmlEventKind::EndDocument => break,
+            kind => kinds.push(kind)
+        }
+    }
+    assert_eq!(kinds, vec![
+        XmlEventKind::StartDocument,
+        XmlEventKind::StartElement,
+        XmlEventKind::StartElement,
+        XmlEventKind::EndElement,
+        XmlEventKind::Characters,
+        XmlEventKind::CData,
+        XmlEventKind::ProcessingInstruction,
+        XmlEventKind::EndElement
+    ]);
+
+    let mut f = File::open("tests/documents/sample_1.xml").unwrap();
+    assert!(EventReader::new(BufReader::new(&mut f)).validate().is_ok());
+
+    for doc in &["<a>", "<a></b>", "<a/><b/>", "<a x='1' x='2'/>", "<a>&unknown;</a>"] {
+        let mut reader = EventReader::from_str(doc);
+        assert!(reader.validate().is_err(), "{:?} should be rejected", doc);
+        // the error is returned again by subsequent calls
+        assert!(reader.validate().is_err());
+    }
+
+    // events read afterwards with next() have all their data
+    let mut reader = EventReader::from_str("<a xmlns:p='urn:p'><b x='1'/><p:c>text</p:c><?pi d?></a>");
+    for _ in 0..3 {
+        reader.next_kind().unwrap();
+    }
+    assert_eq!(reader.current_path().map(|n| n.local_name).collect::<Vec<_>>(), ["a", "b"]);
+    assert_eq!(reader.next().unwrap(), XmlEvent::EndElement { name: OwnedName::local("b") });
+    assert_eq!(reader.next_kind().unwrap(), XmlEventKind::StartElement);
+    assert_eq!(reader.next_kind().unwrap(), XmlEventKind::Characters);
+    assert_eq!(reader.next().unwrap(), XmlEvent::EndElement { name: OwnedName::qualified("c", "urn:p", Some("p")) });
+    assert_eq!(reader.peek().as_ref().unwrap().kind(), XmlEventKind::ProcessingInstruction);
+    assert_eq!(reader.next_kind().unwrap(), XmlEventKind::ProcessingInstruction);
+    assert_eq!(reader.next().unwrap(), XmlEvent::EndElement { name: OwnedName::local("a") });
+
+    // validity errors are still found when validation against the DTD is enabled
+    let document = "<!DOCTYPE a [<!ELEMENT a EMPTY>]><a>text</a>";
+    assert!(EventReader::from_str(document).validate().is_ok());
+    let config = ParserConfig::new().validate_dtd(true);
+    assert!(EventReader::new_with_config(document.as_bytes(), config).validate().is_err());
+}
+
+#[test]
+fn declared_entities_are_exposed() {
+    let mut reader = EventReader::from_str(r#"<!DOCTYPE doc [
+        <!ENTITY % common "<!ENTITY greeting 'Hello, &who;&#33;'>">
+        %common;
+        <!ENTITY who "world">
+        <!ENTITY who "ignored">
+        <!ENTITY logo SYSTEM "logo.png" NDATA png>
+    ]><doc>&greeting;</doc>"#);
+    assert!(reader.entities().is_empty());
+
+    match reader.next() { Ok(XmlEvent::StartDocument { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+    match reader.next() { Ok(XmlEvent::StartElement { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+
+    let mut entities: Vec<_> = reader.entities().iter().map(|(k, v)| (&k[..], &v[..])).collect();
+    entities.sort();
+    assert_eq!(entities, vec![("greeting", "Hello, &who;!"), ("who", "world")]);
+    assert_eq!(reader.parameter_entities().len(), 1);
+    assert_eq!(
+        reader.parameter_entities().get("common").map(|s| &s[..]),
+        Some("<!ENTITY greeting 'Hello, &who;!'>")
+    );
+
+    assert_eq!(reader.next().unwrap(), XmlEvent::Characters("Hello, world!".into()));
+}
+
+#[test]
+fn reporting_doctype() {
+    let doc = "<?xml version='1.0'?>\n<!DOCTYPE doc SYSTEM 'doc.dtd' [<!ENTITY e 'x'>]>\n<doc>&e;</doc>";
+    let mut reader = ParserConfig::new().report_doctype(true).create_reader(doc.as_bytes());
+    match reader.next() { Ok(XmlEvent::StartDocument { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+    assert_eq!(
+        reader.next().unwrap(),
+        XmlEvent::DoctypeDecl {
+            name: "doc".into(),
+            public_id: None,
+            system_id: Some("doc.dtd".into()),
+            internal_subset: Some("<!ENTITY e 'x'>".into())
+        }
+    );
+    assert_eq!(reader.position().to_string(), "2:1");
+    assert_eq!(reader.entities().get("e").map(|s| &s[..]), Some("x"));
+    match reader.next() { Ok(XmlEvent::StartElement { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+    assert_eq!(reader.position().to_string(), "3:1");
+
+    // the declaration is not reported by default
+    let events = reader::parse_str(doc, &ParserConfig::new()).unwrap();
+    assert!(events.iter().all(|e| e.kind() != XmlEventKind::DoctypeDecl));
+}
+
+#[test]
+fn unpaired_quotes_in_internal_subset() {
+    let mut reader = EventReader::from_str(r#"<!DOCTYPE doc [
+        <?pi it's?>
+        <!-- "unpaired -->
+        <!ENTITY markup "it's <b>bold</b> ]>">
+    ]><doc/>"#);
+
+    match reader.next() { Ok(XmlEvent::StartDocument { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+    match reader.next() { Ok(XmlEvent::StartElement { .. }) => {}, e => panic!("Unexpected event: {:?}", e) }
+    assert_eq!(reader.entities().get("markup").map(|s| &s[..]), Some("it's <b>bold</b> ]>"));
+}
+
+#[test]
+fn external_entities() {
+    use std::io::{self, BufRead, Cursor};
+    use xml::reader::ExternalId;
+
+    let doc = r#"<!DOCTYPE doc [
+        <!ENTITY name "world">
+        <!ENTITY greeting SYSTEM "greeting.ent">
+        <!ENTITY missing PUBLIC "-//missing" "missing.ent">
+    ]><doc>&greeting;</doc>"#;
+
+    fn read_text(mut reader: EventReader<&[u8]>) -> Result<String> {
+        loop {
+            match reader.next()? {
+                XmlEvent::Characters(s) => return Ok(s),
+                XmlEvent::EndDocument => return Ok(String::new()),
+                _ => {}
+            }
+        }
+    }
+
+    let mut reader = EventReader::from_str(doc);
+    reader.set_external_entity_resolver(|id: &ExternalId| match &id.system_id[..] {
+        "greeting.ent" => Ok(Box::new(Cursor::new("<?xml encoding='utf-8'?>Hello, &name;!")) as Box<dyn BufRead>),
+        _ => Err(io::Error::new(io::ErrorKind::NotFound, "not found"))
+    });
+    assert_eq!(read_text(reader).unwrap(), "Hello, world!");
+
+    let mut reader = EventReader::from_str(doc);
+    reader.set_external_entity_resolver(|_: &ExternalId| Err(io::Error::new(io::ErrorKind::NotFound, "not found")));
+    let e = read_text(reader).unwrap_err();
+    assert_eq!(e.msg(), "Failed to read external entity &greeting;: not found");
+
+    let e = read_text(EventReader::from_str(doc)).unwrap_err();
+    assert_eq!(e.msg(), "External entities are disabled, as no resolver is set: &greeting;");
+
+    let config = ParserConfig::new().skip_external_entities(true);
+    let reader = EventReader::from_str_with_config(doc, config.clone());
+    assert_eq!(read_text(reader).unwrap(), "");
+
+    let doc = r#"<!DOCTYPE doc [<!ENTITY ext SYSTEM "ext.ent">]><doc a="&ext;"/>"#;
+    let e = read_text(EventReader::from_str_with_config(doc, config)).unwrap_err();
+    assert_eq!(e.msg(), "External entity references are not allowed in attribute values: &ext;");
+}
+
+#[test]
+fn entities_with_markup() {
+    use std::io::{self, BufRead, Cursor};
+    use xml::reader::ExternalId;
+
+    test(
+        br#"<!DOCTYPE doc [
+            <!ENTITY sep "<br/>">
+            <!ENTITY item "<item>&amp;&sep;</item>">
+            <!ENTITY list "text&item;&item;">
+        ]><doc>&list;</doc>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(doc)
+            |Characters("text")
+            |StartElement(item)
+            |Characters("&")
+            |StartElement(br)
+            |EndElement(br)
+            |EndElement(item)
+            |StartElement(item)
+            |Characters("&")
+            |StartElement(br)
+            |EndElement(br)
+            |EndElement(item)
+            |EndElement(doc)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<!DOCTYPE doc [<!ENTITY a "<a>&b;</a>"><!ENTITY b "<b>&a;</b>">]><doc>&a;</doc>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(doc)
+            |StartElement(a)
+            |StartElement(b)
+            |1:74 Recursive entity reference: &a;
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<!DOCTYPE doc [<!ENTITY open "<p>">]><doc>&open;</p></doc>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(doc)
+            |StartElement(p)
+            |1:49 Unbalanced markup in the replacement text of an entity
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<!DOCTYPE doc [<!ENTITY a "<a/>"><!ENTITY b "<b>&a;</b>"><!ENTITY c "<c>&b;</c>">]><doc>&c;</doc>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(doc)
+            |StartElement(c)
+            |StartElement(b)
+            |1:92 Entity expansion depth limit exceeded: more than 2 levels
+        "#,
+        ParserConfig::new().max_entity_expansion_depth(2),
+        false
+    );
+
+    // the content of external entities is parsed in place of the references
+    let doc = r#"<!DOCTYPE doc [<!ENTITY chapter SYSTEM "chapter.ent">]><doc>&chapter;</doc>"#;
+    let mut reader = EventReader::from_str(doc);
+    reader.set_external_entity_resolver(|id: &ExternalId| match &id.system_id[..] {
+        "chapter.ent" => Ok(Box::new(Cursor::new("<?xml encoding='utf-8'?><title>One</title>")) as Box<dyn BufRead>),
+        _ => Err(io::Error::new(io::ErrorKind::NotFound, "not found"))
+    });
+    let events: Vec<_> = reader.into_iter().map(|e| Event(&e).to_string()).collect();
+    assert_eq!(events, [
+        "StartDocument(1.0, UTF-8)", "StartElement(doc)",
+        "StartElement(title)", r#"Characters("One")"#, "EndElement(title)",
+        "EndElement(doc)", "EndDocument"
+    ]);
+}
+
+#[test]
+fn external_dtd_subset() {
+    use std::collections::HashMap;
+    use std::io::{self, BufRead, Cursor};
+    use xml::reader::ExternalId;
+
+    let mut catalog = HashMap::new();
+    catalog.insert("doc.dtd", "<?xml encoding='utf-8'?>\n\
+        <!ENTITY who 'external'>\n\
+        <!ENTITY chapter SYSTEM 'chapter.ent'>\n\
+        <!ELEMENT doc (#PCDATA)>\n\
+        <!ATTLIST doc version CDATA '2'>");
+    catalog.insert("chapter.ent", "Chapter by &who;");
+    catalog.insert("broken.dtd", "<![INCLUDE[ <!ENTITY a 'b'> ]]>");
+    let resolver = move |id: &ExternalId| match catalog.get(&id.system_id[..]) {
+        Some(content) => Ok(Box::new(Cursor::new(content.as_bytes().to_vec())) as Box<dyn BufRead>),
+        None => Err(io::Error::new(io::ErrorKind::NotFound, "not in the catalog"))
+    };
+
+    let read = |doc: &str, with_resolver: bool| {
+        let mut reader = ParserConfig::new().validate_dtd(true).create_reader(doc.as_bytes());
+        if with_resolver {
+            reader.set_external_entity_resolver(resolver.clone());
+        }
+        let mut events = Vec::new();
+        loop {
+            match reader.next() {
+                Ok(XmlEvent::EndDocument) => return Ok(events),
+                Ok(XmlEvent::StartDocument { .. }) => {}
+                Ok(event) => events.push(event),
+                Err(e) => return Err(e.to_string())
+            }
+        }
+    };
+
+    // the internal subset takes precedence
+    let doc = "<!DOCTYPE doc SYSTEM 'doc.dtd' [<!ENTITY who 'internal'>]><doc>&chapter;</doc>";
+    assert_eq!(read(doc, true).unwrap(), vec![
+        XmlEvent::StartElement {
+            name: OwnedName::local("doc"),
+            attributes: vec![OwnedAttribute::new(OwnedName::local("version"), "2")],
+            namespace: namespace::NamespaceStack::default().squash()
+        },
+        XmlEvent::Characters("Chapter by internal".into()),
+        XmlEvent::EndElement { name: OwnedName::local("doc") },
+    ]);
+
+    // without a resolver, the external subset is not read
+    assert_eq!(read(doc, false).unwrap_err(),
+               "1:59 Element doc is not declared");
+
+    assert_eq!(read("<!DOCTYPE doc PUBLIC '-//missing' 'missing.dtd'><doc/>", true).unwrap_err(),
+               "1:48 Failed to read the external DTD subset \"missing.dtd\": not in the catalog");
+    assert_eq!(read("<!DOCTYPE doc SYSTEM 'broken.dtd'><doc/>", true).unwrap_err(),
+               "1:34 Conditional sections are not supported in the external DTD subset \"broken.dtd\"");
+}
+
+#[test]
+fn entity_resolver_in_configuration() {
+    use std::collections::HashMap;
+    use std::io::{BufRead, Cursor};
+    use std::sync::{Arc, Mutex};
+    use xml::reader::{EntityResolver, ExternalId};
+
+    // the public and system identifiers of the resolved entities
+    type Requests = Arc<Mutex<Vec<(Option<String>, String)>>>;
+
+    struct Catalog {
+        entities: HashMap<&'static str, &'static str>,
+        requests: Requests
+    }
+
+    impl EntityResolver for Catalog {
+        fn resolve(&mut self, public_id: Option<&str>, system_id: &str) -> Result<Option<Box<dyn BufRead>>> {
+            self.requests.lock().unwrap().push((public_id.map(|id| id.into()), system_id.into()));
+            Ok(self.entities.get(system_id).map(|&text| Box::new(Cursor::new(text)) as Box<dyn BufRead>))
+        }
+    }
+
+    let mut entities = HashMap::new();
+    entities.insert("doc.dtd", "<!ENTITY chapter PUBLIC '-//chapter' 'chapter.ent'>");
+    entities.insert("chapter.ent", "Chapter one");
+    let requests = Requests::default();
+    let config = ParserConfig::new().entity_resolver(Catalog { entities, requests: requests.clone() });
+
+    let text = |config: &ParserConfig, doc: &str| -> Result<String> {
+        let mut reader = config.clone().create_reader(doc.as_bytes());
+        loop {
+            match reader.next()? {
+                XmlEvent::Characters(s) => return Ok(s),
+                XmlEvent::EndDocument => return Ok(String::new()),
+                _ => {}
+            }
+        }
+    };
+
+    assert_eq!(text(&config, "<!DOCTYPE doc SYSTEM 'doc.dtd'><doc>&chapter;</doc>").unwrap(), "Chapter one");
+    assert_eq!(*requests.lock().unwrap(), vec![
+        (None, "doc.dtd".to_string()),
+        (Some("-//chapter".to_string()), "chapter.ent".to_string())
+    ]);
+
+    // unresolved entities are handled as if there were no resolver
+    let doc = "<!DOCTYPE doc SYSTEM 'other.dtd' [<!ENTITY e SYSTEM 'e.ent'>]><doc>&e;</doc>";
+    assert_eq!(text(&config, doc).unwrap_err().msg(), "External entities are disabled, as no resolver is set: &e;");
+    assert_eq!(text(&config.clone().skip_external_entities(true), doc).unwrap(), "");
+
+    // a function resolves every entity; copies of the configuration share it
+    let config = config.entity_resolver(|_: &ExternalId| Ok(Box::new(Cursor::new("")) as Box<dyn BufRead>));
+    assert_eq!(text(&config, doc).unwrap(), "");
+    assert_eq!(config.clone(), config);
+    assert!(config != ParserConfig::new());
+}
+
+#[test]
+fn filtering_and_mapping_events() {
+    use xml::attribute::OwnedAttribute;
+    use xml::reader::EventIterator;
+
+    let reader = EventReader::new_with_config(
+        &b"<a><!--drop--><b/>\n<!--me--><c/></a>"[..],
+        ParserConfig::new().ignore_comments(false)
+    );
+    let mut events = reader
+        .filter_events(|e| !matches!(*e, XmlEvent::Comment(_)))
+        .map_events(|e| match e {
+            XmlEvent::StartElement { name, mut attributes, namespace } => {
+                attributes.push(OwnedAttribute::new(OwnedName::local("seen"), "yes"));
+                XmlEvent::StartElement { name, attributes, namespace }
+            }
+            e => e
+        })
+        .filter_events(|e| !matches!(*e, XmlEvent::Whitespace(_)));
+
+    let mut result = Vec::new();
+    while let Some(e) = events.next() {
+        result.push(format!("{} {:?}", events.position(), e.unwrap()));
+    }
+    assert_eq!(result, vec![
+        "1:1 StartDocument(1.0, None, UTF-8, None)".to_owned(),
+        "1:1 StartElement(a, {\"\": \"\", \"xml\": \"http://www.w3.org/XML/1998/namespace\", \"xmlns\": \"http://www.w3.org/2000/xmlns/\"}, [seen -> yes])".to_owned(),
+        "1:15 StartElement(b, {\"\": \"\", \"xml\": \"http://www.w3.org/XML/1998/namespace\", \"xmlns\": \"http://www.w3.org/2000/xmlns/\"}, [seen -> yes])".to_owned(),
+        "1:15 EndElement(b)".to_owned(),
+        "2:10 StartElement(c, {\"\": \"\", \"xml\": \"http://www.w3.org/XML/1998/namespace\", \"xmlns\": \"http://www.w3.org/2000/xmlns/\"}, [seen -> yes])".to_owned(),
+        "2:10 EndElement(c)".to_owned(),
+        "2:14 EndElement(a)".to_owned(),
+        "2:18 EndDocument".to_owned()
+    ]);
+
+    // errors are passed through
+    let mut events = EventReader::from_str("<a></b>").filter_events(|_| false);
+    assert!(events.next().unwrap().is_err());
+    assert!(events.next().is_none());
+}
+
+#[test]
+fn iteration_stops_after_end_document_or_error() {
+    let mut events = EventReader::from_str("<a/>").into_iter();
+    assert_eq!(events.by_ref().count(), 4);
+    assert!(events.next().is_none());
+    assert!(events.next().is_none());
+
+    let mut seen = Vec::new();
+    for e in EventReader::from_str("<a><b></a>") {
+        seen.push(e.is_ok());
+    }
+    assert_eq!(seen, vec![true, true, true, false]);
+}
+
+#[test]
+fn reading_whitespace_events() {
+    use xml::common::XmlSpace;
+
+    let mut r = ParserConfig::new()
+        .create_reader("<a> <b xml:space=\"preserve\">\n\t<c/> x </b>\n</a>".as_bytes());
+    let mut events = Vec::new();
+    loop {
+        match r.next().unwrap() {
+            XmlEvent::EndDocument => break,
+            XmlEvent::Whitespace(s) => events.push(format!("Whitespace({:?}, {:?})", s, r.xml_space())),
+            XmlEvent::Characters(s) => events.push(format!("Characters({:?})", s)),
+            _ => {}
+        }
+    }
+    assert_eq!(events, [
+        format!("Whitespace({:?}, {:?})", " ", XmlSpace::Default),
+        format!("Whitespace({:?}, {:?})", "\n\t", XmlSpace::Preserve),
+        "Characters(\" x \")".to_owned(),
+        format!("Whitespace({:?}, {:?})", "\n", XmlSpace::Default),
+    ]);
+}
+
+#[test]
+fn matching_names_of_read_elements() {
+    let r = EventReader::from_str(
+        "<root xmlns=\"urn:x\" xmlns:a=\"urn:x\" xmlns:b=\"urn:y\"><item/><a:item/><b:item/><c xmlns=\"\"/></root>"
+    );
+    let matches: Vec<_> = r.into_iter().filter_map(|e| match e.unwrap() {
+        XmlEvent::StartElement { name, .. } =>
+            Some((name.to_string(), name.matches("item"), name.matches_ns(Some("urn:x"), "item"))),
+        _ => None
+    }).collect();
+    assert_eq!(matches, [
+        ("{urn:x}root".to_owned(), false, false),
+        ("{urn:x}item".to_owned(), true, true),
+        ("{urn:x}a:item".to_owned(), true, true),
+        ("{urn:y}b:item".to_owned(), true, false),
+        ("c".to_owned(), false, false),
+    ]);
+}
+
+#[test]
+fn peeking_events() {
+    let mut r = EventReader::from_str("<a>\n  <b/><c>text</c>\n</a>");
+    assert_eq!(r.next().unwrap(), XmlEvent::StartDocument {
+        version: XmlVersion::Version10,
+        encoding: None,
+        effective_encoding: "UTF-8".into(),
+        standalone: None
+    });
+
+    // peeking returns the same event until it is consumed
+    match *r.peek() {
+        Ok(XmlEvent::StartElement { ref name, .. }) => assert_eq!(name.local_name, "a"),
+        ref e => panic!("Unexpected event: {:?}", e)
+    }
+    assert_eq!(r.position().to_string(), "1:1");
+    assert_eq!(r.depth(), 1);
+    assert_eq!(r.peek().as_ref().map(|e| e.kind()).ok(), Some(XmlEventKind::StartElement));
+    assert_eq!(r.next().map(|e| e.kind()).ok(), Some(XmlEventKind::StartElement));
+    assert_eq!(r.position().to_string(), "1:1");
+    assert_eq!(r.next().map(|e| e.kind()).ok(), Some(XmlEventKind::Whitespace));
+
+    // the position is the one of the last consumed event
+    assert_eq!(r.next().map(|e| e.kind()).ok(), Some(XmlEventKind::StartElement));
+    let position = r.position();
+    assert_eq!(position.to_string(), "2:3");
+    assert_eq!(r.peek().as_ref().map(|e| e.kind()).ok(), Some(XmlEventKind::EndElement));
+    assert_eq!(r.position(), position);
+    assert_eq!(r.next().map(|e| e.kind()).ok(), Some(XmlEventKind::EndElement));
+    assert_eq!(r.position(), position);
+    assert_eq!(r.next().map(|e| e.kind()).ok(), Some(XmlEventKind::StartElement));
+    assert_eq!(r.position().to_string(), "2:7");
+
+    // the iterator returns peeked events too, including errors
+    let mut it = EventReader::from_str("<a></b>").into_iter();
+    assert!(it.next().unwrap().is_ok());
+    assert!(it.next().unwrap().is_ok());
+    assert!(it.peek().unwrap().is_err());
+    let error = it.peek().unwrap().clone().unwrap_err();
+    assert_eq!(it.next().unwrap().unwrap_err(), error);
+    assert!(it.peek().is_none());
+    assert!(it.next().is_none());
+}
+
+#[test]
+fn reporting_entity_references() {
+    let document = "<!DOCTYPE a [<!ENTITY custom \"value\">]>\
+                    <a x=\"&custom;\">one &custom; two&amp;&#33;&undeclared;<b>&custom;</b>  &custom;</a>";
+    let collect = |r: EventReader<&[u8]>| -> Vec<String> {
+        r.into_iter().filter_map(|e| match e.unwrap() {
+            XmlEvent::StartElement { attributes, .. } if !attributes.is_empty() =>
+                Some(format!("attr {}", attributes[0].value)),
+            XmlEvent::Characters(s) => Some(format!("text {}", s)),
+            XmlEvent::Whitespace(s) => Some(format!("whitespace {:?}", s)),
+            XmlEvent::EntityReference(name) => Some(format!("reference {}", name)),
+            _ => None
+        }).collect()
+    };
+
+    let r = ParserConfig::new()
+        .replace_entity_references(false)
+        .create_reader(document.as_bytes());
+    assert_eq!(collect(r), [
+        "attr value", "text one ", "reference custom", "text  two&!", "reference undeclared",
+        "reference custom", "whitespace \"  \"", "reference custom"
+    ]);
+
+    // entities are expanded by default
+    let document = document.replace("&undeclared;", "");
+    let r = ParserConfig::new().create_reader(document.as_bytes());
+    assert_eq!(collect(r), ["attr value", "text one value two&!", "text value", "text   value"]);
+
+    // the position of a reference is the position of its ampersand
+    let mut r = ParserConfig::new()
+        .replace_entity_references(false)
+        .create_reader("<a>\n  text &custom;&other;</a>".as_bytes());
+    let mut positions = Vec::new();
+    loop {
+        match r.next().unwrap() {
+            XmlEvent::EndDocument => break,
+            e => positions.push(format!("{:?} {}", e.kind(), r.position()))
+        }
+    }
+    assert_eq!(positions, [
+        "StartDocument 1:1", "StartElement 1:1", "Characters 1:4", "EntityReference 2:8",
+        "EntityReference 2:16", "EndElement 2:23"
+    ]);
+}
+
+#[test]
+fn rejecting_reserved_namespace_bindings() {
+    fn error(document: &str) -> String {
+        let mut r = EventReader::from_str(document);
+        loop {
+            match r.next() {
+                Ok(XmlEvent::EndDocument) => panic!("Unexpected success: {}", document),
+                Ok(_) => {}
+                Err(e) => return format!("{} {}", e.position(), e.msg())
+            }
+        }
+    }
+
+    let xml = "http://www.w3.org/XML/1998/namespace";
+    let xmlns = "http://www.w3.org/2000/xmlns/";
+
+    // errors point at the end of the offending declaration or element name
+    assert_eq!(error("<a xmlns:xml=\"urn:x\"/>"), "1:20 Prefix 'xml' cannot be rebound to another value");
+    assert_eq!(error(&format!("<a xmlns:p=\"{}\"/>", xml)),
+               format!("1:49 Namespace '{}' cannot be bound to a prefix other than 'xml'", xml));
+    assert_eq!(error(&format!("<a xmlns:p=\"{}\"/>", xmlns)),
+               format!("1:42 Namespace '{}' cannot be bound to a prefix", xmlns));
+    assert_eq!(error(&format!("<a xmlns=\"{}\"/>", xml)), format!("1:47 Namespace '{}' cannot be default", xml));
+    assert_eq!(error(&format!("<a xmlns=\"{}\"/>", xmlns)), format!("1:40 Namespace '{}' cannot be default", xmlns));
+    assert_eq!(error("<a xmlns:xmlns=\"urn:x\"/>"), "1:22 Cannot redefine prefix 'xmlns'");
+    assert_eq!(error(&format!("<a xmlns:xmlns=\"{}\"/>", xmlns)), "1:46 Cannot redefine prefix 'xmlns'");
+    assert_eq!(error("<a>\n<xmlns:b/></a>"), "2:9 'xmlns' cannot be an element name prefix");
+    assert_eq!(error("<a>\n<b></xmlns:b></a>"), "2:13 'xmlns' cannot be an element name prefix");
+
+    // binding the `xml` prefix to its own namespace is allowed, and so are relative URIs
+    let r = EventReader::from_str("<a xmlns:xml=\"http://www.w3.org/XML/1998/namespace\" xmlns=\"xml\" xmlns:p=\"xmlns\"/>");
+    for e in r {
+        e.unwrap();
+    }
+}
+
+#[test]
+fn filtering_elements_and_text() {
+    let document = "<a x=\"1\">one<!--c--><b y=\"2\"/>\n<![CDATA[two]]></a>";
+
+    let mut events = EventReader::from_str(document).elements_only(true);
+    let mut result = Vec::new();
+    while let Some(e) = events.next() {
+        match e.unwrap() {
+            XmlEvent::StartElement { name, attributes, .. } =>
+                result.push(format!("{} <{} {}>", events.position(), name, attributes.len())),
+            XmlEvent::EndElement { name } => result.push(format!("{} </{}>", events.position(), name)),
+            e => panic!("Unexpected event: {:?}", e)
+        }
+    }
+    assert_eq!(result, ["1:1 <a 1>", "1:21 <b 1>", "1:21 </b>", "2:16 </a>"]);
+
+    let attributes: Vec<_> = EventReader::from_str(document).elements_only(false)
+        .map(|e| match e.unwrap() {
+            XmlEvent::StartElement { attributes, .. } => attributes.len(),
+            _ => 0
+        })
+        .collect();
+    assert_eq!(attributes, [0, 0, 0, 0]);
+
+    let mut events = EventReader::from_str(document).text_only();
+    let mut result = Vec::new();
+    while let Some(e) = events.next() {
+        result.push(format!("{} {:?}", events.position(), e.unwrap()));
+    }
+    assert_eq!(result, ["1:10 Characters(one)"]);
+
+    let texts: Vec<_> = ParserConfig::new().cdata_to_characters(true)
+        .create_reader(document.as_bytes())
+        .text_only()
+        .map(|e| e.unwrap())
+        .collect();
+    assert_eq!(texts, [XmlEvent::Characters("one".into()), XmlEvent::Characters("\ntwo".into())]);
+
+    // errors are passed through
+    let mut events = EventReader::from_str("<a>text</b>").elements_only(true);
+    assert!(events.next().unwrap().is_ok());
+    assert!(events.next().unwrap().is_err());
+    assert!(events.next().is_none());
+    let mut events = EventReader::from_str("<a>text</b>").text_only();
+    assert!(events.next().unwrap().is_ok());
+    assert!(events.next().unwrap().is_err());
+    assert!(events.next().is_none());
+}
+
+#[test]
+fn normalizing_line_breaks() {
+    let template = "<?xml version=\"1.0\"?>\n<a>\n  <b x=\"1\n2\">one\ntwo</b>\n<!--c\nc--><![CDATA[d\nd]]>\n</a>";
+
+    let read = |document: &str| {
+        let mut reader = ParserConfig::new()
+            .ignore_comments(false)
+            .create_reader(document.as_bytes());
+        let mut result = Vec::new();
+        loop {
+            let e = reader.next().unwrap();
+            let end = e == XmlEvent::EndDocument;
+            result.push((reader.position().to_string(), e));
+            if end {
+                break;
+            }
+        }
+        result
+    };
+
+    let unix = read(template);
+    let positions: Vec<_> = unix.iter().map(|(p, _)| &p[..]).collect();
+    assert_eq!(positions, ["1:1", "2:1", "2:4", "3:3", "4:4", "5:4", "5:8", "6:1", "7:5", "8:5", "9:1", "9:5"]);
+    assert_eq!(unix[2].1, XmlEvent::Whitespace("\n  ".into()));
+    match unix[3].1 {
+        XmlEvent::StartElement { ref attributes, .. } => assert_eq!(attributes[0].value, "1\n2"),
+        ref e => panic!("Unexpected event: {:?}", e)
+    }
+    assert_eq!(unix[4].1, XmlEvent::Characters("one\ntwo".into()));
+    assert_eq!(unix[7].1, XmlEvent::Comment("c\nc".into()));
+    assert_eq!(unix[8].1, XmlEvent::CData("d\nd".into()));
+
+    // `\r\n` and a lone `\r` are single line breaks, and content reports them as `\n`
+    assert_eq!(read(&template.replace('\n', "\r\n")), unix);
+    assert_eq!(read(&template.replace('\n', "\r")), unix);
+
+    // mixed line breaks, including `\n\r`, which is two line breaks
+    let mut reader = EventReader::from_str("<a>1\r\n2\r3\n4\n\r5\r\r6</a>");
+    reader.next().unwrap();
+    reader.next().unwrap();
+    assert_eq!(reader.next().unwrap(), XmlEvent::Characters("1\n2\n3\n4\n\n5\n\n6".into()));
+    assert_eq!(reader.next().unwrap(), XmlEvent::EndElement { name: OwnedName::local("a") });
+    assert_eq!(reader.position().to_string(), "8:2");
+    assert_eq!(reader.byte_offset(), 21);
+}
+
+#[test]
+fn prolog_and_epilog_whitespace_and_comments() {
+    let document = "<?xml version=\"1.0\"?>\n<!--prolog-->\n<a> <!--content--> </a>\n<!--epilog-->\n";
+
+    let read = |config: ParserConfig| -> Vec<XmlEvent> {
+        config.create_reader(document.as_bytes())
+            .into_iter()
+            .map(|e| e.unwrap())
+            .filter(|e| !matches!(*e, XmlEvent::StartDocument { .. } | XmlEvent::EndDocument))
+            .collect()
+    };
+    let ws = |s: &str| XmlEvent::Whitespace(s.into());
+    let comment = |s: &str| XmlEvent::Comment(s.into());
+    // namespaces are not interesting here
+    let simplify = |events: Vec<XmlEvent>| -> Vec<XmlEvent> {
+        events.into_iter().map(|e| match e {
+            XmlEvent::StartElement { name, attributes, .. } =>
+                XmlEvent::StartElement { name, attributes, namespace: Namespace::empty() },
+            e => e
+        }).collect()
+    };
+    let start = XmlEvent::StartElement { name: OwnedName::local("a"), attributes: Vec::new(), namespace: Namespace::empty() };
+    let end = XmlEvent::EndElement { name: OwnedName::local("a") };
+
+    // everything is reported
+    assert_eq!(simplify(read(ParserConfig::new().ignore_comments(false).ignore_root_level_whitespace(false))), [
+        ws("\n"), comment("prolog"), ws("\n"), start.clone(), ws(" "), comment("content"), ws(" "), end.clone(),
+        ws("\n"), comment("epilog"), ws("\n")
+    ]);
+
+    // root level comments are consumed, while comments in the content are still reported
+    assert_eq!(simplify(read(ParserConfig::new().ignore_comments(false).ignore_root_level_comments(true))), [
+        start.clone(), ws(" "), comment("content"), ws(" "), end.clone()
+    ]);
+
+    // trimming whitespace in the content does not suppress root level whitespace
+    assert_eq!(simplify(read(ParserConfig::new().ignore_root_level_whitespace(false).trim_whitespace(true))), [
+        ws("\n\n"), start.clone(), end.clone(), ws("\n\n")
+    ]);
+
+    // and root level whitespace is suppressed independently of the content
+    assert_eq!(simplify(read(ParserConfig::new())), [start.clone(), ws("  "), end.clone()]);
+
+    // non-whitespace content is still rejected at the root level
+    for &document in &["text<a/>", "<!--c-->text<a/>", "<a/>text", "<a/><!--c-->text"] {
+        let config = ParserConfig::new().ignore_root_level_comments(true).ignore_root_level_whitespace(true);
+        let result: Result<Vec<_>> = config.create_reader(document.as_bytes()).into_iter().collect();
+        let e = result.unwrap_err();
+        assert!(e.msg().starts_with("Unexpected characters outside the root element"), "{}: {}", document, e);
+    }
+
+    // whitespace at the end of the document is reported before its end
+    let mut reader = ParserConfig::new()
+        .ignore_root_level_whitespace(false)
+        .create_reader(&b"<a/>\n <!--x-->\n"[..]);
+    let mut events = Vec::new();
+    for _ in 0..6 {
+        let e = reader.next().unwrap();
+        events.push(format!("{} {:?}", reader.position(), e));
+    }
+    assert_eq!(&events[3..], ["1:5 Whitespace(\n \n)", "3:1 EndDocument", "3:1 EndDocument"]);
+}
+
+#[test]
+fn reading_multiple_documents() {
+    let stream = "<?xml version=\"1.0\"?><a/>\n<?xml version=\"1.1\"?>\n<!DOCTYPE b [<!ENTITY e \"x\">]><b>&e;</b><c>text</c>";
+
+    let mut reader = ParserConfig::new()
+        .multiple_documents(true)
+        .create_reader(stream.as_bytes());
+    let mut events = Vec::new();
+    for _ in 0..13 {
+        let e = reader.next().unwrap();
+        events.push(format!("{} {:?}", reader.position(), e));
+    }
+    assert_eq!(events, [
+        "1:1 StartDocument(1.0, None, UTF-8, None)",
+        "1:22 StartElement(a, {\"\": \"\", \"xml\": \"http://www.w3.org/XML/1998/namespace\", \"xmlns\": \"http://www.w3.org/2000/xmlns/\"})",
+        "1:22 EndElement(a)",
+        "1:24 EndDocument",
+        "2:1 StartDocument(1.1, None, UTF-8, None)",
+        "3:31 StartElement(b, {\"\": \"\", \"xml\": \"http://www.w3.org/XML/1998/namespace\", \"xmlns\": \"http://www.w3.org/2000/xmlns/\"})",
+        "3:37 Characters(x)",
+        "3:40 EndElement(b)",
+        "3:40 EndDocument",
+        "3:41 StartDocument(1.0, None, UTF-8, None)",
+        "3:41 StartElement(c, {\"\": \"\", \"xml\": \"http://www.w3.org/XML/1998/namespace\", \"xmlns\": \"http://www.w3.org/2000/xmlns/\"})",
+        "3:44 Characters(text)",
+        "3:48 EndElement(c)",
+    ]);
+    assert_eq!(reader.next().unwrap(), XmlEvent::EndDocument);
+    // the end of the stream
+    assert_eq!(reader.next().unwrap(), XmlEvent::EndDocument);
+    assert_eq!(reader.next().unwrap(), XmlEvent::EndDocument);
+
+    // the iterator yields every document and ends with the stream
+    let kinds: Vec<_> = ParserConfig::new()
+        .multiple_documents(true)
+        .create_reader(&b"<a/><b/>\n"[..])
+        .into_iter()
+        .map(|e| e.unwrap().kind())
+        .collect();
+    assert_eq!(kinds, [
+        XmlEventKind::StartDocument, XmlEventKind::StartElement, XmlEventKind::EndElement, XmlEventKind::EndDocument,
+        XmlEventKind::StartDocument, XmlEventKind::StartElement, XmlEventKind::EndElement, XmlEventKind::EndDocument
+    ]);
+
+    // whitespace after the last document is reported once, and so is its end
+    let mut reader = ParserConfig::new()
+        .multiple_documents(true)
+        .ignore_root_level_whitespace(false)
+        .create_reader(&b"<a/><b/>\n"[..]);
+    let kinds: Vec<_> = (0..11).map(|_| reader.next().unwrap().kind()).collect();
+    assert_eq!(kinds, [
+        XmlEventKind::StartDocument, XmlEventKind::StartElement, XmlEventKind::EndElement, XmlEventKind::EndDocument,
+        XmlEventKind::StartDocument, XmlEventKind::StartElement, XmlEventKind::EndElement, XmlEventKind::EndDocument,
+        // the end of the stream
+        XmlEventKind::Whitespace, XmlEventKind::EndDocument, XmlEventKind::EndDocument
+    ]);
+    let kinds: Vec<_> = ParserConfig::new()
+        .multiple_documents(true)
+        .ignore_root_level_whitespace(false)
+        .create_reader(&b"<a/><b/>\n"[..])
+        .into_iter()
+        .map(|e| e.unwrap().kind())
+        .collect();
+    assert_eq!(kinds, [
+        XmlEventKind::StartDocument, XmlEventKind::StartElement, XmlEventKind::EndElement, XmlEventKind::EndDocument,
+        XmlEventKind::StartDocument, XmlEventKind::StartElement, XmlEventKind::EndElement, XmlEventKind::EndDocument,
+        XmlEventKind::Whitespace
+    ]);
+
+    // entities of one document are not visible in the next one
+    let result: Result<Vec<_>> = ParserConfig::new()
+        .multiple_documents(true)
+        .create_reader(&b"<!DOCTYPE a [<!ENTITY e 'x'>]><a>&e;</a><b>&e;</b>"[..])
+        .into_iter()
+        .collect();
+    assert!(result.unwrap_err().msg().contains("e"));
+
+    // an incomplete document is still an error
+    let result: Result<Vec<_>> = ParserConfig::new()
+        .multiple_documents(true)
+        .create_reader(&b"<a/><b>"[..])
+        .into_iter()
+        .collect();
+    assert!(result.is_err());
+
+    // without the option, the second declaration is an error
+    let result: Result<Vec<_>> = EventReader::new(stream.as_bytes()).into_iter().collect();
+    assert!(result.is_err());
+}
+
+#[test]
+fn debugging_reader_state() {
+    let mut reader = EventReader::from_str("<a xmlns:p=\"urn:p\"><p:b>secret text<c/>");
+    for _ in 0..3 {
+        reader.next().unwrap();
+    }
+    let state = format!("{:?}", reader);
+    assert!(state.contains("position: 1:20"), "{}", state);
+    assert!(state.contains("depth: 2"), "{}", state);
+    assert!(state.contains("open_elements: [\"a\", \"{urn:p}p:b\"]"), "{}", state);
+    assert!(state.contains("state: OutsideTag"), "{}", state);
+    assert!(state.contains("last_token: Some(TagEnd)"), "{}", state);
+    assert!(state.contains("pending_event: None"), "{}", state);
+    assert!(state.contains("peeked: None"), "{}", state);
+
+    // buffered and peeked data is not shown
+    reader.peek().as_ref().unwrap();
+    let state = format!("{:?}", reader);
+    assert!(!state.contains("secret"), "{}", state);
+    assert!(state.contains("peeked: Some(Ok(Characters))"), "{}", state);
+    assert!(state.contains("last_token: Some(OpeningTagStart)"), "{}", state);
+
+    reader.next().unwrap();
+    reader.next().unwrap();
+    let state = format!("{:?}", reader);
+    assert!(state.contains("pending_event: Some(Ok(EndElement))"), "{}", state);
+    assert!(state.contains("last_token: Some(EmptyTagEnd)"), "{}", state);
+    assert!(state.contains("finished: false"), "{}", state);
+
+    assert!(reader.next().is_ok());
+    assert!(reader.next().is_err());
+    let state = format!("{:?}", reader);
+    assert!(state.contains("finished: true"), "{}", state);
+}
+
+#[test]
+fn looking_up_attributes() {
+    let mut reader = EventReader::from_str(
+        "<root xmlns=\"urn:default\" xmlns:a=\"urn:a\" xmlns:b=\"urn:a\" id=\"1\" a:id=\"2\" xml:lang=\"en\">\
+           <child xmlns:a=\"urn:other\" a:id=\"3\"/>\
+         </root>"
+    );
+    reader.next().unwrap();
+    let root = reader.next().unwrap();
+
+    let names: Vec<_> = root.attributes().map(|a| a.name.to_string()).collect();
+    assert_eq!(names, ["id", "{urn:a}a:id", "{http://www.w3.org/XML/1998/namespace}xml:lang"]);
+
+    // unprefixed names denote attributes without a namespace, even with a default namespace
+    assert_eq!(root.attribute("id"), Some("1"));
+    assert_eq!(root.attribute_ns("urn:default", "id"), None);
+
+    // prefixes are resolved with the namespace mapping of the element
+    assert_eq!(root.attribute("a:id"), Some("2"));
+    assert_eq!(root.attribute("b:id"), Some("2"));
+    assert_eq!(root.attribute_ns("urn:a", "id"), Some("2"));
+    assert_eq!(root.attribute("xml:lang"), Some("en"));
+    assert_eq!(root.attribute_ns("http://www.w3.org/XML/1998/namespace", "lang"), Some("en"));
+    assert_eq!(root.attribute("lang"), None);
+    assert_eq!(root.attribute("c:id"), None);
+    assert_eq!(root.attribute("missing"), None);
+
+    // prefixes can be rebound by nested elements
+    let child = reader.next().unwrap();
+    assert_eq!(child.attribute("a:id"), Some("3"));
+    assert_eq!(child.attribute("b:id"), None);
+    assert_eq!(child.attribute_ns("urn:other", "id"), Some("3"));
+    assert_eq!(child.attribute("id"), None);
+
+    // names are looked up by their namespaces, whatever their prefixes are
+    assert_eq!(root.get_attribute(("id", "urn:a")), Some("2"));
+    assert_eq!(root.get_attribute(("id", "")), Some("1"));
+    assert_eq!(root.get_attribute(("id", "urn:default")), None);
+    assert_eq!(root.get_attribute(&OwnedName::qualified("id", "urn:a", Some("c"))), Some("2"));
+    assert_eq!(root.get_attribute(OwnedName::local("id").borrow()), Some("1"));
+    assert_eq!(child.get_attribute(("id", "urn:a")), None);
+    assert_eq!(child.get_attribute(("id", "urn:other")), Some("3"));
+
+    // other events have no attributes
+    let end = reader.next().unwrap();
+    assert_eq!(end.attributes().count(), 0);
+    assert_eq!(end.attribute("id"), None);
+    assert_eq!(end.attribute_ns("urn:a", "id"), None);
+    assert_eq!(end.get_attribute(("id", "urn:a")), None);
+}
+
+/// A reader which counts the bytes read from it.
+struct CountingReader<'a> {
+    data: &'a [u8],
+    read: Rc<Cell<usize>>
+}
+
+impl<'a> Read for CountingReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.data.read(buf)?;
+        self.read.set(self.read.get() + n);
+        Ok(n)
+    }
+}
+
+fn large_feed() -> String {
+    let mut feed = String::from("<?xml version=\"1.0\"?>\
+        <feed xmlns=\"http://www.w3.org/2005/Atom\" xmlns:m=\"urn:media\">\
+        <title>A <![CDATA[large]]> <b>feed</b></title><link href=\"/feed\" rel=\"self\"/>");
+    for i in 0..10000 {
+        feed.push_str(&format!("<entry><id>{}</id><title>Entry {}</title><m:link href=\"/{}\"/></entry>", i, i, i));
+    }
+    feed.push_str("<m:thumbnail url=\"/last.png\"/></feed>");
+    feed
+}
+
+fn counting_reader(data: &[u8]) -> (EventReader<CountingReader<'_>>, Rc<Cell<usize>>) {
+    let read = Rc::new(Cell::new(0));
+    (EventReader::new(CountingReader { data, read: read.clone() }), read)
+}
+
+#[test]
+fn reading_to_elements() {
+    let feed = large_feed();
+
+    let (mut reader, read) = counting_reader(feed.as_bytes());
+    let attributes = reader.read_to_element("link").unwrap().unwrap();
+    assert_eq!(attributes[0].value, "/feed");
+    assert!(read.get() < 1000);
+
+    // names with a namespace match by the URI, others by the prefix, if it is given
+    let media = xml::name::Name::qualified("link", "urn:media", None);
+    let attributes = reader.read_to_element(media).unwrap().unwrap();
+    assert_eq!(attributes[0].value, "/0");
+    let attributes = reader.read_to_element("m:link").unwrap().unwrap();
+    assert_eq!(attributes[0].value, "/1");
+    assert!(read.get() < 1000);
+
+    let attributes = reader.read_to_element("m:thumbnail").unwrap().unwrap();
+    assert_eq!(attributes[0].value, "/last.png");
+    assert_eq!(reader.read_to_element("m:thumbnail").unwrap(), None);
+    assert_eq!(read.get(), feed.len());
+
+    let mut reader = EventReader::from_str("<a><b></a>");
+    assert!(reader.read_to_element("c").is_err());
+}
+
+#[test]
+fn skipping_to_elements() {
+    let feed = large_feed();
+    let (mut reader, read) = counting_reader(feed.as_bytes());
+    match reader.skip_to_element("title", None).unwrap() {
+        Some(XmlEvent::StartElement { name, .. }) => assert_eq!(name.local_name, "title"),
+        e => panic!("unexpected event: {:?}", e)
+    }
+    assert!(read.get() < 1000);
+
+    let mut reader = EventReader::from_str("<a><b><c n='1'/></b><c n='2'><c n='3'/></c></a>");
+    let mut found = Vec::new();
+    while let Some(XmlEvent::StartElement { attributes, .. }) = reader.skip_to_element("c", Some(2)).unwrap() {
+        found.push(attributes[0].value.clone());
+    }
+    assert_eq!(found, vec!["2"]);
+
+    // errors are not skipped
+    let mut reader = EventReader::from_str("<a><b></a><c/>");
+    assert_eq!(reader.skip_to_element("c", None).unwrap_err().to_string(),
+               "1:10 Unexpected closing tag: a, expected b");
+}
+
+#[test]
+fn reading_first_text() {
+    let feed = large_feed();
+
+    let (mut reader, read) = counting_reader(feed.as_bytes());
+    assert_eq!(reader.read_first_text("feed/title").unwrap().as_ref().map(|s| &s[..]), Some("A large feed"));
+    assert!(read.get() < 1000);
+    assert_eq!(reader.read_first_text("/feed/entry/title").unwrap().as_ref().map(|s| &s[..]), Some("Entry 0"));
+    assert!(read.get() < 1000);
+
+    // the root element does not match
+    let (mut reader, read) = counting_reader(feed.as_bytes());
+    assert_eq!(reader.read_first_text("rss/channel/title").unwrap(), None);
+    assert!(read.get() < 1000);
+
+    let (mut reader, read) = counting_reader(feed.as_bytes());
+    assert_eq!(reader.read_first_text("feed/entry/m:title").unwrap(), None);
+    assert_eq!(read.get(), feed.len());
+}
+
+#[test]
+fn unbound_prefix_policy() {
+    use xml::reader::UnboundPrefixPolicy;
+
+    let document = "<svg xmlns='urn:svg' xmlns:a='urn:a'>\
+        <use xlink:href='#x' a:id='1' id='2'/><h:p/></svg>";
+
+    let mut reader = EventReader::from_str(document);
+    reader.next().unwrap();
+    reader.next().unwrap();
+    assert_eq!(reader.next().unwrap_err().to_string(), "1:74 Attribute xlink:href prefix is unbound");
+
+    let mut reader = ParserConfig::new()
+        .unbound_prefix_policy(UnboundPrefixPolicy::Keep)
+        .create_reader(document.as_bytes());
+    let mut elements = Vec::new();
+    let mut open = Vec::new();
+    loop {
+        match reader.next().unwrap() {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                open.push(name.clone());
+                elements.push((name, attributes));
+            }
+            XmlEvent::EndElement { name } => assert_eq!(Some(name), open.pop()),
+            XmlEvent::EndDocument => break,
+            _ => {}
+        }
+    }
+    let (ref name, ref attributes) = elements[1];
+    assert_eq!(*name, OwnedName::qualified("use", "urn:svg", None::<&str>));
+    // unbound prefixes are kept, and unprefixed attributes are not in the default namespace
+    assert_eq!(attributes[0].name, OwnedName { local_name: "href".into(), namespace: None, prefix: Some("xlink".into()) });
+    assert_eq!(attributes[1].name, OwnedName::qualified("id", "urn:a", Some("a")));
+    assert_eq!(attributes[2].name, OwnedName::local("id"));
+    // the same applies to element names
+    assert_eq!(elements[2].0, OwnedName { local_name: "p".into(), namespace: None, prefix: Some("h".into()) });
+
+    let warnings: Vec<_> = reader.take_warnings().iter().map(|w| w.to_string()).collect();
+    assert_eq!(warnings, vec![
+        "1:74 Attribute xlink:href prefix is unbound".to_owned(),
+        "1:80 Element h:p prefix is unbound".to_owned(),
+    ]);
+}
+
+#[cfg(feature = "encodings")]
+#[test]
+fn reading_with_decoder() {
+    use xml::encodings::UTF_16LE;
+    use xml::reader::ErrorKind;
+
+    let document = "\u{feff}<?xml version='1.0' encoding='UTF-16'?><a x='\u{263A}'>\u{1F600} text</a>";
+    let mut bytes = Vec::new();
+    for u in document.encode_utf16() {
+        bytes.extend_from_slice(&[u as u8, (u >> 8) as u8]);
+    }
+    // characters are split between chunks
+    let config = ParserConfig::new().buffer_size(1);
+    let mut reader = EventReader::new_with_decoder(&bytes[..], UTF_16LE.new_decoder(), config.clone());
+    match reader.next().unwrap() {
+        XmlEvent::StartDocument { encoding, effective_encoding, .. } => {
+            assert_eq!(encoding.as_ref().map(|e| &e[..]), Some("UTF-16"));
+            assert_eq!(effective_encoding, "UTF-16LE");
+        }
+        e => panic!("unexpected event: {:?}", e)
+    }
+    match reader.next().unwrap() {
+        XmlEvent::StartElement { attributes, .. } => assert_eq!(attributes[0].value, "\u{263A}"),
+        e => panic!("unexpected event: {:?}", e)
+    }
+    assert_eq!(reader.next().unwrap(), XmlEvent::Characters("\u{1F600} text".into()));
+    // byte offsets refer to the encoded stream
+    assert_eq!(reader.next().unwrap(), XmlEvent::EndElement { name: OwnedName::local("a") });
+    assert_eq!(reader.byte_offset(), bytes.len() as u64);
+
+    // malformed input is an error
+    bytes.truncate(bytes.len() - 1);
+    let reader = EventReader::new_with_decoder(&bytes[..], UTF_16LE.new_decoder(), config);
+    let err = reader.into_iter().find_map(|e| e.err()).unwrap();
+    match *err.kind() {
+        ErrorKind::Io(ref e) => assert!(e.to_string().contains("malformed UTF-16LE sequence"), "{}", e),
+        ref e => panic!("unexpected error: {:?}", e)
+    }
+}
+
+#[test]
+fn reading_declared_encodings() {
+    let read = |document: &[u8]| {
+        let mut reader = EventReader::new(document);
+        let mut events = Vec::new();
+        loop {
+            match reader.next().unwrap() {
+                XmlEvent::EndDocument => break,
+                e => events.push((format!("{:?}", e), reader.byte_offset()))
+            }
+        }
+        events
+    };
+
+    // the rest of the document is decoded in the declared encoding, and byte offsets
+    // refer to the original bytes
+    let document = b"<?xml version='1.0' encoding='ISO-8859-1'?><a x='\xE9'>caf\xE9 \x80</a>";
+    assert_eq!(read(document), [
+        ("StartDocument(1.0, Some(\"ISO-8859-1\"), ISO-8859-1, None)".to_string(), 43),
+        ("StartElement(a, {\"\": \"\", \"xml\": \"http://www.w3.org/XML/1998/namespace\", \"xmlns\": \"http://www.w3.org/2000/xmlns/\"}, [x -> \u{e9}])".to_string(), 52),
+        ("Characters(caf\u{e9} \u{80})".to_string(), 60),
+        ("EndElement(a)".to_string(), 62)
+    ]);
+
+    // UTF-8 and unsupported encodings are read as UTF-8
+    let document = "<?xml version='1.0' encoding='utf8'?><a>caf\u{E9}</a>";
+    assert_eq!(read(document.as_bytes())[2].0, "Characters(caf\u{e9})");
+    let document = "<?xml version='1.0' encoding='x-unknown'?><a>caf\u{E9}</a>";
+    assert_eq!(read(document.as_bytes())[2].0, "Characters(caf\u{e9})");
+
+    // every document of a stream declares its own encoding
+    let stream = b"<?xml version='1.0' encoding='latin1'?><a>\xE9</a><b>\xC3\xA9</b>";
+    let texts: Vec<_> = ParserConfig::new()
+        .multiple_documents(true)
+        .create_reader(&stream[..])
+        .into_iter()
+        .filter_map(|e| match e.unwrap() {
+            XmlEvent::Characters(text) => Some(text),
+            _ => None
+        })
+        .collect();
+    assert_eq!(texts, ["\u{E9}", "\u{E9}"]);
+
+    #[cfg(feature = "encodings")]
+    {
+        let document = b"<?xml version='1.0' encoding='Shift_JIS'?><a>\x82\xA0</a>";
+        let events = read(document);
+        assert_eq!(events[0].0, "StartDocument(1.0, Some(\"Shift_JIS\"), Shift_JIS, None)");
+        assert_eq!(events[2], ("Characters(\u{3042})".to_string(), 49));
+
+        // a decoder set explicitly is not replaced
+        let mut reader = EventReader::new_with_decoder(&document[..], xml::reader::Latin1Decoder, ParserConfig::new());
+        reader.next().unwrap();
+        reader.next().unwrap();
+        assert_eq!(reader.next().unwrap(), XmlEvent::Characters("\u{82}\u{A0}".into()));
+    }
+}
+
+#[test]
+fn validating_against_dtd() {
+    let doctype = "<!DOCTYPE book [\
+        <!ELEMENT book (title, chapter+)>\
+        <!ELEMENT title (#PCDATA)>\
+        <!ELEMENT chapter EMPTY>\
+        <!ATTLIST book id ID #REQUIRED lang NMTOKEN 'en' xml:space (default|preserve) #FIXED 'preserve'>\
+        <!ATTLIST chapter n CDATA #IMPLIED>\
+    ]>";
+    let read = |body: &str, config: ParserConfig| {
+        let document = format!("{}{}", doctype, body);
+        let mut reader = config.validate_dtd(true).create_reader(document.as_bytes());
+        let mut attributes = Vec::new();
+        loop {
+            match reader.next() {
+                Ok(XmlEvent::StartElement { name, attributes: a, .. }) if name.local_name == "book" => attributes = a,
+                Ok(XmlEvent::EndDocument) => break,
+                Ok(_) => {}
+                Err(e) => return Err(e.to_string())
+            }
+        }
+        let warnings: Vec<_> = reader.take_warnings().iter().map(|w| w.to_string()).collect();
+        Ok((attributes, warnings))
+    };
+
+    // declared defaults are added to the start element
+    let (attributes, warnings) = read("<book id=' b1 '><title>T</title><chapter/><chapter n='2'/></book>",
+                                      ParserConfig::new()).unwrap();
+    assert_eq!(attributes, vec![
+        OwnedAttribute::new(OwnedName::local("id"), "b1"),
+        OwnedAttribute::new(OwnedName::local("lang"), "en"),
+        OwnedAttribute::new(OwnedName::qualified("space", "http://www.w3.org/XML/1998/namespace", Some("xml")), "preserve"),
+    ]);
+    assert!(warnings.is_empty());
+
+    assert_eq!(read("<book><title/><chapter/></book>", ParserConfig::new()).unwrap_err(),
+               "1:233 Required attribute id of element book is missing");
+    assert_eq!(read("<book id='b'><chapter/><title/></book>", ParserConfig::new()).unwrap_err(),
+               "1:246 Element chapter is not allowed in element book with content model (title,chapter+)");
+    assert_eq!(read("<book id='b'><title/></book>", ParserConfig::new()).unwrap_err(),
+               "1:254 Content of element book is incomplete for content model (title,chapter+)");
+    assert_eq!(read("<book id='b'><title/><chapter>x</chapter></book>", ParserConfig::new()).unwrap_err(),
+               "1:263 Text is not allowed in element chapter with content model EMPTY");
+
+    // validity errors can be noted as warnings instead
+    let (_, warnings) = read("<book lang='fr' xml:space='default' x='1'><chapter/><p/></book>",
+                             ParserConfig::new().validity_errors_as_warnings(true)).unwrap();
+    assert_eq!(warnings, vec![
+        "1:233 Attribute xml:space must have the fixed value \"preserve\"".to_owned(),
+        "1:233 Attribute x of element book is not declared".to_owned(),
+        "1:233 Required attribute id of element book is missing".to_owned(),
+        "1:275 Element chapter is not allowed in element book with content model (title,chapter+)".to_owned(),
+        "1:285 Element p is not declared".to_owned(),
+    ]);
+
+    let mut reader = ParserConfig::new().validate_dtd(true).create_reader("<a/>".as_bytes());
+    reader.next().unwrap();
+    assert_eq!(reader.next().unwrap_err().to_string(), "1:1 Document has no document type declaration");
+}
+
+#[test]
+fn converting_borrowed_events() {
+    use std::collections::HashSet;
+    use xml::name::Name;
+    use xml::reader::XmlEventRef;
+
+    let document = "<?xml version='1.0' encoding='utf-8' standalone='yes'?>\
+        <!DOCTYPE r [<!ENTITY e 'x'>]><?pi data?><r xmlns:n='urn:n' n:a='1' b='2'>\
+        <!-- c --> <![CDATA[d]]>t&e;<n:s/></r>";
+    let config = ParserConfig::new()
+        .ignore_comments(false)
+        .coalesce_characters(false)
+        .replace_entity_references(false);
+    let events: Vec<_> = EventReader::new_with_config(document.as_bytes(), config)
+        .into_iter().map(|e| e.unwrap()).collect();
+    let kinds: HashSet<_> = events.iter().map(|e| e.kind()).collect();
+    assert_eq!(kinds.len(), 10);
+
+    for event in &events {
+        let borrowed = event.borrow();
+        assert_eq!(borrowed, event.clone().borrow());
+        assert_eq!(borrowed.clone().into_owned(), *event);
+        assert_eq!(format!("{:?}", borrowed), format!("{:?}", event));
+        assert_eq!(XmlEvent::from(borrowed), *event);
+    }
+
+    let namespace = events.iter().filter_map(|e| match *e {
+        XmlEvent::StartElement { ref namespace, .. } => Some(namespace.clone()),
+        _ => None
+    }).next().unwrap();
+    let attributes = [
+        OwnedAttribute::new(OwnedName::qualified("a", "urn:n", Some("n")), "1"),
+        OwnedAttribute::new(OwnedName::local("b"), "2"),
+    ];
+    let borrowed = XmlEventRef::StartElement {
+        name: Name::local("r"),
+        attributes: &attributes,
+        namespace: &namespace
+    };
+    assert_eq!(borrowed.clone().into_owned().borrow(), borrowed);
+    assert_eq!(Some(&borrowed.into_owned()), events.iter().find(|e| e.kind() == XmlEventKind::StartElement));
+
+    let events_ref = [
+        XmlEventRef::StartDocument { version: XmlVersion::Version10, encoding: Some("utf-8"), effective_encoding: "UTF-8", standalone: Some(true) },
+        XmlEventRef::ProcessingInstruction { name: "pi", data: Some("data") },
+        XmlEventRef::Comment(" c "),
+        XmlEventRef::Whitespace(" "),
+        XmlEventRef::CData("d"),
+        XmlEventRef::Characters("t"),
+        XmlEventRef::EntityReference("e"),
+        XmlEventRef::EndElement { name: Name::qualified("s", "urn:n", Some("n")) },
+        XmlEventRef::EndDocument,
+    ];
+    for event in &events_ref {
+        assert!(events.contains(&event.clone().into_owned()), "{:?}", event);
+    }
+}
+
 lazy_static! {
     // If PRINT_SPEC env variable is set, print the lines
     // to stderr instead of comparing with the output
@@ -553,8 +2411,8 @@ impl<'a> fmt::Display for Event<'a> {
         let empty = String::new();
         match *self.0 {
             Ok(ref e) => match *e {
-                XmlEvent::StartDocument { ref version, ref encoding, .. } =>
-                    write!(f, "StartDocument({}, {})", version, encoding),
+                XmlEvent::StartDocument { ref version, ref encoding, ref effective_encoding, .. } =>
+                    write!(f, "StartDocument({}, {})", version, encoding.as_ref().unwrap_or(effective_encoding)),
                 XmlEvent::EndDocument =>
                     write!(f, "EndDocument"),
                 XmlEvent::ProcessingInstruction { ref name, ref data } =>
@@ -580,6 +2438,10 @@ impl<'a> fmt::Display for Event<'a> {
                     write!(f, r#"Characters("{}")"#, data.escape_debug()),
                 XmlEvent::Whitespace(ref data) =>
                     write!(f, r#"Whitespace("{}")"#, data.escape_debug()),
+                XmlEvent::EntityReference(ref name) =>
+                    write!(f, "EntityReference({})", name),
+                XmlEvent::DoctypeDecl { ref name, ref public_id, ref system_id, .. } =>
+                    write!(f, "DoctypeDecl({}, {:?}, {:?})", name, public_id, system_id),
             },
             Err(ref e) => e.fmt(f),
         }
@@ -224,6 +224,7 @@ fn attribute_escaping() {
         let mut w = EmitterConfig::new()
             .write_document_declaration(false)
             .perform_indent(true)
+            .allow_fragments(true)
             .create_writer(&mut b);
 
         unwrap_all! {
@@ -261,9 +262,2217 @@ fn attribute_escaping() {
     assert_eq!(
         str::from_utf8(&b).unwrap(),
         "<hello testLt=\"&lt;\" testGt=\"&gt;\" />
-<hello testQuot=\"&quot;\" testApos=\"&apos;\" />
+<hello testQuot=\"&quot;\" testApos=\"'\" />
 <hello testAmp=\"&amp;\" />
 <hello testNl=\"&#xA;\" testCr=\"&#xD;\" />
 <hello testNl=\"\\n\" testCr=\"\\r\" />"
     );
-}
\ No newline at end of file
+}
+
+#[test]
+fn writing_bom() {
+    use xml::writer::XmlEvent;
+
+    let mut b = Vec::new();
+
+    {
+        let mut w = EmitterConfig::new()
+            .write_bom(true)
+            .create_writer(&mut b);
+
+        unwrap_all! {
+            w.write(XmlEvent::start_element("hello"));
+            w.write(XmlEvent::end_element())
+        }
+    }
+
+    assert_eq!(&b[..3], b"\xEF\xBB\xBF");
+    assert_eq!(str::from_utf8(&b[3..]).unwrap(), r#"<?xml version="1.0" encoding="UTF-8"?><hello />"#);
+
+    let mut b = Vec::new();
+
+    {
+        let mut w = EmitterConfig::new()
+            .write_bom(true)
+            .write_document_declaration(false)
+            .create_writer(&mut b);
+
+        unwrap_all! {
+            w.write(XmlEvent::comment("c"));
+            w.write(XmlEvent::start_element("hello"));
+            w.write(XmlEvent::end_element())
+        }
+    }
+
+    assert_eq!(b, b"\xEF\xBB\xBF<!-- c --><hello />".to_vec());
+}
+
+#[cfg(feature = "encodings")]
+#[test]
+fn writing_non_utf8_encodings() {
+    use xml::encodings::{SHIFT_JIS, UTF_16LE};
+    use xml::writer::XmlEvent;
+
+    let mut b = Vec::new();
+
+    {
+        let mut w = EmitterConfig::new()
+            .output_encoding(SHIFT_JIS)
+            .create_writer(&mut b);
+
+        unwrap_all! {
+            w.write(XmlEvent::start_element("hello").attr("a", "☺"));
+            w.write("あ☺");
+            w.write(XmlEvent::end_element())
+        }
+    }
+
+    assert_eq!(
+        b,
+        b"<?xml version=\"1.0\" encoding=\"Shift_JIS\"?><hello a=\"&#x263A;\">\x82\xA0&#x263A;</hello>".to_vec()
+    );
+
+    let mut b = Vec::new();
+
+    {
+        let mut w = EmitterConfig::new()
+            .output_encoding(UTF_16LE)
+            .write_bom(true)
+            .write_document_declaration(false)
+            .create_writer(&mut b);
+
+        unwrap_all! {
+            w.write(XmlEvent::start_element("a"));
+            w.write(XmlEvent::end_element())
+        }
+    }
+
+    let expected: Vec<u8> = "\u{feff}<a />".encode_utf16().flat_map(|u| vec![u as u8, (u >> 8) as u8]).collect();
+    assert_eq!(b, expected);
+}
+
+#[test]
+fn writing_cdata_sections() {
+    use xml::reader::{ParserConfig, XmlEvent as ReaderEvent};
+    use xml::writer::XmlEvent;
+
+    let mut b = Vec::new();
+
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .characters_to_cdata(true)
+            .create_writer(&mut b);
+
+        unwrap_all! {
+            w.write(XmlEvent::start_element("a"));
+            w.write(XmlEvent::cdata("x]]>y]]>"));
+            w.write(XmlEvent::start_element("b"));
+            w.write("if (a < b && c]]>d)");
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::start_element("c"));
+            w.write("plain text");
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element())
+        }
+    }
+
+    assert_eq!(
+        str::from_utf8(&b).unwrap(),
+        "<a><![CDATA[x]]]]><![CDATA[>y]]]]><![CDATA[>]]>\
+         <b><![CDATA[if (a < b && c]]]]><![CDATA[>d)]]></b>\
+         <c>plain text</c></a>"
+    );
+
+    let mut b = Vec::new();
+
+    {
+        let mut w = EmitterConfig::new()
+            .characters_to_cdata(true)
+            .create_writer(&mut b);
+
+        unwrap_all! {
+            w.write(XmlEvent::start_element("a"));
+            w.write(XmlEvent::cdata("]]>x]]]>y]]"));
+            w.write(XmlEvent::start_element("b"));
+            w.write("<![CDATA[a & b]]>");
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element())
+        }
+    }
+
+    let r = ParserConfig::new().cdata_to_characters(true).create_reader(&b[..]);
+    let texts: Vec<String> = r.into_iter()
+        .filter_map(|e| match e.unwrap() {
+            ReaderEvent::Characters(s) => Some(s),
+            _ => None
+        })
+        .collect();
+    assert_eq!(texts, vec!["]]>x]]]>y]]".to_owned(), "<![CDATA[a & b]]>".to_owned()]);
+}
+
+#[test]
+fn writing_invalid_characters() {
+    use xml::writer::{Error as EmitterError, InvalidCharacterPolicy, XmlEvent};
+
+    fn write_with(policy: InvalidCharacterPolicy) -> Result<String, EmitterError> {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .write_document_declaration(false)
+                .invalid_character_policy(policy)
+                .create_writer(&mut b);
+
+            w.write(XmlEvent::start_element("a").attr("x", "a\0b"))?;
+            w.write("c\0&d")?;
+            w.write(XmlEvent::cdata("e\0f"))?;
+            w.write(XmlEvent::end_element())?;
+        }
+        Ok(String::from_utf8(b).unwrap())
+    }
+
+    match write_with(InvalidCharacterPolicy::Error) {
+        Err(EmitterError::InvalidCharacter('\0')) => {}
+        e => panic!("Unexpected result: {:?}", e)
+    }
+    assert_eq!(
+        write_with(InvalidCharacterPolicy::Strip).unwrap(),
+        "<a x=\"ab\">c&amp;d<![CDATA[ef]]></a>"
+    );
+    assert_eq!(
+        write_with(InvalidCharacterPolicy::ReplaceWith('?')).unwrap(),
+        "<a x=\"a?b\">c?&amp;d<![CDATA[e?f]]></a>"
+    );
+
+    let mut b = Vec::new();
+    let mut w = EmitterConfig::new().create_writer(&mut b);
+    w.write(XmlEvent::start_element("a")).unwrap();
+    match w.write("x\0") {
+        Err(EmitterError::InvalidCharacter('\0')) => {}
+        e => panic!("Unexpected result: {:?}", e)
+    }
+}
+
+#[test]
+fn writing_end_element_without_open_elements() {
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    for &keep_names in &[true, false] {
+        let mut b = Vec::new();
+        let mut w = EmitterConfig::new()
+            .keep_element_names_stack(keep_names)
+            .create_writer(&mut b);
+
+        w.write(XmlEvent::start_element("a")).unwrap();
+        w.write(XmlEvent::end_element().name("a")).unwrap();
+        match w.write(XmlEvent::end_element().name("a")) {
+            Err(EmitterError::UnexpectedEndElement) => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+    }
+}
+
+#[test]
+fn reading_writing_round_trip_preserves_events() {
+    use xml::reader::{self, ParserConfig};
+
+    let doc = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+               <?style href=\"a.css\"?>\
+               <p:root xmlns:p=\"urn:p\" xmlns=\"urn:d\" attr=\"&lt;&quot;&amp;&apos;&gt;\">\
+               <!-- a comment -->\
+               <child p:x=\"1\">text &amp; more &lt;text&gt;</child>\
+               <empty/>\
+               <![CDATA[<raw> & ]]>\
+               </p:root>";
+    let config = ParserConfig::new().ignore_comments(false);
+    let events = reader::parse_str(doc, &config).unwrap();
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().create_writer(&mut b);
+        for e in &events {
+            if let Some(e) = e.as_writer_event() {
+                w.write(e).unwrap();
+            }
+        }
+    }
+
+    let written = str::from_utf8(&b).unwrap();
+    assert_eq!(reader::parse_str(written, &config).unwrap(), events);
+}
+
+#[test]
+fn writing_with_autoflush() {
+    use std::io::{self, BufWriter};
+    use xml::writer::XmlEvent;
+
+    struct FlushCounter(usize);
+
+    impl Write for FlushCounter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> { Ok(buf.len()) }
+        fn flush(&mut self) -> io::Result<()> { self.0 += 1; Ok(()) }
+    }
+
+    let mut w = EmitterConfig::new().create_writer(FlushCounter(0));
+    unwrap_all! {
+        w.write(XmlEvent::start_element("a"));
+        w.write(XmlEvent::end_element())
+    }
+    assert_eq!(w.inner_mut().0, 0);
+    w.flush().unwrap();
+    assert_eq!(w.inner_mut().0, 1);
+
+    let mut w = EmitterConfig::new().autoflush(true).create_writer(FlushCounter(0));
+    unwrap_all! {
+        w.write(XmlEvent::start_element("a"));
+        w.write("text");
+        w.write(XmlEvent::end_element())
+    }
+    assert_eq!(w.inner_mut().0, 3);
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .autoflush(true)
+            .create_writer(BufWriter::new(&mut b));
+        w.write(XmlEvent::start_element("a")).unwrap();
+        assert_eq!(w.inner_mut().get_ref().as_slice(), b"<a");
+        w.write(XmlEvent::end_element()).unwrap();
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<a />");
+}
+
+#[test]
+fn writing_with_indentation() {
+    use xml::writer::XmlEvent;
+
+    fn write_indented(events: Vec<XmlEvent>) -> String {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .write_document_declaration(false)
+                .perform_indent(true)
+                .indent_string("\t")
+                .line_separator("\r\n")
+                .create_writer(&mut b);
+            for e in events {
+                w.write(e).unwrap();
+            }
+        }
+        String::from_utf8(b).unwrap()
+    }
+
+    // pure element tree with a text-only leaf
+    assert_eq!(
+        write_indented(vec![
+            XmlEvent::start_element("book").into(),
+            XmlEvent::start_element("title").into(),
+            XmlEvent::characters("Hi"),
+            XmlEvent::end_element().into(),
+            XmlEvent::start_element("authors").into(),
+            XmlEvent::start_element("author").into(),
+            XmlEvent::end_element().into(),
+            XmlEvent::end_element().into(),
+            XmlEvent::end_element().into()
+        ]),
+        "<book>\r\n\t<title>Hi</title>\r\n\t<authors>\r\n\t\t<author />\r\n\t</authors>\r\n</book>"
+    );
+
+    // mixed content
+    assert_eq!(
+        write_indented(vec![
+            XmlEvent::start_element("doc").into(),
+            XmlEvent::start_element("p").into(),
+            XmlEvent::characters("Some "),
+            XmlEvent::start_element("b").into(),
+            XmlEvent::start_element("i").into(),
+            XmlEvent::characters("bold"),
+            XmlEvent::end_element().into(),
+            XmlEvent::end_element().into(),
+            XmlEvent::characters(" text"),
+            XmlEvent::start_element("br").into(),
+            XmlEvent::end_element().into(),
+            XmlEvent::end_element().into(),
+            XmlEvent::start_element("p").into(),
+            XmlEvent::end_element().into(),
+            XmlEvent::end_element().into()
+        ]),
+        "<doc>\r\n\t<p>Some <b><i>bold</i></b> text<br /></p>\r\n\t<p />\r\n</doc>"
+    );
+
+    // empty root
+    assert_eq!(
+        write_indented(vec![
+            XmlEvent::start_element("root").into(),
+            XmlEvent::end_element().into()
+        ]),
+        "<root />"
+    );
+}
+
+#[test]
+fn writing_cdata_outside_of_root_element() {
+    use xml::reader::{self, ParserConfig, XmlEvent as ReaderEvent};
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    for &cdata_to_characters in &[false, true] {
+        let mut b = Vec::new();
+        let mut w = EmitterConfig::new()
+            .cdata_to_characters(cdata_to_characters)
+            .create_writer(&mut b);
+
+        match w.write(XmlEvent::cdata("before")) {
+            Err(EmitterError::CDataOutsideOfRootElement) => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+        w.write(XmlEvent::start_element("a")).unwrap();
+        w.write(XmlEvent::cdata("]]>")).unwrap();
+        w.write(XmlEvent::end_element()).unwrap();
+        match w.write(XmlEvent::cdata("after")) {
+            Err(EmitterError::CDataOutsideOfRootElement) => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+        drop(w);
+
+        let events = reader::parse_bytes(&b, &ParserConfig::new().cdata_to_characters(true)).unwrap();
+        assert_eq!(events[2], ReaderEvent::Characters("]]>".into()));
+    }
+}
+
+#[test]
+fn writing_comments() {
+    use xml::common::XmlVersion;
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::comment("before -- root"));
+            w.write(XmlEvent::start_element("a"));
+            w.write(XmlEvent::comment("----"));
+            w.write(XmlEvent::start_element("b").attr("x", "1"));
+            w.write(XmlEvent::comment("inside-"));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::comment("after"))
+        }
+        match w.write(XmlEvent::StartDocument { version: XmlVersion::Version10, encoding: None, standalone: None }) {
+            Err(EmitterError::DocumentStartAlreadyEmitted) => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+    }
+    assert_eq!(
+        str::from_utf8(&b).unwrap(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <!-- before - - root --><a><!-- - - - - --><b x=\"1\"><!-- inside- --></b></a><!-- after -->"
+    );
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .autopad_comments(false)
+            .create_writer(&mut b);
+        w.write(XmlEvent::comment("fine")).unwrap();
+        for content in &["----", "a--b", "a-"] {
+            match w.write(XmlEvent::comment(content)) {
+                Err(EmitterError::InvalidComment) => {}
+                e => panic!("Unexpected result: {:?}", e)
+            }
+        }
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<!--fine-->");
+}
+
+#[test]
+fn writing_processing_instructions() {
+    use xml::reader::{self, ParserConfig, XmlEvent as ReaderEvent};
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().perform_indent(true).create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::processing_instruction("xml-stylesheet", Some("href=\"a.css\" type=\"text/css\"")));
+            w.write(XmlEvent::processing_instruction("empty", None));
+            w.write(XmlEvent::start_element("a"));
+            w.write(XmlEvent::processing_instruction("pi", Some("  spaced data ")));
+            w.write(XmlEvent::end_element())
+        }
+
+        for name in &["", "1pi", "a b", "xml", "XmL"] {
+            match w.write(XmlEvent::processing_instruction(name, None)) {
+                Err(EmitterError::InvalidProcessingInstructionName(ref n)) if n == name => {}
+                e => panic!("Unexpected result: {:?}", e)
+            }
+        }
+        match w.write(XmlEvent::processing_instruction("pi", Some("a ?> b"))) {
+            Err(EmitterError::InvalidProcessingInstructionData) => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+    }
+
+    let written = str::from_utf8(&b).unwrap();
+    assert_eq!(
+        written,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <?xml-stylesheet href=\"a.css\" type=\"text/css\"?>\n\
+         <?empty?>\n\
+         <a>\n  <?pi   spaced data ?>\n</a>"
+    );
+
+    let pis: Vec<_> = reader::parse_str(written, &ParserConfig::new()).unwrap().into_iter()
+        .filter_map(|e| match e {
+            ReaderEvent::ProcessingInstruction { name, data } => Some((name, data)),
+            _ => None
+        })
+        .collect();
+    assert_eq!(pis, vec![
+        ("xml-stylesheet".to_owned(), Some("href=\"a.css\" type=\"text/css\"".to_owned())),
+        ("empty".to_owned(), None),
+        ("pi".to_owned(), Some("  spaced data ".to_owned()))
+    ]);
+}
+
+#[test]
+fn writing_doctype_declarations() {
+    use xml::reader::{self, ParserConfig, XmlEventKind};
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    let xhtml = XmlEvent::DoctypeDecl {
+        name: "html",
+        public_id: Some("-//W3C//DTD XHTML 1.0 Strict//EN"),
+        system_id: Some("http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd"),
+        internal_subset: Some("<!ENTITY product 'xml-rs'>")
+    };
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().perform_indent(true).create_writer(&mut b);
+        unwrap_all! {
+            w.write(xhtml);
+            w.write(XmlEvent::start_element("html").default_ns("http://www.w3.org/1999/xhtml"));
+            w.write("&product;");
+            w.write(XmlEvent::end_element())
+        }
+    }
+    let written = str::from_utf8(&b).unwrap();
+    assert_eq!(
+        written,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Strict//EN\" \
+         \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd\" [<!ENTITY product 'xml-rs'>]>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\">&amp;product;</html>"
+    );
+
+    // the declaration is read back, including the entities of its internal subset
+    let mut r = xml::EventReader::from_str(written);
+    while r.next_kind().unwrap() != XmlEventKind::StartElement {}
+    assert_eq!(r.entities().get("product").map(|s| &s[..]), Some("xml-rs"));
+    let events = reader::parse_str(written, &ParserConfig::new().report_doctype(true)).unwrap();
+    assert_eq!(events[1], reader::XmlEvent::DoctypeDecl {
+        name: "html".into(),
+        public_id: Some("-//W3C//DTD XHTML 1.0 Strict//EN".into()),
+        system_id: Some("http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd".into()),
+        internal_subset: Some("<!ENTITY product 'xml-rs'>".into())
+    });
+
+    fn doctype_error(public_id: Option<&str>, system_id: Option<&str>) -> EmitterError {
+        let mut b = Vec::new();
+        let mut w = EmitterConfig::new().create_writer(&mut b);
+        w.write(XmlEvent::DoctypeDecl { name: "a", public_id, system_id, internal_subset: None }).unwrap_err()
+    }
+    match doctype_error(Some("pub"), None) {
+        EmitterError::InvalidDoctype(_) => {}
+        e => panic!("Unexpected error: {:?}", e)
+    }
+    match doctype_error(None, Some("both \" and '")) {
+        EmitterError::InvalidDoctype(_) => {}
+        e => panic!("Unexpected error: {:?}", e)
+    }
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .create_writer(&mut b);
+        let doctype = XmlEvent::DoctypeDecl { name: "a", public_id: None, system_id: Some("it's.dtd"), internal_subset: None };
+        w.write(doctype).unwrap();
+        match w.write(XmlEvent::DoctypeDecl { name: "a", public_id: None, system_id: None, internal_subset: None }) {
+            Err(EmitterError::DoctypeAlreadyEmitted) => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+        w.write(XmlEvent::start_element("a")).unwrap();
+        w.write(XmlEvent::end_element()).unwrap();
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<!DOCTYPE a SYSTEM \"it's.dtd\"><a />");
+
+    let mut b = Vec::new();
+    let mut w = EmitterConfig::new().create_writer(&mut b);
+    w.write(XmlEvent::start_element("a")).unwrap();
+    match w.write(XmlEvent::DoctypeDecl { name: "a", public_id: None, system_id: None, internal_subset: None }) {
+        Err(EmitterError::DoctypeAfterRootElement) => {}
+        e => panic!("Unexpected result: {:?}", e)
+    }
+}
+
+#[test]
+fn writing_empty_elements_with_all_flag_combinations() {
+    use xml::writer::XmlEvent;
+
+    fn write(normalize: bool, pad: bool) -> String {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .write_document_declaration(false)
+                .normalize_empty_elements(normalize)
+                .pad_self_closing(pad)
+                .create_writer(&mut b);
+            unwrap_all! {
+                w.write(XmlEvent::start_element("p"));
+                w.write(XmlEvent::start_element("br"));
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::start_element("i").attr("a", "b"));
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::start_element("e"));
+                w.write("");
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::end_element())
+            }
+        }
+        String::from_utf8(b).unwrap()
+    }
+
+    assert_eq!(write(true, true), "<p><br /><i a=\"b\" /><e></e></p>");
+    assert_eq!(write(true, false), "<p><br/><i a=\"b\"/><e></e></p>");
+    assert_eq!(write(false, true), "<p><br></br><i a=\"b\"></i><e></e></p>");
+    assert_eq!(write(false, false), "<p><br></br><i a=\"b\"></i><e></e></p>");
+}
+
+#[test]
+fn writing_attributes_with_quote_styles() {
+    use xml::reader::XmlEvent as ReaderEvent;
+    use xml::writer::{QuoteStyle, XmlEvent};
+
+    fn write(style: QuoteStyle, value: &str) -> String {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .write_document_declaration(false)
+                .attribute_quote(style)
+                .create_writer(&mut b);
+            unwrap_all! {
+                w.write(XmlEvent::start_element("a").attr("v", value).ns("p", "urn:p"));
+                w.write(XmlEvent::end_element())
+            }
+        }
+        String::from_utf8(b).unwrap()
+    }
+
+    fn reparse(document: &str) -> String {
+        for e in EventReader::new(document.as_bytes()) {
+            if let ReaderEvent::StartElement { attributes, .. } = e.unwrap() {
+                return attributes[0].value.clone();
+            }
+        }
+        panic!("No start element in {}", document);
+    }
+
+    assert_eq!(write(QuoteStyle::Double, "it's"), "<a xmlns:p=\"urn:p\" v=\"it's\" />");
+    assert_eq!(write(QuoteStyle::Single, "it's"), "<a xmlns:p='urn:p' v='it&apos;s' />");
+    assert_eq!(write(QuoteStyle::Smart, "say \"hi\""), "<a xmlns:p=\"urn:p\" v='say \"hi\"' />");
+    assert_eq!(write(QuoteStyle::Smart, "it's"), "<a xmlns:p=\"urn:p\" v=\"it's\" />");
+
+    let values = ["plain", "it's", "say \"hi\"", "both ' and \" quotes", "'\"'\"", "<&>"];
+    for &style in &[QuoteStyle::Double, QuoteStyle::Single, QuoteStyle::Smart] {
+        for &value in &values {
+            assert_eq!(reparse(&write(style, value)), value, "{:?}", style);
+        }
+    }
+    assert_eq!(write(QuoteStyle::Smart, "both ' and \" quotes"),
+               "<a xmlns:p=\"urn:p\" v=\"both ' and &quot; quotes\" />");
+}
+
+#[test]
+fn writing_with_crlf_line_separator() {
+    use xml::writer::XmlEvent;
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .line_separator("\r\n")
+            .perform_indent(true)
+            .create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::DoctypeDecl { name: "root", public_id: None, system_id: None, internal_subset: None });
+            w.write(XmlEvent::start_element("root"));
+            w.write(XmlEvent::comment("comment"));
+            w.write(XmlEvent::processing_instruction("pi", None));
+            w.write(XmlEvent::start_element("a"));
+            w.write(XmlEvent::start_element("b"));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::start_element("text"));
+            w.write("line 1\nline 2");
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::start_element("cdata"));
+            w.write(XmlEvent::cdata("line 1\nline 2"));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element())
+        }
+    }
+    let output = String::from_utf8(b).unwrap();
+    assert_eq!(
+        output,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\r\n\
+         <!DOCTYPE root>\r\n\
+         <root>\r\n  \
+           <!-- comment -->\r\n  \
+           <?pi?>\r\n  \
+           <a>\r\n    \
+             <b />\r\n  \
+           </a>\r\n  \
+           <text>line 1\nline 2</text>\r\n  \
+           <cdata><![CDATA[line 1\nline 2]]></cdata>\r\n\
+         </root>"
+    );
+
+    // the only lone line feeds are the ones written as a part of the content
+    let without_content = output.replace("line 1\nline 2", "");
+    assert!(!without_content.replace("\r\n", "").contains('\n'));
+}
+
+#[test]
+fn writing_namespaced_names_without_bindings() {
+    use xml::name::Name;
+    use xml::reader::XmlEvent as ReaderEvent;
+    use xml::writer::XmlEvent;
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .create_writer(&mut b);
+        unwrap_all! {
+            // no prefix requested, so a new one is generated and reused by descendants
+            w.write(XmlEvent::start_element(Name::qualified("root", "urn:a", None)));
+            w.write(XmlEvent::start_element(Name::qualified("child", "urn:a", None))
+                .attr(Name::qualified("x", "urn:b", None), "1"));
+            w.write(XmlEvent::end_element());
+            // explicitly requested prefixes are honored and rebound on conflicts
+            w.write(XmlEvent::start_element(Name::qualified("e", "urn:c", Some("p"))));
+            w.write(XmlEvent::start_element(Name::qualified("f", "urn:d", Some("p")))
+                .attr(Name::qualified("y", "urn:c", Some("p")), "2"));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element());
+            // an empty prefix binds the default namespace, which does not apply to attributes
+            w.write(XmlEvent::start_element(Name::qualified("g", "urn:e", Some("")))
+                .attr(Name::qualified("z", "urn:e", Some("")), "3"));
+            w.write(XmlEvent::start_element(Name::qualified("h", "urn:e", None)));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element())
+        }
+    }
+    let output = String::from_utf8(b).unwrap();
+    assert_eq!(
+        output,
+        "<ns0:root xmlns:ns0=\"urn:a\">\
+           <ns0:child xmlns:ns1=\"urn:b\" ns1:x=\"1\" />\
+           <p:e xmlns:p=\"urn:c\">\
+             <p:f xmlns:ns1=\"urn:c\" xmlns:p=\"urn:d\" ns1:y=\"2\" />\
+           </p:e>\
+           <g xmlns=\"urn:e\" xmlns:ns1=\"urn:e\" ns1:z=\"3\">\
+             <h />\
+           </g>\
+         </ns0:root>"
+    );
+
+    let mut names = Vec::new();
+    for e in EventReader::new(output.as_bytes()) {
+        if let ReaderEvent::StartElement { name, attributes, .. } = e.unwrap() {
+            names.push((name.local_name, name.namespace.unwrap()));
+            for attr in attributes {
+                names.push((attr.name.local_name, attr.name.namespace.unwrap()));
+            }
+        }
+    }
+    let expected = [
+        ("root", "urn:a"), ("child", "urn:a"), ("x", "urn:b"), ("e", "urn:c"), ("f", "urn:d"),
+        ("y", "urn:c"), ("g", "urn:e"), ("z", "urn:e"), ("h", "urn:e"),
+    ];
+    assert_eq!(names, expected.iter().map(|&(l, n)| (l.to_owned(), n.to_owned())).collect::<Vec<_>>());
+}
+
+#[test]
+fn writing_raw_fragments() {
+    use xml::reader::XmlEvent as ReaderEvent;
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().perform_indent(true).create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::start_element("root"));
+            w.write(XmlEvent::start_element("before"));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::start_element("cached"));
+            w.write_raw("<item id=\"1\">one &amp; <b>two</b></item><item id=\"2\"/>");
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::start_element("after"));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element())
+        }
+    }
+    let output = String::from_utf8(b).unwrap();
+    assert_eq!(
+        output,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <root>\n  \
+           <before />\n  \
+           <cached><item id=\"1\">one &amp; <b>two</b></item><item id=\"2\"/></cached>\n  \
+           <after />\n\
+         </root>"
+    );
+
+    let names: Vec<_> = EventReader::new(output.as_bytes()).into_iter()
+        .filter_map(|e| match e.unwrap() {
+            ReaderEvent::StartElement { name, .. } => Some(name.local_name),
+            _ => None
+        })
+        .collect();
+    assert_eq!(names, ["root", "before", "cached", "item", "b", "item", "after"]);
+
+    for &declaration in &[false, true] {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new().write_document_declaration(declaration).create_writer(&mut b);
+            match w.write_raw("<a/>") {
+                Err(EmitterError::RawBeforeDocumentStart) => {}
+                e => panic!("Unexpected result: {:?}", e)
+            }
+        }
+        assert!(b.is_empty());
+    }
+}
+
+#[test]
+fn writing_canonical_form() {
+    use xml::common::XmlVersion;
+    use xml::reader::ParserConfig;
+    use xml::writer::XmlEvent;
+
+    fn canonicalize(document: &str) -> String {
+        let reader = ParserConfig::new()
+            .coalesce_characters(true)
+            .ignore_comments(false)
+            .create_reader(document.as_bytes());
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .perform_indent(true)
+                .canonical(true)
+                .create_writer(&mut b);
+            for e in reader {
+                if let Some(e) = e.unwrap().as_writer_event() {
+                    w.write(e).unwrap();
+                }
+            }
+        }
+        String::from_utf8(b).unwrap()
+    }
+
+    // W3C Canonical XML, 3.1 PIs, Comments, and Outside of Document Element
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().canonical(true).create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::StartDocument { version: XmlVersion::Version10, encoding: None, standalone: None });
+            w.write(XmlEvent::processing_instruction("xml-stylesheet", Some("href=\"doc.xsl\"\n   type=\"text/xsl\"   ")));
+            w.write(XmlEvent::DoctypeDecl { name: "doc", public_id: None, system_id: Some("doc.dtd"), internal_subset: None });
+            w.write(XmlEvent::start_element("doc"));
+            w.write("Hello, world!");
+            w.write(XmlEvent::comment(" Comment 1 "));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::processing_instruction("pi-without-data", None));
+            w.write(XmlEvent::comment(" Comment 2 "));
+            w.write(XmlEvent::comment(" Comment 3 "))
+        }
+    }
+    assert_eq!(
+        str::from_utf8(&b).unwrap(),
+        "<?xml-stylesheet href=\"doc.xsl\"\n   type=\"text/xsl\"   ?>\n\
+         <doc>Hello, world!<!-- Comment 1 --></doc>\n\
+         <?pi-without-data?>\n\
+         <!-- Comment 2 -->\n\
+         <!-- Comment 3 -->"
+    );
+
+    // W3C Canonical XML, 3.2 Whitespace in Document Content
+    assert_eq!(
+        canonicalize("<doc>\n   \
+                        <e1   />\n   \
+                        <e2   ></e2>\n   \
+                        <e3   name = \"elem3\"   id=\"elem3\"   />\n   \
+                        <e4   name=\"elem4\"   id=\"elem4\"   ></e4>\n   \
+                        <e5 a:attr=\"out\" b:attr=\"sorted\" attr2=\"all\" attr=\"I'm\"\n      \
+                            xmlns:b=\"http://www.ietf.org\"\n      \
+                            xmlns:a=\"http://www.w3.org\"\n      \
+                            xmlns=\"http://example.org\"/>\n\
+                      </doc>"),
+        "<doc>\n   \
+           <e1></e1>\n   \
+           <e2></e2>\n   \
+           <e3 id=\"elem3\" name=\"elem3\"></e3>\n   \
+           <e4 id=\"elem4\" name=\"elem4\"></e4>\n   \
+           <e5 xmlns=\"http://example.org\" xmlns:a=\"http://www.w3.org\" xmlns:b=\"http://www.ietf.org\" \
+               attr=\"I'm\" attr2=\"all\" b:attr=\"sorted\" a:attr=\"out\"></e5>\n\
+         </doc>"
+    );
+
+    // W3C Canonical XML, 3.4 Character Modifications and Character References
+    assert_eq!(
+        canonicalize("<doc>\n   \
+                        <text>First line&#x0d;&#10;Second line</text>\n   \
+                        <value>&#x32;</value>\n   \
+                        <compute><![CDATA[value>\"0\" && value<\"10\" ?\"valid\":\"error\"]]></compute>\n   \
+                        <compute expr='value>\"0\" &amp;&amp; value&lt;\"10\" ?\"valid\":\"error\"'>valid</compute>\n   \
+                        <norm attr=' &apos;   &#x20;&#13;&#xa;&#9;   &apos; '/>\n\
+                      </doc>"),
+        "<doc>\n   \
+           <text>First line&#xD;\nSecond line</text>\n   \
+           <value>2</value>\n   \
+           <compute>value&gt;\"0\" &amp;&amp; value&lt;\"10\" ?\"valid\":\"error\"</compute>\n   \
+           <compute expr=\"value>&quot;0&quot; &amp;&amp; value&lt;&quot;10&quot; ?&quot;valid&quot;:&quot;error&quot;\">valid</compute>\n   \
+           <norm attr=\" '    &#xD;&#xA;&#x9;   ' \"></norm>\n\
+         </doc>"
+    );
+}
+
+#[test]
+fn writing_with_shorthand_methods() {
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .autopad_comments(false)
+            .create_writer(&mut b);
+        unwrap_all! {
+            w.processing_instruction("style", Some("href=\"a.css\""));
+            w.write(XmlEvent::start_element("a"));
+            w.comment("plain");
+            w.cdata("1]]>2");
+            w.processing_instruction("empty", None)
+        }
+        match w.comment("a--b") {
+            Err(EmitterError::InvalidComment) => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+        match w.processing_instruction("xml", None) {
+            Err(EmitterError::InvalidProcessingInstructionName(_)) => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+        match w.processing_instruction("pi", Some("?>")) {
+            Err(EmitterError::InvalidProcessingInstructionData) => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+        w.write(XmlEvent::end_element()).unwrap();
+    }
+    assert_eq!(
+        str::from_utf8(&b).unwrap(),
+        "<?style href=\"a.css\"?><a><!--plain--><![CDATA[1]]]]><![CDATA[>2]]><?empty?></a>"
+    );
+}
+
+#[test]
+fn writing_with_sorted_attributes() {
+    use xml::name::Name;
+    use xml::writer::XmlEvent;
+
+    fn write(attributes: &[(Name, &str)], sort: bool) -> String {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .write_document_declaration(false)
+                .sort_attributes(sort)
+                .create_writer(&mut b);
+            let mut start = XmlEvent::start_element("e").ns("z", "urn:z").default_ns("urn:d");
+            for &(name, value) in attributes {
+                start = start.attr(name, value);
+            }
+            unwrap_all! {
+                w.write(start);
+                w.write(XmlEvent::end_element())
+            }
+        }
+        String::from_utf8(b).unwrap()
+    }
+
+    let attributes = [
+        (Name::local("b"), "1"),
+        (Name::qualified("x", "urn:z", Some("z")), "2"),
+        (Name::local("a"), "3"),
+        (Name::qualified("x", "urn:y", Some("y")), "4"),
+        (Name::local("c"), "5"),
+    ];
+    let mut reversed = attributes;
+    reversed.reverse();
+
+    let expected = "<e xmlns=\"urn:d\" xmlns:y=\"urn:y\" xmlns:z=\"urn:z\" \
+                    a=\"3\" b=\"1\" c=\"5\" y:x=\"4\" z:x=\"2\" />";
+    assert_eq!(write(&attributes, true), expected);
+    assert_eq!(write(&reversed, true), expected);
+
+    // the default is insertion order
+    assert_eq!(write(&attributes, false),
+               "<e xmlns=\"urn:d\" xmlns:y=\"urn:y\" xmlns:z=\"urn:z\" \
+                b=\"1\" z:x=\"2\" a=\"3\" y:x=\"4\" c=\"5\" />");
+}
+
+#[test]
+fn writing_with_max_line_length() {
+    use xml::writer::{AttributeAlignment, TagEndPlacement, XmlEvent};
+
+    fn write(config: EmitterConfig) -> String {
+        let mut b = Vec::new();
+        {
+            let mut w = config
+                .write_document_declaration(false)
+                .perform_indent(true)
+                .create_writer(&mut b);
+            unwrap_all! {
+                w.write(XmlEvent::start_element("root"));
+                w.write(XmlEvent::start_element("e").attr("a", "1").attr("b", "2"));
+                w.write("text");
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::start_element("empty").attr("a", "1").attr("b", "2"));
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::start_element("short"));
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::end_element())
+            }
+        }
+        String::from_utf8(b).unwrap()
+    }
+
+    // `  <e a="1" b="2">` is exactly 17 characters long, and `  <empty a="1" b="2">` is 21
+    assert_eq!(
+        write(EmitterConfig::new().max_line_length(21)),
+        "<root>\n  \
+           <e a=\"1\" b=\"2\">text</e>\n  \
+           <empty a=\"1\" b=\"2\" />\n  \
+           <short />\n\
+         </root>"
+    );
+    assert_eq!(
+        write(EmitterConfig::new().max_line_length(17)),
+        "<root>\n  \
+           <e a=\"1\" b=\"2\">text</e>\n  \
+           <empty\n    a=\"1\"\n    b=\"2\" />\n  \
+           <short />\n\
+         </root>"
+    );
+    assert_eq!(
+        write(EmitterConfig::new().max_line_length(16).pad_self_closing(false)),
+        "<root>\n  \
+           <e\n    a=\"1\"\n    b=\"2\">text</e>\n  \
+           <empty\n    a=\"1\"\n    b=\"2\"/>\n  \
+           <short/>\n\
+         </root>"
+    );
+    assert_eq!(
+        write(EmitterConfig::new()
+            .max_line_length(16)
+            .wrapped_attribute_alignment(AttributeAlignment::FirstAttribute)),
+        "<root>\n  \
+           <e a=\"1\"\n     b=\"2\">text</e>\n  \
+           <empty a=\"1\"\n         b=\"2\" />\n  \
+           <short />\n\
+         </root>"
+    );
+    assert_eq!(
+        write(EmitterConfig::new()
+            .max_line_length(16)
+            .wrapped_tag_end(TagEndPlacement::OwnLine)),
+        "<root>\n  \
+           <e\n    a=\"1\"\n    b=\"2\"\n  >text</e>\n  \
+           <empty\n    a=\"1\"\n    b=\"2\"\n  />\n  \
+           <short />\n\
+         </root>"
+    );
+
+    // without indentation the option has no effect
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .max_line_length(1)
+            .create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::start_element("e").attr("a", "1").attr("b", "2"));
+            w.write(XmlEvent::end_element())
+        }
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<e a=\"1\" b=\"2\" />");
+}
+
+#[test]
+fn writing_namespace_declarations() {
+    use xml::name::{Name, OwnedName};
+    use xml::writer::{Error as EmitterError, NamespaceConflictPolicy, XmlEvent};
+
+    fn write(policy: NamespaceConflictPolicy) -> Result<String, EmitterError> {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .write_document_declaration(false)
+                .namespace_conflict_policy(policy)
+                .create_writer(&mut b);
+            w.write(XmlEvent::start_element(Name::qualified("root", "urn:a", Some("p"))).ns("q", "urn:q"))?;
+            // declarations in scope are not repeated
+            w.write(XmlEvent::start_element(Name::qualified("child", "urn:a", Some("p"))).ns("q", "urn:q"))?;
+            w.write(XmlEvent::end_element())?;
+            // the prefix is bound to another URI in an ancestor
+            w.write(XmlEvent::start_element(Name::qualified("other", "urn:b", Some("p"))))?;
+            w.write(XmlEvent::end_element())?;
+            w.force_namespace_declaration("q", "urn:q");
+            w.write(XmlEvent::start_element("forced"))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+        }
+        Ok(String::from_utf8(b).unwrap())
+    }
+
+    assert_eq!(
+        write(NamespaceConflictPolicy::Rebind).unwrap(),
+        "<p:root xmlns:p=\"urn:a\" xmlns:q=\"urn:q\">\
+           <p:child />\
+           <p:other xmlns:p=\"urn:b\" />\
+           <forced xmlns:q=\"urn:q\" />\
+         </p:root>"
+    );
+    assert_eq!(
+        write(NamespaceConflictPolicy::GeneratePrefix).unwrap(),
+        "<p:root xmlns:p=\"urn:a\" xmlns:q=\"urn:q\">\
+           <p:child />\
+           <ns0:other xmlns:ns0=\"urn:b\" />\
+           <forced xmlns:q=\"urn:q\" />\
+         </p:root>"
+    );
+    match write(NamespaceConflictPolicy::Error) {
+        Err(EmitterError::NamespacePrefixConflict(ref prefix)) if prefix == "p" => {}
+        e => panic!("Unexpected result: {:?}", e)
+    }
+
+    // a rejected element leaves the writer as it was
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .namespace_conflict_policy(NamespaceConflictPolicy::Error)
+            .create_writer(&mut b);
+        w.write(XmlEvent::start_element(Name::qualified("a", "urn:1", Some("p")))).unwrap();
+        w.force_namespace_declaration("q", "urn:q");
+        match w.write(XmlEvent::start_element(Name::qualified("b", "urn:2", Some("p")))) {
+            Err(EmitterError::NamespacePrefixConflict(ref prefix)) if prefix == "p" => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+        assert_eq!(w.depth(), 1);
+        assert_eq!(w.element_names(), [OwnedName::qualified("a", "urn:1", Some("p"))]);
+        w.write(XmlEvent::start_element(Name::qualified("b", "urn:1", Some("p")))).unwrap();
+        w.write(XmlEvent::end_element()).unwrap();
+        w.write(XmlEvent::end_element()).unwrap();
+    }
+    assert_eq!(
+        str::from_utf8(&b).unwrap(),
+        "<p:a xmlns:p=\"urn:1\"><p:b xmlns:q=\"urn:q\" /></p:a>"
+    );
+
+    // namespace URIs are escaped like attribute values
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .create_writer(&mut b);
+        w.write(XmlEvent::start_element("a").ns("p", "urn:\"x&<").default_ns("urn:'y")).unwrap();
+        w.write(XmlEvent::end_element()).unwrap();
+    }
+    let written = str::from_utf8(&b).unwrap();
+    assert_eq!(written, "<a xmlns=\"urn:'y\" xmlns:p=\"urn:&quot;x&amp;&lt;\" />");
+    match EventReader::from_str(written).into_iter().nth(1) {
+        Some(Ok(xml::reader::XmlEvent::StartElement { namespace, .. })) => {
+            assert_eq!(namespace.get("p"), Some("urn:\"x&<"));
+            assert_eq!(namespace.get(""), Some("urn:'y"));
+        }
+        e => panic!("Unexpected event: {:?}", e)
+    }
+}
+
+#[test]
+fn writing_whitespace_events() {
+    use xml::reader::ParserConfig;
+    use xml::writer::XmlEvent;
+
+    fn rewrite(document: &str, indent: bool) -> String {
+        let reader = ParserConfig::new().create_reader(document.as_bytes());
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .write_document_declaration(false)
+                .perform_indent(indent)
+                .create_writer(&mut b);
+            for e in reader {
+                let e = e.unwrap();
+                if let xml::reader::XmlEvent::StartDocument { .. } = e {
+                    continue;
+                }
+                if let Some(e) = e.as_writer_event() {
+                    w.write(e).unwrap();
+                }
+            }
+        }
+        String::from_utf8(b).unwrap()
+    }
+
+    let document = "<a>\n      <b>text</b>    <c xml:space=\"preserve\">\n <d/>  <e> </e></c>\n</a>";
+
+    // without indentation whitespace is written back as is
+    assert_eq!(rewrite(document, false), document.replace("<d/>", "<d />"));
+
+    // with indentation insignificant whitespace is replaced, but preserved whitespace is kept
+    assert_eq!(
+        rewrite(document, true),
+        "<a>\n  \
+           <b>text</b>\n  \
+           <c xml:space=\"preserve\">\n <d />  <e> </e></c>\n\
+         </a>"
+    );
+
+    // whitespace events containing anything else are written like characters
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .perform_indent(true)
+            .create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::start_element("a"));
+            w.write(XmlEvent::Whitespace(" x "));
+            w.write(XmlEvent::end_element())
+        }
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<a> x </a>");
+}
+
+#[test]
+fn finishing_writer() {
+    use std::io::Write;
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    let mut w = EmitterConfig::new()
+        .write_document_declaration(false)
+        .create_writer(Vec::new());
+    unwrap_all! {
+        w.write(XmlEvent::start_element("a"));
+        w.write(XmlEvent::start_element("b"));
+        w.write(XmlEvent::end_element());
+        w.write(XmlEvent::end_element())
+    }
+    w.flush().unwrap();
+    assert_eq!(str::from_utf8(w.inner_mut()).unwrap(), "<a><b /></a>");
+    let mut b = w.finish().unwrap();
+    b.write_all(b"trailing data").unwrap();
+    assert_eq!(str::from_utf8(&b).unwrap(), "<a><b /></a>trailing data");
+
+    // the last start tag is not finished yet, so the writer may not be finished
+    let mut w = EmitterConfig::new()
+        .write_document_declaration(false)
+        .create_writer(Vec::new());
+    unwrap_all! {
+        w.write(XmlEvent::start_element("a"));
+        w.write(XmlEvent::start_element("p:b").ns("p", "urn:p"))
+    }
+    match w.finish() {
+        Err(EmitterError::UnclosedElements(ref names)) => assert_eq!(names, &["a", "p:b"]),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+        Ok(b) => panic!("Unexpected success: {:?}", str::from_utf8(&b))
+    }
+
+    let mut w = EmitterConfig::new()
+        .write_document_declaration(false)
+        .keep_element_names_stack(false)
+        .create_writer(Vec::new());
+    w.write(XmlEvent::start_element("a")).unwrap();
+    match w.finish() {
+        Err(EmitterError::UnclosedElements(ref names)) => assert!(names.is_empty()),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+        Ok(b) => panic!("Unexpected success: {:?}", str::from_utf8(&b))
+    }
+}
+
+#[test]
+fn writing_into_string() {
+    use std::fmt;
+    use std::io;
+    use xml::writer::{Error as EmitterError, EventWriter, FmtWriter, XmlEvent};
+
+    fn write_events<W: io::Write>(w: &mut EventWriter<W>) {
+        unwrap_all! {
+            w.write(XmlEvent::start_element("a").attr("b", "\"ü\""));
+            w.write(XmlEvent::comment("comment"));
+            w.write(XmlEvent::start_element("c"));
+            w.write(XmlEvent::characters("<☺>"));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element())
+        }
+    }
+
+    let config = EmitterConfig::new().perform_indent(true).write_bom(true);
+
+    let mut b = Vec::new();
+    write_events(&mut config.clone().create_writer(&mut b));
+
+    let mut w = EventWriter::new_string_with_config(config);
+    write_events(&mut w);
+    assert_eq!(w.as_str().len(), b.len());
+    assert_eq!(w.into_string(), str::from_utf8(&b).unwrap());
+
+    // errors of the underlying writer are reported as I/O errors
+    struct Failing;
+
+    impl fmt::Write for Failing {
+        fn write_str(&mut self, _: &str) -> fmt::Result {
+            Err(fmt::Error)
+        }
+    }
+
+    let mut w = EventWriter::new(FmtWriter::new(Failing));
+    match w.write(XmlEvent::start_element("a")) {
+        Err(EmitterError::Io(ref e)) => {
+            assert_eq!(e.kind(), io::ErrorKind::Other);
+            assert!(e.get_ref().unwrap().is::<fmt::Error>());
+        }
+        e => panic!("Unexpected result: {:?}", e)
+    }
+}
+
+#[cfg(feature = "encodings")]
+#[test]
+fn writing_unencodable_characters() {
+    use xml::encodings::{Encoding, UTF_16BE, WINDOWS_1252};
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    let latin1 = Encoding::for_label(b"iso-8859-1").unwrap();
+    assert_eq!(latin1, WINDOWS_1252);
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .output_encoding(latin1)
+            .create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::start_element("café").attr("a", "é☃"));
+            w.write(XmlEvent::characters("é☃"));
+            w.write(XmlEvent::comment("é"));
+            w.write(XmlEvent::cdata("é"));
+            w.write(XmlEvent::end_element())
+        }
+    }
+    assert_eq!(
+        b,
+        b"<?xml version=\"1.0\" encoding=\"windows-1252\"?>\
+          <caf\xE9 a=\"\xE9&#x2603;\">\xE9&#x2603;<!-- \xE9 --><![CDATA[\xE9]]></caf\xE9>".to_vec()
+    );
+
+    // references are not allowed anywhere but in text and attribute values
+    let check = |c: char, write: &dyn Fn(&mut xml::EventWriter<&mut Vec<u8>>) -> xml::writer::Result<()>| {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .output_encoding(WINDOWS_1252)
+                .write_document_declaration(false)
+                .create_writer(&mut b);
+            w.write(XmlEvent::start_element("a")).unwrap();
+            match write(&mut w) {
+                Err(EmitterError::UnencodableCharacter(e)) if e == c => {}
+                e => panic!("Unexpected result: {:?}", e)
+            }
+            // a rejected element is not opened
+            assert_eq!(w.depth(), 1);
+            assert_eq!(w.current_element().map(|n| n.to_repr()), Some("a".into()));
+            w.write(XmlEvent::end_element()).unwrap();
+        }
+        assert_eq!(b, b"<a />");
+    };
+    check('α', &|w| w.write(XmlEvent::start_element("α")));
+    check('α', &|w| w.write(XmlEvent::start_element("b").attr("α", "")));
+    check('☃', &|w| w.write(XmlEvent::comment("☃")));
+    check('☃', &|w| w.write(XmlEvent::processing_instruction("pi", Some("☃"))));
+    check('☃', &|w| w.write(XmlEvent::cdata("☃")));
+
+    // characters which would be written as CDATA are escaped instead
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .output_encoding(WINDOWS_1252)
+            .write_document_declaration(false)
+            .characters_to_cdata(true)
+            .create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::start_element("a"));
+            w.write(XmlEvent::characters("<é>"));
+            w.write(XmlEvent::characters("<☃>"));
+            w.write(XmlEvent::end_element())
+        }
+        match &w.take_warnings()[..] {
+            [EmitterError::UnencodableCharacter('☃')] => {}
+            warnings => panic!("Unexpected warnings: {:?}", warnings)
+        }
+        assert!(w.take_warnings().is_empty());
+    }
+    assert_eq!(b, b"<a><![CDATA[<\xE9>]]>&lt;&#x2603;></a>".to_vec());
+
+    // Unicode encodings can represent everything
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .output_encoding(UTF_16BE)
+            .write_bom(true)
+            .create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::start_element("α"));
+            w.write(XmlEvent::characters("é☃"));
+            w.write(XmlEvent::end_element())
+        }
+    }
+    let expected: Vec<u8> = "\u{feff}<?xml version=\"1.0\" encoding=\"UTF-16BE\"?><α>é☃</α>"
+        .encode_utf16().flat_map(|u| vec![(u >> 8) as u8, u as u8]).collect();
+    assert_eq!(b, expected);
+}
+
+#[test]
+fn inspecting_open_elements() {
+    use xml::name::{Name, OwnedName};
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .create_writer(&mut b);
+        assert_eq!(w.depth(), 0);
+        assert_eq!(w.current_element(), None);
+
+        w.write(XmlEvent::start_element("a")).unwrap();
+        assert_eq!(w.depth(), 1);
+        assert_eq!(w.current_element(), Some(Name::local("a")));
+
+        w.write(XmlEvent::start_element("p:b").ns("p", "urn:p")).unwrap();
+        assert_eq!(w.depth(), 2);
+        assert_eq!(w.current_element().map(|n| n.to_repr()), Some("p:b".into()));
+        assert_eq!(w.element_names(), &[OwnedName::local("a"), "p:b".parse().unwrap()][..]);
+
+        w.write(XmlEvent::characters("text")).unwrap();
+        w.write(XmlEvent::start_element("c")).unwrap();
+        assert_eq!(w.depth(), 3);
+        w.write(XmlEvent::end_element()).unwrap();
+        assert_eq!(w.depth(), 2);
+        w.write(XmlEvent::end_element()).unwrap();
+        assert_eq!(w.depth(), 1);
+        assert_eq!(w.current_element(), Some(Name::local("a")));
+
+        match w.write(XmlEvent::end_element().name("b")) {
+            Err(EmitterError::EndElementNameIsNotEqualToLastStartElementName { .. }) => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+        w.write(XmlEvent::end_element().name("a")).unwrap();
+        assert_eq!(w.depth(), 0);
+        assert_eq!(w.current_element(), None);
+        assert!(w.element_names().is_empty());
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<a><p:b xmlns:p=\"urn:p\">text<c /></p:b></a>");
+
+    // without the stack only the depth is known and end element names are not checked
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .keep_element_names_stack(false)
+            .create_writer(&mut b);
+        w.write(XmlEvent::start_element("a")).unwrap();
+        w.write(XmlEvent::start_element("b")).unwrap();
+        assert_eq!(w.depth(), 2);
+        assert_eq!(w.current_element(), None);
+        assert!(w.element_names().is_empty());
+
+        match w.write(XmlEvent::end_element()) {
+            Err(EmitterError::EndElementNameIsNotSpecified) => {}
+            e => panic!("Unexpected result: {:?}", e)
+        }
+        w.write(XmlEvent::characters("text")).unwrap();
+        w.write(XmlEvent::end_element().name("x")).unwrap();
+        w.write(XmlEvent::end_element().name("a")).unwrap();
+        assert_eq!(w.depth(), 0);
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<a><b>text</x></a>");
+}
+
+#[test]
+fn copying_documents_through_writer_events() {
+    use xml::reader::ParserConfig;
+
+    fn events(document: &[u8]) -> Vec<xml::reader::XmlEvent> {
+        let reader = ParserConfig::new()
+            .ignore_comments(false)
+            .report_doctype(true)
+            .create_reader(document);
+        reader.into_iter().map(Result::unwrap).collect()
+    }
+
+    let mut document = Vec::new();
+    File::open("tests/documents/round_trip.xml").unwrap().read_to_end(&mut document).unwrap();
+    assert!(events(&document).iter().any(|e| e.kind() == xml::reader::XmlEventKind::DoctypeDecl));
+
+    for &indent in &[false, true] {
+        let mut b = Vec::new();
+        {
+            let reader = ParserConfig::new()
+                .ignore_comments(false)
+                .report_doctype(true)
+                .create_reader(&document[..]);
+            let mut w = EmitterConfig::new()
+                .perform_indent(indent)
+                .create_writer(&mut b);
+            for e in reader {
+                if let Some(e) = e.unwrap().as_writer_event() {
+                    w.write(e).unwrap();
+                }
+            }
+        }
+
+        if indent {
+            // indentation replaces the whitespace of the original document
+            let strip = |events: Vec<xml::reader::XmlEvent>| -> Vec<xml::reader::XmlEvent> {
+                events.into_iter().filter(|e| !matches!(*e, xml::reader::XmlEvent::Whitespace(_))).collect()
+            };
+            let (expected, actual) = (strip(events(&document)), strip(events(&b)));
+            assert_eq!(expected, actual, "{}", str::from_utf8(&b).unwrap());
+        } else {
+            assert_eq!(events(&document), events(&b), "{}", str::from_utf8(&b).unwrap());
+        }
+    }
+}
+
+#[test]
+fn writing_all_events() {
+    use xml::reader::{self, ParserConfig};
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    let document = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                    <a xmlns:p=\"urn:p\"><!--c--><p:b x=\"1\">text &amp; <![CDATA[cdata]]></p:b><?pi?></a>";
+    let events = reader::parse_str(document, &ParserConfig::new().ignore_comments(false)).unwrap();
+
+    // borrowed reader events; EndDocument is skipped but counted
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().autopad_comments(false).create_writer(&mut b);
+        assert_eq!(w.write_all(&events).unwrap(), events.len());
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), document);
+
+    // owned writer events and values convertible into them
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().write_document_declaration(false).allow_fragments(true).create_writer(&mut b);
+        let written = vec![XmlEvent::start_element("a").into(), XmlEvent::characters("x"), XmlEvent::end_element().into()];
+        assert_eq!(w.write_all(written).unwrap(), 3);
+        assert_eq!(w.write_all(vec!["text"]).unwrap(), 1);
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<a>x</a>text");
+
+    // writing stops at the first error
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().write_document_declaration(false).create_writer(&mut b);
+        let written = vec![
+            XmlEvent::start_element("a").into(),
+            XmlEvent::end_element().into(),
+            XmlEvent::end_element().into(),
+            XmlEvent::characters("never written")
+        ];
+        match w.write_all(written) {
+            Err((2, EmitterError::UnexpectedEndElement)) => {}
+            r => panic!("Unexpected result: {:?}", r)
+        }
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<a />");
+}
+
+#[test]
+fn writing_with_fluent_api() {
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    let config = EmitterConfig::new().perform_indent(true);
+
+    let mut fluent = Vec::new();
+    {
+        let mut w = config.clone().create_writer(&mut fluent);
+        unwrap_all! {
+            w.start_element("people").default_ns("urn:people").ns_decl("x", "urn:x").write();
+            w.start_element("person").attr("id", "42").attr_ns("urn:x", "lang", "en").write();
+            w.start_element("name").write();
+            w.text("Jane & John");
+            w.end_element();
+            w.start_element("x:note").attr_ns("urn:y", "kind", "short").write();
+            w.text("<none>");
+            w.end_element();
+            w.end_element();
+            w.end_element()
+        }
+    }
+
+    let mut events = Vec::new();
+    {
+        let mut w = config.create_writer(&mut events);
+        unwrap_all! {
+            w.write(XmlEvent::start_element("people").default_ns("urn:people").ns("x", "urn:x"));
+            w.write(XmlEvent::start_element("person").attr("id", "42").attr(xml::name::Name::qualified("lang", "urn:x", None), "en"));
+            w.write(XmlEvent::start_element("name"));
+            w.write(XmlEvent::characters("Jane & John"));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::start_element("x:note").attr(xml::name::Name::qualified("kind", "urn:y", None), "short"));
+            w.write(XmlEvent::characters("<none>"));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element())
+        }
+    }
+
+    assert_eq!(str::from_utf8(&fluent).unwrap(), str::from_utf8(&events).unwrap());
+    assert_eq!(
+        str::from_utf8(&fluent).unwrap(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <people xmlns=\"urn:people\" xmlns:x=\"urn:x\">\n  \
+           <person id=\"42\" x:lang=\"en\">\n    \
+             <name>Jane &amp; John</name>\n    \
+             <x:note xmlns:ns0=\"urn:y\" ns0:kind=\"short\">&lt;none></x:note>\n  \
+           </person>\n\
+         </people>"
+    );
+
+    // names are checked when the element is written, before anything is written
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().write_document_declaration(false).create_writer(&mut b);
+        w.start_element("a").write().unwrap();
+        for &(element, attribute) in &[("1b", "c"), ("b c", "c"), ("b", "c d"), ("p:", "c"), ("b", "-c"), ("a:b:c", "c")] {
+            match w.start_element(element).attr(attribute, "").write() {
+                Err(EmitterError::InvalidName(_)) => {}
+                r => panic!("Unexpected result for {:?}: {:?}", (element, attribute), r)
+            }
+        }
+        w.end_element().unwrap();
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<a />");
+}
+
+#[test]
+fn writing_entity_references() {
+    use xml::reader::ParserConfig;
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    // references read without replacement are written back verbatim
+    let document = "<a x=\"1\">one &custom; two &amp; &#33;<b>&custom;</b>&other;</a>";
+    let reader = ParserConfig::new()
+        .replace_entity_references(false)
+        .coalesce_characters(false)
+        .create_reader(document.as_bytes());
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().create_writer(&mut b);
+        for e in reader {
+            if let Some(e) = e.unwrap().as_writer_event() {
+                w.write(e).unwrap();
+            }
+        }
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(),
+               "<?xml version=\"1.0\" encoding=\"UTF-8\"?><a x=\"1\">one &custom; two &amp; !<b>&custom;</b>&other;</a>");
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().perform_indent(true).write_document_declaration(false).create_writer(&mut b);
+        match w.write(XmlEvent::EntityReference("custom")) {
+            Err(EmitterError::EntityReferenceOutsideOfRootElement) => {}
+            r => panic!("Unexpected result: {:?}", r)
+        }
+        unwrap_all! {
+            w.write(XmlEvent::start_element("a"));
+            w.write(XmlEvent::start_element("b"));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::EntityReference("custom"));
+            w.write(XmlEvent::start_element("c"));
+            w.write(XmlEvent::end_element())
+        }
+        for &name in &["", "1a", "a b", "a;"] {
+            match w.write(XmlEvent::EntityReference(name)) {
+                Err(EmitterError::InvalidName(_)) => {}
+                r => panic!("Unexpected result for {:?}: {:?}", name, r)
+            }
+        }
+        w.write(XmlEvent::end_element()).unwrap();
+    }
+    // no indentation is inserted around references
+    assert_eq!(str::from_utf8(&b).unwrap(), "<a>\n  <b />&custom;<c /></a>");
+}
+
+#[test]
+fn writing_with_extra_escapes() {
+    use xml::writer::XmlEvent;
+
+    let write = |config: EmitterConfig| {
+        let mut b = Vec::new();
+        {
+            let mut w = config.write_document_declaration(false).create_writer(&mut b);
+            unwrap_all! {
+                w.write(XmlEvent::start_element("a").attr("x", "1\u{A0}000 \"'"));
+                w.write(XmlEvent::characters("10\u{A0}km & 'more'"));
+                w.write(XmlEvent::cdata("\u{A0}"));
+                w.write(XmlEvent::end_element())
+            }
+        }
+        String::from_utf8(b).unwrap()
+    };
+
+    assert_eq!(write(EmitterConfig::new()), "<a x=\"1\u{A0}000 &quot;'\">10\u{A0}km &amp; 'more'<![CDATA[\u{A0}]]></a>");
+
+    let config = EmitterConfig::new()
+        .extra_pcdata_escapes(&[('\u{A0}', "&#160;"), ('\'', "&apos;")][..])
+        .extra_attribute_escapes(vec![('\u{A0}', "&#160;"), ('"', "&#34;")]);
+    assert_eq!(write(config.clone()), "<a x=\"1&#160;000 &#34;'\">10&#160;km &amp; &apos;more&apos;<![CDATA[\u{A0}]]></a>");
+
+    // the replacements alone do not turn characters into CDATA sections
+    assert_eq!(
+        write(config.clone().characters_to_cdata(true).cdata_to_characters(true)),
+        "<a x=\"1&#160;000 &#34;'\"><![CDATA[10\u{A0}km & 'more']]>&#160;</a>"
+    );
+
+    // and they are not applied if escaping is disabled
+    assert_eq!(write(EmitterConfig { perform_escaping: false, ..config }), "<a x=\"1\u{A0}000 \"'\">10\u{A0}km & 'more'<![CDATA[\u{A0}]]></a>");
+}
+
+#[test]
+fn rejecting_malformed_documents() {
+    use xml::writer::{Error as EmitterError, XmlEvent};
+
+    fn check<F>(allow_fragments: bool, write: F) -> (Result<(), EmitterError>, String)
+        where F: FnOnce(&mut xml::EventWriter<&mut Vec<u8>>) -> Result<(), EmitterError>
+    {
+        let mut b = Vec::new();
+        let result = {
+            let mut w = EmitterConfig::new()
+                .write_document_declaration(false)
+                .allow_fragments(allow_fragments)
+                .create_writer(&mut b);
+            write(&mut w)
+        };
+        (result, String::from_utf8(b).unwrap())
+    }
+
+    // a second root element
+    let (r, output) = check(false, |w| {
+        w.write(XmlEvent::start_element("a"))?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::start_element("b"))
+    });
+    match r {
+        Err(EmitterError::MultipleRootElements(ref name)) if name == "b" => {}
+        r => panic!("Unexpected result: {:?}", r)
+    }
+    assert_eq!(output, "<a />");
+
+    // an end element when nothing is open
+    match check(false, |w| w.write(XmlEvent::end_element())).0 {
+        Err(EmitterError::UnexpectedEndElement) => {}
+        r => panic!("Unexpected result: {:?}", r)
+    }
+
+    // an end element with a wrong name
+    let (r, _) = check(false, |w| {
+        w.write(XmlEvent::start_element("a"))?;
+        w.write(XmlEvent::start_element("p:b").ns("p", "urn:p"))?;
+        w.write(XmlEvent::end_element().name("c"))
+    });
+    match r {
+        Err(ref e @ EmitterError::EndElementNameIsNotEqualToLastStartElementName { .. }) => {
+            assert_eq!(e.to_string(), "emitter error: end element name c is not equal to last start element name p:b at depth 2");
+        }
+        r => panic!("Unexpected result: {:?}", r)
+    }
+
+    // character data outside of the root element; whitespace is fine
+    for &(before_root, text) in &[(true, "text"), (false, " x ")] {
+        let (r, _) = check(false, |w| {
+            w.write(XmlEvent::characters("\n"))?;
+            if !before_root {
+                w.write(XmlEvent::start_element("a"))?;
+                w.write(XmlEvent::end_element())?;
+            }
+            w.write(XmlEvent::characters(text))
+        });
+        match r {
+            Err(EmitterError::CharactersOutsideOfRootElement) => {}
+            r => panic!("Unexpected result for {:?}: {:?}", text, r)
+        }
+    }
+
+    // attributes with invalid names
+    match check(false, |w| w.write(XmlEvent::start_element("a").attr("1x", "1"))).0 {
+        Err(EmitterError::InvalidName(ref name)) if name == "1x" => {}
+        r => panic!("Unexpected result: {:?}", r)
+    }
+    match check(false, |w| w.write(XmlEvent::start_element("a b"))).0 {
+        Err(EmitterError::InvalidName(ref name)) if name == "a b" => {}
+        r => panic!("Unexpected result: {:?}", r)
+    }
+
+    // duplicate attributes, either by qualified name or by namespace and local name
+    let (r, output) = check(false, |w| w.write(XmlEvent::start_element("a").attr("x", "1").attr("y", "2").attr("x", "3")));
+    match r {
+        Err(ref e @ EmitterError::DuplicateAttribute(..)) => {
+            assert_eq!(e.to_string(), "emitter error: attribute x is written twice in element a");
+        }
+        r => panic!("Unexpected result: {:?}", r)
+    }
+    assert_eq!(output, "");
+    let (r, _) = check(false, |w| {
+        w.write(XmlEvent::start_element("a")
+            .attr(xml::name::Name::qualified("x", "urn:p", Some("p")), "1")
+            .attr(xml::name::Name::qualified("x", "urn:p", Some("q")), "2"))
+    });
+    match r {
+        Err(EmitterError::DuplicateAttribute(ref attr, ref element)) if attr == "q:x" && element == "a" => {}
+        r => panic!("Unexpected result: {:?}", r)
+    }
+
+    // fragments are allowed on request, but they still have to be well-formed otherwise
+    let (r, output) = check(true, |w| {
+        w.write(XmlEvent::characters("text "))?;
+        w.write(XmlEvent::start_element("a"))?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::cdata("<b>"))?;
+        w.write(XmlEvent::EntityReference("c"))?;
+        w.write(XmlEvent::start_element("d"))?;
+        w.write(XmlEvent::end_element())
+    });
+    r.unwrap();
+    assert_eq!(output, "text <a /><![CDATA[<b>]]>&c;<d />");
+    match check(true, |w| w.write(XmlEvent::end_element())).0 {
+        Err(EmitterError::UnexpectedEndElement) => {}
+        r => panic!("Unexpected result: {:?}", r)
+    }
+}
+
+#[test]
+fn writing_without_document_declaration() {
+    use xml::common::XmlVersion;
+    use xml::writer::XmlEvent;
+
+    for &explicit in &[false, true] {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .write_document_declaration(false)
+                .perform_indent(true)
+                .create_writer(&mut b);
+            if explicit {
+                w.write(XmlEvent::StartDocument {
+                    version: XmlVersion::Version11,
+                    encoding: Some("utf-8"),
+                    standalone: Some(true)
+                }).unwrap();
+                assert_eq!(w.xml_version(), XmlVersion::Version11);
+            } else {
+                assert_eq!(w.xml_version(), XmlVersion::Version10);
+            }
+            unwrap_all! {
+                w.write(XmlEvent::start_element("root"));
+                w.write(XmlEvent::start_element("child"));
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::end_element())
+            }
+        }
+        // no declaration and no line break before the root element
+        assert!(b.starts_with(b"<root"));
+        assert_eq!(str::from_utf8(&b).unwrap(), "<root>\n  <child />\n</root>");
+    }
+
+    // the automatic declaration uses the configured version and standalone flag
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .xml_version(XmlVersion::Version11)
+            .standalone(true)
+            .perform_indent(true)
+            .create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::start_element("root"));
+            w.write(XmlEvent::end_element())
+        }
+        assert_eq!(w.xml_version(), XmlVersion::Version11);
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(),
+               "<?xml version=\"1.1\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<root />");
+}
+
+#[test]
+fn writing_declaration_pseudo_attributes() {
+    use xml::common::XmlVersion;
+    use xml::writer::XmlEvent;
+
+    let write = |config: EmitterConfig, explicit: bool| {
+        let mut b = Vec::new();
+        {
+            let mut w = config.standalone(false).create_writer(&mut b);
+            if explicit {
+                w.write(XmlEvent::StartDocument {
+                    version: XmlVersion::Version10,
+                    encoding: Some("UTF-8"),
+                    standalone: Some(true)
+                }).unwrap();
+            }
+            unwrap_all! {
+                w.write(XmlEvent::start_element("root"));
+                w.write(XmlEvent::end_element())
+            }
+        }
+        String::from_utf8(b).unwrap()
+    };
+
+    assert_eq!(write(EmitterConfig::new(), false), "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?><root />");
+    assert_eq!(write(EmitterConfig::new(), true), "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?><root />");
+
+    let config = EmitterConfig::new().declare_standalone(false);
+    assert_eq!(write(config.clone(), false), "<?xml version=\"1.0\" encoding=\"UTF-8\"?><root />");
+    assert_eq!(write(config, true), "<?xml version=\"1.0\" encoding=\"UTF-8\"?><root />");
+
+    let config = EmitterConfig::new().declare_encoding(false);
+    assert_eq!(write(config.clone(), false), "<?xml version=\"1.0\" standalone=\"no\"?><root />");
+    assert_eq!(write(config.declare_standalone(false), true), "<?xml version=\"1.0\"?><root />");
+}
+
+#[test]
+fn unwrapping_writer() {
+    use xml::writer::XmlEvent;
+
+    // the pending end of a start tag is written before the stream is returned
+    let mut w = EmitterConfig::new()
+        .write_document_declaration(false)
+        .perform_indent(true)
+        .max_line_length(10)
+        .wrapped_tag_end(xml::writer::TagEndPlacement::OwnLine)
+        .create_writer(Vec::new());
+    unwrap_all! {
+        w.write(XmlEvent::start_element("root").attr("first", "1").attr("second", "2"))
+    }
+    assert_eq!(str::from_utf8(&w.into_inner()).unwrap(), "<root\n  first=\"1\"\n  second=\"2\"\n>");
+
+    // a finished document is returned as is
+    let mut w = EmitterConfig::new().write_document_declaration(false).create_writer(Vec::new());
+    unwrap_all! {
+        w.write(XmlEvent::start_element("a"));
+        w.write(XmlEvent::end_element())
+    }
+    assert_eq!(w.into_inner(), b"<a />");
+
+    let mut w = EmitterConfig::new()
+        .write_document_declaration(false)
+        .normalize_empty_elements(false)
+        .create_writer(Vec::new());
+    w.write(XmlEvent::start_element("a")).unwrap();
+    assert_eq!(w.into_inner(), b"<a>");
+}
+
+#[test]
+fn normalizing_newlines() {
+    use xml::reader::{EventReader, XmlEvent as ReaderEvent};
+    use xml::writer::XmlEvent;
+
+    let source = "one\r\ntwo\rthree\nfour";
+
+    let round_trip = |config: EmitterConfig| {
+        let mut b = Vec::new();
+        {
+            let mut w = config.write_document_declaration(false).create_writer(&mut b);
+            w.write(XmlEvent::start_element("a").attr("v", source)).unwrap();
+            w.write(XmlEvent::characters(source)).unwrap();
+            w.write(XmlEvent::end_element()).unwrap();
+        }
+        let written = String::from_utf8(b).unwrap();
+        let mut attribute = None;
+        let mut text = String::new();
+        for e in EventReader::from_str(&written) {
+            match e.unwrap() {
+                ReaderEvent::StartElement { attributes, .. } => attribute = Some(attributes[0].value.clone()),
+                ReaderEvent::Characters(s) => text.push_str(&s),
+                _ => {}
+            }
+        }
+        (written, attribute.unwrap(), text)
+    };
+
+    // by default the exact bytes are written; line breaks in attribute values are escaped,
+    // while the ones in text are normalized by the reader
+    let logical = source.replace("\r\n", "\n").replace('\r', "\n");
+    let (written, attribute, text) = round_trip(EmitterConfig::new());
+    assert_eq!(written, "<a v=\"one&#xD;&#xA;two&#xD;three&#xA;four\">one\r\ntwo\rthree\nfour</a>");
+    assert_eq!(attribute, source);
+    assert_eq!(text, logical);
+
+    // with normalization both values round-trip as the logical content of the source
+    let (written, attribute, text) = round_trip(EmitterConfig::new().normalize_newlines(true));
+    assert_eq!(written, "<a v=\"one&#xA;two&#xA;three&#xA;four\">one\ntwo\nthree\nfour</a>");
+    assert_eq!(attribute, logical);
+    assert_eq!(text, logical);
+
+    let (written, attribute, text) = round_trip(EmitterConfig::new().normalize_newlines(true).line_separator("\r\n"));
+    assert_eq!(written, "<a v=\"one&#xA;two&#xA;three&#xA;four\">one\r\ntwo\r\nthree\r\nfour</a>");
+    assert_eq!(attribute, logical);
+    assert_eq!(text, logical);
+}
+
+#[test]
+fn writing_inline_elements() {
+    use xml::writer::XmlEvent;
+
+    let render = |inline: &[&str]| {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .write_document_declaration(false)
+                .perform_indent(true)
+                .inline_elements(inline.iter().map(|s| s.to_string()).collect())
+                .create_writer(&mut b);
+            unwrap_all! {
+                w.write(XmlEvent::start_element("body"));
+                w.write(XmlEvent::start_element("p"));
+                w.write(XmlEvent::start_element("em"));
+                w.write(XmlEvent::characters("Hello"));
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::start_element("a").attr("href", "#"));
+                w.write(XmlEvent::start_element("em"));
+                w.write(XmlEvent::characters("world"));
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::start_element("hr"));
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::end_element())
+            }
+        }
+        String::from_utf8(b).unwrap()
+    };
+
+    // without inline elements the children of <p> are indented
+    assert_eq!(
+        render(&[]),
+        "<body>\n  <p>\n    <em>Hello</em>\n    <a href=\"#\">\n      <em>world</em>\n    </a>\n  </p>\n  <hr />\n</body>"
+    );
+
+    // inline elements, including nested ones, are written without added whitespace,
+    // but the position of <p> within its parent is still indented
+    let written = render(&["p", "em", "a"]);
+    assert_eq!(written, "<body>\n  <p><em>Hello</em><a href=\"#\"><em>world</em></a></p>\n  <hr />\n</body>");
+    let p = &written[written.find("<p>").unwrap()..written.find("</p>").unwrap()];
+    assert!(!p.contains('\n') && !p.contains("  "));
+
+    // inline elements themselves are positioned within a parent with element content
+    assert_eq!(
+        render(&["em", "a"]),
+        "<body>\n  <p>\n    <em>Hello</em>\n    <a href=\"#\"><em>world</em></a>\n  </p>\n  <hr />\n</body>"
+    );
+}
+
+#[test]
+fn limiting_indentation_depth() {
+    use xml::writer::XmlEvent;
+
+    let render = |config: EmitterConfig| {
+        let mut b = Vec::new();
+        {
+            let mut w = config
+                .write_document_declaration(false)
+                .perform_indent(true)
+                .create_writer(&mut b);
+            unwrap_all! {
+                w.write(XmlEvent::start_element("root"));
+                w.write(XmlEvent::start_element("item"));
+                w.write(XmlEvent::start_element("name"));
+                w.write(XmlEvent::characters("a"));
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::start_element("tags"));
+                w.write(XmlEvent::start_element("tag"));
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::start_element("item"));
+                w.write(XmlEvent::start_element("name"));
+                w.write(XmlEvent::characters("b"));
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::end_element());
+                w.write(XmlEvent::end_element())
+            }
+        }
+        String::from_utf8(b).unwrap()
+    };
+
+    assert_eq!(
+        render(EmitterConfig::new()),
+        "<root>\n  <item>\n    <name>a</name>\n    <tags>\n      <tag />\n    </tags>\n  </item>\n  \
+         <item>\n    <name>b</name>\n  </item>\n</root>"
+    );
+    assert_eq!(
+        render(EmitterConfig::new().max_indent_depth(1)),
+        "<root>\n  <item><name>a</name><tags><tag /></tags></item>\n  <item><name>b</name></item>\n</root>"
+    );
+    assert_eq!(
+        render(EmitterConfig::new().max_indent_depth(2)),
+        "<root>\n  <item>\n    <name>a</name>\n    <tags><tag /></tags>\n  </item>\n  \
+         <item>\n    <name>b</name>\n  </item>\n</root>"
+    );
+    assert_eq!(
+        render(EmitterConfig::new().max_indent_depth(0)),
+        "<root><item><name>a</name><tags><tag /></tags></item><item><name>b</name></item></root>"
+    );
+
+    // inline elements stay inline above the limit
+    let inline = vec!["item".to_string()].into_iter().collect();
+    assert_eq!(
+        render(EmitterConfig::new().max_indent_depth(2).inline_elements(inline)),
+        "<root>\n  <item><name>a</name><tags><tag /></tags></item>\n  <item><name>b</name></item>\n</root>"
+    );
+}
+
+#[test]
+fn writing_with_attributes_sorted_by_namespace() {
+    use xml::name::Name;
+    use xml::writer::XmlEvent;
+
+    fn write(attributes: &[(Name, &str)], config: EmitterConfig) -> String {
+        let mut b = Vec::new();
+        {
+            let mut w = config
+                .write_document_declaration(false)
+                .create_writer(&mut b);
+            let mut start = XmlEvent::start_element("e").ns("a", "urn:z").ns("b", "urn:a");
+            for &(name, value) in attributes {
+                start = start.attr(name, value);
+            }
+            unwrap_all! {
+                w.write(start);
+                w.write(XmlEvent::end_element())
+            }
+        }
+        String::from_utf8(b).unwrap()
+    }
+
+    let attributes = [
+        (Name::qualified("y", "urn:z", Some("a")), "1"),
+        (Name::local("z"), "2"),
+        (Name::qualified("x", "urn:a", Some("b")), "3"),
+        (Name::qualified("x", "urn:z", Some("a")), "4"),
+        (Name::local("m"), "5"),
+    ];
+    let mut reversed = attributes;
+    reversed.reverse();
+
+    // attributes without a namespace come first, then the ones in "urn:a" and in "urn:z"
+    let expected = "<e xmlns:a=\"urn:z\" xmlns:b=\"urn:a\" m=\"5\" z=\"2\" b:x=\"3\" a:x=\"4\" a:y=\"1\" />";
+    let config = EmitterConfig::new().sort_attributes_by_namespace(true);
+    assert_eq!(write(&attributes, config.clone()), expected);
+    assert_eq!(write(&reversed, config.clone()), expected);
+
+    // the option takes precedence over sorting by qualified names
+    assert_eq!(write(&attributes, config.sort_attributes(true)), expected);
+    assert_eq!(write(&attributes, EmitterConfig::new().sort_attributes(true)),
+               "<e xmlns:a=\"urn:z\" xmlns:b=\"urn:a\" a:x=\"4\" a:y=\"1\" b:x=\"3\" m=\"5\" z=\"2\" />");
+
+    // the default is insertion order
+    assert_eq!(write(&attributes, EmitterConfig::new()),
+               "<e xmlns:a=\"urn:z\" xmlns:b=\"urn:a\" a:y=\"1\" z=\"2\" b:x=\"3\" a:x=\"4\" m=\"5\" />");
+}
+
+#[cfg(feature = "c14n")]
+#[test]
+fn writing_canonical_namespaces() {
+    use xml::reader::ParserConfig;
+    use xml::writer::Canonicalization;
+
+    fn canonicalize(document: &str, method: Canonicalization) -> String {
+        let reader = ParserConfig::new().create_reader(document.as_bytes());
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new().canonicalization(method).create_writer(&mut b);
+            for e in reader {
+                if let Some(e) = e.unwrap().as_writer_event() {
+                    w.write(e).unwrap();
+                }
+            }
+        }
+        String::from_utf8(b).unwrap()
+    }
+
+    // modelled on W3C Canonical XML, 3.3 Start and End Tags, and the examples of
+    // Exclusive XML Canonicalization
+    let document = "<?xml version=\"1.0\"?>\
+        <doc xmlns=\"urn:d\" xmlns:a=\"urn:a\" xmlns:b=\"urn:b\">\
+          <e1 xmlns=\"urn:d\" a:x=\"1\"/>\
+          <e2 xmlns=\"\"><e3 xmlns:c=\"urn:c\"><b:e4 xmlns:a=\"urn:a2\" c:y=\"2\"/></e3></e2>\
+          <e5 xmlns:a=\"urn:a\" xmlns:b=\"urn:b2\"><e6 xmlns:b=\"urn:b\"/></e5>\
+        </doc>";
+
+    // redundant declarations are removed, and the others are sorted by prefix
+    assert_eq!(
+        canonicalize(document, Canonicalization::Inclusive),
+        "<doc xmlns=\"urn:d\" xmlns:a=\"urn:a\" xmlns:b=\"urn:b\">\
+           <e1 a:x=\"1\"></e1>\
+           <e2 xmlns=\"\"><e3 xmlns:c=\"urn:c\"><b:e4 xmlns:a=\"urn:a2\" c:y=\"2\"></b:e4></e3></e2>\
+           <e5 xmlns:b=\"urn:b2\"><e6 xmlns:b=\"urn:b\"></e6></e5>\
+         </doc>"
+    );
+
+    // only the namespaces used by names are declared, where they are first used
+    assert_eq!(
+        canonicalize(document, Canonicalization::Exclusive),
+        "<doc xmlns=\"urn:d\">\
+           <e1 xmlns:a=\"urn:a\" a:x=\"1\"></e1>\
+           <e2 xmlns=\"\"><e3><b:e4 xmlns:b=\"urn:b\" xmlns:c=\"urn:c\" c:y=\"2\"></b:e4></e3></e2>\
+           <e5><e6></e6></e5>\
+         </doc>"
+    );
+}
+
+#[test]
+fn writing_parsed_attributes() {
+    use xml::writer::XmlEvent;
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().write_document_declaration(false).create_writer(&mut b);
+        let start = XmlEvent::start_element("a").ns("p", "urn:p")
+            .try_attr("p:x='1'").unwrap()
+            .try_attr("y = \"say 'hi'\"").unwrap();
+        unwrap_all! {
+            w.write(start);
+            w.write(XmlEvent::end_element())
+        }
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), "<a xmlns:p=\"urn:p\" p:x=\"1\" y=\"say 'hi'\" />");
+
+    assert!(XmlEvent::start_element("a").try_attr("x=1").is_err());
+    assert!(XmlEvent::start_element("a").try_attr("x='&lt;'").is_err());
+}
@@ -0,0 +1,117 @@
+#![forbid(unsafe_code)]
+
+extern crate xml;
+
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+
+use xml::attribute::OwnedAttribute;
+use xml::common::Position;
+use xml::name::OwnedName;
+use xml::reader::{self, Handler, HandlerContext, ParserConfig};
+
+#[derive(Default)]
+struct ElementCounter {
+    counts: BTreeMap<String, usize>,
+    positions: Vec<String>,
+    namespaces: Vec<Option<String>>,
+    text: String,
+    doctype: Option<String>,
+    references: Vec<String>,
+    ended: bool,
+    errors: usize,
+    stop_at: Option<&'static str>
+}
+
+impl Handler for ElementCounter {
+    fn start_element(&mut self, name: &OwnedName, _: &[OwnedAttribute], context: &HandlerContext) -> ControlFlow<()> {
+        *self.counts.entry(name.local_name.clone()).or_insert(0) += 1;
+        self.positions.push(context.position().to_string());
+        if self.stop_at == Some(&name.local_name[..]) {
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn end_element(&mut self, _: &OwnedName, context: &HandlerContext) -> ControlFlow<()> {
+        self.namespaces.push(context.namespace().get("x").map(|s| s.to_owned()));
+        ControlFlow::Continue(())
+    }
+
+    fn characters(&mut self, data: &str, _: &HandlerContext) -> ControlFlow<()> {
+        self.text.push_str(data);
+        ControlFlow::Continue(())
+    }
+
+    fn doctype_decl(&mut self, name: &str, _: Option<&str>, system_id: Option<&str>, _: Option<&str>,
+                    _: &HandlerContext) -> ControlFlow<()> {
+        self.doctype = Some(format!("{} {}", name, system_id.unwrap_or_default()));
+        ControlFlow::Continue(())
+    }
+
+    fn entity_reference(&mut self, name: &str, _: &HandlerContext) -> ControlFlow<()> {
+        self.references.push(name.to_owned());
+        ControlFlow::Continue(())
+    }
+
+    fn end_document(&mut self, _: &HandlerContext) -> ControlFlow<()> {
+        self.ended = true;
+        ControlFlow::Continue(())
+    }
+
+    fn error(&mut self, _: &reader::Error) {
+        self.errors += 1;
+    }
+}
+
+const DOCUMENT: &str = "<list><item>a</item><x:item xmlns:x=\"urn:x\"><![CDATA[b]]></x:item>\
+                        <other/><item>c</item></list>";
+
+#[test]
+fn counting_elements() {
+    let mut counter = ElementCounter::default();
+    reader::parse_with_handler(DOCUMENT.as_bytes(), &ParserConfig::new(), &mut counter).unwrap();
+
+    let counts: Vec<_> = counter.counts.iter().map(|(k, &v)| (&k[..], v)).collect();
+    assert_eq!(counts, [("item", 3), ("list", 1), ("other", 1)]);
+    assert_eq!(counter.positions, ["1:1", "1:7", "1:21", "1:67", "1:75"]);
+    assert_eq!(counter.namespaces, [None, Some("urn:x".to_owned()), None, None, None]);
+    assert_eq!(counter.text, "abc");
+    assert!(counter.ended);
+    assert_eq!(counter.errors, 0);
+}
+
+#[test]
+fn receiving_doctype_and_entity_references() {
+    let config = ParserConfig::new()
+        .report_doctype(true)
+        .replace_entity_references(false);
+    let mut counter = ElementCounter::default();
+    reader::parse_with_handler(&b"<!DOCTYPE list SYSTEM 'list.dtd'><list>a&b;c</list>"[..], &config, &mut counter).unwrap();
+    assert_eq!(counter.doctype.as_ref().map(|s| &s[..]), Some("list list.dtd"));
+    assert_eq!(counter.references, ["b"]);
+    assert_eq!(counter.text, "ac");
+
+    let mut counter = ElementCounter::default();
+    reader::parse_with_handler(&b"<!DOCTYPE list><list/>"[..], &ParserConfig::new(), &mut counter).unwrap();
+    assert_eq!(counter.doctype, None);
+}
+
+#[test]
+fn stopping_early() {
+    let mut counter = ElementCounter { stop_at: Some("other"), ..ElementCounter::default() };
+    reader::parse_with_handler(DOCUMENT.as_bytes(), &ParserConfig::new(), &mut counter).unwrap();
+    assert_eq!(counter.counts["item"], 2);
+    assert_eq!(counter.text, "ab");
+    assert!(!counter.ended);
+}
+
+#[test]
+fn reporting_errors() {
+    let mut counter = ElementCounter::default();
+    let err = reader::parse_with_handler(&b"<a><b></a>"[..], &ParserConfig::new(), &mut counter).unwrap_err();
+    assert_eq!(err.position().to_string(), "1:10");
+    assert_eq!(counter.counts.len(), 2);
+    assert_eq!(counter.errors, 1);
+    assert!(!counter.ended);
+}
@@ -0,0 +1,51 @@
+#![forbid(unsafe_code)]
+#![cfg(feature = "json")]
+
+extern crate serde_json;
+extern crate xml;
+
+use xml::json::{to_json, JsonOptions};
+use xml::EventReader;
+
+fn convert(document: &[u8], options: &JsonOptions) -> String {
+    let value = to_json(EventReader::new(document), options).unwrap();
+    serde_json::to_string_pretty(&value).unwrap() + "\n"
+}
+
+#[test]
+fn converting_attributes() {
+    assert_eq!(
+        convert(include_bytes!("documents/json_attributes.xml"), &JsonOptions::new()),
+        include_str!("documents/json_attributes.json")
+    );
+}
+
+#[test]
+fn converting_repeated_elements() {
+    assert_eq!(
+        convert(include_bytes!("documents/json_list.xml"), &JsonOptions::new()),
+        include_str!("documents/json_list.json")
+    );
+}
+
+#[test]
+fn converting_mixed_content() {
+    assert_eq!(
+        convert(include_bytes!("documents/json_mixed.xml"), &JsonOptions::new()),
+        include_str!("documents/json_mixed.json")
+    );
+}
+
+#[test]
+fn converting_with_options() {
+    let options = JsonOptions::new().attribute_prefix("-").text_key("$");
+    let value = to_json(EventReader::from_str("<a x='1'>t<b/></a>"), &options).unwrap();
+    assert_eq!(value.to_string(), r#"{"a":{"$":"t","-x":"1","b":null}}"#);
+
+    // attributes and child elements with the same keys are collected together
+    let options = JsonOptions::new().attribute_prefix("");
+    let value = to_json(EventReader::from_str("<a b='1'><b>2</b><b>3</b></a>"), &options).unwrap();
+    assert_eq!(value.to_string(), r#"{"a":{"b":["1","2","3"]}}"#);
+
+    assert!(to_json(EventReader::from_str("<a><b></a>"), &JsonOptions::new()).is_err());
+}
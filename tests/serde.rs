@@ -0,0 +1,393 @@
+#![forbid(unsafe_code)]
+#![cfg(feature = "serde")]
+
+extern crate xml;
+#[macro_use]
+extern crate serde_derive;
+
+use std::collections::HashMap;
+
+use xml::de::{from_event_reader, from_reader, from_str};
+use xml::ser::{to_string, to_string_with_config, to_writer};
+use xml::{EmitterConfig, ParserConfig};
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Project {
+    #[serde(rename = "@name")]
+    name: String,
+    libraries: Libraries,
+    #[serde(rename = "module")]
+    modules: Vec<Module>
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Libraries {
+    #[serde(rename = "library")]
+    libraries: Vec<Library>
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Library {
+    #[serde(rename = "@groupId")]
+    group_id: String,
+    #[serde(rename = "@artifactId")]
+    artifact_id: String,
+    #[serde(rename = "@version")]
+    version: String
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Module {
+    #[serde(rename = "@name")]
+    name: String,
+    files: Files
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Files {
+    #[serde(rename = "file")]
+    files: Vec<File>
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct File {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "@type")]
+    kind: String,
+    #[serde(rename = "$value")]
+    text: String
+}
+
+#[test]
+fn deserializing_nested_structs() {
+    let project: Project = from_reader(&include_bytes!("documents/sample_1.xml")[..]).unwrap();
+
+    assert_eq!(project.name, "project-name");
+    assert_eq!(project.libraries.libraries, [
+        Library { group_id: "org.example".into(), artifact_id: "<name>".into(), version: "0.1".into() },
+        Library { group_id: "com.example".into(), artifact_id: "\"cool-lib&".into(), version: "999".into() }
+    ]);
+    assert_eq!(project.modules.iter().map(|m| &m.name[..]).collect::<Vec<_>>(), ["module-1", "module-2"]);
+    let files = &project.modules[0].files.files;
+    assert_eq!(files.len(), 3);
+    assert_eq!(files[0].name, "somefile.java");
+    assert_eq!(files[0].kind, "java");
+    assert_eq!(files[0].text.trim(), "Some <java> class");
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Name(String);
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Person {
+    #[serde(rename = "@id")]
+    id: u32,
+    #[serde(rename = "@nick")]
+    nick: Option<String>,
+    name: Name,
+    age: Option<u8>,
+    email: Option<String>,
+    #[serde(default, rename = "tag")]
+    tags: Vec<String>,
+    active: bool
+}
+
+#[test]
+fn deserializing_options_sequences_and_newtypes() {
+    let person: Person = from_str(
+        "<person id=\"1\">\n  \
+           <name>Ann</name>\n  \
+           <age> 42 </age>\n  \
+           <tag>a</tag><tag>b &amp; c</tag>\n  \
+           <active>true</active>\n\
+         </person>"
+    ).unwrap();
+    assert_eq!(person, Person {
+        id: 1,
+        nick: None,
+        name: Name("Ann".into()),
+        age: Some(42),
+        email: None,
+        tags: vec!["a".into(), "b & c".into()],
+        active: true
+    });
+
+    let person: Person = from_str(
+        "<person id=\"2\" nick=\"bo\"><name>Bob</name><email></email><active>0</active></person>"
+    ).unwrap();
+    assert_eq!(person.nick, Some("bo".into()));
+    assert_eq!(person.email, Some("".into()));
+    assert_eq!(person.age, None);
+    assert!(person.tags.is_empty());
+    assert!(!person.active);
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Element {
+    #[serde(rename = "@value")]
+    attribute: Option<String>,
+    value: Option<String>
+}
+
+#[test]
+fn deserializing_attributes_and_elements() {
+    // attributes and child elements with the same name are distinct entries
+    let e: Element = from_str("<e value=\"attribute\"><value>element</value></e>").unwrap();
+    assert_eq!(e, Element { attribute: Some("attribute".into()), value: Some("element".into()) });
+
+    let e: Element = from_str("<e><value>element</value></e>").unwrap();
+    assert_eq!(e, Element { attribute: None, value: Some("element".into()) });
+
+    let e: Element = from_str("<e value=\"attribute\"/>").unwrap();
+    assert_eq!(e, Element { attribute: Some("attribute".into()), value: None });
+
+    // namespaces are not taken into account
+    let e: Element = from_str("<p:e xmlns:p=\"urn:p\" p:value=\"attribute\"><p:value>element</p:value></p:e>").unwrap();
+    assert_eq!(e, Element { attribute: Some("attribute".into()), value: Some("element".into()) });
+
+    let map: HashMap<String, String> = from_str("<e a=\"1\"><b>2</b>text</e>").unwrap();
+    assert_eq!(map.len(), 3);
+    assert_eq!(map["@a"], "1");
+    assert_eq!(map["b"], "2");
+    assert_eq!(map["$value"], "text");
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Shape {
+    Circle {
+        #[serde(rename = "@r")]
+        radius: f64
+    },
+    Square(Side),
+    Point
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Side {
+    #[serde(rename = "@side")]
+    side: u32
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Color {
+    Red,
+    Green
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Drawing {
+    #[serde(rename = "@color")]
+    color: Color,
+    background: Color,
+    main: Shape,
+    #[serde(rename = "$value")]
+    shapes: Vec<Shape>
+}
+
+#[test]
+fn deserializing_enums() {
+    let drawing: Drawing = from_str(
+        "<drawing color=\"red\">\
+           <background>green</background>\
+           <main><square side=\"3\"/></main>\
+           <circle r=\"1.5\"/>\
+           <point/>\
+           <square side=\"2\"/>\
+         </drawing>"
+    ).unwrap();
+    assert_eq!(drawing, Drawing {
+        color: Color::Red,
+        background: Color::Green,
+        main: Shape::Square(Side { side: 3 }),
+        shapes: vec![Shape::Circle { radius: 1.5 }, Shape::Point, Shape::Square(Side { side: 2 })]
+    });
+
+    // the root element is dispatched on its own name
+    let shape: Shape = from_str("<circle r=\"2\"/>").unwrap();
+    assert_eq!(shape, Shape::Circle { radius: 2.0 });
+}
+
+#[test]
+fn deserializing_errors() {
+    let err = from_str::<Person>("<person id=\"1\">\n  <age>42</age>\n</person>").unwrap_err();
+    assert_eq!(err.msg(), "missing field `name`");
+    assert_eq!(err.position().map(|p| p.to_string()), Some("1:1".into()));
+    assert_eq!(err.to_string(), "1:1 missing field `name`");
+
+    let err = from_str::<Person>("<person id=\"1\">\n  <name>A</name>\n  <age>old</age>\n</person>").unwrap_err();
+    assert_eq!(err.msg(), "Invalid value `old`, expected an unsigned integer");
+    assert_eq!(err.position().map(|p| p.to_string()), Some("3:3".into()));
+
+    let err = from_str::<Person>("<person id=\"x\"><name>A</name></person>").unwrap_err();
+    assert_eq!(err.msg(), "Invalid value `x`, expected an unsigned integer");
+    assert_eq!(err.position().map(|p| p.to_string()), Some("1:1".into()));
+
+    let err = from_str::<Person>("<person id=\"1\"><name>A</name><name>B</name></person>").unwrap_err();
+    assert_eq!(err.msg(), "Unexpected repeated element `name`");
+    assert_eq!(err.position().map(|p| p.to_string()), Some("1:30".into()));
+
+    let err = from_str::<Person>("<person id=\"1\"><name><first>A</first></name></person>").unwrap_err();
+    assert_eq!(err.msg(), "Unexpected element `first`, expected text content of `name`");
+    assert_eq!(err.position().map(|p| p.to_string()), Some("1:22".into()));
+
+    let err = from_str::<Shape>("<triangle/>").unwrap_err();
+    assert!(err.msg().starts_with("unknown variant `triangle`"), "{}", err);
+    assert_eq!(err.position().map(|p| p.to_string()), Some("1:1".into()));
+
+    let err = from_str::<Person>("<person id=\"1\"><name>A</name>").unwrap_err();
+    assert_eq!(err.msg(), "Unexpected end of stream: still inside the root element");
+
+    let err = from_str::<Person>("<!-- nothing -->").unwrap_err();
+    assert_eq!(err.position().map(|p| p.to_string()), Some("1:17".into()));
+}
+
+#[test]
+fn deserializing_with_config() {
+    let reader = ParserConfig::new()
+        .whitespace_to_characters(true)
+        .create_reader(&b"<file name=\"a\" type=\"b\">  </file>"[..]);
+    let file: File = from_event_reader(reader).unwrap();
+    assert_eq!(file.text, "  ");
+
+    let file: File = from_str("<file name=\"a\" type=\"b\">  </file>").unwrap();
+    assert_eq!(file.text, "");
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Branch {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "@open")]
+    open: Option<bool>,
+    address: Address,
+    #[serde(rename = "book")]
+    books: Vec<Book>,
+    #[serde(default, rename = "shelf")]
+    shelves: Vec<u16>,
+    description: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Address {
+    street: String,
+    number: u32
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Book {
+    #[serde(rename = "@isbn")]
+    isbn: String,
+    title: String,
+    year: Option<i32>,
+    format: Format,
+    #[serde(rename = "$value")]
+    notes: Vec<Note>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    Paperback,
+    Hardcover
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Note {
+    Remark(String),
+    Damaged,
+    Loan {
+        #[serde(rename = "@to")]
+        to: String
+    }
+}
+
+fn branch() -> Branch {
+    Branch {
+        name: "City <Library> & Co".into(),
+        open: Some(true),
+        address: Address { street: "Main \"Street\"".into(), number: 12 },
+        books: vec![
+            Book {
+                isbn: "1".into(),
+                title: "First".into(),
+                year: Some(-300),
+                format: Format::Hardcover,
+                notes: vec![Note::Remark("rare".into()), Note::Damaged, Note::Loan { to: "Ann".into() }]
+            },
+            Book { isbn: "2".into(), title: "Second".into(), year: None, format: Format::Paperback, notes: Vec::new() }
+        ],
+        shelves: vec![1, 2],
+        description: None
+    }
+}
+
+#[test]
+fn serializing_round_trip() {
+    let branch = branch();
+    let document = to_string(&branch).unwrap();
+    assert_eq!(
+        document,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <Branch name=\"City &lt;Library&gt; &amp; Co\" open=\"true\">\
+           <address><street>Main \"Street\"</street><number>12</number></address>\
+           <book isbn=\"1\"><title>First</title><year>-300</year><format>hardcover</format>\
+             <remark>rare</remark><damaged /><loan to=\"Ann\" /></book>\
+           <book isbn=\"2\"><title>Second</title><format>paperback</format></book>\
+           <shelf>1</shelf><shelf>2</shelf>\
+         </Branch>"
+    );
+    assert_eq!(from_str::<Branch>(&document).unwrap(), branch);
+
+    // indentation is controlled by the emitter configuration
+    let indented = to_string_with_config(&branch.address, EmitterConfig::new()
+        .perform_indent(true)
+        .write_document_declaration(false)).unwrap();
+    assert_eq!(indented, "<Address>\n  <street>Main \"Street\"</street>\n  <number>12</number>\n</Address>");
+    assert_eq!(from_str::<Address>(&indented).unwrap(), branch.address);
+
+    let mut output = Vec::new();
+    to_writer(&mut output, &branch).unwrap();
+    assert_eq!(from_reader::<_, Branch>(&output[..]).unwrap(), branch);
+}
+
+#[derive(Serialize)]
+struct Counts {
+    counts: HashMap<u32, String>
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Untagged {
+    Text(String),
+    Address(Address)
+}
+
+#[derive(Serialize)]
+struct Attributes {
+    #[serde(rename = "@shelves")]
+    shelves: Vec<u16>
+}
+
+#[test]
+fn serializing_errors() {
+    let counts = Counts { counts: vec![(1, "one".into())].into_iter().collect() };
+    assert_eq!(to_string(&counts).unwrap_err().to_string(), "Map keys must be strings, found an integer");
+
+    let mut names = HashMap::new();
+    names.insert("name", "value");
+    assert_eq!(to_string(&names).unwrap_err().to_string(), "Cannot serialize a map as the root element, it has no name");
+
+    assert_eq!(to_string(&Untagged::Text("text".into())).unwrap_err().to_string(),
+               "Cannot serialize a string as the root element");
+    let address = Untagged::Address(Address { street: "a".into(), number: 1 });
+    assert_eq!(to_string_with_config(&address, EmitterConfig::new().write_document_declaration(false)).unwrap(),
+               "<Address><street>a</street><number>1</number></Address>");
+
+    assert_eq!(to_string(&Attributes { shelves: vec![1] }).unwrap_err().to_string(),
+               "Cannot serialize a sequence as an attribute value (attribute `shelves`)");
+    assert_eq!(to_string(&vec![1, 2]).unwrap_err().to_string(), "Cannot serialize a sequence as the root element");
+}
@@ -67,8 +67,7 @@ fn reading_streamed_content() {
 #[test]
 fn reading_streamed_content2() {
     let buf = Cursor::new(b"<root>".to_vec());
-    let mut config = ParserConfig::new();
-    config.ignore_end_of_stream = true;
+    let config = ParserConfig::new().ignore_end_of_stream(true);
     let readerb = EventReader::new_with_config(buf, config);
 
     let mut reader = readerb.into_iter();
@@ -101,3 +100,129 @@ fn reading_streamed_content2() {
     assert_match!(reader.next(), Some(Ok(XmlEvent::StartElement { ref name, .. })) if name.local_name == "child-4");
 }
 
+#[test]
+fn reading_buffered_content_followed_by_binary_data() {
+    let data: &[u8] = b"<root><child>text</child></root>\x00\xff\xfe binary data";
+    let reader = EventReader::new_buffered_with_config(data, ParserConfig::new().buffer_size(5));
+
+    let mut it = reader.into_iter();
+
+    assert_match!(it.next(), Some(Ok(XmlEvent::StartDocument { .. })));
+    assert_match!(it.next(), Some(Ok(XmlEvent::StartElement { ref name, .. })) if name.local_name == "root");
+    assert_match!(it.next(), Some(Ok(XmlEvent::StartElement { ref name, .. })) if name.local_name == "child");
+    assert_match!(it.next(), Some(Ok(XmlEvent::Characters(ref c))) if c == "text");
+    assert_match!(it.next(), Some(Ok(XmlEvent::EndElement { ref name })) if name.local_name == "child");
+    assert_match!(it.next(), Some(Ok(XmlEvent::EndElement { ref name })) if name.local_name == "root");
+
+    let (rest, buffered) = it.into_inner().into_inner_with_buffer();
+    assert!(!buffered.is_empty());
+    assert_eq!([&buffered[..], rest].concat(), b"\x00\xff\xfe binary data".to_vec());
+}
+
+#[test]
+fn reading_in_chunks_of_buffer_size() {
+    use std::io::{self, Read};
+    use std::rc::Rc;
+    use std::cell::RefCell;
+
+    // records the sizes of the requested chunks
+    struct Recorder<'a> {
+        data: &'a [u8],
+        requests: Rc<RefCell<Vec<usize>>>
+    }
+
+    impl<'a> Read for Recorder<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.requests.borrow_mut().push(buf.len());
+            self.data.read(buf)
+        }
+    }
+
+    let data = b"<root><child>text</child></root>";
+    for &(size, expected) in &[(8 * 1024, 8 * 1024), (8, 8), (0, 1)] {
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        let source = Recorder { data, requests: requests.clone() };
+        let config = ParserConfig::new().buffer_size(size);
+        let events: Vec<_> = EventReader::new_buffered_with_config(source, config)
+            .into_iter().map(Result::unwrap).collect();
+        assert_eq!(events.len(), 7);
+        assert!(requests.borrow().iter().all(|&n| n == expected), "{:?}", requests);
+    }
+}
+
+#[test]
+fn reading_line_break_split_between_chunks() {
+    let buf = Cursor::new(b"<root>a\r".to_vec());
+    let reader = ParserConfig::new().ignore_end_of_stream(true).create_reader(buf);
+
+    let mut it = reader.into_iter();
+
+    assert_match!(it.next(), Some(Ok(XmlEvent::StartDocument { .. })));
+    assert_match!(it.next(), Some(Ok(XmlEvent::StartElement { ref name, .. })) if name.local_name == "root");
+    assert_match!(it.next(), Some(Err(_)));
+
+    // the line feed completes the `\r\n` line break read before
+    write_and_reset_position(it.source_mut(), b"\nb</root>");
+    assert_match!(it.next(), Some(Ok(XmlEvent::Characters(ref c))) if c == "a\nb");
+    assert_match!(it.next(), Some(Ok(XmlEvent::EndElement { ref name })) if name.local_name == "root");
+}
+
+#[test]
+fn reading_character_split_between_chunks() {
+    let buf = Cursor::new(b"<root>caf\xC3".to_vec());
+    let reader = ParserConfig::new().ignore_end_of_stream(true).create_reader(buf);
+
+    let mut it = reader.into_iter();
+
+    assert_match!(it.next(), Some(Ok(XmlEvent::StartDocument { .. })));
+    assert_match!(it.next(), Some(Ok(XmlEvent::StartElement { ref name, .. })) if name.local_name == "root");
+    assert_match!(it.next(), Some(Err(_)));
+
+    // the rest of the sequence completes the bytes read before
+    write_and_reset_position(it.source_mut(), b"\xA9 \xF0\x9F");
+    assert_match!(it.next(), Some(Err(_)));
+    write_and_reset_position(it.source_mut(), b"\x98\x80</root>");
+    assert_match!(it.next(), Some(Ok(XmlEvent::Characters(ref c))) if c == "caf\u{E9} \u{1F600}");
+    assert_match!(it.next(), Some(Ok(XmlEvent::EndElement { ref name })) if name.local_name == "root");
+}
+
+#[test]
+fn reading_multibyte_characters_with_small_buffers() {
+    let data = "<r a='\u{E9}\u{20AC}'>\u{1F600}\r\n\u{E9}\r<![CDATA[\u{20AC}]]>\u{10FFFF}</r>".as_bytes();
+    let expected: Vec<_> = EventReader::new(data).into_iter().map(Result::unwrap).collect();
+    assert_eq!(expected.len(), 7);
+    for size in 0..8 {
+        let config = ParserConfig::new().buffer_size(size);
+        let events: Vec<_> = EventReader::new_buffered_with_config(data, config)
+            .into_iter().map(Result::unwrap).collect();
+        assert_eq!(events, expected, "buffer size {}", size);
+    }
+}
+
+#[test]
+fn reading_malformed_multibyte_sequences() {
+    let documents: &[&[u8]] = &[
+        b"<r>\xC0\xAF</r>",          // overlong encoding of '/'
+        b"<r>\xE0\x80\xBC</r>",      // overlong encoding of '<'
+        b"<r>\xED\xA0\x80</r>",      // surrogate
+        b"<r>\xF4\x90\x80\x80</r>",  // beyond U+10FFFF
+        b"<r>\xE2\x82</r>",          // truncated by markup
+        b"<r>\xFF</r>",
+    ];
+    for &document in documents {
+        for &size in &[1, 2, 8 * 1024] {
+            let config = ParserConfig::new().buffer_size(size);
+            let mut reader = EventReader::new_buffered_with_config(document, config);
+            assert_match!(reader.next(), Ok(XmlEvent::StartDocument { .. }));
+            assert_match!(reader.next(), Ok(XmlEvent::StartElement { .. }));
+            let e = reader.next().unwrap_err();
+            assert_match!(*e.kind(), xml::reader::ErrorKind::Utf8(_));
+        }
+    }
+
+    // a sequence truncated by the end of the stream
+    let mut reader = EventReader::new(&b"<r>\xE2\x82"[..]);
+    assert_match!(reader.next(), Ok(XmlEvent::StartDocument { .. }));
+    assert_match!(reader.next(), Ok(XmlEvent::StartElement { .. }));
+    assert_match!(*reader.next().unwrap_err().kind(), xml::reader::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,209 @@
+#![forbid(unsafe_code)]
+
+extern crate xml;
+
+use std::cell::Cell;
+
+use xml::attribute::OwnedAttribute;
+use xml::name::OwnedName;
+use xml::reader::XmlEvent;
+use xml::transform::{Action, Transformer};
+use xml::{EmitterConfig, EventReader, ParserConfig};
+
+fn writer() -> xml::EventWriter<Vec<u8>> {
+    EmitterConfig::new()
+        .write_document_declaration(false)
+        .create_writer(Vec::new())
+}
+
+fn events(document: &[u8]) -> Vec<XmlEvent> {
+    ParserConfig::new()
+        .trim_whitespace(true)
+        .ignore_comments(false)
+        .create_reader(document)
+        .into_iter()
+        .map(|e| e.unwrap())
+        .collect()
+}
+
+fn is_start(event: &XmlEvent, local_name: &str) -> bool {
+    match *event {
+        XmlEvent::StartElement { ref name, .. } => name.local_name == local_name,
+        _ => false
+    }
+}
+
+#[test]
+fn stripping_and_renaming_subtrees() {
+    let reader = EventReader::new(&include_bytes!("documents/sample_1.xml")[..]);
+    let output = Transformer::new(reader, writer())
+        // drop the libraries of modules, but not the ones of the project
+        .filter_subtree(|path, _| {
+            let path: Vec<_> = path.map(|n| n.local_name).collect();
+            if path == ["project", "module", "libraries"] { Action::Skip } else { Action::Keep }
+        })
+        // rename files to sources, keeping their contents
+        .filter_subtree(|_, start| match *start {
+            XmlEvent::StartElement { ref name, ref attributes, ref namespace } if name.local_name == "file" =>
+                Action::Replace(XmlEvent::StartElement {
+                    name: OwnedName::local("source"),
+                    attributes: attributes.clone(),
+                    namespace: namespace.clone()
+                }),
+            _ => Action::Keep
+        })
+        // add an attribute to modules
+        .map_event(|event| match event {
+            XmlEvent::StartElement { name, mut attributes, namespace } => {
+                if name.local_name == "module" {
+                    attributes.push(OwnedAttribute::new(OwnedName::local("checked"), "yes"));
+                }
+                Some(XmlEvent::StartElement { name, attributes, namespace })
+            }
+            event => Some(event)
+        })
+        .run()
+        .unwrap();
+
+    // the result is checked by the paths of the elements in the re-parsed document
+    let mut reader = ParserConfig::new().trim_whitespace(true).create_reader(&output[..]);
+    let mut paths = Vec::new();
+    let mut texts = Vec::new();
+    loop {
+        match reader.next().unwrap() {
+            XmlEvent::EndDocument => break,
+            XmlEvent::StartElement { ref name, .. } if name.local_name != "source" && name.local_name != "library" =>
+                paths.push(reader.current_path().map(|n| n.local_name).collect::<Vec<_>>().join("/")),
+            XmlEvent::Characters(ref s) => texts.push(s.clone()),
+            _ => {}
+        }
+    }
+    assert_eq!(paths, [
+        "project", "project/libraries",
+        "project/module", "project/module/files",
+        "project/module", "project/module/files"
+    ]);
+    assert_eq!(texts, [
+        "Some <java> class", "Another \"java\" class", "Weird 'XML' config",
+        "JavaScript & program", "Cascading style sheet: \u{a9} - \u{489}"
+    ]);
+
+    let events = self::events(&output);
+    assert_eq!(events.iter().filter(|e| is_start(e, "libraries")).count(), 1);
+    assert_eq!(events.iter().filter(|e| is_start(e, "library")).count(), 2);
+    assert_eq!(events.iter().filter(|e| is_start(e, "file")).count(), 0);
+    assert_eq!(events.iter().filter(|e| is_start(e, "source")).count(), 5);
+    let sources: Vec<_> = events.iter().filter_map(|e| match *e {
+        XmlEvent::StartElement { ref name, ref attributes, .. } if name.local_name == "source" =>
+            Some(attributes[0].value.clone()),
+        _ => None
+    }).collect();
+    assert_eq!(sources, ["somefile.java", "another_file.java", "config.xml", "program.js", "style.css"]);
+
+    let modules: Vec<_> = events.iter().filter_map(|e| match *e {
+        XmlEvent::StartElement { ref name, ref attributes, .. } if name.local_name == "module" =>
+            Some(attributes.iter().map(|a| a.to_string()).collect::<Vec<_>>()),
+        _ => None
+    }).collect();
+    assert_eq!(modules, [["name=\"module-1\"", "checked=\"yes\""], ["name=\"module-2\"", "checked=\"yes\""]]);
+}
+
+#[test]
+fn replacing_and_unwrapping_elements() {
+    let calls = Cell::new(0);
+    let reader = ParserConfig::new()
+        .ignore_comments(false)
+        .create_reader(&b"<a><secret><b/>data</secret><wrapper><c>1</c><!--x--></wrapper><d/></a>"[..]);
+    let output = Transformer::new(reader, writer())
+        .filter_subtree(|_, start| {
+            calls.set(calls.get() + 1);
+            if is_start(start, "secret") { Action::Replace(XmlEvent::Comment(" removed ".into())) } else { Action::Keep }
+        })
+        .map_event(|event| match event {
+            // unwrap the element, keeping its contents
+            ref e if is_start(e, "wrapper") => None,
+            XmlEvent::Comment(_) => Some(XmlEvent::Characters("comment".into())),
+            e if is_start(&e, "d") => Some(XmlEvent::Characters("d".into())),
+            e => Some(e)
+        })
+        .run()
+        .unwrap();
+
+    assert_eq!(String::from_utf8(output).unwrap(), "<a>comment<c>1</c>commentd</a>");
+    // the contents of the replaced element are not filtered
+    assert_eq!(calls.get(), 5);
+}
+
+#[test]
+fn transforming_errors() {
+    let reader = EventReader::from_str("<a><b></a>");
+    let err = Transformer::new(reader, writer()).run().unwrap_err();
+    assert_eq!(err.to_string(), "1:10 Unexpected closing tag: a, expected b");
+
+    let reader = EventReader::from_str("<a><skipped><b></skipped></a>");
+    assert!(Transformer::new(reader, writer())
+        .filter_subtree(|_, _| Action::Skip)
+        .run()
+        .is_err());
+}
+
+#[test]
+fn reindenting_documents() {
+    let reader = ParserConfig::new().ignore_comments(false).create_reader(&b"<?xml version=\"1.0\"?>\
+        <!-- header --><doc><?pi data?>   <list>\n\t\t<item a=\"1\"/><item>one</item>\
+        <!-- note --></list><p>Mixed <b>bold <i>and</i></b>\n  <i>italic</i> text</p>\
+        <pre xml:space=\"preserve\">\n  <line/>\n</pre><data><![CDATA[ ]]></data></doc>"[..]);
+    let output = xml::transform::reindent(reader, Vec::new(), "\t").unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!-- header -->\n\
+         <doc>\n\
+         \t<?pi data?>\n\
+         \t<list>\n\
+         \t\t<item a=\"1\" />\n\
+         \t\t<item>one</item>\n\
+         \t\t<!-- note -->\n\
+         \t</list>\n\
+         \t<p>Mixed <b>bold <i>and</i></b>\n  <i>italic</i> text</p>\n\
+         \t<pre xml:space=\"preserve\">\n  <line />\n</pre>\n\
+         \t<data><![CDATA[ ]]></data>\n\
+         </doc>"
+    );
+
+    // the result is reindented the same way again
+    let reader = EventReader::from_str("<a><b><c>  </c></b></a>");
+    let once = xml::transform::reindent(reader, Vec::new(), "  ").unwrap();
+    let twice = xml::transform::reindent(EventReader::new(&once[..]), Vec::new(), "  ").unwrap();
+    assert_eq!(String::from_utf8(once).unwrap(), String::from_utf8(twice).unwrap());
+
+    // text after a child element makes the content mixed from the start
+    let reader = EventReader::from_str("<a><p><b>bold</b> text</p><p>\n<b/>\n<i/>text</p></a>");
+    let output = xml::transform::reindent(reader, Vec::new(), "  ").unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <a>\n  <p><b>bold</b> text</p>\n  <p>\n<b />\n<i />text</p>\n</a>"
+    );
+}
+
+#[test]
+fn reindenting_long_elements() {
+    use xml::transform::REINDENT_LOOKAHEAD;
+
+    // beyond the lookahead, an element is reindented until text appears in it
+    let count = REINDENT_LOOKAHEAD / 2 + 1;
+    let document = format!("<a>{}<b/>text</a>", "<b/>".repeat(count));
+    let output = xml::transform::reindent(EventReader::from_str(&document), Vec::new(), " ").unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let expected = format!("<a>{}\n <b />text</a>", "\n <b />".repeat(count));
+    assert!(output.ends_with(&expected), "{}", output);
+
+    // up to it, the whole element is written as it is read
+    let count = REINDENT_LOOKAHEAD / 2 - 1;
+    let document = format!("<a>{}<b/>text</a>", "<b/>".repeat(count));
+    let output = xml::transform::reindent(EventReader::from_str(&document), Vec::new(), " ").unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let expected = format!("<a>{}<b />text</a>", "<b />".repeat(count));
+    assert!(output.ends_with(&expected), "{}", output);
+}